@@ -5,6 +5,13 @@
 //! This crate contains types used across kernel, userspace, and IPC boundaries.
 //! No implementation logic belongs here - only definitions.
 
+/// Reading file descriptors
+pub mod io {
+    /// `sys_read` flag: fail with `WouldBlock` (-11) instead of waiting
+    /// for input
+    pub const O_NONBLOCK: u64 = 0o4000;
+}
+
 /// IPC message types
 pub mod ipc {
     /// Raw IPC message transmitted over ring buffer
@@ -55,6 +62,353 @@ pub mod ipc {
     }
 }
 
+/// Process information records
+pub mod process {
+    use super::{read_padded, write_padded};
+
+    /// Layout version of [`ProcessInfo`]
+    ///
+    /// Bumped whenever fields are added or reordered so consumers can
+    /// reject records they do not understand.
+    pub const PROCESS_INFO_VERSION: u32 = 5;
+
+    /// Size of the NUL-padded name field in [`ProcessInfo`]
+    pub const PROCESS_NAME_LEN: usize = 32;
+
+    /// Size of the NUL-padded command line field in [`ProcessInfo`]
+    pub const PROCESS_CMDLINE_LEN: usize = 128;
+
+    /// PID of the init process, which adopts orphaned processes
+    pub const INIT_PID: u64 = 1;
+
+    /// `sys_wait` PID argument: wait for any child
+    pub const WAIT_ANY: i64 = -1;
+
+    /// `sys_wait` flag: return 0 instead of blocking if no child has exited
+    pub const WAIT_NOHANG: u64 = 1;
+
+    /// Highest scheduling priority a process can ask for with `sys_setpriority`
+    pub const NICE_MIN: i32 = -20;
+    /// Lowest scheduling priority
+    pub const NICE_MAX: i32 = 19;
+    /// Nice value shells give the background tasks they `spawn`
+    pub const NICE_BATCH: i32 = 10;
+
+    /// Scheduling policy: normal time sharing, ordered by nice value
+    pub const SCHED_OTHER: u32 = 0;
+    /// Real-time policy: run until blocked or preempted by a higher priority
+    pub const SCHED_FIFO: u32 = 1;
+    /// Real-time policy: like `SCHED_FIFO`, but take turns within a priority
+    pub const SCHED_RR: u32 = 2;
+    /// Lowest real-time priority (real-time threads always run before
+    /// `SCHED_OTHER` ones)
+    pub const RT_PRIORITY_MIN: u32 = 1;
+    /// Highest real-time priority
+    pub const RT_PRIORITY_MAX: u32 = 99;
+
+    /// Short name of a scheduling policy, as shown by `ps`
+    pub fn policy_name(policy: u32) -> &'static str {
+        match policy {
+            SCHED_OTHER => "TS",
+            SCHED_FIFO => "FF",
+            SCHED_RR => "RR",
+            _ => "?",
+        }
+    }
+
+    /// Illegal instruction
+    pub const SIGILL: u32 = 4;
+    /// Bus error: misaligned access or bad segment
+    pub const SIGBUS: u32 = 7;
+    /// Arithmetic error: divide by zero, x87/SIMD exception
+    pub const SIGFPE: u32 = 8;
+    /// Kill request that cannot be ignored (`sys_kill`)
+    pub const SIGKILL: u32 = 9;
+    /// Invalid memory access
+    pub const SIGSEGV: u32 = 11;
+    /// Termination request (`sys_kill`); there are no handlers yet, so it
+    /// kills like `SIGKILL`
+    pub const SIGTERM: u32 = 15;
+
+    /// Name of a signal the kernel kills processes with
+    pub fn signal_name(signal: u32) -> &'static str {
+        match signal {
+            SIGILL => "SIGILL",
+            SIGBUS => "SIGBUS",
+            SIGFPE => "SIGFPE",
+            SIGKILL => "SIGKILL",
+            SIGSEGV => "SIGSEGV",
+            SIGTERM => "SIGTERM",
+            _ => "SIG?",
+        }
+    }
+
+    /// Scheduling state of a process as reported to userspace
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(u32)]
+    pub enum ProcessState {
+        /// Waiting to be scheduled
+        Ready = 0,
+        /// Currently executing
+        Running = 1,
+        /// Waiting for I/O or an event
+        Blocked = 2,
+        /// Terminated (see `exit_code`)
+        Exited = 3,
+        /// Killed after a fault or by `sys_kill` (`exit_code` is the signal)
+        Killed = 4,
+    }
+
+    impl ProcessState {
+        /// Decode a raw state value from a [`ProcessInfo`] record
+        pub fn from_raw(raw: u32) -> Option<Self> {
+            match raw {
+                0 => Some(ProcessState::Ready),
+                1 => Some(ProcessState::Running),
+                2 => Some(ProcessState::Blocked),
+                3 => Some(ProcessState::Exited),
+                4 => Some(ProcessState::Killed),
+                _ => None,
+            }
+        }
+
+        /// Human-readable state name
+        pub fn as_str(self) -> &'static str {
+            match self {
+                ProcessState::Ready => "Ready",
+                ProcessState::Running => "Running",
+                ProcessState::Blocked => "Blocked",
+                ProcessState::Exited => "Exited",
+                ProcessState::Killed => "Killed",
+            }
+        }
+    }
+
+    /// Fixed-size process record filled in by `sys_ps`
+    ///
+    /// The kernel only copies these records out; formatting is left to
+    /// userspace tools (`ps`, the management daemon).
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct ProcessInfo {
+        /// Record layout version (`PROCESS_INFO_VERSION`)
+        pub version: u32,
+        /// Raw [`ProcessState`] value
+        pub state: u32,
+        /// Process ID
+        pub pid: u64,
+        /// Parent process ID (0 if spawned by the kernel)
+        pub ppid: u64,
+        /// Exit code when `state` is `Exited`, signal number when `Killed`
+        pub exit_code: i64,
+        /// Timer ticks spent running
+        pub ticks: u64,
+        /// Memory owned by the process in bytes
+        pub memory_bytes: u64,
+        /// Process name, NUL-padded
+        pub name: [u8; PROCESS_NAME_LEN],
+        /// Command line (argv joined by spaces), NUL-padded and truncated
+        pub cmdline: [u8; PROCESS_CMDLINE_LEN],
+        /// Nice value (`NICE_MIN`..=`NICE_MAX`, lower runs first)
+        pub nice: i32,
+        /// Scheduling policy (`SCHED_OTHER`, `SCHED_FIFO` or `SCHED_RR`)
+        pub policy: u32,
+        /// Real-time priority (0 under `SCHED_OTHER`)
+        pub rt_priority: u32,
+        /// Reserved, 0
+        pub reserved: u32,
+        /// Times one of its threads was switched onto a CPU
+        pub switches: u64,
+        /// Switches away after blocking, sleeping or exiting
+        pub voluntary_switches: u64,
+        /// Switches away while still runnable (time slice used up, or
+        /// preempted by a real-time thread)
+        pub involuntary_switches: u64,
+        /// Nanoseconds its threads spent Ready, waiting for a CPU
+        pub ready_ns: u64,
+    }
+
+    impl ProcessInfo {
+        /// An all-zero record, useful for preparing output buffers
+        pub const fn empty() -> Self {
+            ProcessInfo {
+                version: PROCESS_INFO_VERSION,
+                state: 0,
+                pid: 0,
+                ppid: 0,
+                exit_code: 0,
+                ticks: 0,
+                memory_bytes: 0,
+                name: [0; PROCESS_NAME_LEN],
+                cmdline: [0; PROCESS_CMDLINE_LEN],
+                nice: 0,
+                policy: SCHED_OTHER,
+                rt_priority: 0,
+                reserved: 0,
+                switches: 0,
+                voluntary_switches: 0,
+                involuntary_switches: 0,
+                ready_ns: 0,
+            }
+        }
+
+        /// Decoded process state
+        pub fn state(&self) -> Option<ProcessState> {
+            ProcessState::from_raw(self.state)
+        }
+
+        /// Process name up to the first NUL byte
+        pub fn name(&self) -> &str {
+            read_padded(&self.name)
+        }
+
+        /// Copy `name` into the record, truncating to `PROCESS_NAME_LEN`
+        pub fn set_name(&mut self, name: &str) {
+            write_padded(&mut self.name, name);
+        }
+
+        /// Command line up to the first NUL byte
+        pub fn cmdline(&self) -> &str {
+            read_padded(&self.cmdline)
+        }
+
+        /// Copy `cmdline` into the record, truncating to `PROCESS_CMDLINE_LEN`
+        pub fn set_cmdline(&mut self, cmdline: &str) {
+            write_padded(&mut self.cmdline, cmdline);
+        }
+    }
+}
+
+/// Scheduler statistics read with `sys_sched_stats`
+pub mod sched {
+    use super::{read_padded, write_padded};
+
+    /// Layout version of [`SchedStats`]
+    pub const SCHED_STATS_VERSION: u32 = 1;
+
+    /// Buckets of the ready latency histogram
+    ///
+    /// Bucket 0 counts waits under 1 µs, bucket `i` waits from 2^(i-1) to
+    /// 2^i µs, and the last bucket everything longer.
+    pub const LATENCY_BUCKETS: usize = 24;
+
+    /// Size of the NUL-padded policy name in [`SchedStats`]
+    pub const POLICY_NAME_LEN: usize = 16;
+
+    /// Histogram bucket of a wait of `ns` nanoseconds
+    pub fn latency_bucket(ns: u64) -> usize {
+        let us = ns / 1000;
+        let bucket = (u64::BITS - us.leading_zeros()) as usize;
+        bucket.min(LATENCY_BUCKETS - 1)
+    }
+
+    /// Upper bound of bucket `bucket` in microseconds (`None` for the last,
+    /// open-ended one)
+    pub fn latency_bucket_limit_us(bucket: usize) -> Option<u64> {
+        (bucket < LATENCY_BUCKETS - 1).then(|| 1 << bucket)
+    }
+
+    /// System-wide scheduler counters, filled in by `sys_sched_stats`
+    ///
+    /// Everything counts from boot; tools such as `top` show the
+    /// difference between two reads.
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct SchedStats {
+        /// Record layout version (`SCHED_STATS_VERSION`)
+        pub version: u32,
+        /// CPUs online
+        pub cpus: u32,
+        /// Timer interrupt rate, to turn `ProcessInfo::ticks` into time
+        pub timer_hz: u32,
+        /// Reserved, 0
+        pub reserved: u32,
+        /// Nanoseconds since boot when the record was filled in
+        pub uptime_ns: u64,
+        /// Threads switched onto a CPU
+        pub switches: u64,
+        /// Switches away after blocking, sleeping or exiting
+        pub voluntary_switches: u64,
+        /// Switches away from a thread that was still runnable
+        pub involuntary_switches: u64,
+        /// Time slices that ran out, whether or not another thread was
+        /// waiting to take over
+        pub quantum_expirations: u64,
+        /// Nanoseconds the CPUs spent halted with nothing to run, summed
+        pub idle_ns: u64,
+        /// How long threads waited Ready before running, by
+        /// [`latency_bucket`]
+        pub latency: [u64; LATENCY_BUCKETS],
+        /// Name of the scheduling policy, NUL-padded
+        pub policy: [u8; POLICY_NAME_LEN],
+    }
+
+    impl SchedStats {
+        /// An all-zero record, useful for preparing output buffers
+        pub const fn empty() -> Self {
+            SchedStats {
+                version: SCHED_STATS_VERSION,
+                cpus: 0,
+                timer_hz: 0,
+                reserved: 0,
+                uptime_ns: 0,
+                switches: 0,
+                voluntary_switches: 0,
+                involuntary_switches: 0,
+                quantum_expirations: 0,
+                idle_ns: 0,
+                latency: [0; LATENCY_BUCKETS],
+                policy: [0; POLICY_NAME_LEN],
+            }
+        }
+
+        /// Policy name up to the first NUL byte
+        pub fn policy(&self) -> &str {
+            read_padded(&self.policy)
+        }
+
+        /// Copy `name` into the record, truncating to `POLICY_NAME_LEN`
+        pub fn set_policy(&mut self, name: &str) {
+            write_padded(&mut self.policy, name);
+        }
+    }
+}
+
+/// Clocks read with `sys_clock_gettime`
+pub mod time {
+    /// Time since boot; never jumps, not affected by setting the date
+    pub const CLOCK_MONOTONIC: u32 = 1;
+
+    /// Nanoseconds in a second
+    pub const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+    /// A point in time, as written by `sys_clock_gettime` (same layout as
+    /// C's `struct timespec`)
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Timespec {
+        /// Whole seconds
+        pub tv_sec: i64,
+        /// Nanoseconds past `tv_sec` (0-999,999,999)
+        pub tv_nsec: i64,
+    }
+
+    impl Timespec {
+        /// Split a nanosecond count
+        pub fn from_nanos(nanos: u64) -> Self {
+            Timespec {
+                tv_sec: (nanos / NANOS_PER_SEC) as i64,
+                tv_nsec: (nanos % NANOS_PER_SEC) as i64,
+            }
+        }
+
+        /// Total nanoseconds
+        pub fn as_nanos(&self) -> u64 {
+            self.tv_sec as u64 * NANOS_PER_SEC + self.tv_nsec as u64
+        }
+    }
+}
+
 /// Error types
 #[derive(Debug, Clone, Copy)]
 pub enum OrbitalError {
@@ -65,3 +419,19 @@ pub enum OrbitalError {
     /// Permission denied
     PermissionDenied,
 }
+
+/// Read a NUL-padded UTF-8 field
+fn read_padded(field: &[u8]) -> &str {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..len]).unwrap_or("?")
+}
+
+/// Fill a NUL-padded field, truncating at a character boundary
+fn write_padded(field: &mut [u8], value: &str) {
+    let mut len = value.len().min(field.len());
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    field.fill(0);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
@@ -1,11 +1,15 @@
 //! Build script for Orbital Kernel
 //!
-//! Compiles and embeds the userspace binary into the kernel.
-//! This allows the kernel to load and execute it as a userspace process.
+//! Compiles and embeds the userspace binaries into the kernel.
+//! This allows the kernel to load and execute them as userspace processes.
 
 use std::path::PathBuf;
 
 fn main() {
+    println!("cargo::rustc-check-cfg=cfg(have_cli_binary)");
+    println!("cargo::rustc-check-cfg=cfg(have_init_binary)");
+    println!("cargo::rustc-check-cfg=cfg(have_managementd_binary)");
+
     // Phase 4.1: Use minimal userspace shell (1.2 KB, compiled for x86_64-orbital)
     let cli_binary_path = PathBuf::from("../userspace/minimal/target/x86_64-orbital/release/minimal-shell");
     
@@ -26,5 +30,34 @@ fn main() {
 
     // Tell cargo to rerun if minimal shell source changes
     println!("cargo:rerun-if-changed=../userspace/minimal/src");
+
+    // Timer interrupt rate, read by `clock::TIMER_HZ`
+    println!("cargo:rerun-if-env-changed=ORBITAL_TIMER_HZ");
+
+    // Soft-lockup action, read by `watchdog::boot_action`
+    println!("cargo:rerun-if-env-changed=ORBITAL_WATCHDOG");
+
+    // PID 1 and the services it starts
+    embed("init", "init", "have_init_binary");
+    embed("managementd", "managementd", "have_managementd_binary");
 }
 
+/// Embed `userspace/<dir>/.../release/<bin>` if it has been built
+fn embed(dir: &str, bin: &str, cfg: &str) {
+    let path = PathBuf::from(format!("../userspace/{dir}/target/x86_64-orbital/release/{bin}"));
+
+    if path.exists() {
+        println!("cargo:rerun-if-changed={}", path.display());
+        println!("cargo:rustc-cfg={cfg}");
+        println!(
+            "cargo:warning=Embedding {} ({} bytes)",
+            bin,
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        );
+    } else {
+        eprintln!("Warning: {} binary not found at {:?}", bin, path);
+        eprintln!("  cd userspace/{dir} && cargo build --release");
+    }
+
+    println!("cargo:rerun-if-changed=../userspace/{dir}/src");
+}
@@ -0,0 +1,418 @@
+//! ACPI tables - just enough to find the interrupt controllers and timers
+//!
+//! The firmware leaves a Root System Description Pointer (RSDP) in the
+//! first KiB of the EBDA or in the BIOS area below 1 MiB. It points at the
+//! RSDT (or, from ACPI 2.0, the XSDT): a list of the physical addresses of
+//! the other tables. Two are read here: the MADT (signature "APIC"), which
+//! lists the CPUs' local APICs, the IOAPICs, and the ISA IRQs that are not
+//! wired to the IOAPIC input of the same number; and the HPET table, which
+//! gives the address of the HPET's registers.
+//!
+//! Tables are read through the bootloader's mapping of physical memory, so
+//! `init` must run after `memory::install`.
+
+use alloc::vec::Vec;
+use conquer_once::spin::OnceCell;
+use x86_64::PhysAddr;
+
+/// Signature of the RSDP, on a 16-byte boundary
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+
+/// Signature of the MADT
+const MADT_SIGNATURE: &[u8; 4] = b"APIC";
+
+/// Signature of the HPET description table
+const HPET_SIGNATURE: &[u8; 4] = b"HPET";
+
+/// Length of the header every system description table starts with
+const SDT_HEADER_LEN: usize = 36;
+
+/// Real-mode pointer to the EBDA (segment) in the BIOS data area
+const EBDA_POINTER: u64 = 0x40E;
+
+/// BIOS read-only area searched when the EBDA has no RSDP
+const BIOS_AREA: (u64, usize) = (0xE0000, 0x20000);
+
+/// Sanity limit on table lengths; real MADTs are a few hundred bytes
+const MAX_TABLE_LEN: usize = 64 * 1024;
+
+/// MADT entry types
+const ENTRY_LOCAL_APIC: u8 = 0;
+const ENTRY_IO_APIC: u8 = 1;
+const ENTRY_INTERRUPT_OVERRIDE: u8 = 2;
+const ENTRY_LOCAL_APIC_ADDRESS: u8 = 5;
+
+/// MADT flag: the machine also has 8259 PICs
+const MADT_PCAT_COMPAT: u32 = 1;
+
+/// Local APIC flags: usable now, or can be brought online
+const LOCAL_APIC_ENABLED: u32 = 1;
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 2;
+
+/// HPET table body: block ID, base address, number, minimum tick, flags
+const HPET_TABLE_LEN: usize = 20;
+
+/// Generic address structure: the register block is in memory space
+const GAS_SYSTEM_MEMORY: u8 = 0;
+
+/// A CPU, as the MADT lists it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cpu {
+    pub processor_id: u8,
+    pub apic_id: u8,
+}
+
+/// An IOAPIC and the first global system interrupt (GSI) it handles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoApicInfo {
+    pub id: u8,
+    pub address: u32,
+    pub gsi_base: u32,
+}
+
+/// How an ISA IRQ reaches the IOAPICs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqRoute {
+    /// IOAPIC input (global system interrupt)
+    pub gsi: u32,
+    pub active_low: bool,
+    pub level_triggered: bool,
+}
+
+/// What the kernel uses of the MADT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Madt {
+    /// Physical address of every CPU's local APIC registers
+    pub local_apic_address: u64,
+    /// Whether 8259 PICs are present (and must be masked)
+    pub has_8259: bool,
+    /// Usable CPUs, in table order (the boot CPU is normally first)
+    pub cpus: Vec<Cpu>,
+    pub io_apics: Vec<IoApicInfo>,
+    /// ISA IRQs that are not identity-mapped or not edge/active-high
+    overrides: Vec<(u8, IrqRoute)>,
+}
+
+impl Madt {
+    /// Parse a MADT, header included
+    ///
+    /// Returns `None` if the table is truncated or not a MADT. Unknown
+    /// entry types are skipped.
+    pub fn parse(table: &[u8]) -> Option<Madt> {
+        if table.len() < SDT_HEADER_LEN + 8 || &table[..4] != MADT_SIGNATURE {
+            return None;
+        }
+        let len = read_u32(table, 4)? as usize;
+        let table = table.get(..len)?;
+
+        let mut madt = Madt {
+            local_apic_address: read_u32(table, SDT_HEADER_LEN)? as u64,
+            has_8259: read_u32(table, SDT_HEADER_LEN + 4)? & MADT_PCAT_COMPAT != 0,
+            cpus: Vec::new(),
+            io_apics: Vec::new(),
+            overrides: Vec::new(),
+        };
+
+        let mut offset = SDT_HEADER_LEN + 8;
+        while offset + 2 <= table.len() {
+            let kind = table[offset];
+            let entry_len = table[offset + 1] as usize;
+            if entry_len < 2 {
+                return None;
+            }
+            let entry = table.get(offset..offset + entry_len)?;
+            match kind {
+                ENTRY_LOCAL_APIC if entry_len >= 8 => {
+                    let flags = read_u32(entry, 4)?;
+                    if flags & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE) != 0 {
+                        madt.cpus.push(Cpu {
+                            processor_id: entry[2],
+                            apic_id: entry[3],
+                        });
+                    }
+                }
+                ENTRY_IO_APIC if entry_len >= 12 => madt.io_apics.push(IoApicInfo {
+                    id: entry[2],
+                    address: read_u32(entry, 4)?,
+                    gsi_base: read_u32(entry, 8)?,
+                }),
+                ENTRY_INTERRUPT_OVERRIDE if entry_len >= 10 => {
+                    // Bus 0 is ISA; nothing else is defined
+                    if entry[2] == 0 {
+                        let flags = read_u16(entry, 8)?;
+                        madt.overrides.push((
+                            entry[3],
+                            IrqRoute {
+                                gsi: read_u32(entry, 4)?,
+                                // MPS INTI flags: 0b11 is active low / level
+                                active_low: flags & 0b11 == 0b11,
+                                level_triggered: (flags >> 2) & 0b11 == 0b11,
+                            },
+                        ));
+                    }
+                }
+                ENTRY_LOCAL_APIC_ADDRESS if entry_len >= 12 => {
+                    madt.local_apic_address = read_u64(entry, 4)?;
+                }
+                _ => {}
+            }
+            offset += entry_len;
+        }
+
+        Some(madt)
+    }
+
+    /// The IOAPIC input, polarity and trigger mode of an ISA IRQ
+    ///
+    /// ISA IRQs default to the GSI of the same number, edge-triggered and
+    /// active high, unless the MADT overrides them.
+    pub fn isa_route(&self, irq: u8) -> IrqRoute {
+        self.overrides
+            .iter()
+            .find(|&&(source, _)| source == irq)
+            .map(|&(_, route)| route)
+            .unwrap_or(IrqRoute {
+                gsi: irq as u32,
+                active_low: false,
+                level_triggered: false,
+            })
+    }
+}
+
+/// The MADT, once `init` has found it
+static MADT: OnceCell<Madt> = OnceCell::uninit();
+
+/// Find and parse the MADT
+///
+/// Returns `None` if there is no valid RSDP or MADT (no ACPI, or
+/// `memory::install` has not run).
+pub fn init() -> Option<&'static Madt> {
+    if let Ok(madt) = MADT.try_get() {
+        return Some(madt);
+    }
+    let table = find_table(MADT_SIGNATURE)?;
+    let madt = Madt::parse(table)?;
+    MADT.try_init_once(|| madt).ok()?;
+    MADT.try_get().ok()
+}
+
+/// The MADT, if `init` found one
+pub fn madt() -> Option<&'static Madt> {
+    MADT.try_get().ok()
+}
+
+/// Physical address of the HPET registers, from an HPET table
+///
+/// Returns `None` if the table is truncated, not an HPET table, or places
+/// the registers outside system memory.
+pub fn parse_hpet(table: &[u8]) -> Option<u64> {
+    if table.len() < SDT_HEADER_LEN + HPET_TABLE_LEN || &table[..4] != HPET_SIGNATURE {
+        return None;
+    }
+    // The event timer block ID, then a generic address structure
+    let address_space = table[SDT_HEADER_LEN + 4];
+    if address_space != GAS_SYSTEM_MEMORY {
+        return None;
+    }
+    read_u64(table, SDT_HEADER_LEN + 8).filter(|&address| address != 0)
+}
+
+/// Find the HPET through its ACPI table
+///
+/// Returns `None` if there is no HPET table (or no ACPI, or
+/// `memory::install` has not run).
+pub fn hpet_address() -> Option<u64> {
+    parse_hpet(find_table(HPET_SIGNATURE)?)
+}
+
+/// Bytes of physical memory
+///
+/// # Safety
+/// The range must be memory the firmware set aside (BIOS areas, ACPI
+/// tables) so nothing else writes it while the slice lives.
+unsafe fn phys_bytes(addr: u64, len: usize) -> Option<&'static [u8]> {
+    let virt = crate::memory::phys_to_virt(PhysAddr::new(addr))?;
+    Some(unsafe { core::slice::from_raw_parts(virt.as_ptr(), len) })
+}
+
+/// Whether a structure's bytes sum to zero, as ACPI requires
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+/// Search a physical range for the RSDP
+fn scan_rsdp(start: u64, len: usize) -> Option<&'static [u8]> {
+    let area = unsafe { phys_bytes(start, len)? };
+    area.chunks_exact(16)
+        .enumerate()
+        .filter(|(_, chunk)| chunk.starts_with(RSDP_SIGNATURE))
+        .find_map(|(index, _)| {
+            let rsdp = area.get(index * 16..index * 16 + 20)?;
+            checksum_ok(rsdp).then(|| &area[index * 16..])
+        })
+}
+
+/// Find the RSDP: first KiB of the EBDA, then the BIOS area
+fn find_rsdp() -> Option<&'static [u8]> {
+    let ebda_segment = unsafe { phys_bytes(EBDA_POINTER, 2)? };
+    let ebda = (u16::from_le_bytes([ebda_segment[0], ebda_segment[1]]) as u64) << 4;
+    let from_ebda = if ebda != 0 {
+        scan_rsdp(ebda, 1024)
+    } else {
+        None
+    };
+    from_ebda.or_else(|| scan_rsdp(BIOS_AREA.0, BIOS_AREA.1))
+}
+
+/// A whole system description table at `addr`, if its checksum is right
+fn read_table(addr: u64) -> Option<&'static [u8]> {
+    let header = unsafe { phys_bytes(addr, SDT_HEADER_LEN)? };
+    let len = read_u32(header, 4)? as usize;
+    if !(SDT_HEADER_LEN..=MAX_TABLE_LEN).contains(&len) {
+        return None;
+    }
+    let table = unsafe { phys_bytes(addr, len)? };
+    checksum_ok(table).then_some(table)
+}
+
+/// Find a table by signature through the XSDT (ACPI 2.0+) or the RSDT
+fn find_table(signature: &[u8; 4]) -> Option<&'static [u8]> {
+    let rsdp = find_rsdp()?;
+    let revision = rsdp[15];
+
+    // ACPI 2.0 RSDPs are 36 bytes and point at the 64-bit XSDT
+    let xsdt = if revision >= 2 {
+        rsdp.get(..36)
+            .filter(|rsdp| checksum_ok(rsdp))
+            .and_then(|rsdp| read_u64(rsdp, 24))
+            .filter(|&addr| addr != 0)
+    } else {
+        None
+    };
+    let (root, entry_size) = match xsdt {
+        Some(addr) => (read_table(addr)?, 8),
+        None => (read_table(read_u32(rsdp, 16)? as u64)?, 4),
+    };
+
+    root[SDT_HEADER_LEN..]
+        .chunks_exact(entry_size)
+        .filter_map(|entry| match entry_size {
+            8 => read_u64(entry, 0),
+            _ => read_u32(entry, 0).map(u64::from),
+        })
+        .filter_map(read_table)
+        .find(|table| &table[..4] == signature)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A MADT like QEMU's: two CPUs (one disabled), an IOAPIC, and the
+    /// PIT's IRQ 0 moved to GSI 2
+    fn qemu_madt() -> Vec<u8> {
+        let mut table = Vec::new();
+        table.extend_from_slice(MADT_SIGNATURE);
+        table.extend_from_slice(&[0; SDT_HEADER_LEN - 4]);
+        table.extend_from_slice(&0xFEE0_0000u32.to_le_bytes());
+        table.extend_from_slice(&MADT_PCAT_COMPAT.to_le_bytes());
+        table.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 0, 0, 1, 0, 0, 0]);
+        table.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 1, 1, 0, 0, 0, 0]);
+        table.extend_from_slice(&[ENTRY_IO_APIC, 12, 0, 0]);
+        table.extend_from_slice(&0xFEC0_0000u32.to_le_bytes());
+        table.extend_from_slice(&0u32.to_le_bytes());
+        table.extend_from_slice(&[ENTRY_INTERRUPT_OVERRIDE, 10, 0, 0]);
+        table.extend_from_slice(&2u32.to_le_bytes());
+        table.extend_from_slice(&0u16.to_le_bytes());
+        table.extend_from_slice(&[ENTRY_INTERRUPT_OVERRIDE, 10, 0, 9]);
+        table.extend_from_slice(&9u32.to_le_bytes());
+        table.extend_from_slice(&0b1111u16.to_le_bytes());
+        table.extend_from_slice(&[4, 6, 0xFF, 0, 0, 1]); // Local APIC NMI: skipped
+        let len = table.len() as u32;
+        table[4..8].copy_from_slice(&len.to_le_bytes());
+        table
+    }
+
+    #[test_case]
+    fn test_parse_madt() {
+        let madt = Madt::parse(&qemu_madt()).unwrap();
+        assert_eq!(madt.local_apic_address, 0xFEE0_0000);
+        assert!(madt.has_8259);
+        assert_eq!(
+            madt.cpus,
+            [Cpu {
+                processor_id: 0,
+                apic_id: 0
+            }]
+        );
+        assert_eq!(
+            madt.io_apics,
+            [IoApicInfo {
+                id: 0,
+                address: 0xFEC0_0000,
+                gsi_base: 0
+            }]
+        );
+
+        assert_eq!(madt.isa_route(0).gsi, 2);
+        assert_eq!(
+            madt.isa_route(1),
+            IrqRoute {
+                gsi: 1,
+                active_low: false,
+                level_triggered: false
+            }
+        );
+        let sci = madt.isa_route(9);
+        assert!(sci.active_low && sci.level_triggered);
+    }
+
+    #[test_case]
+    fn test_parse_bad_madt() {
+        let mut table = qemu_madt();
+        assert_eq!(Madt::parse(&table[..20]), None);
+        // An entry running past the end of the table
+        let len = table.len() as u32 + 4;
+        table[4..8].copy_from_slice(&len.to_le_bytes());
+        table.extend_from_slice(&[ENTRY_IO_APIC, 12, 0, 0]);
+        assert_eq!(Madt::parse(&table), None);
+        assert!(!checksum_ok(&[1, 2, 3]));
+        assert!(checksum_ok(&[1, 2, 253]));
+    }
+
+    #[test_case]
+    fn test_parse_hpet() {
+        let mut table = Vec::new();
+        table.extend_from_slice(HPET_SIGNATURE);
+        table.extend_from_slice(&[0; SDT_HEADER_LEN - 4]);
+        table.extend_from_slice(&0x8086_A201u32.to_le_bytes());
+        table.extend_from_slice(&[GAS_SYSTEM_MEMORY, 64, 0, 0]);
+        table.extend_from_slice(&0xFED0_0000u64.to_le_bytes());
+        table.extend_from_slice(&[0, 0x80, 0, 0]);
+        assert_eq!(parse_hpet(&table), Some(0xFED0_0000));
+
+        // Truncated, or registers in I/O space
+        assert_eq!(parse_hpet(&table[..SDT_HEADER_LEN + 12]), None);
+        table[SDT_HEADER_LEN + 4] = 1;
+        assert_eq!(parse_hpet(&table), None);
+        assert_eq!(parse_hpet(&qemu_madt()), None);
+    }
+}
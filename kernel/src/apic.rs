@@ -0,0 +1,386 @@
+//! Local APIC and IOAPIC interrupt controllers
+//!
+//! At boot interrupts come through the legacy 8259 PICs and the tick from
+//! the PIT. When the MADT (see `acpi`) lists an IOAPIC, `init` switches to
+//! the APICs instead:
+//!
+//! - the PICs are masked;
+//! - this CPU's local APIC is enabled, and its timer, calibrated against
+//!   the PIT, becomes the scheduling tick at `clock::TIMER_HZ`;
+//! - ISA IRQs are routed through the IOAPIC to the vectors the PICs used,
+//!   so the IDT does not change.
+//!
+//! Interrupt handlers acknowledge with `interrupts::end_of_interrupt`,
+//! which picks the controller in use. Machines without an APIC or an MADT
+//! keep the PICs and the PIT.
+//!
+//! The other CPUs are started with inter-processor interrupts
+//! (`send_init`, `send_startup`; see `smp`) and enable their own local
+//! APIC with `init_ap`. Every CPU's tick comes from its own APIC timer;
+//! device interrupts all go to the boot CPU.
+
+use crate::acpi::{IrqRoute, Madt};
+use crate::interrupts::{InterruptIndex, PICS};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::registers::model_specific::Msr;
+use x86_64::{PhysAddr, VirtAddr};
+
+/// Vector of the local APIC's spurious interrupt; needs no EOI
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// IA32_APIC_BASE MSR: physical base and global enable bit
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+
+/// Local APIC registers (offsets from the base)
+const LAPIC_ID: usize = 0x20;
+const LAPIC_TPR: usize = 0x80;
+const LAPIC_EOI: usize = 0xB0;
+const LAPIC_SVR: usize = 0xF0;
+const LAPIC_ICR_LOW: usize = 0x300;
+const LAPIC_ICR_HIGH: usize = 0x310;
+const LAPIC_LVT_TIMER: usize = 0x320;
+const LAPIC_LVT_LINT0: usize = 0x350;
+const LAPIC_TIMER_INITIAL: usize = 0x380;
+const LAPIC_TIMER_CURRENT: usize = 0x390;
+const LAPIC_TIMER_DIVIDE: usize = 0x3E0;
+
+/// Spurious vector register: software enable
+const SVR_ENABLE: u32 = 1 << 8;
+/// Local vector table: entry masked
+const LVT_MASKED: u32 = 1 << 16;
+/// Timer LVT: reload the initial count when it reaches zero
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+/// Timer divide configuration: bus clock / 16
+const TIMER_DIVIDE_16: u32 = 0b0011;
+
+/// Interrupt command register: delivery modes, level and status bits
+const ICR_INIT: u32 = 0b101 << 8;
+const ICR_STARTUP: u32 = 0b110 << 8;
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+const ICR_PENDING: u32 = 1 << 12;
+
+/// IOAPIC registers: index and data windows, and the indexed registers
+const IOREGSEL: usize = 0x00;
+const IOWIN: usize = 0x10;
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION: u32 = 0x10;
+
+/// Redirection entry bits
+const REDIRECT_ACTIVE_LOW: u32 = 1 << 13;
+const REDIRECT_LEVEL: u32 = 1 << 15;
+const REDIRECT_MASKED: u32 = 1 << 16;
+
+/// ISA IRQ of the PS/2 keyboard
+const KEYBOARD_IRQ: u8 = 1;
+
+/// Virtual address of the local APIC registers (0 = APIC not in use)
+static LAPIC_BASE: AtomicU64 = AtomicU64::new(0);
+
+/// Initial count of the APIC timer for a `clock::TIMER_HZ` tick, shared by
+/// every CPU (they run off the same bus clock)
+static TIMER_INITIAL: AtomicU32 = AtomicU32::new(0);
+
+/// The IOAPICs, once mapped
+static IO_APICS: Mutex<Vec<IoApic>> = Mutex::new(Vec::new());
+
+/// Whether interrupts are delivered by the APICs rather than the PICs
+pub fn is_enabled() -> bool {
+    LAPIC_BASE.load(Ordering::Relaxed) != 0
+}
+
+/// Signal the end of an interrupt to the local APIC
+pub fn end_of_interrupt() {
+    if let Some(lapic) = LocalApic::current() {
+        lapic.write(LAPIC_EOI, 0);
+    }
+}
+
+/// APIC ID of the running CPU, once the APIC is in use
+pub fn local_apic_id() -> Option<u8> {
+    LocalApic::current().map(|lapic| (lapic.read(LAPIC_ID) >> 24) as u8)
+}
+
+/// Send an INIT IPI to the CPU with APIC ID `apic_id`, resetting it into
+/// its wait-for-STARTUP state
+pub fn send_init(apic_id: u8) {
+    if let Some(lapic) = LocalApic::current() {
+        lapic.send_ipi(apic_id, ICR_INIT | ICR_LEVEL_ASSERT);
+    }
+}
+
+/// Send a STARTUP IPI to the CPU with APIC ID `apic_id`: it starts in real
+/// mode at physical address `page << 12`
+pub fn send_startup(apic_id: u8, page: u8) {
+    if let Some(lapic) = LocalApic::current() {
+        lapic.send_ipi(apic_id, ICR_STARTUP | page as u32);
+    }
+}
+
+/// Registers of the running CPU's local APIC
+#[derive(Clone, Copy)]
+struct LocalApic {
+    base: VirtAddr,
+}
+
+impl LocalApic {
+    fn current() -> Option<LocalApic> {
+        match LAPIC_BASE.load(Ordering::Relaxed) {
+            0 => None,
+            base => Some(LocalApic {
+                base: VirtAddr::new(base),
+            }),
+        }
+    }
+
+    fn read(self, reg: usize) -> u32 {
+        unsafe { core::ptr::read_volatile((self.base + reg as u64).as_ptr::<u32>()) }
+    }
+
+    fn write(self, reg: usize, value: u32) {
+        unsafe { core::ptr::write_volatile((self.base + reg as u64).as_mut_ptr::<u32>(), value) }
+    }
+
+    /// Send an IPI and wait until the APIC has delivered it
+    fn send_ipi(self, apic_id: u8, command: u32) {
+        self.write(LAPIC_ICR_HIGH, (apic_id as u32) << 24);
+        // Writing the low half sends it
+        self.write(LAPIC_ICR_LOW, command);
+        while self.read(LAPIC_ICR_LOW) & ICR_PENDING != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Enable the APIC in software and let every interrupt through
+    fn enable(self) {
+        unsafe {
+            let mut apic_base = Msr::new(IA32_APIC_BASE);
+            let value = apic_base.read();
+            apic_base.write(value | APIC_BASE_ENABLE);
+        }
+        self.write(LAPIC_SVR, SVR_ENABLE | SPURIOUS_VECTOR as u32);
+        self.write(LAPIC_TPR, 0);
+    }
+
+    /// Start the periodic scheduling tick
+    fn start_timer(self) {
+        self.write(LAPIC_TIMER_DIVIDE, TIMER_DIVIDE_16);
+        self.write(
+            LAPIC_LVT_TIMER,
+            LVT_TIMER_PERIODIC | InterruptIndex::Timer as u32,
+        );
+        self.write(LAPIC_TIMER_INITIAL, TIMER_INITIAL.load(Ordering::Relaxed));
+    }
+}
+
+/// An IOAPIC's registers and the GSIs it serves
+struct IoApic {
+    base: VirtAddr,
+    gsi_base: u32,
+    inputs: u32,
+}
+
+impl IoApic {
+    fn read(&self, reg: u32) -> u32 {
+        unsafe {
+            core::ptr::write_volatile((self.base + IOREGSEL as u64).as_mut_ptr::<u32>(), reg);
+            core::ptr::read_volatile((self.base + IOWIN as u64).as_ptr::<u32>())
+        }
+    }
+
+    fn write(&self, reg: u32, value: u32) {
+        unsafe {
+            core::ptr::write_volatile((self.base + IOREGSEL as u64).as_mut_ptr::<u32>(), reg);
+            core::ptr::write_volatile((self.base + IOWIN as u64).as_mut_ptr::<u32>(), value);
+        }
+    }
+
+    fn serves(&self, gsi: u32) -> bool {
+        (self.gsi_base..self.gsi_base + self.inputs).contains(&gsi)
+    }
+
+    /// Program the redirection entry of `gsi`
+    fn redirect(&self, gsi: u32, low: u32, apic_id: u8) {
+        let reg = IOAPIC_REDIRECTION + 2 * (gsi - self.gsi_base);
+        // Mask while the halves disagree
+        self.write(reg, REDIRECT_MASKED);
+        self.write(reg + 1, (apic_id as u32) << 24);
+        self.write(reg, low);
+    }
+}
+
+/// Low half of a redirection entry: fixed delivery to a physical APIC ID
+fn redirection_entry(vector: u8, route: IrqRoute) -> u32 {
+    let mut low = vector as u32;
+    if route.active_low {
+        low |= REDIRECT_ACTIVE_LOW;
+    }
+    if route.level_triggered {
+        low |= REDIRECT_LEVEL;
+    }
+    low
+}
+
+/// Whether the CPU has a local APIC
+fn has_apic() -> bool {
+    // CPUID.01H:EDX bit 9 reports an on-chip APIC
+    core::arch::x86_64::__cpuid(1).edx & (1 << 9) != 0
+}
+
+/// Local APIC timer ticks per second at `TIMER_DIVIDE_16`, measured
+/// against the PIT
+fn calibrate_timer(lapic: LocalApic) -> Option<u64> {
+    lapic.write(LAPIC_TIMER_DIVIDE, TIMER_DIVIDE_16);
+    lapic.write(LAPIC_LVT_TIMER, LVT_MASKED);
+    let started = crate::clock::pit_oneshot(crate::clock::CALIBRATION_MS, || {
+        lapic.write(LAPIC_TIMER_INITIAL, u32::MAX)
+    });
+    let elapsed = u32::MAX - lapic.read(LAPIC_TIMER_CURRENT);
+    lapic.write(LAPIC_TIMER_INITIAL, 0);
+    if !started || elapsed == 0 {
+        return None;
+    }
+    Some(elapsed as u64 * 1000 / crate::clock::CALIBRATION_MS)
+}
+
+/// Route an ISA IRQ to `vector` on this CPU
+///
+/// Returns false if no IOAPIC serves the IRQ's GSI.
+pub fn route_isa_irq(irq: u8, vector: u8) -> bool {
+    match crate::acpi::madt() {
+        Some(madt) => route_gsi(madt.isa_route(irq), vector),
+        None => false,
+    }
+}
+
+/// Route a GSI to `vector` on this CPU, with the trigger mode and polarity
+/// in `route`
+///
+/// Returns false if the APICs are not in use or no IOAPIC serves the GSI.
+pub fn route_gsi(route: IrqRoute, vector: u8) -> bool {
+    let apic_id = match local_apic_id() {
+        Some(apic_id) => apic_id,
+        None => return false,
+    };
+    let io_apics = IO_APICS.lock();
+    match io_apics.iter().find(|io_apic| io_apic.serves(route.gsi)) {
+        Some(io_apic) => {
+            io_apic.redirect(route.gsi, redirection_entry(vector, route), apic_id);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Map the IOAPICs and mask all their inputs
+fn init_io_apics(madt: &Madt) -> Option<()> {
+    let mut io_apics = IO_APICS.lock();
+    for info in &madt.io_apics {
+        let base = crate::memory::map_mmio(PhysAddr::new(info.address as u64))?;
+        let mut io_apic = IoApic {
+            base,
+            gsi_base: info.gsi_base,
+            inputs: 0,
+        };
+        io_apic.inputs = ((io_apic.read(IOAPIC_VERSION) >> 16) & 0xFF) + 1;
+        for input in 0..io_apic.inputs {
+            io_apic.write(IOAPIC_REDIRECTION + 2 * input, REDIRECT_MASKED);
+        }
+        io_apics.push(io_apic);
+    }
+    Some(())
+}
+
+/// Switch interrupt delivery from the PICs to the APICs
+///
+/// Call once, after `memory::install`. Returns false, leaving the PICs and
+/// the PIT in charge, if the machine has no APIC, no MADT or no IOAPIC, or
+/// the APIC timer cannot be calibrated.
+pub fn init() -> bool {
+    if is_enabled() || !has_apic() {
+        return is_enabled();
+    }
+    let madt = match crate::acpi::init() {
+        Some(madt) if !madt.io_apics.is_empty() => madt,
+        _ => return false,
+    };
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let base = match crate::memory::map_mmio(PhysAddr::new(madt.local_apic_address)) {
+            Some(base) => base,
+            None => return false,
+        };
+        let lapic = LocalApic { base };
+        lapic.enable();
+
+        let timer_hz = match calibrate_timer(lapic) {
+            Some(hz) => hz,
+            None => return false,
+        };
+        if init_io_apics(madt).is_none() {
+            return false;
+        }
+
+        // LINT0 carries the PICs in virtual wire mode; they are going away
+        lapic.write(LAPIC_LVT_LINT0, LVT_MASKED);
+        unsafe {
+            PICS.lock().disable();
+        }
+        LAPIC_BASE.store(base.as_u64(), Ordering::Relaxed);
+
+        route_isa_irq(KEYBOARD_IRQ, InterruptIndex::Keyboard as u8);
+
+        let initial = (timer_hz + crate::clock::TIMER_HZ / 2) / crate::clock::TIMER_HZ;
+        TIMER_INITIAL.store(initial.clamp(1, u32::MAX as u64) as u32, Ordering::Relaxed);
+        lapic.start_timer();
+        true
+    })
+}
+
+/// Enable the local APIC of an application processor and start its tick
+///
+/// Called by each AP as it comes up (see `smp`), with interrupts disabled.
+/// Its LINT0 is masked: only the boot CPU ever saw the PICs.
+pub fn init_ap() {
+    let lapic = match LocalApic::current() {
+        Some(lapic) => lapic,
+        None => return,
+    };
+    lapic.enable();
+    lapic.write(LAPIC_LVT_LINT0, LVT_MASKED);
+    lapic.start_timer();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_redirection_entry() {
+        let isa = IrqRoute {
+            gsi: 1,
+            active_low: false,
+            level_triggered: false,
+        };
+        assert_eq!(redirection_entry(33, isa), 33);
+
+        let sci = IrqRoute {
+            gsi: 9,
+            active_low: true,
+            level_triggered: true,
+        };
+        assert_eq!(
+            redirection_entry(41, sci),
+            41 | REDIRECT_ACTIVE_LOW | REDIRECT_LEVEL
+        );
+    }
+
+    #[test_case]
+    fn test_pic_mode_by_default() {
+        // Tests run on the PICs: nothing calls `init`
+        assert!(!is_enabled());
+        assert_eq!(local_apic_id(), None);
+    }
+}
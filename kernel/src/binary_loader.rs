@@ -4,6 +4,7 @@
 //! Provides mechanism for loading embedded or external binaries into userspace
 
 use crate::process::Process;
+use crate::stack::{StackError, StackSpec};
 use crate::task::executor::Executor;
 
 /// Embedded userspace minimal shell (Phase 4.1)
@@ -14,11 +15,38 @@ const ORBITAL_CLI_BINARY: &[u8] = include_bytes!(concat!(
     "/../userspace/minimal/target/x86_64-orbital/release/minimal-shell"
 ));
 
+/// Embedded init binary, started as PID 1
+#[cfg(have_init_binary)]
+const ORBITAL_INIT_BINARY: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../userspace/init/target/x86_64-orbital/release/init"
+));
+
+/// Embedded management daemon, started by init
+#[cfg(have_managementd_binary)]
+const ORBITAL_MANAGEMENTD_BINARY: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../userspace/managementd/target/x86_64-orbital/release/managementd"
+));
+
 /// Load a binary blob and create a userspace process
 ///
 /// Takes raw binary code, allocates memory, sets up stack and entry point.
 /// Returns a process that can be executed by the task executor.
 pub fn load_binary(binary: &[u8], name: &str) -> Result<Process, &'static str> {
+    load_binary_with(binary, |stack| Process::new_with_name(name, stack))
+}
+
+/// Load the init binary as PID 1
+pub fn load_init(binary: &[u8]) -> Result<Process, &'static str> {
+    load_binary_with(binary, Process::new_init)
+}
+
+/// Load a binary into a process built by `new_process`
+fn load_binary_with(
+    binary: &[u8],
+    new_process: impl FnOnce(StackSpec) -> Result<Process, StackError>,
+) -> Result<Process, &'static str> {
     if binary.is_empty() {
         return Err("Binary is empty");
     }
@@ -27,16 +55,20 @@ pub fn load_binary(binary: &[u8], name: &str) -> Result<Process, &'static str> {
     let elf_info = crate::elf_loader::parse_elf(binary)
         .map_err(|_| "Invalid ELF binary format")?;
 
-    // Create process structure
-    let mut process = Process::new_with_name(name);
-
-    // Check binary fits in process stack
-    if binary.len() > crate::process::TASK_STACK_SIZE {
+    // Create process structure, with room for the binary below a
+    // default-sized stack. The image lives in the stack, so this one is
+    // fixed rather than growable
+    let stack_size = binary.len().next_multiple_of(crate::stack::PAGE_SIZE)
+        + crate::stack::DEFAULT_STACK_SIZE;
+    if stack_size > crate::stack::MAX_STACK_SIZE {
         return Err("Binary too large for process stack");
     }
-    
-    // Copy entire ELF binary into process stack
-    let stack_bytes = &mut process.stack[..];
+    let mut process = new_process(StackSpec::Fixed(stack_size)).map_err(|e| e.as_str())?;
+
+    // Copy entire ELF binary into the main thread's stack
+    let main_thread = process.main_thread_mut();
+    let stack_top = main_thread.stack.top();
+    let stack_bytes = main_thread.stack.as_mut_slice();
     stack_bytes[..binary.len()].copy_from_slice(binary);
     
     // Calculate base address of binary in stack
@@ -44,14 +76,21 @@ pub fn load_binary(binary: &[u8], name: &str) -> Result<Process, &'static str> {
     
     // Set entry point to ELF entry point offset from stack base
     // ELF entry point is a virtual address, convert to physical
-    process.entry_point = stack_base + elf_info.entry_point as usize;
+    let entry_point = stack_base + elf_info.entry_point as usize;
+    main_thread.entry_point = entry_point;
 
     
-    // Set up context for userspace execution:
-    // RIP points to _start() of the binary
-    // RSP points to near the top of stack (will grow downward)
-    process.saved_context.rip = stack_base as u64;
-    process.saved_context.rsp = (stack_base + crate::process::TASK_STACK_SIZE - 8) as u64;
+    // Set up context for userspace execution: the task wrapper calls
+    // _start() of the binary on the top of the stack (grows downward)
+    main_thread.saved_context = crate::process::TaskContext::new(entry_point as u64, stack_top);
+
+    // Give the initial thread its copy of the PT_TLS image
+    if let Some(segment) = elf_info.tls {
+        let block = crate::tls::TlsBlock::new(&segment, binary)
+            .ok_or("Invalid TLS segment")?;
+        main_thread.set_tls(block);
+    }
+    process.entry_point = entry_point;
     
     // Mark process as ready
     process.status = crate::process::ProcessStatus::Ready;
@@ -71,11 +110,37 @@ pub fn get_cli_binary() -> Option<&'static [u8]> {
     }
 }
 
-/// Execute userspace CLI as a task
+/// Get the embedded init binary
+pub fn get_init_binary() -> Option<&'static [u8]> {
+    #[cfg(have_init_binary)]
+    {
+        Some(ORBITAL_INIT_BINARY)
+    }
+    #[cfg(not(have_init_binary))]
+    {
+        None
+    }
+}
+
+/// Find an embedded program by name
 ///
-/// Phase 4.2: Loads the embedded minimal shell binary into a userspace process
-/// and spawns it as a task. The shell runs with userspace privileges via syscalls.
-pub fn execute_cli(executor: &mut Executor) -> Result<(), &'static str> {
+/// There is no filesystem yet, so these are the programs `sys_spawn` can
+/// start.
+pub fn find_program(name: &str) -> Option<&'static [u8]> {
+    match name {
+        "shell" => get_cli_binary(),
+        #[cfg(have_managementd_binary)]
+        "managementd" => Some(ORBITAL_MANAGEMENTD_BINARY),
+        _ => None,
+    }
+}
+
+/// Execute userspace CLI as a process
+///
+/// Phase 4.2: Loads the embedded minimal shell binary into a userspace process,
+/// adds it to the process table and queues it for the scheduler, like
+/// `sys_spawn` does. The shell runs with userspace privileges via syscalls.
+pub fn execute_cli() -> Result<(), &'static str> {
     match get_cli_binary() {
         Some(binary) => {
             crate::println!("[Phase 4.2] 🚀 Loading userspace shell...");
@@ -83,31 +148,15 @@ pub fn execute_cli(executor: &mut Executor) -> Result<(), &'static str> {
             
             // Load binary into a process structure
             let process = load_binary(binary, "orbital-shell")?;
-            let entry_point = process.entry_point;
+            let pid = process.pid();
             
-            crate::println!("[Phase 4.2] Entry point: 0x{:x}", entry_point);
-            crate::println!("[Phase 4.2] PID: {}", process.pid());
+            crate::println!("[Phase 4.2] Entry point: 0x{:x}", process.entry_point);
+            crate::println!("[Phase 4.2] PID: {}", pid);
             
-            // Transmute entry point to function pointer and execute
-            // Note: This is unsafe and a full implementation would use proper context switching
-            unsafe {
-                let entry_fn: extern "C" fn() -> ! = core::mem::transmute(entry_point);
-                
-                // For Phase 4.2, we'll call the entry point directly from an async task
-                // This allows it to run within the executor's event loop
-                use crate::task::Task;
-                
-                // We need to wrap this in an async context
-                // Create a simple async wrapper that will execute the binary
-                let shell_runner = async move {
-                    // Call the userspace entry point
-                    // Since it expects to run forever (no_main style), this won't return
-                    // So the task will block indefinitely on syscalls
-                    entry_fn();
-                };
-                
-                executor.spawn(Task::new(shell_runner));
+            if crate::process::insert_process(process) < 0 {
+                return Err("Process table is full");
             }
+            crate::scheduler::enqueue_process(pid);
             
             crate::println!("[Phase 4.2] ✅ Userspace shell spawned successfully");
             Ok(())
@@ -131,13 +180,20 @@ pub fn execute_binary(_binary: &[u8], name: &str, _executor: &mut Executor) -> R
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_binary_loader_rejects_empty() {
         let empty: &[u8] = &[];
         assert!(load_binary(empty, "test").is_err());
     }
 
-    #[test]
+    #[test_case]
+    fn test_find_program_rejects_unknown() {
+        assert!(find_program("no-such-program").is_none());
+        // init is started by the kernel, not spawned
+        assert!(find_program("init").is_none());
+    }
+
+    #[test_case]
     #[cfg(have_cli_binary)]
     fn test_cli_binary_available() {
         assert!(get_cli_binary().is_some());
@@ -0,0 +1,373 @@
+//! Hardware clocks - the PIT tick, the TSC and the choice of clock source
+//!
+//! Channel 0 of the 8254 PIT drives IRQ 0, the scheduler tick. Left alone
+//! it divides its 1.193182 MHz input by 65536, about 18.2 Hz; `init`
+//! programs it to `TIMER_HZ` (100 by default, or `ORBITAL_TIMER_HZ` at
+//! build time). Tick-based code converts with `TICK_NS`, the exact length
+//! of a tick at the divisor actually programmed.
+//!
+//! The monotonic clock reads the best counter available, its
+//! `ClockSource`. In order of preference: an invariant TSC (constant rate
+//! in every power state, and the cheapest to read), the HPET (see `hpet`),
+//! a TSC that may change rate, and at worst the ticks themselves. The TSC
+//! is calibrated at boot against a one-shot count on PIT channel 2, which
+//! needs no interrupts. Sources are offered as they come up during boot;
+//! when a better one is taken, time carries on from where the old one
+//! left it.
+
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use orbital_common::time::NANOS_PER_SEC;
+use x86_64::instructions::port::Port;
+
+/// Input clock of the PIT, in Hz
+pub const PIT_FREQUENCY: u64 = 1_193_182;
+
+/// Tick rate used when `ORBITAL_TIMER_HZ` is not set
+pub const DEFAULT_TIMER_HZ: u64 = 100;
+
+/// Slowest tick rate: the PIT divisor is 16 bits
+pub const MIN_TIMER_HZ: u64 = 19;
+
+/// Fastest tick rate we allow; the interrupt path is not free
+pub const MAX_TIMER_HZ: u64 = 1000;
+
+/// Timer interrupt rate, in Hz
+pub const TIMER_HZ: u64 = parse_hz(option_env!("ORBITAL_TIMER_HZ"));
+
+/// Length of a timer tick, in nanoseconds
+pub const TICK_NS: u64 = pit_divisor(TIMER_HZ) as u64 * NANOS_PER_SEC / PIT_FREQUENCY;
+
+/// Length of a timer calibration run, in milliseconds
+pub(crate) const CALIBRATION_MS: u64 = 10;
+
+/// Reads of the PIT status before calibration gives up
+const CALIBRATION_SPINS: u32 = 1_000_000;
+
+/// PIT mode/command register
+const PIT_COMMAND: u16 = 0x43;
+/// PIT channel 0 data port (IRQ 0)
+const PIT_CHANNEL0: u16 = 0x40;
+/// PIT channel 2 data port (gated through port 0x61)
+const PIT_CHANNEL2: u16 = 0x42;
+/// Keyboard controller port B: channel 2 gate, speaker enable, OUT2 status
+const PORT_B: u16 = 0x61;
+
+/// Channel 0, low then high byte, mode 2 (rate generator)
+const CHANNEL0_RATE: u8 = 0x34;
+/// Channel 2, low then high byte, mode 0 (interrupt on terminal count)
+const CHANNEL2_ONESHOT: u8 = 0xB0;
+
+const PORT_B_GATE2: u8 = 0x01;
+const PORT_B_SPEAKER: u8 = 0x02;
+const PORT_B_OUT2: u8 = 0x20;
+
+/// Calibrated TSC frequency in Hz (0 = not calibrated)
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+
+/// The `ClockSource` in use
+static SOURCE: AtomicU8 = AtomicU8::new(ClockSource::Ticks as u8);
+
+/// Counter value of the source when it was taken
+static SOURCE_BASE: AtomicU64 = AtomicU64::new(0);
+
+/// Monotonic time when the source was taken
+static SOURCE_OFFSET_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Counters the monotonic clock can be read from, worst first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum ClockSource {
+    /// Timer interrupts, counted: `TICK_NS` resolution
+    Ticks = 0,
+    /// A TSC whose rate may follow the CPU's power state
+    Tsc = 1,
+    /// The HPET main counter
+    Hpet = 2,
+    /// A TSC that runs at a constant rate in every power state
+    InvariantTsc = 3,
+}
+
+impl ClockSource {
+    fn from_u8(value: u8) -> ClockSource {
+        match value {
+            1 => ClockSource::Tsc,
+            2 => ClockSource::Hpet,
+            3 => ClockSource::InvariantTsc,
+            _ => ClockSource::Ticks,
+        }
+    }
+
+    /// Short name, as shown at boot
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSource::Ticks => "ticks",
+            ClockSource::Tsc => "tsc",
+            ClockSource::Hpet => "hpet",
+            ClockSource::InvariantTsc => "invariant tsc",
+        }
+    }
+}
+
+/// Parse `ORBITAL_TIMER_HZ`; a bad value fails the build
+const fn parse_hz(value: Option<&str>) -> u64 {
+    let bytes = match value {
+        Some(value) => value.as_bytes(),
+        None => return DEFAULT_TIMER_HZ,
+    };
+    assert!(!bytes.is_empty(), "ORBITAL_TIMER_HZ must be a number");
+    let mut hz = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i].is_ascii_digit(),
+            "ORBITAL_TIMER_HZ must be a number"
+        );
+        hz = hz * 10 + (bytes[i] - b'0') as u64;
+        i += 1;
+    }
+    assert!(
+        hz >= MIN_TIMER_HZ && hz <= MAX_TIMER_HZ,
+        "ORBITAL_TIMER_HZ must be between 19 and 1000"
+    );
+    hz
+}
+
+/// PIT divisor for a tick rate, rounded to the nearest rate available
+pub const fn pit_divisor(hz: u64) -> u16 {
+    ((PIT_FREQUENCY + hz / 2) / hz) as u16
+}
+
+/// Program the PIT, calibrate the TSC and take it as the clock source
+///
+/// Must run with interrupts disabled, before the PICs are unmasked.
+pub fn init() {
+    let divisor = pit_divisor(TIMER_HZ);
+    let mut command = Port::<u8>::new(PIT_COMMAND);
+    let mut channel0 = Port::<u8>::new(PIT_CHANNEL0);
+    unsafe {
+        command.write(CHANNEL0_RATE);
+        channel0.write(divisor as u8);
+        channel0.write((divisor >> 8) as u8);
+    }
+
+    if let Some(hz) = calibrate_tsc() {
+        TSC_HZ.store(hz, Ordering::Relaxed);
+        if has_invariant_tsc() {
+            offer(ClockSource::InvariantTsc);
+        } else {
+            offer(ClockSource::Tsc);
+        }
+    }
+}
+
+/// Whether the CPU has a time stamp counter
+fn has_tsc() -> bool {
+    // CPUID.01H:EDX bit 4 reports the TSC
+    core::arch::x86_64::__cpuid(1).edx & (1 << 4) != 0
+}
+
+/// Whether the TSC keeps a constant rate in every power state
+fn has_invariant_tsc() -> bool {
+    // CPUID.80000007H:EDX bit 8, if the extended leaf exists
+    let max_extended = core::arch::x86_64::__cpuid(0x8000_0000).eax;
+    max_extended >= 0x8000_0007 && core::arch::x86_64::__cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+/// Read the time stamp counter
+fn rdtsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Busy-wait `ms` milliseconds (at most 54) on PIT channel 2
+///
+/// `start` runs the moment the count starts, so what it measures from
+/// spans exactly `ms`. Returns false if the PIT never finished counting.
+/// Used to calibrate the other timers against the PIT.
+pub(crate) fn pit_oneshot(ms: u64, start: impl FnOnce()) -> bool {
+    let count = PIT_FREQUENCY * ms / 1000;
+    let mut command = Port::<u8>::new(PIT_COMMAND);
+    let mut channel2 = Port::<u8>::new(PIT_CHANNEL2);
+    let mut port_b = Port::<u8>::new(PORT_B);
+
+    unsafe {
+        // Gate low and speaker off while the count is loaded
+        let saved = port_b.read();
+        let quiet = saved & !(PORT_B_SPEAKER | PORT_B_GATE2);
+        port_b.write(quiet);
+        command.write(CHANNEL2_ONESHOT);
+        channel2.write(count as u8);
+        channel2.write((count >> 8) as u8);
+
+        // Raising the gate starts the count; OUT2 goes high when it ends
+        port_b.write(quiet | PORT_B_GATE2);
+        start();
+        let mut spins = 0;
+        while port_b.read() & PORT_B_OUT2 == 0 {
+            spins += 1;
+            if spins >= CALIBRATION_SPINS {
+                port_b.write(saved);
+                return false;
+            }
+        }
+        port_b.write(saved);
+    }
+    true
+}
+
+/// Count TSC cycles over `CALIBRATION_MS` of PIT channel 2
+///
+/// Returns the TSC frequency in Hz, or `None` if there is no TSC or the
+/// PIT never finished counting.
+fn calibrate_tsc() -> Option<u64> {
+    if !has_tsc() {
+        return None;
+    }
+
+    let mut start = 0;
+    if !pit_oneshot(CALIBRATION_MS, || start = rdtsc()) {
+        return None;
+    }
+    let cycles = rdtsc().wrapping_sub(start);
+    Some(cycles * 1000 / CALIBRATION_MS).filter(|&hz| hz > 0)
+}
+
+/// Calibrated TSC frequency in Hz, if the TSC is in use
+pub fn tsc_hz() -> Option<u64> {
+    match TSC_HZ.load(Ordering::Relaxed) {
+        0 => None,
+        hz => Some(hz),
+    }
+}
+
+/// Convert TSC cycles at `hz` to nanoseconds
+fn cycles_to_ns(cycles: u64, hz: u64) -> u64 {
+    (cycles as u128 * NANOS_PER_SEC as u128 / hz as u128) as u64
+}
+
+/// Convert timer ticks to nanoseconds
+pub fn ticks_to_ns(ticks: u64) -> u64 {
+    ticks.saturating_mul(TICK_NS)
+}
+
+/// The clock source in use
+pub fn clock_source() -> ClockSource {
+    ClockSource::from_u8(SOURCE.load(Ordering::Relaxed))
+}
+
+/// Frequency of the clock source's counter, in Hz
+pub fn source_hz() -> u64 {
+    match clock_source() {
+        ClockSource::Ticks => TIMER_HZ,
+        ClockSource::Tsc | ClockSource::InvariantTsc => TSC_HZ.load(Ordering::Relaxed),
+        ClockSource::Hpet => crate::hpet::frequency().unwrap_or(0),
+    }
+}
+
+/// Raw counter value of a clock source
+fn read_counter(source: ClockSource) -> u64 {
+    match source {
+        ClockSource::Ticks => crate::scheduler::get_elapsed_ticks(),
+        ClockSource::Tsc | ClockSource::InvariantTsc => rdtsc(),
+        ClockSource::Hpet => crate::hpet::counter().unwrap_or(0),
+    }
+}
+
+/// Convert a count of a clock source's counter to nanoseconds
+fn counter_to_ns(source: ClockSource, count: u64) -> u64 {
+    match source {
+        ClockSource::Ticks => ticks_to_ns(count),
+        ClockSource::Tsc | ClockSource::InvariantTsc => {
+            cycles_to_ns(count, TSC_HZ.load(Ordering::Relaxed))
+        }
+        ClockSource::Hpet => crate::hpet::counter_to_ns(count),
+    }
+}
+
+/// Take `source` as the clock source if it is better than the one in use
+///
+/// The source must be running. Monotonic time carries on from its current
+/// value. Only called during boot, before any other CPU reads the clock.
+/// Returns whether the source was taken.
+pub fn offer(source: ClockSource) -> bool {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        if source <= clock_source() {
+            return false;
+        }
+        let now = monotonic_ns();
+        SOURCE_BASE.store(read_counter(source), Ordering::Relaxed);
+        SOURCE_OFFSET_NS.store(now, Ordering::Relaxed);
+        SOURCE.store(source as u8, Ordering::Relaxed);
+        true
+    })
+}
+
+/// Nanoseconds since boot, never going backwards
+///
+/// Read from the clock source (see `clock_source`); with nothing better
+/// than the PIT it advances in whole timer ticks.
+pub fn monotonic_ns() -> u64 {
+    let source = clock_source();
+    let elapsed = read_counter(source).saturating_sub(SOURCE_BASE.load(Ordering::Relaxed));
+    SOURCE_OFFSET_NS
+        .load(Ordering::Relaxed)
+        .saturating_add(counter_to_ns(source, elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_parse_hz() {
+        assert_eq!(parse_hz(None), DEFAULT_TIMER_HZ);
+        assert_eq!(parse_hz(Some("250")), 250);
+        assert_eq!(parse_hz(Some("1000")), MAX_TIMER_HZ);
+    }
+
+    #[test_case]
+    fn test_pit_divisor() {
+        assert_eq!(pit_divisor(100), 11932);
+        assert_eq!(pit_divisor(1000), 1193);
+        // The slowest rate still fits the 16-bit divisor
+        assert_eq!(pit_divisor(MIN_TIMER_HZ), 62799);
+        // Within 0.1% of the requested rate
+        let nominal = NANOS_PER_SEC / TIMER_HZ;
+        assert!(ticks_to_ns(1).abs_diff(nominal) * 1000 <= nominal);
+    }
+
+    #[test_case]
+    fn test_cycles_to_ns() {
+        assert_eq!(cycles_to_ns(3_000_000_000, 3_000_000_000), NANOS_PER_SEC);
+        assert_eq!(cycles_to_ns(3, 3_000_000_000), 1);
+        // No overflow after years of uptime at 5 GHz
+        let year = 365 * 24 * 3600;
+        assert_eq!(
+            cycles_to_ns(5_000_000_000 * year, 5_000_000_000),
+            NANOS_PER_SEC * year
+        );
+    }
+
+    #[test_case]
+    fn test_clock_source_order() {
+        assert!(ClockSource::InvariantTsc > ClockSource::Hpet);
+        assert!(ClockSource::Hpet > ClockSource::Tsc);
+        assert!(ClockSource::Tsc > ClockSource::Ticks);
+        for source in [
+            ClockSource::Ticks,
+            ClockSource::Hpet,
+            ClockSource::InvariantTsc,
+        ] {
+            assert_eq!(ClockSource::from_u8(source as u8), source);
+        }
+        // Offering the source in use, or a worse one, changes nothing
+        assert!(!offer(clock_source()));
+        assert!(!offer(ClockSource::Ticks));
+    }
+
+    #[test_case]
+    fn test_monotonic() {
+        let first = monotonic_ns();
+        let second = monotonic_ns();
+        assert!(second >= first);
+    }
+}
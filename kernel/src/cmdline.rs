@@ -0,0 +1,136 @@
+//! Kernel command line
+//!
+//! bootloader 0.9 does not pass a command line, so the kernel reads one
+//! from QEMU's firmware configuration device (fw_cfg): the file
+//! `opt/orbital/cmdline`, given to QEMU as
+//!
+//! ```text
+//! -fw_cfg name=opt/orbital/cmdline,string="sched=cfs"
+//! ```
+//!
+//! The command line is a list of `key=value` words separated by spaces.
+//! `init` copies it once at boot and `get` looks a key up. Without the
+//! device or the file the command line is empty, and every setting keeps
+//! its default.
+
+use conquer_once::spin::OnceCell;
+use x86_64::instructions::port::Port;
+
+/// fw_cfg selector (16-bit write) and data (8-bit read) ports
+const FW_CFG_SELECTOR: u16 = 0x510;
+const FW_CFG_DATA: u16 = 0x511;
+
+/// fw_cfg items: the "QEMU" signature and the file directory
+const FW_CFG_SIGNATURE: u16 = 0x0000;
+const FW_CFG_FILE_DIR: u16 = 0x0019;
+
+/// Size of a file directory entry: size, select, reserved, 56-byte name
+const FILE_ENTRY_SIZE: usize = 64;
+const FILE_NAME_OFFSET: usize = 8;
+
+/// fw_cfg file holding the command line
+const CMDLINE_FILE: &[u8] = b"opt/orbital/cmdline";
+
+/// Longest command line kept; the rest is dropped
+pub const MAX_CMDLINE: usize = 256;
+
+struct Cmdline {
+    buf: [u8; MAX_CMDLINE],
+    len: usize,
+}
+
+static CMDLINE: OnceCell<Cmdline> = OnceCell::uninit();
+
+/// Read the command line from fw_cfg (once; later calls do nothing)
+pub fn init() {
+    CMDLINE.init_once(|| {
+        let mut cmdline = Cmdline {
+            buf: [0; MAX_CMDLINE],
+            len: 0,
+        };
+        if let Some((select, size)) = find_file(CMDLINE_FILE) {
+            cmdline.len = (size as usize).min(MAX_CMDLINE);
+            fw_cfg_select(select);
+            fw_cfg_read(&mut cmdline.buf[..cmdline.len]);
+        }
+        cmdline
+    });
+}
+
+/// The whole command line ("" before `init`, or if it is not UTF-8)
+pub fn as_str() -> &'static str {
+    CMDLINE
+        .try_get()
+        .ok()
+        .and_then(|cmdline| core::str::from_utf8(&cmdline.buf[..cmdline.len]).ok())
+        .unwrap_or("")
+}
+
+/// Value of the last `key=value` word for `key`
+pub fn get(key: &str) -> Option<&'static str> {
+    lookup(as_str(), key)
+}
+
+fn lookup<'a>(cmdline: &'a str, key: &str) -> Option<&'a str> {
+    cmdline
+        .split_ascii_whitespace()
+        .rev()
+        .filter_map(|word| word.split_once('='))
+        .find(|&(name, _)| name == key)
+        .map(|(_, value)| value)
+}
+
+/// Find a file in the fw_cfg directory: its selector and size
+fn find_file(name: &[u8]) -> Option<(u16, u32)> {
+    let mut signature = [0u8; 4];
+    fw_cfg_select(FW_CFG_SIGNATURE);
+    fw_cfg_read(&mut signature);
+    if &signature != b"QEMU" {
+        return None;
+    }
+
+    let mut count = [0u8; 4];
+    fw_cfg_select(FW_CFG_FILE_DIR);
+    fw_cfg_read(&mut count);
+    // The directory is big-endian; each read continues where the last ended
+    for _ in 0..u32::from_be_bytes(count) {
+        let mut entry = [0u8; FILE_ENTRY_SIZE];
+        fw_cfg_read(&mut entry);
+        let entry_name = &entry[FILE_NAME_OFFSET..];
+        let len = entry_name.iter().position(|&b| b == 0).unwrap_or(entry_name.len());
+        // Compared byte by byte: the debug build's memcmp faults on
+        // unaligned 16-byte reads for names this long
+        if entry_name[..len].iter().eq(name) {
+            let size = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            let select = u16::from_be_bytes([entry[4], entry[5]]);
+            return Some((select, size));
+        }
+    }
+    None
+}
+
+fn fw_cfg_select(item: u16) {
+    unsafe { Port::<u16>::new(FW_CFG_SELECTOR).write(item) };
+}
+
+fn fw_cfg_read(buf: &mut [u8]) {
+    let mut data = Port::<u8>::new(FW_CFG_DATA);
+    for byte in buf {
+        *byte = unsafe { data.read() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_lookup() {
+        let cmdline = "quiet sched=cfs  watchdog=kill sched=rr";
+        assert_eq!(lookup(cmdline, "watchdog"), Some("kill"));
+        // The last word for a key wins
+        assert_eq!(lookup(cmdline, "sched"), Some("rr"));
+        assert_eq!(lookup(cmdline, "quiet"), None);
+        assert_eq!(lookup("", "sched"), None);
+    }
+}
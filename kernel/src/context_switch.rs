@@ -1,11 +1,12 @@
-//! x86_64 context switching assembly and utilities
+//! x86_64 context switching
 //!
-//! Implements the low-level task switching mechanism that saves/restores
-//! all CPU registers and switches between task stacks.
+//! Threads are switched from the timer interrupt. The naked entry stub
+//! (`interrupts::timer_interrupt_entry`) pushes every general purpose
+//! register on top of the frame the CPU pushed, giving an `InterruptFrame`:
 //!
 //! Register Layout (saved on stack):
 //! ```text
-//! RSP -> [R15]    <- Latest saved register
+//! RSP -> [R15]    <- Latest saved register (pushed by the stub)
 //!        [R14]
 //!        [R13]
 //!        [R12]
@@ -20,164 +21,144 @@
 //!        [RCX]
 //!        [RBX]
 //!        [RAX]
+//!        [RIP]    <- Pushed by the CPU on interrupt entry
+//!        [CS]
 //!        [RFLAGS]
-//!        [RIP]   <- Entry point or return address
+//!        [RSP]
+//!        [SS]
 //! ```
+//!
+//! `context_switch` copies that frame into the interrupted thread's
+//! `saved_context` and overwrites it in place with the next thread's
+//! registers, so the stub's pops and `iretq` resume the next thread on its
+//! own stack.
+//!
+//! The FS and GS segment bases are not part of the register file proper;
+//! they live in MSRs and are saved/restored alongside it so each thread
+//! keeps its own thread-local storage pointer. The thread's GS base is the
+//! one parked in `IA32_KERNEL_GS_BASE` while the kernel runs (see
+//! `percpu`).
+//!
+//! The timer interrupt runs on a stack of its own (`gdt::TIMER_IST_INDEX`),
+//! not on the interrupted thread's: once the process table is released,
+//! another CPU may resume that thread while this one is still on its way
+//! out of the interrupt.
 
+use crate::fpu::FpuState;
 use crate::println;
-use crate::process::TaskContext;
-
-/// Save the current CPU state to a TaskContext structure
-///
-/// This is typically called when switching away from a running task.
-/// All general purpose registers plus RIP and RFLAGS are preserved.
-#[inline(never)]
-pub fn save_context() -> TaskContext {
-    let mut ctx = TaskContext {
-        rax: 0,
-        rbx: 0,
-        rcx: 0,
-        rdx: 0,
-        rsi: 0,
-        rdi: 0,
-        rbp: 0,
-        rsp: 0,
-        r8: 0,
-        r9: 0,
-        r10: 0,
-        r11: 0,
-        r12: 0,
-        r13: 0,
-        r14: 0,
-        r15: 0,
-        rip: 0,
-        rflags: 0,
-    };
-
-    unsafe {
-        // Get current RSP (we're in a function, so RSP points to return address)
-        core::arch::asm!(
-            "mov {}, rsp",
-            out(reg) ctx.rsp,
-            options(nostack, preserves_flags),
-        );
-
-        // Get RBP
-        core::arch::asm!(
-            "mov {}, rbp",
-            out(reg) ctx.rbp,
-            options(nostack, preserves_flags),
-        );
+use crate::process::{SwitchLock, TaskContext};
+use crate::smp::MAX_CPUS;
+use spin::Mutex;
 
-        // Get general purpose registers
-        core::arch::asm!(
-            "mov {}, rax",
-            "mov {}, rbx",
-            "mov {}, rcx",
-            "mov {}, rdx",
-            "mov {}, rsi",
-            "mov {}, rdi",
-            "mov {}, r8",
-            "mov {}, r9",
-            "mov {}, r10",
-            "mov {}, r11",
-            "mov {}, r12",
-            "mov {}, r13",
-            "mov {}, r14",
-            "mov {}, r15",
-            out(reg) ctx.rax,
-            out(reg) ctx.rbx,
-            out(reg) ctx.rcx,
-            out(reg) ctx.rdx,
-            out(reg) ctx.rsi,
-            out(reg) ctx.rdi,
-            out(reg) ctx.r8,
-            out(reg) ctx.r9,
-            out(reg) ctx.r10,
-            out(reg) ctx.r11,
-            out(reg) ctx.r12,
-            out(reg) ctx.r13,
-            out(reg) ctx.r14,
-            out(reg) ctx.r15,
-            options(nostack, preserves_flags),
-        );
-
-        // Get RFLAGS
-        core::arch::asm!(
-            "pushfq",
-            "pop {}",
-            out(reg) ctx.rflags,
-            options(nostack),
-        );
+/// Registers of an interrupted task, as laid out on the stack by the timer
+/// interrupt entry stub
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct InterruptFrame {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+    /// Pushed by the CPU
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
 
-        // RIP is trickier - we want the instruction after this call
-        // The return address is on the stack
-        let rsp_val = ctx.rsp as *const u64;
-        ctx.rip = *rsp_val;
-        ctx.rsp += 8; // Skip return address when switching
+impl InterruptFrame {
+    /// Copy the interrupted registers into `ctx`
+    ///
+    /// The FS/GS bases are not in the frame and are left untouched.
+    pub fn save_into(&self, ctx: &mut TaskContext) {
+        ctx.rax = self.rax;
+        ctx.rbx = self.rbx;
+        ctx.rcx = self.rcx;
+        ctx.rdx = self.rdx;
+        ctx.rsi = self.rsi;
+        ctx.rdi = self.rdi;
+        ctx.rbp = self.rbp;
+        ctx.rsp = self.rsp;
+        ctx.r8 = self.r8;
+        ctx.r9 = self.r9;
+        ctx.r10 = self.r10;
+        ctx.r11 = self.r11;
+        ctx.r12 = self.r12;
+        ctx.r13 = self.r13;
+        ctx.r14 = self.r14;
+        ctx.r15 = self.r15;
+        ctx.rip = self.rip;
+        ctx.rflags = self.rflags;
     }
 
-    ctx
+    /// Make `iretq` resume `ctx`
+    ///
+    /// Every task runs in ring 0, so the code segment is the kernel's and
+    /// the stack segment is null.
+    pub fn load_from(&mut self, ctx: &TaskContext, code_selector: u64) {
+        self.rax = ctx.rax;
+        self.rbx = ctx.rbx;
+        self.rcx = ctx.rcx;
+        self.rdx = ctx.rdx;
+        self.rsi = ctx.rsi;
+        self.rdi = ctx.rdi;
+        self.rbp = ctx.rbp;
+        self.rsp = ctx.rsp;
+        self.r8 = ctx.r8;
+        self.r9 = ctx.r9;
+        self.r10 = ctx.r10;
+        self.r11 = ctx.r11;
+        self.r12 = ctx.r12;
+        self.r13 = ctx.r13;
+        self.r14 = ctx.r14;
+        self.r15 = ctx.r15;
+        self.rip = ctx.rip;
+        self.rflags = ctx.rflags;
+        self.cs = code_selector;
+        self.ss = 0;
+    }
 }
 
-/// Restore CPU state from a TaskContext structure
+/// A CPU's own context: the async executor and whatever it runs on the
+/// boot CPU, the idle loop on the others
 ///
-/// This is called when switching to a different task.
-/// All registers are restored from the context.
-///
-/// # Safety
-/// This is extremely unsafe as it modifies all CPU registers.
-/// Only call when you want to actually switch to this task.
-#[inline(never)]
-pub unsafe fn restore_context(ctx: &TaskContext) -> ! {
-    // We need to restore all 18 registers from the TaskContext
-    // Since we have limited inline asm registers, we'll use a helper approach
-
-    // Cast context to a pointer so we can load it directly in asm
-    let ctx_ptr = ctx as *const TaskContext as usize;
-
-    unsafe {
-        core::arch::asm!(
-            // Load RSP first - we'll use it as our base pointer
-            "mov rsp, [{ctx_ptr} + 56]",    // TaskContext.rsp at offset 56
-
-            // Load and restore all GP registers from context structure
-            "mov rax, [{ctx_ptr} + 0]",     // rax offset 0
-            "mov rbx, [{ctx_ptr} + 8]",     // rbx offset 8
-            "mov rcx, [{ctx_ptr} + 16]",    // rcx offset 16
-            "mov rdx, [{ctx_ptr} + 24]",    // rdx offset 24
-            "mov rsi, [{ctx_ptr} + 32]",    // rsi offset 32
-            "mov rdi, [{ctx_ptr} + 40]",    // rdi offset 40
-            "mov rbp, [{ctx_ptr} + 48]",    // rbp offset 48
-            "mov r8,  [{ctx_ptr} + 64]",    // r8 offset 64
-            "mov r9,  [{ctx_ptr} + 72]",    // r9 offset 72
-            "mov r10, [{ctx_ptr} + 80]",    // r10 offset 80
-            "mov r11, [{ctx_ptr} + 88]",    // r11 offset 88
-            "mov r12, [{ctx_ptr} + 96]",    // r12 offset 96
-            "mov r13, [{ctx_ptr} + 104]",   // r13 offset 104
-            "mov r14, [{ctx_ptr} + 112]",   // r14 offset 112
-            "mov r15, [{ctx_ptr} + 120]",   // r15 offset 120
-
-            // Load RFLAGS and restore it
-            "mov r10, [{ctx_ptr} + 136]",   // rflags at offset 136 (temporarily in r10)
-            "push r10",                      // push RFLAGS to stack
-            "popfq",                         // pop into RFLAGS
-
-            // Load RIP and jump to it
-            "mov r10, [{ctx_ptr} + 128]",   // rip at offset 128 (temporarily in r10)
-            "jmp r10",                       // jump to RIP
-
-            ctx_ptr = in(reg) ctx_ptr,
-            options(noreturn),
-        );
-    }
+/// It is scheduled like a thread (as `scheduler::KERNEL_TID` on the boot
+/// CPU) but has no process table entry, so its registers are kept here
+/// while a thread has the CPU.
+struct KernelContext {
+    regs: TaskContext,
+    fpu: FpuState,
+    /// Whether `regs` holds a preempted kernel context
+    saved: bool,
 }
 
+/// Each CPU's own context, indexed by `smp::cpu_id`
+static KERNEL_CONTEXTS: [Mutex<KernelContext>; MAX_CPUS] = [const {
+    Mutex::new(KernelContext {
+        regs: TaskContext::empty(),
+        fpu: FpuState::new(),
+        saved: false,
+    })
+}; MAX_CPUS];
+
 /// Validate a TaskContext before context switching
 ///
 /// This catches invalid contexts early rather than double faulting
 /// Returns true if context is valid, false otherwise
-/// Will be used when preemptive multitasking is implemented
+/// Prints its findings, so it is a debugging aid for code outside the
+/// timer interrupt rather than part of the switch itself
 #[allow(dead_code)]
 fn validate_context(ctx: &TaskContext) -> bool {
     // Check 1: Stack pointer not NULL
@@ -192,15 +173,16 @@ fn validate_context(ctx: &TaskContext) -> bool {
         return false;
     }
 
-    // Check 3: Stack pointer in valid kernel space
-    // Kernel stacks are allocated from the heap at 0x_4444_4444_0000
-    const KERNEL_HEAP_START: u64 = 0x0000_4444_4444_0000;
-    const KERNEL_HEAP_END: u64 = 0x0000_4444_4444_0000 + (100 * 1024); // 100 KiB heap
+    // Check 3: Stack pointer inside the task stack region
+    let region_end = crate::stack::STACK_REGION_START
+        + (crate::stack::MAX_STACKS * crate::stack::STACK_SLOT_SIZE) as u64;
 
-    if ctx.rsp < KERNEL_HEAP_START || ctx.rsp > KERNEL_HEAP_END {
+    if ctx.rsp < crate::stack::STACK_REGION_START || ctx.rsp > region_end {
         println!(
-            "ERROR: RSP 0x{:x} outside valid heap range [0x{:x}, 0x{:x})!",
-            ctx.rsp, KERNEL_HEAP_START, KERNEL_HEAP_END
+            "ERROR: RSP 0x{:x} outside task stack region [0x{:x}, 0x{:x})!",
+            ctx.rsp,
+            crate::stack::STACK_REGION_START,
+            region_end
         );
         return false;
     }
@@ -215,8 +197,7 @@ fn validate_context(ctx: &TaskContext) -> bool {
     }
 
     // Check 5: RBP - RSP shouldn't exceed max stack size
-    const MAX_STACK_SIZE: u64 = 4096 + 256; // Allow some overflow room
-    if ctx.rbp - ctx.rsp > MAX_STACK_SIZE {
+    if ctx.rbp - ctx.rsp > crate::stack::MAX_STACK_SIZE as u64 {
         println!(
             "ERROR: Stack too large (RBP - RSP = 0x{:x})!",
             ctx.rbp - ctx.rsp
@@ -240,9 +221,102 @@ fn validate_context(ctx: &TaskContext) -> bool {
     true
 }
 
-pub fn context_switch(current_pid: Option<u64>, next_pid: Option<u64>) {
-    // For now, context switching is disabled
-    // Tasks will be executed directly via execute_process(), not via context switch
-    // Just return and let the scheduler/executor continue normally
-    let _ = (current_pid, next_pid); // Suppress unused warning
+/// Switch from `current` to `next` at the end of a timer interrupt
+///
+/// `frame` holds the interrupted registers; they are saved (with the FPU
+/// state and FS/GS bases) into `current`'s context, and `next`'s context is
+/// loaded into the frame and the CPU. `None` is this CPU's own context.
+///
+/// Runs with interrupts disabled and never spins: the caller holds the
+/// process table in `table`, taken before the scheduler picked `next`. If
+/// the CPU's own context is picked before it was ever preempted there is
+/// nothing to load, and the frame is left alone: whatever was interrupted
+/// keeps running as the kernel.
+pub fn context_switch(
+    frame: &mut InterruptFrame,
+    current: Option<u64>,
+    next: Option<u64>,
+    table: &mut SwitchLock,
+) {
+    let mut ctx = TaskContext::empty();
+    frame.save_into(&mut ctx);
+    ctx.fs_base = crate::tls::read_fs_base();
+    ctx.gs_base = crate::tls::read_gs_base();
+
+    let percpu = crate::percpu::this_cpu();
+    let cpu = percpu.id();
+    let mut kernel = match KERNEL_CONTEXTS[cpu].try_lock() {
+        Some(kernel) => kernel,
+        None => return,
+    };
+    if next.is_none() && !kernel.saved {
+        return;
+    }
+
+    match current {
+        Some(tid) => {
+            if table.switch_out(tid, &ctx) {
+                percpu.count_preemption();
+            } else {
+                crate::scheduler::stats::count_voluntary();
+            }
+        }
+        None => {
+            kernel.fpu.save();
+            kernel.regs = ctx;
+            kernel.saved = true;
+        }
+    }
+
+    let next_ctx = match next {
+        Some(tid) => table.switch_in(tid, cpu),
+        None => {
+            kernel.fpu.restore();
+            kernel.saved = false;
+            Some(kernel.regs.clone())
+        }
+    };
+
+    if let Some(ctx) = next_ctx {
+        crate::tls::write_fs_base(ctx.fs_base);
+        crate::tls::write_gs_base(ctx.gs_base);
+        frame.load_from(&ctx, u64::from(crate::gdt::kernel_code_selector().0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_interrupt_frame_layout() {
+        // 15 registers pushed by the stub, then the CPU's 5-word frame
+        assert_eq!(core::mem::size_of::<InterruptFrame>(), 20 * 8);
+        assert_eq!(core::mem::offset_of!(InterruptFrame, r15), 0);
+        assert_eq!(core::mem::offset_of!(InterruptFrame, rax), 14 * 8);
+        assert_eq!(core::mem::offset_of!(InterruptFrame, rip), 15 * 8);
+        assert_eq!(core::mem::offset_of!(InterruptFrame, ss), 19 * 8);
+    }
+
+    #[test_case]
+    fn test_frame_context_round_trip() {
+        let ctx = TaskContext::new(0x1000, 0x8000);
+        let mut frame = InterruptFrame::default();
+        frame.load_from(&ctx, 0x08);
+        assert_eq!(frame.rip, ctx.rip);
+        assert_eq!(frame.rsp, ctx.rsp);
+        assert_eq!(frame.rdi, 0x1000);
+        assert_eq!(frame.cs, 0x08);
+        assert_eq!(frame.ss, 0);
+
+        let mut saved = TaskContext::empty();
+        saved.fs_base = 0xdead;
+        frame.save_into(&mut saved);
+        assert_eq!(saved.rip, ctx.rip);
+        assert_eq!(saved.rsp, ctx.rsp);
+        assert_eq!(saved.rbp, ctx.rbp);
+        assert_eq!(saved.rflags, ctx.rflags);
+        // Segment bases live in MSRs, not in the frame
+        assert_eq!(saved.fs_base, 0xdead);
+    }
 }
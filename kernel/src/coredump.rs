@@ -0,0 +1,517 @@
+//! ELF core dumps for crashed user processes
+//!
+//! When a user process is killed for a fault, the exception handler writes
+//! an ELF `ET_CORE` image of it to the serial port, base64-encoded between
+//! marker lines:
+//!
+//! ```text
+//! -----BEGIN ORBITAL CORE pid=5 name=shell-----
+//! f0VMRgIBAQAAAAAAAAAAAAQAPgABAAAAAAAAAAAAAABAAAAAAAAAAAAAAAAAAAAAAAAAAEAAOAAD
+//! ...
+//! -----END ORBITAL CORE-----
+//! ```
+//!
+//! On the host, cut the block out of the serial log, decode it and open it
+//! next to the program's ELF file:
+//!
+//! ```text
+//! sed -n '/BEGIN ORBITAL CORE/,/END ORBITAL CORE/p' serial.log | sed '1d;$d' | base64 -d > core
+//! gdb userspace/minimal/target/x86_64-orbital/release/minimal-shell core
+//! ```
+//!
+//! Layout: one `PT_NOTE` segment with `NT_PRSTATUS` (the faulting thread's
+//! registers) and `NT_PRPSINFO` (name and command line), then a `PT_LOAD`
+//! segment for every mapped thread stack and TLS block. Note structures
+//! follow the Linux x86_64 definitions so stock `gdb` reads them.
+//!
+//! Exception handlers must not allocate or block, so the image is never
+//! built in memory: `write_core` streams it straight into a `CoreSink`.
+
+use crate::process::{MAX_THREADS_PER_PROCESS, Process};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether killed processes are dumped (on by default)
+static CORE_DUMPS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn core dumps on or off
+pub fn set_enabled(enabled: bool) {
+    CORE_DUMPS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether killed processes are dumped
+pub fn is_enabled() -> bool {
+    CORE_DUMPS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// At most one stack and one TLS block per thread
+pub const MAX_CORE_SEGMENTS: usize = 2 * MAX_THREADS_PER_PROCESS;
+
+const ELF_HEADER_SIZE: u64 = 64;
+const PROGRAM_HEADER_SIZE: u64 = 56;
+const ET_CORE: u16 = 4;
+const EM_X86_64: u16 = 62;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+
+/// Note name, NUL-terminated and padded to 4 bytes
+const NOTE_NAME: &[u8; 8] = b"CORE\0\0\0\0";
+/// `namesz` of a note (includes the NUL)
+const NOTE_NAME_SIZE: u32 = 5;
+/// Size of `struct elf_prstatus` on x86_64
+const PRSTATUS_SIZE: u32 = 336;
+/// Size of `struct elf_prpsinfo` on x86_64
+const PRPSINFO_SIZE: u32 = 136;
+/// `pr_fname` and `pr_psargs` lengths in `struct elf_prpsinfo`
+const FNAME_LEN: usize = 16;
+const PSARGS_LEN: usize = 80;
+
+/// Register values for `NT_PRSTATUS`, in `user_regs_struct` order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct CoreRegisters {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl CoreRegisters {
+    fn as_array(&self) -> [u64; 27] {
+        [
+            self.r15,
+            self.r14,
+            self.r13,
+            self.r12,
+            self.rbp,
+            self.rbx,
+            self.r11,
+            self.r10,
+            self.r9,
+            self.r8,
+            self.rax,
+            self.rcx,
+            self.rdx,
+            self.rsi,
+            self.rdi,
+            self.orig_rax,
+            self.rip,
+            self.cs,
+            self.rflags,
+            self.rsp,
+            self.ss,
+            self.fs_base,
+            self.gs_base,
+            self.ds,
+            self.es,
+            self.fs,
+            self.gs,
+        ]
+    }
+}
+
+/// A range of process memory to include in the dump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoreSegment {
+    pub vaddr: u64,
+    pub size: u64,
+}
+
+/// Everything about a process the dump needs, copied out of the process
+/// table so the table can be unlocked while the (slow) dump is written
+#[derive(Debug, Clone)]
+pub struct CoreProcess {
+    /// Thread that faulted (the LWP `gdb` shows)
+    pub tid: u64,
+    pub pid: u64,
+    pub ppid: u64,
+    /// Signal the process was killed with
+    pub signal: u32,
+    fname: [u8; FNAME_LEN],
+    psargs: [u8; PSARGS_LEN],
+    segments: [CoreSegment; MAX_CORE_SEGMENTS],
+    segment_count: usize,
+}
+
+impl CoreProcess {
+    /// Describe `process`, killed with `signal` after a fault in thread `tid`
+    ///
+    /// The memory itself is only read by `write_core`; it stays mapped
+    /// until the process is reaped.
+    pub fn capture(process: &Process, tid: u64, signal: u32) -> Self {
+        let mut dump = CoreProcess {
+            tid,
+            pid: process.pid(),
+            ppid: process.parent_id,
+            signal,
+            fname: [0; FNAME_LEN],
+            psargs: [0; PSARGS_LEN],
+            segments: [CoreSegment::default(); MAX_CORE_SEGMENTS],
+            segment_count: 0,
+        };
+        copy_truncated(&mut dump.fname, process.name.as_bytes());
+        let mut psargs = &mut dump.psargs[..];
+        for (i, arg) in process.args.iter().enumerate() {
+            if i > 0 {
+                psargs = copy_truncated(psargs, b" ");
+            }
+            psargs = copy_truncated(psargs, arg.as_bytes());
+        }
+
+        for thread in &process.threads {
+            dump.push(thread.stack.bottom(), thread.stack.size() as u64);
+            if let Some(tls) = &thread.tls {
+                dump.push(tls.start(), tls.size() as u64);
+            }
+        }
+        dump
+    }
+
+    fn push(&mut self, vaddr: u64, size: u64) {
+        if self.segment_count < MAX_CORE_SEGMENTS && size > 0 {
+            self.segments[self.segment_count] = CoreSegment { vaddr, size };
+            self.segment_count += 1;
+        }
+    }
+
+    /// Memory ranges included in the dump
+    pub fn segments(&self) -> &[CoreSegment] {
+        &self.segments[..self.segment_count]
+    }
+
+    /// Process name as recorded in `NT_PRPSINFO` (truncated to 15 bytes)
+    pub fn name(&self) -> &str {
+        let len = self.fname.iter().position(|&b| b == 0).unwrap_or(FNAME_LEN);
+        core::str::from_utf8(&self.fname[..len]).unwrap_or("?")
+    }
+}
+
+/// Copy as much of `src` as fits into `dst`, keeping a trailing NUL, and
+/// return the unused rest of `dst`
+fn copy_truncated<'a>(dst: &'a mut [u8], src: &[u8]) -> &'a mut [u8] {
+    let len = src.len().min(dst.len().saturating_sub(1));
+    dst[..len].copy_from_slice(&src[..len]);
+    &mut dst[len..]
+}
+
+/// Destination of a core image
+pub trait CoreSink {
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl CoreSink for alloc::vec::Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+/// Size of the `PT_NOTE` segment
+fn notes_size() -> u64 {
+    let header = 12 + NOTE_NAME.len() as u64;
+    2 * header + PRSTATUS_SIZE as u64 + PRPSINFO_SIZE as u64
+}
+
+/// Stream the ELF core image of `process` with the faulting thread's `regs`
+pub fn write_core(sink: &mut impl CoreSink, process: &CoreProcess, regs: &CoreRegisters) {
+    let segments = process.segments();
+    let phnum = 1 + segments.len() as u64;
+    let notes_offset = ELF_HEADER_SIZE + phnum * PROGRAM_HEADER_SIZE;
+
+    // ELF header
+    sink.write(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    sink.write(&[0; 8]);
+    sink.write(&ET_CORE.to_le_bytes());
+    sink.write(&EM_X86_64.to_le_bytes());
+    sink.write(&1u32.to_le_bytes()); // e_version
+    sink.write(&0u64.to_le_bytes()); // e_entry
+    sink.write(&ELF_HEADER_SIZE.to_le_bytes()); // e_phoff
+    sink.write(&0u64.to_le_bytes()); // e_shoff
+    sink.write(&0u32.to_le_bytes()); // e_flags
+    sink.write(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    sink.write(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+    sink.write(&(phnum as u16).to_le_bytes());
+    sink.write(&64u16.to_le_bytes()); // e_shentsize
+    sink.write(&0u16.to_le_bytes()); // e_shnum
+    sink.write(&0u16.to_le_bytes()); // e_shstrndx
+
+    // Program headers: notes, then memory in file order
+    write_program_header(sink, PT_NOTE, 0, notes_offset, 0, notes_size(), 4);
+    let mut offset = notes_offset + notes_size();
+    for segment in segments {
+        write_program_header(
+            sink,
+            PT_LOAD,
+            PF_R | PF_W,
+            offset,
+            segment.vaddr,
+            segment.size,
+            1,
+        );
+        offset += segment.size;
+    }
+
+    write_prstatus(sink, process, regs);
+    write_prpsinfo(sink, process);
+
+    for segment in segments {
+        // Stacks and TLS blocks stay mapped until the process is reaped
+        let memory = unsafe {
+            core::slice::from_raw_parts(segment.vaddr as *const u8, segment.size as usize)
+        };
+        sink.write(memory);
+    }
+}
+
+fn write_program_header(
+    sink: &mut impl CoreSink,
+    p_type: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    size: u64,
+    align: u64,
+) {
+    sink.write(&p_type.to_le_bytes());
+    sink.write(&flags.to_le_bytes());
+    sink.write(&offset.to_le_bytes());
+    sink.write(&vaddr.to_le_bytes());
+    sink.write(&0u64.to_le_bytes()); // p_paddr
+    sink.write(&size.to_le_bytes()); // p_filesz
+    sink.write(&size.to_le_bytes()); // p_memsz
+    sink.write(&align.to_le_bytes());
+}
+
+fn write_note_header(sink: &mut impl CoreSink, note_type: u32, desc_size: u32) {
+    sink.write(&NOTE_NAME_SIZE.to_le_bytes());
+    sink.write(&desc_size.to_le_bytes());
+    sink.write(&note_type.to_le_bytes());
+    sink.write(NOTE_NAME);
+}
+
+/// `struct elf_prstatus`
+fn write_prstatus(sink: &mut impl CoreSink, process: &CoreProcess, regs: &CoreRegisters) {
+    write_note_header(sink, NT_PRSTATUS, PRSTATUS_SIZE);
+
+    sink.write(&(process.signal as i32).to_le_bytes()); // si_signo
+    sink.write(&0i32.to_le_bytes()); // si_code
+    sink.write(&0i32.to_le_bytes()); // si_errno
+    sink.write(&(process.signal as i16).to_le_bytes()); // pr_cursig
+    sink.write(&[0; 2]);
+    sink.write(&0u64.to_le_bytes()); // pr_sigpend
+    sink.write(&0u64.to_le_bytes()); // pr_sighold
+    sink.write(&(process.tid as i32).to_le_bytes()); // pr_pid
+    sink.write(&(process.ppid as i32).to_le_bytes()); // pr_ppid
+    sink.write(&(process.pid as i32).to_le_bytes()); // pr_pgrp
+    sink.write(&(process.pid as i32).to_le_bytes()); // pr_sid
+    sink.write(&[0; 64]); // pr_utime, pr_stime, pr_cutime, pr_cstime
+    for reg in regs.as_array() {
+        sink.write(&reg.to_le_bytes());
+    }
+    sink.write(&0i32.to_le_bytes()); // pr_fpvalid
+    sink.write(&[0; 4]);
+}
+
+/// `struct elf_prpsinfo`
+fn write_prpsinfo(sink: &mut impl CoreSink, process: &CoreProcess) {
+    write_note_header(sink, NT_PRPSINFO, PRPSINFO_SIZE);
+
+    sink.write(&[0, b'R', 0, 0]); // pr_state, pr_sname, pr_zomb, pr_nice
+    sink.write(&[0; 4]);
+    sink.write(&0u64.to_le_bytes()); // pr_flag
+    sink.write(&0u32.to_le_bytes()); // pr_uid
+    sink.write(&0u32.to_le_bytes()); // pr_gid
+    sink.write(&(process.pid as i32).to_le_bytes());
+    sink.write(&(process.ppid as i32).to_le_bytes());
+    sink.write(&(process.pid as i32).to_le_bytes()); // pr_pgrp
+    sink.write(&(process.pid as i32).to_le_bytes()); // pr_sid
+    sink.write(&process.fname);
+    sink.write(&process.psargs);
+}
+
+/// Base64 encoder that emits 76-character lines through `out`
+pub struct Base64<F: FnMut(u8)> {
+    out: F,
+    pending: [u8; 3],
+    pending_len: usize,
+    line_len: usize,
+}
+
+impl<F: FnMut(u8)> Base64<F> {
+    const ALPHABET: &'static [u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const LINE_LEN: usize = 76;
+
+    pub fn new(out: F) -> Self {
+        Base64 {
+            out,
+            pending: [0; 3],
+            pending_len: 0,
+            line_len: 0,
+        }
+    }
+
+    fn emit(&mut self, chunk: [u8; 3], len: usize) {
+        let n = u32::from_be_bytes([0, chunk[0], chunk[1], chunk[2]]);
+        for i in 0..4 {
+            let c = if i <= len {
+                Self::ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]
+            } else {
+                b'='
+            };
+            (self.out)(c);
+        }
+        self.line_len += 4;
+        if self.line_len >= Self::LINE_LEN {
+            (self.out)(b'\n');
+            self.line_len = 0;
+        }
+    }
+
+    /// Encode any buffered bytes (with padding) and end the last line
+    pub fn finish(mut self) {
+        if self.pending_len > 0 {
+            let mut chunk = [0; 3];
+            chunk[..self.pending_len].copy_from_slice(&self.pending[..self.pending_len]);
+            self.emit(chunk, self.pending_len);
+        }
+        if self.line_len > 0 {
+            (self.out)(b'\n');
+        }
+    }
+}
+
+impl<F: FnMut(u8)> CoreSink for Base64<F> {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.pending[self.pending_len] = byte;
+            self.pending_len += 1;
+            if self.pending_len == 3 {
+                self.emit(self.pending, 3);
+                self.pending_len = 0;
+            }
+        }
+    }
+}
+
+/// Dump a killed process to the serial port
+///
+/// Called from exception handlers. Returns false (and writes nothing) if
+/// dumps are disabled or the serial port is in use by the code that
+/// faulted.
+pub fn dump_to_serial(process: &CoreProcess, regs: &CoreRegisters) -> bool {
+    use core::fmt::Write;
+
+    if !is_enabled() {
+        return false;
+    }
+    let mut serial = match crate::serial::SERIAL1.try_lock() {
+        Some(serial) => serial,
+        None => return false,
+    };
+
+    let _ = writeln!(
+        serial,
+        "-----BEGIN ORBITAL CORE pid={} name={}-----",
+        process.pid,
+        process.name()
+    );
+    let mut encoder = Base64::new(|byte| serial.send(byte));
+    write_core(&mut encoder, process, regs);
+    encoder.finish();
+    let _ = writeln!(serial, "-----END ORBITAL CORE-----");
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stack::StackSpec;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    fn read_u16(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn read_u64(bytes: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+    }
+
+    #[test_case]
+    fn test_base64_encoding() {
+        let mut out = Vec::new();
+        let mut encoder = Base64::new(|byte| out.push(byte));
+        encoder.write(b"core");
+        encoder.finish();
+        assert_eq!(out, b"Y29yZQ==\n");
+    }
+
+    #[test_case]
+    fn test_core_image_layout() {
+        let mut process = Process::new_with_name("shell", StackSpec::Fixed(0)).unwrap();
+        process.args.push(String::from("--login"));
+        process.parent_id = 1;
+        let pid = process.pid();
+
+        let dump = CoreProcess::capture(&process, pid, orbital_common::process::SIGSEGV);
+        assert_eq!(dump.segments().len(), 1);
+        assert_eq!(dump.name(), "shell");
+
+        let regs = CoreRegisters {
+            rip: 0x1234,
+            ..CoreRegisters::default()
+        };
+        let mut image = Vec::new();
+        write_core(&mut image, &dump, &regs);
+
+        // ELF core for x86_64 with a note and one load segment
+        assert_eq!(&image[..4], b"\x7fELF");
+        assert_eq!(read_u16(&image, 16), ET_CORE);
+        assert_eq!(read_u16(&image, 18), EM_X86_64);
+        assert_eq!(read_u16(&image, 56), 2);
+
+        // The load segment's data is the stack, at the end of the file
+        let load = (ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE) as usize;
+        let offset = read_u64(&image, load + 8) as usize;
+        let size = read_u64(&image, load + 32) as usize;
+        assert_eq!(
+            read_u64(&image, load + 16),
+            process.main_thread().stack.bottom()
+        );
+        assert_eq!(offset + size, image.len());
+
+        // pr_reg.rip inside NT_PRSTATUS (after the 20-byte note header)
+        let notes = (ELF_HEADER_SIZE + 2 * PROGRAM_HEADER_SIZE) as usize;
+        assert_eq!(read_u64(&image, notes + 20 + 112 + 16 * 8), 0x1234);
+
+        // pr_psargs holds the command line
+        let psargs = notes + 2 * 20 + PRSTATUS_SIZE as usize + 56;
+        assert_eq!(&image[psargs..psargs + 14], b"shell --login\0");
+    }
+}
@@ -1,6 +1,8 @@
 //! ELF Binary Format Loader
 //!
 //! Phase 5: Parses ELF headers and extracts binary entry point and sections.
+//! The `PT_TLS` program header, if present, describes the thread-local
+//! storage template each thread's TLS block is initialised from.
 //! Supports x86_64 ELF LSB format (little-endian, 64-bit).
 //!
 //! ELF Header Format:
@@ -43,6 +45,12 @@ const ELF_TYPE_EXECUTABLE: u16 = 2;
 /// ELF machine type: x86_64
 const ELF_MACHINE_X86_64: u16 = 0x3e;
 
+/// Program header type: thread-local storage template
+const PT_TLS: u32 = 7;
+
+/// Size of a 64-bit program header entry
+const PROGRAM_HEADER_SIZE: usize = 56;
+
 /// ELF format error types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ElfError {
@@ -60,15 +68,34 @@ pub enum ElfError {
     TooSmall,
     /// Version mismatch
     BadVersion,
+    /// Program header table is truncated or malformed
+    BadProgramHeader,
+}
+
+/// `PT_TLS` segment: the initial image of a thread's TLS block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlsSegment {
+    /// Virtual address of the template
+    pub vaddr: u64,
+    /// File offset of the initialised data (`.tdata`)
+    pub offset: u64,
+    /// Bytes of initialised data
+    pub file_size: u64,
+    /// Total size including zero-filled `.tbss`
+    pub mem_size: u64,
+    /// Required alignment of the block
+    pub align: u64,
 }
 
 /// Parsed ELF executable information (minimal)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ElfInfo {
     /// Virtual address where execution should start
     pub entry_point: u64,
     /// Size of the entire binary
     pub size: u64,
+    /// Thread-local storage template, if the binary has one
+    pub tls: Option<TlsSegment>,
 }
 
 /// Parse ELF header from a binary blob
@@ -136,9 +163,67 @@ pub fn parse_elf(binary: &[u8]) -> Result<ElfInfo, ElfError> {
     Ok(ElfInfo {
         entry_point,
         size: binary.len() as u64,
+        tls: parse_tls(binary)?,
     })
 }
 
+/// Read a little-endian u64 at `offset`
+fn read_u64(binary: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&binary[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Find the `PT_TLS` program header, if any
+///
+/// Assumes the ELF header itself has already been validated.
+fn parse_tls(binary: &[u8]) -> Result<Option<TlsSegment>, ElfError> {
+    let ph_offset = read_u64(binary, 0x20) as usize;
+    let ph_entry_size = u16::from_le_bytes([binary[0x36], binary[0x37]]) as usize;
+    let ph_count = u16::from_le_bytes([binary[0x38], binary[0x39]]) as usize;
+
+    if ph_count == 0 {
+        return Ok(None);
+    }
+    if ph_entry_size < PROGRAM_HEADER_SIZE {
+        return Err(ElfError::BadProgramHeader);
+    }
+    let table_end = ph_entry_size
+        .checked_mul(ph_count)
+        .and_then(|size| size.checked_add(ph_offset))
+        .ok_or(ElfError::BadProgramHeader)?;
+    if table_end > binary.len() {
+        return Err(ElfError::BadProgramHeader);
+    }
+
+    for i in 0..ph_count {
+        let header = ph_offset + i * ph_entry_size;
+        let p_type = u32::from_le_bytes([
+            binary[header],
+            binary[header + 1],
+            binary[header + 2],
+            binary[header + 3],
+        ]);
+        if p_type != PT_TLS {
+            continue;
+        }
+
+        let segment = TlsSegment {
+            offset: read_u64(binary, header + 0x08),
+            vaddr: read_u64(binary, header + 0x10),
+            file_size: read_u64(binary, header + 0x20),
+            mem_size: read_u64(binary, header + 0x28),
+            align: read_u64(binary, header + 0x30).max(1),
+        };
+        if segment.file_size > segment.mem_size || !segment.align.is_power_of_two() {
+            return Err(ElfError::BadProgramHeader);
+        }
+        return Ok(Some(segment));
+    }
+
+    Ok(None)
+}
+
 /// Validate an ELF header without full parsing
 ///
 /// Quick check to ensure binary is a valid ELF executable.
@@ -151,19 +236,20 @@ pub fn is_valid_elf(binary: &[u8]) -> bool {
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_invalid_magic() {
-        let bad_magic = b"NotELF\x00\x00";
-        assert_eq!(parse_elf(bad_magic), Err(ElfError::BadMagic));
+        let mut bad_magic = [0u8; 64];
+        bad_magic[..6].copy_from_slice(b"NotELF");
+        assert_eq!(parse_elf(&bad_magic), Err(ElfError::BadMagic));
     }
 
-    #[test]
+    #[test_case]
     fn test_too_small() {
         let small = b"ELF";
         assert_eq!(parse_elf(small), Err(ElfError::TooSmall));
     }
 
-    #[test]
+    #[test_case]
     fn test_valid_minimal_elf() {
         // Construct minimal valid ELF header for testing
         let mut header = [0u8; 64];
@@ -187,5 +273,35 @@ mod tests {
         let result = parse_elf(&header);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().entry_point, 0x1000);
+        assert_eq!(result.unwrap().tls, None);
+    }
+
+    #[test_case]
+    fn test_tls_program_header() {
+        let mut binary = [0u8; 64 + PROGRAM_HEADER_SIZE];
+
+        binary[0..4].copy_from_slice(ELF_MAGIC);
+        binary[4] = ELF_CLASS_64BIT;
+        binary[5] = ELF_DATA_LSB;
+        binary[6] = 1;
+        binary[16..18].copy_from_slice(&ELF_TYPE_EXECUTABLE.to_le_bytes());
+        binary[18..20].copy_from_slice(&ELF_MACHINE_X86_64.to_le_bytes());
+        // One program header right after the ELF header
+        binary[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        binary[0x36..0x38].copy_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+        binary[0x38..0x3A].copy_from_slice(&1u16.to_le_bytes());
+
+        let ph = &mut binary[64..];
+        ph[0..4].copy_from_slice(&PT_TLS.to_le_bytes());
+        ph[0x08..0x10].copy_from_slice(&0x40u64.to_le_bytes()); // offset
+        ph[0x20..0x28].copy_from_slice(&8u64.to_le_bytes()); // file size
+        ph[0x28..0x30].copy_from_slice(&24u64.to_le_bytes()); // mem size
+        ph[0x30..0x38].copy_from_slice(&8u64.to_le_bytes()); // align
+
+        let tls = parse_elf(&binary).unwrap().tls.unwrap();
+        assert_eq!(tls.offset, 0x40);
+        assert_eq!(tls.file_size, 8);
+        assert_eq!(tls.mem_size, 24);
+        assert_eq!(tls.align, 8);
     }
 }
@@ -0,0 +1,180 @@
+//! FPU/SSE/AVX register state
+//!
+//! The kernel itself is built soft-float, so the x87, SSE and AVX registers
+//! only ever hold userspace state. Each thread owns an `FpuState` area and
+//! the state is switched eagerly: when a thread stops running its registers
+//! are saved into its area, and the next thread's area is loaded before it
+//! runs.
+//!
+//! XSAVE is used when the CPU supports it (this covers the AVX upper
+//! halves); otherwise FXSAVE saves the x87 and SSE state.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x86_64::registers::control::{Cr0, Cr0Flags, Cr4, Cr4Flags};
+use x86_64::registers::xcontrol::{XCr0, XCr0Flags};
+
+/// Size of a per-thread save area
+///
+/// Large enough for the legacy region, the XSAVE header and the AVX
+/// component; CPUs whose XSAVE layout does not fit fall back to FXSAVE.
+pub const FPU_AREA_SIZE: usize = 1024;
+
+/// Offset of the MXCSR register in the legacy save region
+const MXCSR_OFFSET: usize = 24;
+
+/// x87 control word after `fninit`
+const DEFAULT_FCW: u16 = 0x037F;
+
+/// MXCSR after reset: all exceptions masked, round to nearest
+const DEFAULT_MXCSR: u32 = 0x1F80;
+
+/// Whether XSAVE/XRSTOR are in use (set by `init`)
+static XSAVE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// State components saved by XSAVE (mirrors XCR0)
+static XSAVE_MASK: AtomicU64 = AtomicU64::new(0);
+
+/// Enable the FPU and SSE, and XSAVE/AVX if the CPU supports them
+pub fn init() {
+    unsafe {
+        Cr0::update(|flags| {
+            flags.remove(Cr0Flags::EMULATE_COPROCESSOR);
+            flags.insert(Cr0Flags::MONITOR_COPROCESSOR);
+        });
+        Cr4::update(|flags| flags.insert(Cr4Flags::OSFXSR | Cr4Flags::OSXMMEXCPT_ENABLE));
+    }
+
+    // CPUID.01H:ECX bit 26 = XSAVE, bit 28 = AVX
+    let leaf1 = core::arch::x86_64::__cpuid(1);
+    if leaf1.ecx & (1 << 26) == 0 {
+        return;
+    }
+
+    let mut components = XCr0Flags::X87 | XCr0Flags::SSE;
+    if leaf1.ecx & (1 << 28) != 0 {
+        components |= XCr0Flags::AVX;
+    }
+
+    unsafe {
+        Cr4::update(|flags| flags.insert(Cr4Flags::OSXSAVE));
+        XCr0::write(components);
+    }
+
+    // CPUID.(EAX=0DH, ECX=0):EBX = save area size for the enabled components
+    let size = core::arch::x86_64::__cpuid_count(0xD, 0).ebx as usize;
+    if size > FPU_AREA_SIZE {
+        return;
+    }
+
+    XSAVE_MASK.store(components.bits(), Ordering::Relaxed);
+    XSAVE_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether thread state is saved with XSAVE rather than FXSAVE
+pub fn xsave_enabled() -> bool {
+    XSAVE_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Saved x87/SSE/AVX state of one thread
+#[derive(Clone)]
+#[repr(C, align(64))]
+pub struct FpuState {
+    area: [u8; FPU_AREA_SIZE],
+}
+
+impl FpuState {
+    /// The state a freshly started thread sees (as after `fninit`)
+    ///
+    /// The XSAVE header is left zeroed, so XRSTOR puts every extended
+    /// component in its initial configuration.
+    pub const fn new() -> Self {
+        let mut area = [0u8; FPU_AREA_SIZE];
+        let fcw = DEFAULT_FCW.to_le_bytes();
+        area[0] = fcw[0];
+        area[1] = fcw[1];
+        let mxcsr = DEFAULT_MXCSR.to_le_bytes();
+        area[MXCSR_OFFSET] = mxcsr[0];
+        area[MXCSR_OFFSET + 1] = mxcsr[1];
+        area[MXCSR_OFFSET + 2] = mxcsr[2];
+        area[MXCSR_OFFSET + 3] = mxcsr[3];
+        FpuState { area }
+    }
+
+    /// Save the CPU's current FPU/SIMD registers into this area
+    pub fn save(&mut self) {
+        let area = self.area.as_mut_ptr();
+        unsafe {
+            if xsave_enabled() {
+                let mask = XSAVE_MASK.load(Ordering::Relaxed);
+                core::arch::asm!(
+                    "xsave64 [{}]",
+                    in(reg) area,
+                    in("eax") mask as u32,
+                    in("edx") (mask >> 32) as u32,
+                    options(nostack, preserves_flags),
+                );
+            } else {
+                core::arch::asm!("fxsave64 [{}]", in(reg) area, options(nostack, preserves_flags));
+            }
+        }
+    }
+
+    /// Load this area into the CPU's FPU/SIMD registers
+    pub fn restore(&self) {
+        let area = self.area.as_ptr();
+        unsafe {
+            if xsave_enabled() {
+                let mask = XSAVE_MASK.load(Ordering::Relaxed);
+                core::arch::asm!(
+                    "xrstor64 [{}]",
+                    in(reg) area,
+                    in("eax") mask as u32,
+                    in("edx") (mask >> 32) as u32,
+                    options(nostack, preserves_flags, readonly),
+                );
+            } else {
+                core::arch::asm!(
+                    "fxrstor64 [{}]",
+                    in(reg) area,
+                    options(nostack, preserves_flags, readonly),
+                );
+            }
+        }
+    }
+
+    /// Saved MXCSR (SSE control/status) value
+    pub fn mxcsr(&self) -> u32 {
+        let bytes = &self.area[MXCSR_OFFSET..MXCSR_OFFSET + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+impl Default for FpuState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for FpuState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FpuState")
+            .field("mxcsr", &format_args!("{:#x}", self.mxcsr()))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_fpu_state_round_trip() {
+        let initial = FpuState::new();
+        assert_eq!(initial.mxcsr(), DEFAULT_MXCSR);
+
+        let mut saved = FpuState::new();
+        initial.restore();
+        saved.save();
+        assert_eq!(saved.mxcsr(), DEFAULT_MXCSR);
+    }
+}
@@ -1,38 +1,54 @@
+use crate::scheduler::KERNEL_TID;
+use crate::stack::{Stack, StackError};
+use alloc::boxed::Box;
 use lazy_static::lazy_static;
 use x86_64::VirtAddr;
 use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
 use x86_64::structures::tss::TaskStateSegment;
 
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+pub const PAGE_FAULT_IST_INDEX: u16 = 1;
+/// The timer interrupt switches threads, and the thread it switches away
+/// from may resume on another CPU before this one has returned from the
+/// interrupt, so the interrupt must not run on the thread's stack
+pub const TIMER_IST_INDEX: u16 = 2;
+
+/// Size of each interrupt stack
+const IST_STACK_SIZE: usize = 4096 * 5;
+
+/// Every IST index in use; each CPU has a stack for each
+///
+/// Page faults get their own stack so a task that overflowed into its
+/// guard page can still be reported and killed.
+const IST_INDEXES: [u16; 3] = [
+    DOUBLE_FAULT_IST_INDEX,
+    PAGE_FAULT_IST_INDEX,
+    TIMER_IST_INDEX,
+];
+
+/// The boot CPU's interrupt stacks, in `IST_INDEXES` order
+static mut BOOT_IST_STACKS: [[u8; IST_STACK_SIZE]; IST_INDEXES.len()] =
+    [[0; IST_STACK_SIZE]; IST_INDEXES.len()];
+
+/// Top of the `size`-byte stack at `start` (stacks grow down)
+fn stack_top(start: VirtAddr, size: usize) -> VirtAddr {
+    start + size as u64
+}
 
 lazy_static! {
     static ref TSS: TaskStateSegment = {
         let mut tss = TaskStateSegment::new();
-        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = 4096 * 5;
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-
-            let stack_start = VirtAddr::from_ptr(&raw const STACK);
-            let stack_end = stack_start + STACK_SIZE;
-            stack_end
-        };
+        let stacks = &raw const BOOT_IST_STACKS;
+        for (i, index) in IST_INDEXES.into_iter().enumerate() {
+            let start = VirtAddr::from_ptr(unsafe { &raw const (*stacks)[i] });
+            tss.interrupt_stack_table[index as usize] = stack_top(start, IST_STACK_SIZE);
+        }
         tss
     };
 }
 
 lazy_static! {
-    static ref GDT: (GlobalDescriptorTable, Selectors) = {
-        let mut gdt = GlobalDescriptorTable::new();
-        let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
-        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
-        (
-            gdt,
-            Selectors {
-                code_selector,
-                tss_selector,
-            },
-        )
-    };
+    static ref GDT: (GlobalDescriptorTable, Selectors) = build_gdt(&TSS);
 }
 
 struct Selectors {
@@ -40,13 +56,70 @@ struct Selectors {
     tss_selector: SegmentSelector,
 }
 
+/// A GDT holding the kernel code segment and `tss`
+fn build_gdt(tss: &'static TaskStateSegment) -> (GlobalDescriptorTable, Selectors) {
+    let mut gdt = GlobalDescriptorTable::new();
+    let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+    let tss_selector = gdt.add_entry(Descriptor::tss_segment(tss));
+    (
+        gdt,
+        Selectors {
+            code_selector,
+            tss_selector,
+        },
+    )
+}
+
+/// Descriptor tables of an application processor (see `smp`)
+///
+/// Each CPU needs a TSS of its own for its interrupt stacks, and so a GDT
+/// of its own to hold it. The layout is the boot CPU's, so
+/// `kernel_code_selector` is the same on every CPU.
+pub struct CpuTables {
+    gdt: GlobalDescriptorTable,
+    selectors: Selectors,
+}
+
+impl CpuTables {
+    /// Allocate the interrupt stacks, TSS and GDT of a CPU; they are never
+    /// freed
+    pub fn new() -> Result<&'static CpuTables, StackError> {
+        let mut tss = TaskStateSegment::new();
+        for index in IST_INDEXES {
+            let stack = Stack::new(IST_STACK_SIZE, KERNEL_TID)?;
+            let start = VirtAddr::new(stack.bottom());
+            tss.interrupt_stack_table[index as usize] = stack_top(start, stack.size());
+            core::mem::forget(stack);
+        }
+
+        let (gdt, selectors) = build_gdt(Box::leak(Box::new(tss)));
+        Ok(Box::leak(Box::new(CpuTables { gdt, selectors })))
+    }
+
+    /// Load the tables on the running CPU
+    pub fn load(&'static self) {
+        load(&self.gdt, &self.selectors);
+    }
+}
+
+/// Selector of the kernel code segment
+///
+/// Exception handlers use it to resume a killed task in kernel mode.
+pub fn kernel_code_selector() -> SegmentSelector {
+    GDT.1.code_selector
+}
+
 pub fn init() {
+    load(&GDT.0, &GDT.1);
+}
+
+fn load(gdt: &'static GlobalDescriptorTable, selectors: &Selectors) {
     use x86_64::instructions::segmentation::{CS, Segment};
     use x86_64::instructions::tables::load_tss;
 
-    GDT.0.load();
+    gdt.load();
     unsafe {
-        CS::set_reg(GDT.1.code_selector);
-        load_tss(GDT.1.tss_selector);
+        CS::set_reg(selectors.code_selector);
+        load_tss(selectors.tss_selector);
     }
 }
@@ -0,0 +1,280 @@
+//! HPET - High Precision Event Timer
+//!
+//! The HPET is a block of memory-mapped registers: a free-running main
+//! counter of at least 10 MHz, and a few comparators that raise an
+//! interrupt when the counter reaches them. `init` finds it through ACPI
+//! (`acpi::hpet_address`), starts the counter and offers it to `clock` as
+//! a clock source.
+//!
+//! Comparator 0 runs in one-shot mode, routed through the IOAPIC to
+//! `InterruptIndex::Hpet`. `timer` points it at the earliest pending
+//! deadline with `arm`, so sleepers wake when their time is up instead of
+//! at the next tick. Without the APICs the counter still serves as a
+//! clock, and sleeps end on the tick.
+//!
+//! Only HPETs with a 64-bit main counter are used; a 32-bit one wraps
+//! within minutes.
+
+use crate::acpi::IrqRoute;
+use crate::clock::ClockSource;
+use crate::interrupts::InterruptIndex;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x86_64::{PhysAddr, VirtAddr};
+
+/// Femtoseconds in a second; the counter period is given in femtoseconds
+const FEMTOS_PER_SEC: u64 = 1_000_000_000_000_000;
+
+/// Femtoseconds in a nanosecond
+const FEMTOS_PER_NANO: u64 = 1_000_000;
+
+/// Longest counter period the specification allows (100 ns)
+const MAX_PERIOD_FS: u64 = 100_000_000;
+
+/// General registers (offsets from the base)
+const CAPABILITIES: usize = 0x000;
+const CONFIG: usize = 0x010;
+const MAIN_COUNTER: usize = 0x0F0;
+
+/// Comparator registers, repeated every `TIMER_STRIDE` bytes
+const TIMER_CONFIG: usize = 0x100;
+const TIMER_COMPARATOR: usize = 0x108;
+const TIMER_STRIDE: usize = 0x20;
+
+/// Capabilities: the main counter is 64 bits wide
+const CAP_COUNTER_64: u64 = 1 << 13;
+
+/// General configuration: counter running, legacy replacement routing
+const CONFIG_ENABLE: u64 = 1 << 0;
+const CONFIG_LEGACY: u64 = 1 << 1;
+
+/// Comparator configuration bits
+const TIMER_LEVEL: u64 = 1 << 1;
+const TIMER_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_64BIT_CAPABLE: u64 = 1 << 5;
+const TIMER_32BIT_MODE: u64 = 1 << 8;
+const TIMER_ROUTE_SHIFT: u32 = 9;
+const TIMER_ROUTE_MASK: u64 = 0x1F << TIMER_ROUTE_SHIFT;
+const TIMER_FSB: u64 = 1 << 14;
+
+/// Comparator used for one-shot wakeups
+const ONESHOT_TIMER: usize = 0;
+
+/// Virtual address of the HPET registers (0 = no HPET)
+static HPET_BASE: AtomicU64 = AtomicU64::new(0);
+
+/// Main counter period, in femtoseconds
+static PERIOD_FS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the one-shot comparator is routed to `InterruptIndex::Hpet`
+static ONESHOT: AtomicBool = AtomicBool::new(false);
+
+/// Deadline the one-shot comparator is set for, in nanoseconds
+static ARMED: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the HPET's counter is running
+pub fn is_enabled() -> bool {
+    HPET_BASE.load(Ordering::Relaxed) != 0
+}
+
+/// Whether `arm` can raise interrupts
+pub fn has_oneshot() -> bool {
+    ONESHOT.load(Ordering::Relaxed)
+}
+
+/// Main counter frequency in Hz, if the HPET is in use
+pub fn frequency() -> Option<u64> {
+    match PERIOD_FS.load(Ordering::Relaxed) {
+        0 => None,
+        period => Some(FEMTOS_PER_SEC / period),
+    }
+}
+
+/// Current main counter value, if the HPET is in use
+pub fn counter() -> Option<u64> {
+    Hpet::current().map(|hpet| hpet.read(MAIN_COUNTER))
+}
+
+/// Convert main counter ticks to nanoseconds
+pub fn counter_to_ns(count: u64) -> u64 {
+    ticks_to_ns(count, PERIOD_FS.load(Ordering::Relaxed))
+}
+
+/// Nanoseconds in `count` ticks of `period` femtoseconds
+fn ticks_to_ns(count: u64, period: u64) -> u64 {
+    (count as u128 * period as u128 / FEMTOS_PER_NANO as u128) as u64
+}
+
+/// Ticks of `period` femtoseconds in `ns` nanoseconds, rounding up
+fn ns_to_ticks(ns: u64, period: u64) -> u64 {
+    (ns as u128 * FEMTOS_PER_NANO as u128).div_ceil(period as u128) as u64
+}
+
+/// The HPET's registers
+#[derive(Clone, Copy)]
+struct Hpet {
+    base: VirtAddr,
+}
+
+impl Hpet {
+    fn current() -> Option<Hpet> {
+        match HPET_BASE.load(Ordering::Relaxed) {
+            0 => None,
+            base => Some(Hpet {
+                base: VirtAddr::new(base),
+            }),
+        }
+    }
+
+    fn read(self, reg: usize) -> u64 {
+        unsafe { core::ptr::read_volatile((self.base + reg as u64).as_ptr::<u64>()) }
+    }
+
+    fn write(self, reg: usize, value: u64) {
+        unsafe { core::ptr::write_volatile((self.base + reg as u64).as_mut_ptr::<u64>(), value) }
+    }
+}
+
+/// Raise `InterruptIndex::Hpet` at `deadline`, in `clock::monotonic_ns`
+/// nanoseconds
+///
+/// Replaces the deadline set before. Returns false if there is no one-shot
+/// comparator or the deadline has passed; the next tick must then see to
+/// it.
+pub fn arm(deadline: u64) -> bool {
+    let hpet = match Hpet::current() {
+        Some(hpet) if has_oneshot() => hpet,
+        _ => return false,
+    };
+    let now = crate::clock::monotonic_ns();
+    if deadline <= now {
+        return false;
+    }
+    if ARMED.swap(deadline, Ordering::Relaxed) == deadline {
+        return true;
+    }
+
+    let period = PERIOD_FS.load(Ordering::Relaxed);
+    let target = hpet
+        .read(MAIN_COUNTER)
+        .wrapping_add(ns_to_ticks(deadline - now, period));
+    hpet.write(TIMER_COMPARATOR + ONESHOT_TIMER * TIMER_STRIDE, target);
+
+    // One-shot comparators fire on an exact match: a target the counter
+    // went past while it was being written would never fire
+    if hpet.read(MAIN_COUNTER) >= target {
+        ARMED.store(0, Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+
+/// Route the one-shot comparator to `InterruptIndex::Hpet`
+fn init_oneshot(hpet: Hpet) -> bool {
+    let reg = TIMER_CONFIG + ONESHOT_TIMER * TIMER_STRIDE;
+    let config = hpet.read(reg);
+    if config & TIMER_64BIT_CAPABLE == 0 {
+        return false;
+    }
+
+    // The high half lists the IOAPIC inputs the comparator can drive. Try
+    // the highest first: the low ones are ISA IRQs.
+    let routes = (config >> 32) as u32;
+    let mut gsi = 32;
+    loop {
+        if gsi == 0 {
+            return false;
+        }
+        gsi -= 1;
+        let route = IrqRoute {
+            gsi,
+            active_low: false,
+            level_triggered: false,
+        };
+        if routes & (1 << gsi) != 0 && crate::apic::route_gsi(route, InterruptIndex::Hpet as u8) {
+            break;
+        }
+    }
+
+    // Edge-triggered one-shot, never matching until armed
+    let config = (config
+        & !(TIMER_LEVEL | TIMER_PERIODIC | TIMER_32BIT_MODE | TIMER_ROUTE_MASK | TIMER_FSB))
+        | ((gsi as u64) << TIMER_ROUTE_SHIFT);
+    hpet.write(reg, config);
+    hpet.write(TIMER_COMPARATOR + ONESHOT_TIMER * TIMER_STRIDE, u64::MAX);
+    hpet.write(reg, config | TIMER_ENABLE);
+    true
+}
+
+/// Start the HPET and make it a clock source
+///
+/// Call once, after `memory::install` and `apic::init`. Returns false if
+/// there is no HPET table or the HPET is unusable (bad period, 32-bit
+/// counter). The one-shot comparator is only set up when the APICs are in
+/// use.
+pub fn init() -> bool {
+    if is_enabled() {
+        return true;
+    }
+    let address = match crate::acpi::hpet_address() {
+        Some(address) => address,
+        None => return false,
+    };
+    let base = match crate::memory::map_mmio(PhysAddr::new(address)) {
+        Some(base) => base,
+        None => return false,
+    };
+    let hpet = Hpet { base };
+
+    let capabilities = hpet.read(CAPABILITIES);
+    let period = capabilities >> 32;
+    if period == 0 || period > MAX_PERIOD_FS || capabilities & CAP_COUNTER_64 == 0 {
+        return false;
+    }
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        // Restart the counter from zero, without legacy replacement: the
+        // PIT and RTC keep their IRQs
+        let config = hpet.read(CONFIG) & !(CONFIG_ENABLE | CONFIG_LEGACY);
+        hpet.write(CONFIG, config);
+        hpet.write(MAIN_COUNTER, 0);
+        hpet.write(CONFIG, config | CONFIG_ENABLE);
+
+        PERIOD_FS.store(period, Ordering::Relaxed);
+        HPET_BASE.store(base.as_u64(), Ordering::Relaxed);
+        if crate::apic::is_enabled() && init_oneshot(hpet) {
+            ONESHOT.store(true, Ordering::Relaxed);
+        }
+    });
+
+    crate::clock::offer(ClockSource::Hpet);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// QEMU's HPET: 100 MHz
+    const QEMU_PERIOD_FS: u64 = 10_000_000;
+
+    /// A common chipset HPET: 14.31818 MHz
+    const ICH_PERIOD_FS: u64 = 69_841_279;
+
+    #[test_case]
+    fn test_tick_conversions() {
+        assert_eq!(ticks_to_ns(100_000_000, QEMU_PERIOD_FS), 1_000_000_000);
+        assert_eq!(ns_to_ticks(1_000, QEMU_PERIOD_FS), 100);
+        assert_eq!(ticks_to_ns(14_318_180, ICH_PERIOD_FS) / 1_000_000, 1000);
+        // Rounding up: a comparator never fires early
+        assert_eq!(ns_to_ticks(1, ICH_PERIOD_FS), 1);
+        assert!(ticks_to_ns(ns_to_ticks(12_345, ICH_PERIOD_FS), ICH_PERIOD_FS) >= 12_345);
+    }
+
+    #[test_case]
+    fn test_arm_without_hpet() {
+        // Tests run without `init`: no comparator to arm
+        assert!(!has_oneshot());
+        assert!(!arm(u64::MAX));
+    }
+}
@@ -3,18 +3,46 @@
 //! Provides a character queue that gets filled as users type in the terminal.
 //! sys_read syscall reads from this queue.
 //!
+//! A read with nothing to return waits for input: a thread blocks in
+//! `read_blocking`, an in-kernel task awaits `read`, and `add_input_char`
+//! wakes both. A thread is marked Blocked and listed as a reader under the
+//! same lock that `add_input_char` takes to queue a character, so input
+//! arriving while it goes to sleep always finds it listed.
+//!
 //! Uses lazy initialization to avoid heap allocation during early kernel init.
 
+use crate::process::ProcessStatus;
+use alloc::vec::Vec;
 use conquer_once::spin::OnceCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 use crossbeam_queue::ArrayQueue;
+use futures_util::task::AtomicWaker;
 use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+/// Characters typed and not yet read, and the threads waiting for them
+struct Stdin {
+    chars: ArrayQueue<u8>,
+    /// Threads blocked in `read_blocking`
+    readers: Vec<u64>,
+}
 
-static INPUT_BUFFER: OnceCell<Mutex<ArrayQueue<u8>>> = OnceCell::uninit();
+static INPUT_BUFFER: OnceCell<Mutex<Stdin>> = OnceCell::uninit();
 static SCANCODE_BUFFER: OnceCell<Mutex<ArrayQueue<u8>>> = OnceCell::uninit();
 
+/// Wakes the task awaiting `read`
+static READ_WAKER: AtomicWaker = AtomicWaker::new();
+
 /// Get or initialize the input buffer on first access
-fn get_or_init_buffer() -> &'static Mutex<ArrayQueue<u8>> {
-    INPUT_BUFFER.get_or_init(|| Mutex::new(ArrayQueue::new(256)))
+fn get_or_init_buffer() -> &'static Mutex<Stdin> {
+    INPUT_BUFFER.get_or_init(|| {
+        Mutex::new(Stdin {
+            chars: ArrayQueue::new(256),
+            readers: Vec::new(),
+        })
+    })
 }
 
 /// Get or initialize the scancode buffer
@@ -23,9 +51,20 @@ fn get_or_init_scancode_buffer() -> &'static Mutex<ArrayQueue<u8>> {
 }
 
 /// Add a character to the input buffer (from keyboard input)
+///
+/// Wakes every waiting reader. Called from the terminal task, not from
+/// the keyboard interrupt: waking a thread may have to wait for the
+/// scheduler's lock.
 pub fn add_input_char(ch: u8) {
-    let buf = get_or_init_buffer().lock();
-    let _ = buf.push(ch);
+    let readers = interrupts::without_interrupts(|| {
+        let mut stdin = get_or_init_buffer().lock();
+        let _ = stdin.chars.push(ch);
+        core::mem::take(&mut stdin.readers)
+    });
+    for tid in readers {
+        crate::scheduler::wake(tid);
+    }
+    READ_WAKER.wake();
 }
 
 /// Add a scancode for terminal_main to read
@@ -40,12 +79,11 @@ pub fn get_scancode() -> Option<u8> {
     buf.pop()
 }
 
-/// Read up to `len` bytes from the input buffer into `buf`
-pub fn read_input(buf: &mut [u8]) -> usize {
-    let q = get_or_init_buffer().lock();
+/// Move queued characters into `buf`, returning how many
+fn pop_into(chars: &ArrayQueue<u8>, buf: &mut [u8]) -> usize {
     let mut count = 0;
     for byte in buf {
-        match q.pop() {
+        match chars.pop() {
             Some(ch) => {
                 *byte = ch;
                 count += 1;
@@ -56,13 +94,108 @@ pub fn read_input(buf: &mut [u8]) -> usize {
     count
 }
 
+/// Read up to `len` bytes from the input buffer into `buf`
+///
+/// Never waits: returns 0 if nothing has been typed.
+pub fn read_input(buf: &mut [u8]) -> usize {
+    interrupts::without_interrupts(|| pop_into(&get_or_init_buffer().lock().chars, buf))
+}
+
+/// Read at least one byte into `buf`, blocking the current thread until
+/// input arrives
+///
+/// Returns 0 if the thread was killed while it waited. The kernel's own
+/// context has no thread to block, and the input it would wait for comes
+/// from its own terminal task: it gets what `read_input` returns.
+pub fn read_blocking(buf: &mut [u8]) -> usize {
+    let tid = match crate::scheduler::current_thread() {
+        Some(tid) => tid,
+        None => return read_input(buf),
+    };
+
+    loop {
+        let read = interrupts::without_interrupts(|| {
+            let mut stdin = get_or_init_buffer().lock();
+            let count = pop_into(&stdin.chars, buf);
+            if count == 0 {
+                if !crate::process::block_thread(tid) {
+                    return None;
+                }
+                stdin.readers.push(tid);
+            }
+            Some(count)
+        });
+        match read {
+            Some(0) => {}
+            Some(count) => return count,
+            None => return 0,
+        }
+
+        // The next tick switches away; we get here again once woken
+        crate::scheduler::request_reschedule();
+        while crate::process::get_thread_status(tid) == Some(ProcessStatus::Blocked) {
+            x86_64::instructions::hlt();
+        }
+    }
+}
+
+/// Future returned by `read`
+pub struct Read<'a> {
+    buf: &'a mut [u8],
+}
+
+/// Read at least one byte into `buf`, from an async task
+///
+/// One task reads at a time: a second one would take the first's wakeup.
+pub fn read(buf: &mut [u8]) -> Read<'_> {
+    Read { buf }
+}
+
+impl Future for Read<'_> {
+    /// Bytes read
+    type Output = usize;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<usize> {
+        // fast path
+        let count = read_input(self.buf);
+        if count > 0 {
+            return Poll::Ready(count);
+        }
+
+        READ_WAKER.register(cx.waker());
+        match read_input(self.buf) {
+            0 => Poll::Pending,
+            count => {
+                READ_WAKER.take();
+                Poll::Ready(count)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::task::Waker;
 
-    #[test]
+    #[test_case]
     fn test_input_buffer_init() {
         // Just verify it initializes without panic
         // (Note: can only initialize once per test run)
     }
+
+    #[test_case]
+    fn test_read_waits_for_input() {
+        let mut context = Context::from_waker(Waker::noop());
+        let mut buf = [0u8; 4];
+        while read_input(&mut buf) > 0 {}
+
+        let mut reading = read(&mut buf);
+        assert_eq!(Pin::new(&mut reading).poll(&mut context), Poll::Pending);
+        add_input_char(b'h');
+        add_input_char(b'i');
+        assert_eq!(Pin::new(&mut reading).poll(&mut context), Poll::Ready(2));
+        assert_eq!(&buf[..2], b"hi");
+        assert_eq!(read_input(&mut buf), 0);
+    }
 }
@@ -1,7 +1,12 @@
+use crate::context_switch::InterruptFrame;
+use crate::percpu::UserEntry;
+use crate::process::ExitReason;
 use crate::{gdt, hlt_loop, println};
 use lazy_static::lazy_static;
+use orbital_common::process::{SIGBUS, SIGFPE, SIGILL, SIGSEGV, signal_name};
 use pic8259::ChainedPics;
 use spin;
+use x86_64::VirtAddr;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
 
 pub const PIC_1_OFFSET: u8 = 32;
@@ -12,6 +17,8 @@ pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
     Keyboard,
+    /// HPET one-shot comparator; only routed when the APICs are in use
+    Hpet,
 }
 
 impl InterruptIndex {
@@ -31,14 +38,38 @@ lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
         idt.breakpoint.set_handler_fn(breakpoint_handler);
-        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.divide_error.set_handler_fn(divide_error_handler);
+        idt.overflow.set_handler_fn(overflow_handler);
+        idt.bound_range_exceeded
+            .set_handler_fn(bound_range_exceeded_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
+        idt.segment_not_present
+            .set_handler_fn(segment_not_present_handler);
+        idt.stack_segment_fault
+            .set_handler_fn(stack_segment_fault_handler);
+        idt.general_protection_fault
+            .set_handler_fn(general_protection_fault_handler);
+        idt.x87_floating_point
+            .set_handler_fn(x87_floating_point_handler);
+        idt.alignment_check.set_handler_fn(alignment_check_handler);
+        idt.simd_floating_point
+            .set_handler_fn(simd_floating_point_handler);
         unsafe {
+            idt.page_fault
+                .set_handler_fn(page_fault_handler)
+                .set_stack_index(gdt::PAGE_FAULT_IST_INDEX);
             idt.double_fault
                 .set_handler_fn(double_fault_handler)
                 .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
         }
-        idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
+        unsafe {
+            idt[InterruptIndex::Timer.as_usize()]
+                .set_handler_addr(VirtAddr::new(timer_interrupt_entry as *const () as u64))
+                .set_stack_index(gdt::TIMER_IST_INDEX);
+        }
         idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+        idt[InterruptIndex::Hpet.as_usize()].set_handler_fn(hpet_interrupt_handler);
+        idt[crate::apic::SPURIOUS_VECTOR as usize].set_handler_fn(spurious_interrupt_handler);
         idt
     };
 }
@@ -47,16 +78,69 @@ pub fn init_idt() {
     IDT.load();
 }
 
+/// Load the IDT on an application processor (see `smp`)
+///
+/// Every CPU shares the same IDT.
+pub fn load_idt() {
+    IDT.load();
+}
+
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
 }
 
 extern "x86-interrupt" fn page_fault_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: PageFaultErrorCode,
 ) {
+    let entry = UserEntry::enter(stack_frame.code_segment);
+    handle_page_fault(&mut stack_frame, error_code);
+    entry.leave(stack_frame.code_segment);
+}
+
+/// Grow the faulting stack, or kill the faulting process
+fn handle_page_fault(stack_frame: &mut InterruptStackFrame, error_code: PageFaultErrorCode) {
     use x86_64::registers::control::Cr2;
 
+    let addr = Cr2::read().as_u64();
+
+    // Faults in the stack region: demand growth, overflow into the guard
+    // area, or a wild access inside a stack's reserve. These are charged to
+    // the stack's owner, but only if the owner is the thread that faulted:
+    // killing it would hijack the interrupted context and dump its
+    // registers as the owner's, so any other thread (or the executor)
+    // faulting in the slot is reported as a kernel fault.
+    let segfault = ExitReason::Killed {
+        signal: SIGSEGV,
+        rip: stack_frame.instruction_pointer.as_u64(),
+        addr: Some(addr),
+    };
+    let mut stack_fault = None;
+    if !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) {
+        use crate::stack::StackFault;
+
+        match crate::stack::handle_fault(addr) {
+            Some(StackFault::Grown) => return,
+            Some(StackFault::Overflow(stack)) => {
+                let rip = stack_frame.instruction_pointer.as_u64();
+                stack_fault = Some((stack.owner, ExitReason::StackOverflow { rip, addr }));
+            }
+            Some(StackFault::Segfault(stack)) => stack_fault = Some((stack.owner, segfault)),
+            None => {}
+        }
+    }
+
+    let killed = match stack_fault {
+        Some((owner, reason)) if Some(owner) == crate::scheduler::current_thread() => {
+            Some((owner, reason))
+        }
+        Some(_) => None,
+        None => faulting_user_thread(stack_frame).map(|tid| (tid, segfault)),
+    };
+    if killed.is_some_and(|(tid, reason)| kill_faulting_thread(stack_frame, tid, reason)) {
+        return;
+    }
+
     println!("EXCEPTION: PAGE FAULT");
     println!("Accessed Address: {:?}", Cr2::read());
     println!("Error Code: {:?}", error_code);
@@ -64,6 +148,163 @@ extern "x86-interrupt" fn page_fault_handler(
     hlt_loop();
 }
 
+/// The user thread that raised an exception, if any
+///
+/// A fault counts as a user fault when CS has RPL 3, or when a user thread
+/// is current. Programs do not run in ring 3 yet: they run in ring 0 as
+/// scheduled threads, so the current thread is what identifies user code
+/// for now.
+fn faulting_user_thread(stack_frame: &InterruptStackFrame) -> Option<u64> {
+    let tid = crate::scheduler::current_thread();
+    if stack_frame.code_segment & 3 == 3 {
+        debug_assert!(tid.is_some(), "user-mode fault with no current thread");
+    }
+    tid
+}
+
+/// Kill the process owning `tid` for `reason` and resume the interrupted
+/// task in an idle loop on the top of its (still mapped) stack until the
+/// scheduler switches away from it
+///
+/// Returns false if the process could not be killed (the fault interrupted
+/// the kernel while it held the process table), in which case the caller
+/// must handle it as a kernel fault.
+fn kill_faulting_thread(
+    stack_frame: &mut InterruptStackFrame,
+    tid: u64,
+    reason: ExitReason,
+) -> bool {
+    let (pid, stack_top) = match crate::process::kill_thread_owner(tid, reason) {
+        Some(killed) => killed,
+        None => return false,
+    };
+
+    if let ExitReason::StackOverflow { .. } = reason {
+        println!("stack overflow in PID {}", pid);
+    }
+    let dumped = dump_core(stack_frame, tid, reason.signal().unwrap_or(SIGSEGV));
+    println!(
+        "PID {} {}{}",
+        pid,
+        reason,
+        if dumped { " (core dumped)" } else { "" }
+    );
+
+    let user_mode = stack_frame.code_segment & 3 == 3;
+    unsafe {
+        stack_frame.as_mut().update(|frame| {
+            frame.instruction_pointer =
+                VirtAddr::new(crate::task_entry::killed_task_loop as *const () as u64);
+            frame.stack_pointer = VirtAddr::new(stack_top - 8);
+            if user_mode {
+                frame.code_segment = u64::from(gdt::kernel_code_selector().0);
+                frame.stack_segment = 0;
+            }
+        });
+    }
+
+    crate::scheduler::request_reschedule();
+    true
+}
+
+/// Write a core dump of the process owning `tid` to serial
+///
+/// Only the registers in the exception frame (plus the FS/GS bases, which
+/// still belong to the faulting thread) are known here; the general
+/// purpose registers are reported as zero.
+fn dump_core(stack_frame: &InterruptStackFrame, tid: u64, signal: u32) -> bool {
+    use crate::coredump::{CoreProcess, CoreRegisters};
+
+    let regs = CoreRegisters {
+        rip: stack_frame.instruction_pointer.as_u64(),
+        cs: stack_frame.code_segment,
+        rflags: stack_frame.cpu_flags,
+        rsp: stack_frame.stack_pointer.as_u64(),
+        ss: stack_frame.stack_segment,
+        fs_base: crate::tls::read_fs_base(),
+        gs_base: crate::tls::read_gs_base(),
+        ..CoreRegisters::default()
+    };
+
+    crate::process::try_with_thread_owner(tid, |process| CoreProcess::capture(process, tid, signal))
+        .is_some_and(|process| crate::coredump::dump_to_serial(&process, &regs))
+}
+
+/// Kill the current user process for a CPU exception, or panic if the
+/// exception came from the kernel itself
+fn handle_exception(stack_frame: &mut InterruptStackFrame, name: &str, signal: u32) {
+    let entry = UserEntry::enter(stack_frame.code_segment);
+    let tid = faulting_user_thread(stack_frame);
+    let reason = ExitReason::Killed {
+        signal,
+        rip: stack_frame.instruction_pointer.as_u64(),
+        addr: None,
+    };
+    if tid.is_some_and(|tid| kill_faulting_thread(stack_frame, tid, reason)) {
+        entry.leave(stack_frame.code_segment);
+        return;
+    }
+
+    panic!(
+        "EXCEPTION: {} ({})\n{:#?}",
+        name,
+        signal_name(signal),
+        stack_frame
+    );
+}
+
+extern "x86-interrupt" fn divide_error_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "DIVIDE ERROR", SIGFPE);
+}
+
+extern "x86-interrupt" fn overflow_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "OVERFLOW", SIGSEGV);
+}
+
+extern "x86-interrupt" fn bound_range_exceeded_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "BOUND RANGE EXCEEDED", SIGSEGV);
+}
+
+extern "x86-interrupt" fn invalid_opcode_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "INVALID OPCODE", SIGILL);
+}
+
+extern "x86-interrupt" fn segment_not_present_handler(
+    mut stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) {
+    handle_exception(&mut stack_frame, "SEGMENT NOT PRESENT", SIGBUS);
+}
+
+extern "x86-interrupt" fn stack_segment_fault_handler(
+    mut stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) {
+    handle_exception(&mut stack_frame, "STACK SEGMENT FAULT", SIGBUS);
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(
+    mut stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) {
+    handle_exception(&mut stack_frame, "GENERAL PROTECTION FAULT", SIGSEGV);
+}
+
+extern "x86-interrupt" fn x87_floating_point_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "X87 FLOATING POINT", SIGFPE);
+}
+
+extern "x86-interrupt" fn alignment_check_handler(
+    mut stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) {
+    handle_exception(&mut stack_frame, "ALIGNMENT CHECK", SIGBUS);
+}
+
+extern "x86-interrupt" fn simd_floating_point_handler(mut stack_frame: InterruptStackFrame) {
+    handle_exception(&mut stack_frame, "SIMD FLOATING POINT", SIGFPE);
+}
+
 extern "x86-interrupt" fn double_fault_handler(
     stack_frame: InterruptStackFrame,
     _error_code: u64,
@@ -71,25 +312,106 @@ extern "x86-interrupt" fn double_fault_handler(
     panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
-extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    // Tick the scheduler to count time ticks
-    let need_switch = crate::scheduler::timer_tick();
+/// Timer interrupt entry point
+///
+/// Saves every general purpose register on the timer's interrupt stack,
+/// next to the frame the CPU pushed there, so the handler sees the whole
+/// `InterruptFrame` and can replace it with another thread's registers
+/// before `iretq`. 15 pushes on top of the CPU's 5-word frame keep the
+/// stack 16-byte aligned for the call.
+///
+/// Coming from user mode (RPL 3 in the saved CS) it loads the kernel's GS
+/// base with `swapgs`, and swaps back if the frame it returns through,
+/// possibly another thread's, goes to user mode.
+#[unsafe(naked)]
+extern "C" fn timer_interrupt_entry() {
+    core::arch::naked_asm!(
+        "test qword ptr [rsp + 8], 3",
+        "jz 2f",
+        "swapgs",
+        "2:",
+        "push rax",
+        "push rbx",
+        "push rcx",
+        "push rdx",
+        "push rsi",
+        "push rdi",
+        "push rbp",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov rdi, rsp",
+        "cld",
+        "call {handler}",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rbp",
+        "pop rdi",
+        "pop rsi",
+        "pop rdx",
+        "pop rcx",
+        "pop rbx",
+        "pop rax",
+        "test qword ptr [rsp + 8], 3",
+        "jz 3f",
+        "swapgs",
+        "3:",
+        "iretq",
+        handler = sym timer_interrupt_handler,
+    );
+}
 
-    // Only perform context switch if preemption is enabled AND quantum expired
-    // When async executor is running, preemption is disabled (cooperative multitasking)
-    if crate::scheduler::is_preemption_enabled() && need_switch {
-        // Get next process from scheduler
-        let (current_pid, next_pid) = crate::scheduler::schedule();
+extern "C" fn timer_interrupt_handler(frame: &mut InterruptFrame) {
+    let cpu = crate::percpu::this_cpu();
+    cpu.end_idle();
 
-        // Perform context switch if there's a next process
-        if let Some(next) = next_pid {
-            crate::context_switch::context_switch(current_pid, Some(next));
-        }
+    // Tick the scheduler to count time ticks, then wake sleepers whose
+    // deadline has passed (the boot CPU keeps the timer queue)
+    let need_switch = crate::scheduler::timer_tick();
+    if crate::smp::cpu_id() == crate::smp::BOOT_CPU {
+        crate::timer::expire();
     }
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+    // Only perform context switch if preemption is enabled AND quantum
+    // expired, and the running code has not asked to keep the CPU, unless
+    // the watchdog found it has kept the CPU far too long
+    let preemptible = crate::scheduler::is_preemption_enabled() && !cpu.preempt_disabled();
+    let forced = crate::watchdog::check(frame.rip, preemptible);
+    if (preemptible && need_switch) || forced {
+        preempt(frame);
+    }
+
+    end_of_interrupt(InterruptIndex::Timer);
+}
+
+/// Switch to the next ready thread, if the process table and the
+/// scheduler can be reached
+///
+/// The process table is held from the pick to the end of the switch, so
+/// no other CPU can pick the outgoing thread before it is saved.
+fn preempt(frame: &mut InterruptFrame) {
+    let mut table = match crate::process::try_lock_for_switch() {
+        Some(table) => table,
+        None => return,
+    };
+    let (current, next) = match crate::scheduler::try_schedule(&|tid| table.sched_info(tid)) {
+        Some(switch) => switch,
+        None => return,
+    };
+
+    if current != next {
+        crate::context_switch::context_switch(frame, current, next, &mut table);
     }
 }
 
@@ -105,9 +427,29 @@ extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStac
     // Also add to async task keyboard stream for backward compatibility
     crate::task::keyboard::add_scancode(scancode);
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
+    end_of_interrupt(InterruptIndex::Keyboard);
+}
+
+/// The HPET comparator reached the earliest sleeper's deadline
+extern "x86-interrupt" fn hpet_interrupt_handler(stack_frame: InterruptStackFrame) {
+    let entry = UserEntry::enter(stack_frame.code_segment);
+    crate::timer::expire();
+    end_of_interrupt(InterruptIndex::Hpet);
+    entry.leave(stack_frame.code_segment);
+}
+
+/// The local APIC raises this when an interrupt goes away before the CPU
+/// accepts it. There is nothing to do, not even an EOI.
+extern "x86-interrupt" fn spurious_interrupt_handler(_stack_frame: InterruptStackFrame) {}
+
+/// Acknowledge a hardware interrupt at the controller that delivered it
+fn end_of_interrupt(index: InterruptIndex) {
+    if crate::apic::is_enabled() {
+        crate::apic::end_of_interrupt();
+    } else {
+        unsafe {
+            PICS.lock().notify_end_of_interrupt(index.as_u8());
+        }
     }
 }
 
@@ -116,29 +458,3 @@ fn test_breakpoint_exception() {
     // invoke a breakpoint exception
     x86_64::instructions::interrupts::int3();
 }
-
-// ============================================================================
-// Syscall Entry Point (Assembly Stub)
-// ============================================================================
-//
-// The syscall instruction (0x0F 0x05) is not routed through the IDT.
-// Instead, it uses Model-Specific Registers (MSRs):
-//   - IA32_LSTAR (0xC0000082): Entry point address
-//   - IA32_STAR (0xC0000081): Segment selectors and return location
-//   - IA32_FMASK (0xC0000084): RFLAGS mask
-//
-// When syscall is executed from userspace:
-//   1. RCX = return address (RIP)
-//   2. R11 = RFLAGS value on entry
-//   3. RAX = syscall number
-//   4. RDI, RSI, RDX, RCX, R8, R9 = arguments (System V AMD64 ABI)
-//
-// Entry point should:
-//   1. Save userspace context (RCX, R11)
-//   2. Set up kernel stack
-//   3. Call dispatch_syscall(rax, rdi, rsi, rdx, rcx, r8, r9)
-//   4. Return result in RAX
-//   5. sysret back to userspace
-//
-// TODO: Implement syscall_entry assembly and call init_syscall_msr() during boot
-// For now, syscall is not yet wired up. Full implementation in phase 2.
@@ -115,6 +115,7 @@ impl RingBuffer {
         unsafe {
             let messages = &mut *self.messages.get();
             messages.clear();
+            messages.reserve_exact(RING_BUFFER_SIZE);
             for _ in 0..RING_BUFFER_SIZE {
                 messages.push(RingMessage::new(0, 0, 0));
             }
@@ -221,7 +222,7 @@ impl Default for RingBuffer {
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_enqueue_dequeue() {
         let rb = RingBuffer::new();
         rb.init();
@@ -241,7 +242,7 @@ mod tests {
         assert_eq!(received.payload_slice(), b"hello");
     }
 
-    #[test]
+    #[test_case]
     fn test_empty() {
         let rb = RingBuffer::new();
         rb.init();
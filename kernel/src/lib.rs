@@ -8,30 +8,47 @@
 extern crate alloc;
 use core::panic::PanicInfo;
 
+pub mod acpi;
 pub mod allocator;
+pub mod apic;
 pub mod binary_loader;
+pub mod clock;
+pub mod cmdline;
 pub mod context_switch;
+pub mod coredump;
 pub mod elf_loader;
+pub mod fpu;
 pub mod gdt;
+pub mod hpet;
 pub mod input;
 pub mod interrupts;
 pub mod ipc;
 pub mod memory;
 pub mod multiprocess;
+pub mod percpu;
 pub mod process;
 pub mod scheduler;
 pub mod serial;
 pub mod shell_commands;
+pub mod smp;
+pub mod stack;
 pub mod syscall;
 pub mod task;
 pub mod task_entry;
 pub mod tasks;
+pub mod timer;
+pub mod tls;
 pub mod tty;
 pub mod vga_buffer;
+pub mod watchdog;
 
 pub fn init() {
     gdt::init();
+    percpu::init(smp::BOOT_CPU);
     interrupts::init_idt();
+    fpu::init();
+    tls::init();
+    clock::init();
     unsafe { interrupts::PICS.lock().initialize() };
     x86_64::instructions::interrupts::enable();
 }
@@ -95,8 +112,18 @@ entry_point!(test_kernel_main);
 
 /// Entry point for `cargo xtest`
 #[cfg(test)]
-fn test_kernel_main(_boot_info: &'static BootInfo) -> ! {
+fn test_kernel_main(boot_info: &'static BootInfo) -> ! {
+    use memory::BootInfoFrameAllocator;
+    use x86_64::VirtAddr;
+
     init();
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::install(mapper, frame_allocator);
+    scheduler::init(scheduler::DEFAULT_POLICY);
+
     test_main();
     hlt_loop();
 }
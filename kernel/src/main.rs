@@ -9,7 +9,7 @@ extern crate alloc;
 use bootloader::{BootInfo, entry_point};
 use core::panic::PanicInfo;
 use orbital_kernel::println;
-use orbital_kernel::task::{Task, executor::Executor};
+use orbital_kernel::task::executor::Executor;
 
 entry_point!(kernel_main);
 
@@ -24,31 +24,55 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
     let mut mapper = unsafe { memory::init(phys_mem_offset) };
     let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    orbital_kernel::smp::reserve_trampoline(&mut frame_allocator);
 
     allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::install(mapper, frame_allocator);
+    orbital_kernel::cmdline::init();
+    orbital_kernel::scheduler::init(orbital_kernel::scheduler::boot_policy());
+
+    if orbital_kernel::apic::init() {
+        println!("Interrupts: local APIC and IOAPIC");
+    } else {
+        println!("Interrupts: 8259 PIC");
+    }
+    if orbital_kernel::hpet::init() && !orbital_kernel::hpet::has_oneshot() {
+        println!("HPET: clock only, sleeps end on the timer tick");
+    }
+    println!("CPUs: {} online", orbital_kernel::smp::init());
+    let source = orbital_kernel::clock::clock_source();
+    let hz = orbital_kernel::clock::source_hz();
+    println!(
+        "Clock source: {} ({}.{:03} MHz), timer {} Hz",
+        source.name(),
+        hz / 1_000_000,
+        hz / 1_000 % 1_000,
+        orbital_kernel::clock::TIMER_HZ
+    );
 
     #[cfg(test)]
     test_main();
 
-    // Disable timer interrupt preemption - let the async executor manage scheduling
-    orbital_kernel::scheduler::disable_preemption();
+    println!("Scheduler policy: {}", orbital_kernel::scheduler::policy_name());
 
     let mut executor = Executor::new();
-    executor.spawn(Task::new(orbital_kernel::task::terminal::terminal()));
+    executor.spawn("terminal", orbital_kernel::task::terminal::terminal());
     
-    // Phase 6: Load and execute multiple concurrent userspace shells as tasks
-    match orbital_kernel::multiprocess::execute_multi_cli(3, &mut executor) {
-        Ok(()) => {
-            // Multiple userspace shells were spawned
-            crate::println!("[Phase 6] All shells spawned successfully");
-        }
-        Err(e) => {
-            println!("Error loading multiprocess shells: {}", e);
-            // Fall back to single kernel shell
-            println!("Falling back to kernel shell");
-            executor.spawn(Task::new(orbital_kernel::task::cli::shell()));
-        }
+    // Init (PID 1) reaches the kernel only through `syscall`, which has no
+    // entry point yet (docs/vision/syscall-skeleton.md), so the kernel
+    // shell stays the default; `init=on` on the command line starts init
+    if orbital_kernel::cmdline::get("init") != Some("on") {
+        executor.spawn("kshell", orbital_kernel::task::cli::shell());
+    } else if let Err(e) = orbital_kernel::multiprocess::execute_init() {
+        println!("Error starting init: {}", e);
+        // Fall back to single kernel shell
+        println!("Falling back to kernel shell");
+        executor.spawn("kshell", orbital_kernel::task::cli::shell());
     }
+
+    // From here on the timer interrupt switches between the executor and
+    // the scheduled processes
+    orbital_kernel::scheduler::enable_preemption();
     
     executor.run();
 }
@@ -1,7 +1,13 @@
+use alloc::vec::Vec;
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use conquer_once::spin::OnceCell;
+use spin::Mutex;
 use x86_64::{
     PhysAddr, VirtAddr,
-    structures::paging::{FrameAllocator, OffsetPageTable, PageTable, PhysFrame, Size4KiB},
+    structures::paging::{
+        FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags,
+        PhysFrame, Size4KiB, Translate,
+    },
 };
 
 /// Initialize a new OffsetPageTable.
@@ -75,6 +81,20 @@ impl BootInfoFrameAllocator {
         // create `PhysFrame` types from the start addresses
         frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
     }
+
+    /// Allocate a frame that lies entirely below physical address `limit`,
+    /// for hardware that cannot reach all of memory.
+    ///
+    /// Frames are handed out lowest first, so this only succeeds early in
+    /// boot, before low memory is used up.
+    pub fn allocate_frame_below(&mut self, limit: u64) -> Option<PhysFrame> {
+        let frame = self.usable_frames().nth(self.next)?;
+        if frame.start_address().as_u64() + frame.size() > limit {
+            return None;
+        }
+        self.next += 1;
+        Some(frame)
+    }
 }
 
 unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
@@ -84,3 +104,136 @@ unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
         frame
     }
 }
+
+/// Frame allocator that recycles freed frames before taking new ones
+/// from the bootloader's memory map.
+pub struct FramePool {
+    boot: BootInfoFrameAllocator,
+    free: Vec<PhysFrame>,
+}
+
+unsafe impl FrameAllocator<Size4KiB> for FramePool {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        self.free.pop().or_else(|| self.boot.allocate_frame())
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for FramePool {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+        self.free.push(frame);
+    }
+}
+
+/// Paging state for code that maps memory after boot (task stacks, ...)
+pub struct KernelMemory {
+    pub mapper: OffsetPageTable<'static>,
+    pub frames: FramePool,
+}
+
+/// Set by `install` once the heap is up
+static KERNEL_MEMORY: OnceCell<Mutex<KernelMemory>> = OnceCell::uninit();
+
+/// Hand the page table and frame allocator over to the kernel.
+///
+/// Must be called after the heap is initialized. Later calls are ignored.
+pub fn install(mapper: OffsetPageTable<'static>, frame_allocator: BootInfoFrameAllocator) {
+    let _ = KERNEL_MEMORY.try_init_once(|| {
+        Mutex::new(KernelMemory {
+            mapper,
+            frames: FramePool {
+                boot: frame_allocator,
+                free: Vec::new(),
+            },
+        })
+    });
+}
+
+/// Run `f` with exclusive access to the kernel's paging state.
+///
+/// Interrupts are disabled for the duration, so no interrupt handler runs
+/// on this CPU while the lock is held. Page faults still can, and this
+/// spins until the lock is free: the page fault handler must use
+/// `try_with_kernel_memory` instead, since the faulting code may be the
+/// lock holder. Returns `None` if `install` has not been called yet.
+pub fn with_kernel_memory<R>(f: impl FnOnce(&mut KernelMemory) -> R) -> Option<R> {
+    let memory = KERNEL_MEMORY.try_get().ok()?;
+    Some(x86_64::instructions::interrupts::without_interrupts(|| {
+        f(&mut memory.lock())
+    }))
+}
+
+/// Like `with_kernel_memory`, but gives up instead of waiting for the lock
+///
+/// Returns `None` if the lock is held or `install` has not been called yet.
+pub fn try_with_kernel_memory<R>(f: impl FnOnce(&mut KernelMemory) -> R) -> Option<R> {
+    let memory = KERNEL_MEMORY.try_get().ok()?;
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        memory.try_lock().map(|mut memory| f(&mut memory))
+    })
+}
+
+/// Where physical address `phys` appears in the bootloader's mapping of
+/// physical memory. Returns `None` before `install`.
+pub fn phys_to_virt(phys: PhysAddr) -> Option<VirtAddr> {
+    with_kernel_memory(|memory| memory.mapper.phys_offset() + phys.as_u64())
+}
+
+/// Make the page of device registers at `phys` accessible
+///
+/// The page is used at its place in the physical memory mapping. The
+/// bootloader maps everything up to the highest address in its memory map,
+/// which usually covers the APICs; otherwise the page is mapped there,
+/// uncached. Returns the virtual address of `phys`.
+pub fn map_mmio(phys: PhysAddr) -> Option<VirtAddr> {
+    with_kernel_memory(|memory| {
+        let virt = memory.mapper.phys_offset() + phys.as_u64();
+        if memory.mapper.translate_addr(virt).is_some() {
+            return Some(virt);
+        }
+
+        let page = Page::<Size4KiB>::containing_address(virt);
+        let frame = PhysFrame::containing_address(phys);
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::WRITE_THROUGH
+            | PageTableFlags::NO_CACHE;
+        unsafe { memory.mapper.map_to(page, frame, flags, &mut memory.frames) }
+            .ok()?
+            .flush();
+        Some(virt)
+    })
+    .flatten()
+}
+
+/// Map `frame` at its own physical address, executable, for code that
+/// runs with paging enabled before it can jump to the kernel's addresses
+/// (see `smp`). Returns false if the page is in use or out of memory.
+pub fn map_identity(frame: PhysFrame) -> bool {
+    with_kernel_memory(|memory| {
+        let page =
+            Page::<Size4KiB>::containing_address(VirtAddr::new(frame.start_address().as_u64()));
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        if let Ok(mapped) = memory.mapper.translate_page(page) {
+            return mapped == frame;
+        }
+        match unsafe { memory.mapper.map_to(page, frame, flags, &mut memory.frames) } {
+            Ok(flush) => {
+                flush.flush();
+                true
+            }
+            Err(_) => false,
+        }
+    })
+    .unwrap_or(false)
+}
+
+/// Remove a mapping made by `map_identity`
+pub fn unmap_identity(frame: PhysFrame) {
+    with_kernel_memory(|memory| {
+        let page =
+            Page::<Size4KiB>::containing_address(VirtAddr::new(frame.start_address().as_u64()));
+        if let Ok((_, flush)) = memory.mapper.unmap(page) {
+            flush.flush();
+        }
+    });
+}
@@ -1,11 +1,12 @@
 //! Phase 6: Multi-Process Support
 //!
 //! Enables spawning multiple userspace processes concurrently.
-//! Each process runs as an independent async task in the executor.
-//! Uses cooperative multitasking via async/await.
+//! Each process is added to the process table and queued for the
+//! scheduler, like the ones `sys_spawn` starts, so it has its own stack
+//! and shows up in `ps`.
 
-use crate::task::executor::Executor;
-use crate::task::Task;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 /// Multi-process launcher - manages spawning multiple userspace tasks
 pub struct MultiProcessLauncher {
@@ -25,17 +26,10 @@ impl MultiProcessLauncher {
     /// * `binary` - ELF binary to execute
     /// * `base_name` - Base name for processes (shell-0, shell-1, etc.)
     /// * `count` - Number of instances to spawn
-    /// * `executor` - Task executor to spawn tasks into
     ///
     /// # Returns
     /// Number of processes successfully spawned
-    pub fn spawn_multiple(
-        &mut self,
-        binary: &[u8],
-        base_name: &str,
-        count: usize,
-        executor: &mut Executor,
-    ) -> usize {
+    pub fn spawn_multiple(&mut self, binary: &[u8], base_name: &str, count: usize) -> usize {
         let mut spawned = 0;
 
         for i in 0..count {
@@ -44,7 +38,7 @@ impl MultiProcessLauncher {
             use core::fmt::Write;
             let _ = write!(name, "{}-{}", base_name, i);
 
-            match self.spawn_single(binary, &name, executor) {
+            match self.spawn_single(binary, &name) {
                 Ok(pid) => {
                     crate::println!("[Phase 6] ✅ Spawned process {}: PID {}", name, pid);
                     spawned += 1;
@@ -62,28 +56,18 @@ impl MultiProcessLauncher {
     }
 
     /// Spawn a single process instance
-    fn spawn_single(
-        &self,
-        binary: &[u8],
-        name: &str,
-        executor: &mut Executor,
-    ) -> Result<u64, &'static str> {
+    ///
+    /// The process becomes a child of the current process and runs once
+    /// the scheduler picks it.
+    fn spawn_single(&self, binary: &[u8], name: &str) -> Result<u64, &'static str> {
         // Load binary as a new process
         let process = crate::binary_loader::load_binary(binary, name)?;
         let pid = process.pid();
-        let entry_point = process.entry_point;
-
-        // Transmute entry point and create async task
-        unsafe {
-            let entry_fn: extern "C" fn() -> ! = core::mem::transmute(entry_point);
-
-            let process_runner = async move {
-                // Execute userspace code
-                entry_fn();
-            };
 
-            executor.spawn(Task::new(process_runner));
+        if crate::process::insert_process(process) < 0 {
+            return Err("Process table is full");
         }
+        crate::scheduler::enqueue_process(pid);
 
         Ok(pid)
     }
@@ -92,8 +76,8 @@ impl MultiProcessLauncher {
 /// Phase 6: Execute multiple concurrent userspace processes
 ///
 /// Loads the embedded shell binary and spawns N instances concurrently.
-/// Each instance runs independently as a separate async task.
-pub fn execute_multi_cli(count: usize, executor: &mut Executor) -> Result<(), &'static str> {
+/// Each instance runs independently as a separate scheduled process.
+pub fn execute_multi_cli(count: usize) -> Result<(), &'static str> {
     match crate::binary_loader::get_cli_binary() {
         Some(binary) => {
             crate::println!("[Phase 6] 🚀 Multi-Process Shell Launcher");
@@ -101,11 +85,11 @@ pub fn execute_multi_cli(count: usize, executor: &mut Executor) -> Result<(), &'
             crate::println!("[Phase 6] Binary size: {} bytes", binary.len());
 
             let mut launcher = MultiProcessLauncher::new();
-            let spawned = launcher.spawn_multiple(binary, "orbital-shell", count, executor);
+            let spawned = launcher.spawn_multiple(binary, "orbital-shell", count);
 
             if spawned > 0 {
                 crate::println!("[Phase 6] ✅ Multi-process execution ready");
-                crate::println!("[Phase 6] {} shells running concurrently (cooperative async/await)", spawned);
+                crate::println!("[Phase 6] {} shells running concurrently (preemptively scheduled)", spawned);
                 Ok(())
             } else {
                 Err("Failed to spawn any processes")
@@ -118,22 +102,55 @@ pub fn execute_multi_cli(count: usize, executor: &mut Executor) -> Result<(), &'
     }
 }
 
-/// Get information about all running processes
-pub fn list_processes() -> alloc::vec::Vec<(u64, alloc::string::String, &'static str)> {
-    let mut processes = alloc::vec::Vec::new();
+/// Start the embedded init binary as PID 1
+///
+/// Init is the only process the kernel starts itself; it brings up the
+/// shells and daemons and supervises them. It stays in the process table
+/// so `sys_wait` and `sys_ps` see it like any other process, and is
+/// queued for the scheduler: it runs once preemption is enabled.
+pub fn execute_init() -> Result<u64, &'static str> {
+    let binary = crate::binary_loader::get_init_binary().ok_or("No embedded init binary")?;
+    let process = crate::binary_loader::load_init(binary)?;
+    let pid = process.pid();
+
+    if crate::process::insert_process(process) < 0 {
+        return Err("Process table is full");
+    }
+    crate::scheduler::enqueue_process(pid);
+
+    crate::println!("Started init (PID {}, {} bytes)", pid, binary.len());
+    Ok(pid)
+}
 
-    // This would iterate through the process table
-    // For now, return a placeholder
-    processes
+/// Get information about all processes: PID, name and state
+///
+/// Covers everything in the process table, including the executor's
+/// tasks and processes that have exited but not been reaped.
+pub fn list_processes() -> Vec<(u64, String, &'static str)> {
+    crate::process::process_info(0, usize::MAX)
+        .iter()
+        .map(|info| {
+            let state = info.state().map_or("?", |state| state.as_str());
+            (info.pid, String::from(info.name()), state)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_launcher_creation() {
         let launcher = MultiProcessLauncher::new();
         assert_eq!(launcher.process_count, 0);
     }
+
+    #[test_case]
+    fn test_list_processes_sees_table() {
+        let pid = crate::process::create_process(0x1000) as u64;
+        let listed = list_processes();
+        let entry = listed.iter().find(|(p, _, _)| *p == pid).unwrap();
+        assert_eq!(entry.2, "Ready");
+    }
 }
@@ -0,0 +1,382 @@
+//! Per-CPU data, reached through the GS segment base
+//!
+//! Each CPU has a `PerCpu` block, and its GS base points at it for as long
+//! as the kernel runs. The running CPU's number, current thread and
+//! process, and scheduler are then one `gs`-relative load away: no lock,
+//! no lookup, and no window in which the caller could be moved to another
+//! CPU between finding the block and reading it.
+//!
+//! A thread's own GS base (`arch_prctl(ARCH_SET_GS)`) waits in
+//! `IA32_KERNEL_GS_BASE` meanwhile. Kernel entries from user mode exchange
+//! the two with `swapgs`, and exits back to user mode swap them again: the
+//! timer's entry stub does it itself, handlers using the `x86-interrupt`
+//! ABI go through `UserEntry`. Programs still run in ring 0, where GS
+//! always holds the per-CPU block.
+//!
+//! `syscall` entry has no `swapgs` yet because it has no entry point at
+//! all: LSTAR and EFER.SCE are never set (see
+//! docs/vision/syscall-skeleton.md). The stub, once written, must
+//! `swapgs` before touching per-CPU data and again before `sysret`.
+//!
+//! While the executor polls one of its tasks, the CPU's own context counts
+//! as that task's process (`set_task`).
+//!
+//! Only a CPU writes its own block, apart from `init`.
+
+use crate::scheduler::{KERNEL_TID, Scheduler};
+use crate::smp::MAX_CPUS;
+use core::arch::asm;
+use core::mem::offset_of;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::registers::model_specific::GsBase;
+
+/// Data the kernel keeps for one CPU
+#[repr(C)]
+pub struct PerCpu {
+    /// Address of the block, so `gs:0` turns GS into a pointer
+    this: AtomicU64,
+    /// Number of the CPU, as `smp::cpu_id` reports it
+    id: u64,
+    /// Thread the scheduler runs here (`KERNEL_TID` for the CPU's own
+    /// context)
+    current_tid: AtomicU64,
+    /// Process owning `current_tid` (`task_pid` for the CPU's own
+    /// context)
+    current_pid: AtomicU64,
+    /// Top of the current thread's stack, which an entry from user mode
+    /// switches to (0 until a thread has run here)
+    kernel_stack_top: AtomicU64,
+    /// This CPU's scheduler, once it has been looked up
+    scheduler: AtomicPtr<Mutex<Scheduler>>,
+    /// Open `PreemptGuard`s; the timer does not switch threads while
+    /// nonzero
+    preempt_count: AtomicU32,
+    /// Threads switched out by the timer interrupt while still runnable
+    preemptions: AtomicU64,
+    /// When the CPU halted with nothing to run (0 while it is busy)
+    idle_since: AtomicU64,
+    /// Nanoseconds spent halted with nothing to run
+    idle_ns: AtomicU64,
+    /// Executor task the CPU's own context is polling (`KERNEL_TID` for
+    /// none)
+    task_pid: AtomicU64,
+    /// When the poll of `task_pid` started (0 while not polling)
+    poll_since: AtomicU64,
+}
+
+/// Every CPU's block, indexed by CPU number
+static CPUS: [PerCpu; MAX_CPUS] = {
+    let mut cpus = [const { PerCpu::new() }; MAX_CPUS];
+    let mut cpu = 0;
+    while cpu < MAX_CPUS {
+        cpus[cpu].id = cpu as u64;
+        cpu += 1;
+    }
+    cpus
+};
+
+/// Read a word of the running CPU's block with a single instruction
+macro_rules! read_this_cpu {
+    ($field:ident) => {{
+        let value: u64;
+        unsafe {
+            asm!(
+                "mov {}, gs:[{}]",
+                out(reg) value,
+                const offset_of!(PerCpu, $field),
+                options(nostack, preserves_flags, readonly),
+            );
+        }
+        value
+    }};
+}
+
+impl PerCpu {
+    const fn new() -> Self {
+        PerCpu {
+            this: AtomicU64::new(0),
+            id: 0,
+            current_tid: AtomicU64::new(KERNEL_TID),
+            current_pid: AtomicU64::new(KERNEL_TID),
+            kernel_stack_top: AtomicU64::new(0),
+            scheduler: AtomicPtr::new(core::ptr::null_mut()),
+            preempt_count: AtomicU32::new(0),
+            preemptions: AtomicU64::new(0),
+            idle_since: AtomicU64::new(0),
+            idle_ns: AtomicU64::new(0),
+            task_pid: AtomicU64::new(KERNEL_TID),
+            poll_since: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of the CPU
+    pub fn id(&self) -> usize {
+        self.id as usize
+    }
+
+    /// Thread the CPU runs, `None` for its own context
+    pub fn current_thread(&self) -> Option<u64> {
+        Some(self.current_tid.load(Ordering::Relaxed)).filter(|&tid| tid != KERNEL_TID)
+    }
+
+    /// Top of the current thread's stack
+    pub fn kernel_stack_top(&self) -> u64 {
+        self.kernel_stack_top.load(Ordering::Relaxed)
+    }
+
+    /// Whether a `PreemptGuard` is open on the CPU
+    pub fn preempt_disabled(&self) -> bool {
+        self.preempt_count.load(Ordering::Relaxed) != 0
+    }
+
+    /// Forget the open `PreemptGuard`s of a thread that was killed and
+    /// will never drop them
+    pub fn clear_preempt_count(&self) {
+        self.preempt_count.store(0, Ordering::Relaxed);
+    }
+
+    /// Threads the timer interrupt has switched out on the CPU while they
+    /// were still runnable
+    pub fn preemptions(&self) -> u64 {
+        self.preemptions.load(Ordering::Relaxed)
+    }
+
+    /// Nanoseconds the CPU has spent halted with nothing to run
+    pub fn idle_ns(&self) -> u64 {
+        self.idle_ns.load(Ordering::Relaxed)
+    }
+
+    /// Record the thread the scheduler picked and the process owning it
+    ///
+    /// For the CPU's own context (`None`) the process is the executor task
+    /// it was polling, if any.
+    pub fn set_current(&self, current: Option<(u64, u64)>) {
+        let task = self.task_pid.load(Ordering::Relaxed);
+        let (tid, pid) = current.unwrap_or((KERNEL_TID, task));
+        self.current_tid.store(tid, Ordering::Relaxed);
+        self.current_pid.store(pid, Ordering::Relaxed);
+    }
+
+    /// Record the executor task the CPU's own context starts (`Some`) or
+    /// stops (`None`) polling, so `current_process` reports it meanwhile
+    ///
+    /// Called by the executor, which only runs in the CPU's own context.
+    pub fn set_task(&self, pid: Option<u64>) {
+        let since = match pid {
+            Some(_) => crate::clock::monotonic_ns().max(1),
+            None => 0,
+        };
+        let pid = pid.unwrap_or(KERNEL_TID);
+        self.task_pid.store(pid, Ordering::Relaxed);
+        self.current_pid.store(pid, Ordering::Relaxed);
+        self.poll_since.store(since, Ordering::Relaxed);
+    }
+
+    /// Executor task the CPU's own context is polling, and since when
+    pub fn polling(&self) -> Option<(u64, u64)> {
+        let pid = self.task_pid.load(Ordering::Relaxed);
+        let since = self.poll_since.load(Ordering::Relaxed);
+        (pid != KERNEL_TID && since != 0).then_some((pid, since))
+    }
+
+    /// Record the stack of the thread being switched to
+    pub fn set_kernel_stack_top(&self, top: u64) {
+        self.kernel_stack_top.store(top, Ordering::Relaxed);
+    }
+
+    /// Count a runnable thread switched out by the timer interrupt
+    pub fn count_preemption(&self) {
+        self.preemptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Halt until the next interrupt, counting the time as idle
+    ///
+    /// Called by the CPU's own context when it has nothing to do, with
+    /// interrupts disabled so the wakeup cannot come before the `hlt`.
+    pub fn idle_hlt(&self) {
+        self.idle_since
+            .store(crate::clock::monotonic_ns(), Ordering::Relaxed);
+        x86_64::instructions::interrupts::enable_and_hlt();
+        self.end_idle();
+    }
+
+    /// Stop counting idle time, if the CPU was halted
+    ///
+    /// `idle_hlt` does this when the `hlt` returns. The timer interrupt
+    /// does it too, as it may switch the CPU to a thread first.
+    pub fn end_idle(&self) {
+        let since = self.idle_since.swap(0, Ordering::Relaxed);
+        if since != 0 {
+            let idle = crate::clock::monotonic_ns().saturating_sub(since);
+            self.idle_ns.fetch_add(idle, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Point GS at CPU `cpu`'s block
+///
+/// Called first thing on each CPU, before anything reads per-CPU data:
+/// from `crate::init` on the BSP and `smp::ap_main` on the others.
+pub fn init(cpu: usize) {
+    let block = &CPUS[cpu];
+    let addr = block as *const PerCpu as u64;
+    block.this.store(addr, Ordering::Relaxed);
+    GsBase::write(VirtAddr::new(addr));
+}
+
+/// CPU `cpu`'s block, for reading another CPU's data
+pub fn cpu(cpu: usize) -> &'static PerCpu {
+    &CPUS[cpu]
+}
+
+/// The running CPU's block
+///
+/// The caller must not move to another CPU while it uses the block: call
+/// it from an interrupt handler or with interrupts disabled. The free
+/// functions below need no such care.
+pub fn this_cpu() -> &'static PerCpu {
+    let this = read_this_cpu!(this) as *const PerCpu;
+    unsafe { &*this }
+}
+
+/// Number of the running CPU
+pub fn cpu_id() -> usize {
+    read_this_cpu!(id) as usize
+}
+
+/// Thread the running CPU runs, `None` for its own context
+pub fn current_thread() -> Option<u64> {
+    Some(read_this_cpu!(current_tid)).filter(|&tid| tid != KERNEL_TID)
+}
+
+/// Process owning the thread the running CPU runs
+pub fn current_process() -> Option<u64> {
+    Some(read_this_cpu!(current_pid)).filter(|&pid| pid != KERNEL_TID)
+}
+
+/// The running CPU's scheduler, or `None` before `scheduler::init`
+///
+/// Looked up in `scheduler` on first use and kept in the block. Never
+/// blocks, so it is safe from interrupt handlers. The caller must not move
+/// to another CPU while it uses it.
+pub fn scheduler() -> Option<&'static Mutex<Scheduler>> {
+    let cached = read_this_cpu!(scheduler) as *const Mutex<Scheduler>;
+    if let Some(sched) = unsafe { cached.as_ref() } {
+        return Some(sched);
+    }
+    let block = this_cpu();
+    let sched = crate::scheduler::cpu_scheduler(block.id())?;
+    block
+        .scheduler
+        .store(sched as *const _ as *mut _, Ordering::Relaxed);
+    Some(sched)
+}
+
+/// Preemption is off on this CPU while one of these is alive
+///
+/// Guards nest. The thread holding one stays on its CPU, so dropping it
+/// decrements the same counter `preempt_disable` incremented.
+pub struct PreemptGuard {
+    _private: (),
+}
+
+/// Keep the timer from switching the running CPU to another thread until
+/// the guard is dropped
+pub fn preempt_disable() -> PreemptGuard {
+    // One instruction, so an interrupt cannot split the update
+    unsafe {
+        asm!(
+            "inc dword ptr gs:[{}]",
+            const offset_of!(PerCpu, preempt_count),
+            options(nostack),
+        );
+    }
+    PreemptGuard { _private: () }
+}
+
+impl Drop for PreemptGuard {
+    fn drop(&mut self) {
+        unsafe {
+            asm!(
+                "dec dword ptr gs:[{}]",
+                const offset_of!(PerCpu, preempt_count),
+                options(nostack),
+            );
+        }
+    }
+}
+
+/// Kernel GS for an `x86-interrupt` handler entered from user mode
+///
+/// Those handlers have no entry stub of their own to `swapgs` in, so they
+/// call `enter` with the interrupted code segment before touching per-CPU
+/// data, and `leave` with the code segment they return to.
+pub struct UserEntry {
+    swapped: bool,
+}
+
+impl UserEntry {
+    /// Load the kernel's GS base if the interrupt came from user mode
+    pub fn enter(code_segment: u64) -> Self {
+        let swapped = code_segment & 3 == 3;
+        if swapped {
+            unsafe { asm!("swapgs", options(nostack, preserves_flags)) };
+        }
+        UserEntry { swapped }
+    }
+
+    /// Give user mode its GS base back, unless the handler redirected the
+    /// return into the kernel
+    pub fn leave(self, code_segment: u64) {
+        if self.swapped && code_segment & 3 == 3 {
+            unsafe { asm!("swapgs", options(nostack, preserves_flags)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_block_layout() {
+        // `this_cpu` reads the block's address at gs:0
+        assert_eq!(offset_of!(PerCpu, this), 0);
+        assert_eq!(cpu(3).id(), 3);
+    }
+
+    #[test_case]
+    fn test_boot_cpu_block() {
+        // `crate::init` has pointed GS at the boot CPU's block
+        assert!(core::ptr::eq(this_cpu(), cpu(crate::smp::BOOT_CPU)));
+        assert_eq!(cpu_id(), crate::smp::BOOT_CPU);
+        assert_eq!(current_thread(), None);
+    }
+
+    #[test_case]
+    fn test_preempt_guards_nest() {
+        let outer = preempt_disable();
+        let inner = preempt_disable();
+        drop(inner);
+        assert!(this_cpu().preempt_disabled());
+        drop(outer);
+        assert!(!this_cpu().preempt_disabled());
+    }
+
+    #[test_case]
+    fn test_task_is_current_process() {
+        let cpu = this_cpu();
+        cpu.set_task(Some(42));
+        assert_eq!(current_process(), Some(42));
+        assert_eq!(current_thread(), None);
+        assert!(cpu.polling().is_some_and(|(pid, _)| pid == 42));
+        // Still reported after the context is switched out and back in
+        cpu.set_current(None);
+        assert_eq!(current_process(), Some(42));
+        cpu.set_task(None);
+        assert_eq!(current_process(), None);
+        assert_eq!(cpu.polling(), None);
+    }
+}
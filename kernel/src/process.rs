@@ -4,11 +4,14 @@
 //! Policy (what tasks do, scheduling priorities) is left to userspace.
 //!
 //! Task Memory Layout:
-//! Each task gets a 4KB stack allocated from the kernel heap.
+//! Each thread gets its own stack mapped in the stack region with an
+//! unmapped guard page below it, see `crate::stack`. Kernel tasks get a
+//! fixed stack (16 KiB by default); tasks spawned from userspace get a
+//! growable one that reserves up to 8 MiB but starts with a single page.
 //! Stack grows downward (high to low address).
 //!
 //! Stack Layout (grows downward):
-//! ┌─────────────────┐ 0x7FFF
+//! ┌─────────────────┐ top
 //! │   top (unused)  │
 //! ├─────────────────┤
 //! │    local vars   │
@@ -16,16 +19,62 @@
 //! │    args         │
 //! ├─────────────────┤ (RSP)
 //! │ (grows downward)│
-//! └─────────────────┘ 0x0000
+//! ├─────────────────┤ bottom
+//! │   guard page    │ (unmapped)
+//! └─────────────────┘
 //!
 //! Context switching saves/restores the full CPU state (all registers).
-
+//!
+//! Processes and Threads:
+//! A `Process` owns the resources shared by everything running in it
+//! (name, parent, accounting, exit status). Each process owns one or more
+//! `Thread`s, which carry the schedulable state: stack, saved `TaskContext`
+//! and status. Threads of one process share its memory.
+//!
+//! Thread IDs and process IDs come from the same counter and the main thread
+//! of a process reuses the process ID, so the scheduler only ever deals with
+//! thread IDs and a single-threaded process looks exactly as before.
+//!
+//! Parents and Children:
+//! PID 1 is reserved for the init process. A process's children stay in the
+//! table after they exit until the parent reaps them with `wait_child`;
+//! when a process exits, its children are handed to init, which reaps them
+//! in turn. Children also inherit their parent's nice value and scheduling
+//! class, which decide how the scheduler queues their threads.
+//!
+//! Executor Tasks:
+//! Async tasks polled by the kernel's executor (the terminal, the kernel
+//! shell) are processes too, so they have a PID and show up in `ps`, and
+//! `wait` and `kill` work on them. They have no threads: the executor runs
+//! them on its own stack and records which one it is polling as the CPU's
+//! current process (see `percpu::PerCpu::set_task`).
+
+use crate::fpu::FpuState;
+use crate::scheduler::{SchedClass, SchedInfo};
+use crate::stack::{Stack, StackError, StackSpec};
+use crate::tls::TlsBlock;
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
 use conquer_once::spin::OnceCell;
-use spin::Mutex;
+use core::fmt;
+use core::task::Waker;
+use orbital_common::process::{
+    INIT_PID, NICE_MAX, NICE_MIN, ProcessInfo, ProcessState, SIGSEGV, signal_name,
+};
+use spin::{Mutex, MutexGuard};
+
+/// Maximum number of threads a single process may own
+pub const MAX_THREADS_PER_PROCESS: usize = 64;
 
-pub const TASK_STACK_SIZE: usize = 4096; // 4KB per task
+/// Allocate the next ID from the shared process/thread ID space
+///
+/// `INIT_PID` is never handed out here; only `Process::new_init` uses it.
+fn next_id() -> u64 {
+    use core::sync::atomic::{AtomicU64, Ordering};
+    static NEXT_ID: AtomicU64 = AtomicU64::new(INIT_PID + 1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 /// Unique identifier for a process/task
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -34,9 +83,23 @@ pub struct ProcessId(u64);
 impl ProcessId {
     /// Generate a new unique process ID
     fn new() -> Self {
-        use core::sync::atomic::{AtomicU64, Ordering};
-        static NEXT_PID: AtomicU64 = AtomicU64::new(1);
-        ProcessId(NEXT_PID.fetch_add(1, Ordering::Relaxed))
+        ProcessId(next_id())
+    }
+}
+
+/// Unique identifier for a thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    /// Generate a new unique thread ID
+    fn new() -> Self {
+        ThreadId(next_id())
+    }
+
+    /// Get the raw thread ID
+    pub fn as_u64(self) -> u64 {
+        self.0
     }
 }
 
@@ -50,7 +113,77 @@ pub enum ProcessStatus {
     /// Process is waiting for I/O or event
     Blocked,
     /// Process has exited
-    Exited(i64),
+    Exited(ExitReason),
+}
+
+/// Why a process or thread stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// Exited on its own (sys_exit or returning from its entry point)
+    Code(i64),
+    /// Killed with `sys_kill`
+    Signaled(u32),
+    /// Killed by the kernel after a CPU exception in user code
+    Killed {
+        /// Signal describing the fault (`orbital_common::process::SIGSEGV`, ...)
+        signal: u32,
+        /// Instruction that faulted
+        rip: u64,
+        /// Faulting address (CR2) for page faults
+        addr: Option<u64>,
+    },
+    /// Killed with `SIGSEGV` after running off the end of a stack into its
+    /// guard area
+    StackOverflow {
+        /// Instruction that faulted
+        rip: u64,
+        /// Faulting address (CR2)
+        addr: u64,
+    },
+}
+
+impl ExitReason {
+    /// Exit code reported to `sys_wait`: the process's own code, or the
+    /// negated signal number if it was killed
+    pub fn code(self) -> i64 {
+        match self {
+            ExitReason::Code(code) => code,
+            ExitReason::Signaled(signal) | ExitReason::Killed { signal, .. } => -(signal as i64),
+            ExitReason::StackOverflow { .. } => -(SIGSEGV as i64),
+        }
+    }
+
+    /// Signal that ended the process, if it did not exit on its own
+    pub fn signal(self) -> Option<u32> {
+        match self {
+            ExitReason::Code(_) => None,
+            ExitReason::Signaled(signal) | ExitReason::Killed { signal, .. } => Some(signal),
+            ExitReason::StackOverflow { .. } => Some(SIGSEGV),
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExitReason::Code(code) => write!(f, "exited with code {}", code),
+            ExitReason::Signaled(signal) => write!(f, "killed by {}", signal_name(signal)),
+            ExitReason::Killed { signal, rip, addr } => {
+                write!(f, "killed by {} at RIP {:#x}", signal_name(signal), rip)?;
+                if let Some(addr) = addr {
+                    write!(f, " (CR2 {:#x})", addr)?;
+                }
+                Ok(())
+            }
+            ExitReason::StackOverflow { rip, addr } => write!(
+                f,
+                "killed by {} (stack overflow) at RIP {:#x} (CR2 {:#x})",
+                signal_name(SIGSEGV),
+                rip,
+                addr
+            ),
+        }
+    }
 }
 
 /// CPU context - all registers saved for a process
@@ -80,23 +213,40 @@ pub struct TaskContext {
     pub rip: u64,
     /// CPU flags register
     pub rflags: u64,
+    /// FS segment base (thread pointer for TLS)
+    pub fs_base: u64,
+    /// GS segment base
+    pub gs_base: u64,
 }
 
 impl TaskContext {
     /// Create a new context for a task starting at entry_point
     /// Stack pointer is set to the top of the stack (grows downward)
-    pub fn new(entry_point: u64, _stack_top: u64) -> Self {
-        // For now, we don't actually use context switching
-        // Just store the entry point for reference
+    ///
+    /// The task starts in `task_wrapper_entry` with the task function in
+    /// RDI, so returning from it exits the task.
+    pub fn new(entry_point: u64, stack_top: u64) -> Self {
+        TaskContext {
+            rdi: entry_point, // Task function pointer
+            rbp: stack_top,
+            rsp: crate::task_entry::init_task_stack(stack_top, entry_point),
+            rip: crate::task_entry::get_task_entry_point(),
+            rflags: 0x200, // Interrupts enabled
+            ..Self::empty()
+        }
+    }
+
+    /// A context with every register zeroed
+    pub const fn empty() -> Self {
         TaskContext {
             rax: 0,
             rbx: 0,
             rcx: 0,
             rdx: 0,
             rsi: 0,
-            rdi: entry_point, // Task function pointer
-            rbp: 0,           // Not used
-            rsp: 0,           // Not used
+            rdi: 0,
+            rbp: 0,
+            rsp: 0,
             r8: 0,
             r9: 0,
             r10: 0,
@@ -105,73 +255,280 @@ impl TaskContext {
             r13: 0,
             r14: 0,
             r15: 0,
-            rip: 0,    // Not used
-            rflags: 0, // Not used
+            rip: 0,
+            rflags: 0,
+            fs_base: 0,
+            gs_base: 0,
         }
     }
 }
 
+/// A schedulable thread of execution inside a process
+#[derive(Debug)]
+pub struct Thread {
+    /// Unique thread identifier (equal to the PID for the main thread)
+    pub id: ThreadId,
+    /// Process this thread belongs to
+    pub process: ProcessId,
+    /// Entry point address (function pointer cast to usize)
+    pub entry_point: usize,
+    /// Argument passed to the entry point (secondary threads only)
+    pub arg: usize,
+    /// Stack mapped for this thread, with a guard page below it
+    pub stack: Stack,
+    /// Saved CPU context (for context switching)
+    pub saved_context: TaskContext,
+    /// Thread-local storage block (`saved_context.fs_base` points into it)
+    pub tls: Option<TlsBlock>,
+    /// Saved x87/SSE/AVX registers
+    pub fpu: Box<FpuState>,
+    /// Label set with sys_set_name (the main thread uses the process name)
+    pub name: Option<String>,
+    /// Current status
+    pub status: ProcessStatus,
+    /// CPU that has the thread as its current thread, from the moment it
+    /// is switched in until its registers are saved again
+    pub running_on: Option<usize>,
+    /// When the thread last became Ready (monotonic ns), to measure how
+    /// long it waits for a CPU
+    pub ready_since: u64,
+}
+
+impl Thread {
+    /// Create a thread for `process` starting at `entry_point`
+    fn new(
+        id: ThreadId,
+        process: ProcessId,
+        entry_point: usize,
+        arg: usize,
+        stack: StackSpec,
+    ) -> Result<Self, StackError> {
+        let stack = stack.allocate(id.0)?;
+
+        let mut saved_context = TaskContext::new(entry_point as u64, stack.top());
+        if id.0 != process.0 {
+            // Secondary threads take an argument and exit only themselves
+            saved_context.rip = crate::task_entry::get_thread_entry_point();
+            saved_context.rsi = arg as u64;
+        }
+
+        Ok(Thread {
+            id,
+            process,
+            entry_point,
+            arg,
+            stack,
+            saved_context,
+            tls: None,
+            fpu: Box::new(FpuState::new()),
+            name: None,
+            status: ProcessStatus::Ready,
+            running_on: None,
+            ready_since: crate::clock::monotonic_ns(),
+        })
+    }
+
+    /// Attach a TLS block and point the thread's FS base at it
+    pub fn set_tls(&mut self, block: TlsBlock) {
+        self.saved_context.fs_base = block.thread_pointer();
+        self.tls = Some(block);
+    }
+
+    /// Get the thread ID
+    pub fn tid(&self) -> u64 {
+        self.id.0
+    }
+
+    /// Whether this is the main thread of its process
+    pub fn is_main(&self) -> bool {
+        self.id.0 == self.process.0
+    }
+}
+
 /// A lightweight process/task that the kernel manages
 #[derive(Debug)]
 pub struct Process {
     /// Unique process identifier
     pub id: ProcessId,
-    /// Process name (for debugging)
-    pub name: alloc::string::String,
+    /// Process name (the executable's name unless changed with sys_set_name)
+    pub name: String,
+    /// Command line; `args[0]` is the executable name
+    pub args: Vec<String>,
     /// Entry point address (function pointer cast to usize)
     pub entry_point: usize,
-    /// Allocated stack for this task (4KB) - using Box for stable address
-    pub stack: Box<[u8; TASK_STACK_SIZE]>,
-    /// Saved CPU context (for context switching)
-    pub saved_context: TaskContext,
+    /// Threads owned by this process; `threads[0]` is the main thread
+    /// (empty for executor tasks)
+    pub threads: Vec<Thread>,
     /// Current status
     pub status: ProcessStatus,
     /// Return value (when exited)
     pub exit_code: i64,
+    /// Parent process ID (0 if spawned by the kernel)
+    pub parent_id: u64,
+    /// Timer ticks this process has spent running
+    pub cpu_ticks: u64,
+    /// Scheduling priority (`NICE_MIN`..=`NICE_MAX`, inherited from the parent)
+    pub nice: i32,
+    /// Scheduling class (normal or real-time, inherited from the parent)
+    pub sched_class: SchedClass,
+    /// Times one of its threads was switched in
+    pub switches: u64,
+    /// Times one of its threads gave up the CPU by blocking or exiting
+    pub voluntary_switches: u64,
+    /// Times one of its threads was preempted while still runnable
+    pub involuntary_switches: u64,
+    /// Nanoseconds its threads spent Ready, waiting for a CPU
+    pub ready_ns: u64,
+    /// Waker of the executor task this process stands for (`None` for
+    /// processes with threads)
+    pub task: Option<Waker>,
 }
 
 impl Process {
     /// Create a new process with the given entry point
-    /// Allocates a stack and initializes CPU context
-    pub fn new(entry_point: usize) -> Self {
-        // For now, we don't allocate stacks - just store the task function
-        // Tasks will be executed directly by calling the function, not by context switching
-        let task_fn_ptr = entry_point as u64;
-        let saved_context = TaskContext::new(task_fn_ptr, 0);
+    /// Maps the main thread's stack and initializes its CPU context
+    pub fn new(entry_point: usize, stack: StackSpec) -> Result<Self, StackError> {
+        Self::with_id(ProcessId::new(), entry_point, stack)
+    }
 
-        Process {
-            id: ProcessId::new(),
-            name: alloc::string::String::from("unnamed"),
+    /// Create the init process (always `INIT_PID`)
+    pub fn new_init(stack: StackSpec) -> Result<Self, StackError> {
+        let mut process = Self::with_id(ProcessId(INIT_PID), 0, stack)?;
+        process.name = String::from("init");
+        process.args = alloc::vec![process.name.clone()];
+        Ok(process)
+    }
+
+    fn with_id(id: ProcessId, entry_point: usize, stack: StackSpec) -> Result<Self, StackError> {
+        let main_thread = Thread::new(ThreadId(id.0), id, entry_point, 0, stack)?;
+
+        Ok(Process {
+            id,
+            name: String::from("unnamed"),
+            args: Vec::new(),
             entry_point,
-            stack: Box::new([0u8; TASK_STACK_SIZE]), // Still allocate but don't use yet
-            saved_context,
+            threads: alloc::vec![main_thread],
             status: ProcessStatus::Ready,
             exit_code: 0,
-        }
+            parent_id: 0,
+            cpu_ticks: 0,
+            nice: 0,
+            sched_class: SchedClass::Normal,
+            switches: 0,
+            voluntary_switches: 0,
+            involuntary_switches: 0,
+            ready_ns: 0,
+            task: None,
+        })
     }
 
-    /// Create a new process with a name
-    /// Used for Phase 3 userspace binary loading
-    pub fn new_with_name(name: &str) -> Self {
-        let task_fn_ptr = 0u64;
-        let saved_context = TaskContext::new(task_fn_ptr, 0);
-
+    /// Create the process record of an executor task, woken with `waker`
+    pub fn new_task(name: &str, waker: Waker) -> Self {
         Process {
             id: ProcessId::new(),
-            name: alloc::string::String::from(name),
+            name: String::from(name),
+            args: alloc::vec![String::from(name)],
             entry_point: 0,
-            stack: Box::new([0u8; TASK_STACK_SIZE]),
-            saved_context,
+            threads: Vec::new(),
             status: ProcessStatus::Ready,
             exit_code: 0,
+            parent_id: 0,
+            cpu_ticks: 0,
+            nice: 0,
+            sched_class: SchedClass::Normal,
+            switches: 0,
+            voluntary_switches: 0,
+            involuntary_switches: 0,
+            ready_ns: 0,
+            task: Some(waker),
         }
     }
 
+    /// Create a new process with a name
+    /// Used for Phase 3 userspace binary loading
+    pub fn new_with_name(name: &str, stack: StackSpec) -> Result<Self, StackError> {
+        let mut process = Process::new(0, stack)?;
+        process.name = String::from(name);
+        process.args = alloc::vec![process.name.clone()];
+        Ok(process)
+    }
+
     /// Get the process ID
     pub fn pid(&self) -> u64 {
         self.id.0
     }
 
+    /// The main thread (always present, except in executor tasks)
+    pub fn main_thread(&self) -> &Thread {
+        &self.threads[0]
+    }
+
+    /// Mutable access to the main thread
+    pub fn main_thread_mut(&mut self) -> &mut Thread {
+        &mut self.threads[0]
+    }
+
+    /// Find one of this process's threads by ID
+    pub fn thread(&self, tid: u64) -> Option<&Thread> {
+        self.threads.iter().find(|t| t.id.0 == tid)
+    }
+
+    /// Find one of this process's threads by ID (mutable)
+    pub fn thread_mut(&mut self, tid: u64) -> Option<&mut Thread> {
+        self.threads.iter_mut().find(|t| t.id.0 == tid)
+    }
+
+    /// Command line as shown by `ps`: the arguments joined by spaces
+    pub fn cmdline(&self) -> String {
+        self.args.join(" ")
+    }
+
+    /// Memory owned by this process in bytes
+    pub fn memory_bytes(&self) -> u64 {
+        self.threads
+            .iter()
+            .map(|t| {
+                let tls = t.tls.as_ref().map_or(0, |block| block.size());
+                let fpu = core::mem::size_of::<FpuState>();
+                (t.stack.size() + tls + fpu) as u64
+            })
+            .sum()
+    }
+
+    /// Build the userspace-visible record for this process
+    pub fn info(&self) -> ProcessInfo {
+        let (state, exit_code) = match self.status {
+            ProcessStatus::Ready => (ProcessState::Ready, 0),
+            ProcessStatus::Running => (ProcessState::Running, 0),
+            ProcessStatus::Blocked => (ProcessState::Blocked, 0),
+            ProcessStatus::Exited(ExitReason::Code(code)) => (ProcessState::Exited, code),
+            ProcessStatus::Exited(
+                ExitReason::Signaled(signal) | ExitReason::Killed { signal, .. },
+            ) => (ProcessState::Killed, signal as i64),
+            ProcessStatus::Exited(ExitReason::StackOverflow { .. }) => {
+                (ProcessState::Killed, SIGSEGV as i64)
+            }
+        };
+
+        let mut info = ProcessInfo::empty();
+        info.state = state as u32;
+        info.pid = self.id.0;
+        info.ppid = self.parent_id;
+        info.exit_code = exit_code;
+        info.ticks = self.cpu_ticks;
+        info.memory_bytes = self.memory_bytes();
+        info.set_name(&self.name);
+        info.set_cmdline(&self.cmdline());
+        info.nice = self.nice;
+        info.policy = self.sched_class.policy();
+        info.rt_priority = self.sched_class.rt_priority().unwrap_or(0) as u32;
+        info.switches = self.switches;
+        info.voluntary_switches = self.voluntary_switches;
+        info.involuntary_switches = self.involuntary_switches;
+        info.ready_ns = self.ready_ns;
+        info
+    }
+
     /// Load binary code segment into process (Phase 3)
     /// For now, this is a placeholder that validates the binary
     pub fn load_code_segment(&mut self, _binary: &[u8]) -> Result<(), &'static str> {
@@ -194,6 +551,11 @@ fn get_or_init_process_table() -> &'static Mutex<Vec<Process>> {
     PROCESS_TABLE.get_or_init(|| Mutex::new(Vec::new()))
 }
 
+/// Find a thread by ID anywhere in the process table
+fn find_thread_mut(processes: &mut [Process], tid: u64) -> Option<&mut Thread> {
+    processes.iter_mut().find_map(|p| p.thread_mut(tid))
+}
+
 /// Create a new process/task
 ///
 /// # Arguments
@@ -202,11 +564,31 @@ fn get_or_init_process_table() -> &'static Mutex<Vec<Process>> {
 /// # Returns
 /// Process ID if successful, or negative error code
 pub fn create_process(entry_point: usize) -> i64 {
+    create_process_with_stack(entry_point, StackSpec::Fixed(0))
+}
+
+/// Create a new process/task with a specific kind of stack
+///
+/// A task started by a running process runs that process's code, so it
+/// inherits the parent's name and command line.
+///
+/// # Arguments
+/// * `entry_point` - Address of the task's entry function
+/// * `stack` - Stack size, or address space to reserve for a growable stack
+///
+/// # Returns
+/// Process ID if successful, or negative error code
+/// (-1 invalid entry point or stack size, -2 too many processes,
+/// -3 no memory for the stack)
+pub fn create_process_with_stack(entry_point: usize, stack: StackSpec) -> i64 {
     // Validate entry point is not NULL
     if entry_point == 0 {
         return -1; // Invalid address
     }
 
+    // Resolve the parent before taking the table lock (it reads the table)
+    let parent_id = crate::scheduler::current_process().unwrap_or(0);
+
     let table = get_or_init_process_table();
     let mut processes = table.lock();
 
@@ -215,7 +597,17 @@ pub fn create_process(entry_point: usize) -> i64 {
         return -2; // Too many processes
     }
 
-    let process = Process::new(entry_point);
+    let mut process = match Process::new(entry_point, stack) {
+        Ok(process) => process,
+        Err(e) => return stack_error_code(e),
+    };
+    process.parent_id = parent_id;
+    if let Some(parent) = processes.iter().find(|p| p.id.0 == parent_id) {
+        process.name = parent.name.clone();
+        process.args = parent.args.clone();
+        process.nice = parent.nice;
+        process.sched_class = parent.sched_class;
+    }
     let pid = process.id.0;
     processes.push(process);
 
@@ -226,6 +618,43 @@ pub fn create_process(entry_point: usize) -> i64 {
     pid as i64
 }
 
+/// Add an already built process (e.g. a loaded binary) to the table
+///
+/// The process becomes a child of the current process and inherits its
+/// nice value. It is not queued for scheduling; the caller decides how it
+/// starts.
+///
+/// # Returns
+/// Process ID, or -2 if the table is full
+pub fn insert_process(mut process: Process) -> i64 {
+    // Resolve the parent before taking the table lock (it reads the table)
+    let parent_id = crate::scheduler::current_process().unwrap_or(0);
+
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    if processes.len() >= 256 {
+        return -2; // Too many processes
+    }
+
+    process.parent_id = parent_id;
+    if let Some(parent) = processes.iter().find(|p| p.id.0 == parent_id) {
+        process.nice = parent.nice;
+        process.sched_class = parent.sched_class;
+    }
+    let pid = process.id.0;
+    processes.push(process);
+    pid as i64
+}
+
+/// Map a stack allocation failure to a process-creation error code
+fn stack_error_code(error: StackError) -> i64 {
+    match error {
+        StackError::InvalidSize => -1,
+        StackError::NoSlots | StackError::OutOfMemory | StackError::NotInitialized => -3,
+    }
+}
+
 /// Get process by ID
 pub fn get_process(pid: u64) -> Option<ProcessId> {
     let table = get_or_init_process_table();
@@ -242,42 +671,573 @@ pub fn get_process_status(pid: u64) -> Option<ProcessStatus> {
     processes.iter().find(|p| p.id.0 == pid).map(|p| p.status)
 }
 
+/// Get the parent of a process
+pub fn get_parent(pid: u64) -> Option<u64> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    processes
+        .iter()
+        .find(|p| p.id.0 == pid)
+        .map(|p| p.parent_id)
+}
+
 /// Update process status
+///
+/// The main thread follows the process status. When the process exits,
+/// every thread it owns is marked exited as well.
 pub fn set_process_status(pid: u64, status: ProcessStatus) -> bool {
     let table = get_or_init_process_table();
     let mut processes = table.lock();
 
     if let Some(process) = processes.iter_mut().find(|p| p.id.0 == pid) {
-        process.status = status;
+        if let ProcessStatus::Exited(reason) = status {
+            mark_exited(process, reason);
+            reparent_children(&mut processes, pid);
+        } else {
+            process.status = status;
+            if let Some(main_thread) = process.threads.first_mut() {
+                main_thread.status = status;
+            }
+        }
         true
     } else {
         false
     }
 }
 
-/// Wait for a process to exit and return its exit code
-pub fn wait_process(pid: u64) -> Option<i64> {
-    loop {
+/// Mark a process and every thread still running in it exited
+fn mark_exited(process: &mut Process, reason: ExitReason) {
+    process.status = ProcessStatus::Exited(reason);
+    process.exit_code = reason.code();
+    for thread in process.threads.iter_mut() {
+        if !matches!(thread.status, ProcessStatus::Exited(_)) {
+            thread.status = ProcessStatus::Exited(reason);
+        }
+    }
+}
+
+/// Kill the process owning thread `tid` after a fault in user code
+///
+/// Called from exception handlers, so it never spins on the process table:
+/// if the lock is held (the fault interrupted the kernel while it was
+/// updating the table) this returns `None` and the caller must treat the
+/// fault as a kernel bug. Otherwise the whole process exits with `reason`
+/// as with `set_process_status`, so `join_thread` and `wait_child` callers
+/// see it on their next check.
+///
+/// Returns the PID and the top of `tid`'s stack, which stays mapped until
+/// the process is reaped.
+pub fn kill_thread_owner(tid: u64, reason: ExitReason) -> Option<(u64, u64)> {
+    let table = get_or_init_process_table();
+    let mut processes = table.try_lock()?;
+
+    let process = processes.iter_mut().find(|p| p.thread(tid).is_some())?;
+    let pid = process.id.0;
+    let stack_top = process.thread(tid)?.stack.top();
+    mark_exited(process, reason);
+    reparent_children(&mut processes, pid);
+
+    Some((pid, stack_top))
+}
+
+/// Kill a process with `signal`, as `sys_kill` does
+///
+/// The process and its threads exit at once. Threads running on a CPU are
+/// switched out on its next tick, and an executor task is dropped the next
+/// time the executor would poll it. Returns false if there is no such
+/// process or it has already exited.
+pub fn kill_process(pid: u64, signal: u32) -> bool {
+    let (cpus, waker) = {
         let table = get_or_init_process_table();
-        let processes = table.lock();
-
-        if let Some(process) = processes.iter().find(|p| p.id.0 == pid) {
-            match process.status {
-                ProcessStatus::Exited(code) => return Some(code),
-                _ => {
-                    // Process still running, need to yield and retry
-                    drop(processes);
-                    // Small busy-wait (in real implementation would use events)
-                    for _ in 0..1000 {
-                        core::hint::spin_loop();
-                    }
-                }
+        let mut processes = table.lock();
+
+        let process = match processes.iter_mut().find(|p| p.id.0 == pid) {
+            Some(process) if !matches!(process.status, ProcessStatus::Exited(_)) => process,
+            _ => return false,
+        };
+        let cpus: Vec<usize> = process
+            .threads
+            .iter()
+            .filter_map(|t| t.running_on)
+            .collect();
+        let waker = process.task.clone();
+        mark_exited(process, ExitReason::Signaled(signal));
+        reparent_children(&mut processes, pid);
+        (cpus, waker)
+    };
+
+    // Release the table before taking the schedulers or waking the executor
+    for cpu in cpus {
+        crate::scheduler::request_reschedule_on(cpu);
+    }
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+    true
+}
+
+/// Kill a process with `signal` from an interrupt handler
+///
+/// Like `kill_process`, but never spins: returns false, leaving the
+/// process alone, if the interrupted code holds the process table. The
+/// caller switches away from the process itself; nothing is woken.
+pub fn try_kill_process(pid: u64, signal: u32) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = match table.try_lock() {
+        Some(processes) => processes,
+        None => return false,
+    };
+
+    let process = match processes.iter_mut().find(|p| p.id.0 == pid) {
+        Some(process) if !matches!(process.status, ProcessStatus::Exited(_)) => process,
+        _ => return false,
+    };
+    mark_exited(process, ExitReason::Signaled(signal));
+    reparent_children(&mut processes, pid);
+    true
+}
+
+/// Add the process record of an executor task, as a child of the current
+/// process
+///
+/// # Returns
+/// Process ID, or -2 if the table is full
+pub fn register_task(name: &str, waker: Waker) -> i64 {
+    insert_process(Process::new_task(name, waker))
+}
+
+/// Mark an executor task Running before the executor polls it
+///
+/// Returns false if the task was killed (or reaped) and must be dropped
+/// instead.
+pub fn start_task_poll(pid: u64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    match processes.iter_mut().find(|p| p.id.0 == pid) {
+        Some(process) if !matches!(process.status, ProcessStatus::Exited(_)) => {
+            process.status = ProcessStatus::Running;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Record how a poll of an executor task ended
+///
+/// A finished task exits with code 0; a pending one is Blocked until its
+/// waker queues it again.
+pub fn finish_task_poll(pid: u64, done: bool) {
+    if done {
+        set_process_status(pid, ProcessStatus::Exited(ExitReason::Code(0)));
+        return;
+    }
+
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+    if let Some(process) = processes.iter_mut().find(|p| p.id.0 == pid)
+        && process.status == ProcessStatus::Running
+    {
+        process.status = ProcessStatus::Blocked;
+    }
+}
+
+/// Create a new thread inside an existing process
+///
+/// # Arguments
+/// * `pid` - Process that will own the thread
+/// * `entry_point` - Address of the thread function (`fn(usize) -> i64`)
+/// * `arg` - Value passed to the thread function
+/// * `stack` - Stack size, or address space to reserve for a growable stack
+///
+/// # Returns
+/// Thread ID if successful, or negative error code
+/// (-1 invalid entry point or stack size, -2 too many threads,
+/// -3 no memory for the stack, -5 no such process)
+pub fn create_thread(pid: u64, entry_point: usize, arg: usize, stack: StackSpec) -> i64 {
+    if entry_point == 0 {
+        return -1; // Invalid address
+    }
+
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    let process = match processes.iter_mut().find(|p| p.id.0 == pid) {
+        Some(process) => process,
+        None => return -5,
+    };
+    if matches!(process.status, ProcessStatus::Exited(_)) {
+        return -5;
+    }
+    if process.threads.len() >= MAX_THREADS_PER_PROCESS {
+        return -2; // Too many threads
+    }
+
+    let thread = match Thread::new(ThreadId::new(), process.id, entry_point, arg, stack) {
+        Ok(thread) => thread,
+        Err(e) => return stack_error_code(e),
+    };
+    let tid = thread.id.0;
+    process.threads.push(thread);
+
+    drop(processes); // Release the lock before calling scheduler
+    crate::scheduler::enqueue_process(tid);
+
+    tid as i64
+}
+
+/// Get the process that owns a thread
+pub fn thread_owner(tid: u64) -> Option<u64> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    processes
+        .iter()
+        .find(|p| p.thread(tid).is_some())
+        .map(|p| p.id.0)
+}
+
+/// Label a thread
+///
+/// Naming the main thread renames the process, which is what `ps` shows;
+/// other threads keep their own label.
+pub fn set_thread_name(tid: u64, name: &str) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    let process = match processes.iter_mut().find(|p| p.thread(tid).is_some()) {
+        Some(process) => process,
+        None => return false,
+    };
+
+    if process.id.0 == tid {
+        process.name = String::from(name);
+    } else if let Some(thread) = process.thread_mut(tid) {
+        thread.name = Some(String::from(name));
+    }
+    true
+}
+
+/// Get the status of a thread
+pub fn get_thread_status(tid: u64) -> Option<ProcessStatus> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    processes
+        .iter()
+        .find_map(|p| p.thread(tid))
+        .map(|t| t.status)
+}
+
+/// Mark a Running or Ready thread as Blocked (it will not be scheduled
+/// until woken); returns false if it is neither
+pub fn block_thread(tid: u64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    set_blocked(&mut processes, tid, true)
+}
+
+/// Mark a Blocked thread as Ready, from an interrupt handler
+///
+/// `enqueue` is given what the scheduler needs to requeue the thread and
+/// returns whether it did; only then is the thread made Ready. Returns
+/// false, to be retried later, if the process table is held by the
+/// interrupted code or `enqueue` failed, and true once the thread is awake
+/// or if it is not Blocked (it exited, or was never blocked).
+///
+/// The process table is taken before the scheduler, as in the timer
+/// interrupt's switch.
+pub fn try_wake_thread(tid: u64, enqueue: impl FnOnce(&SchedInfo) -> bool) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = match table.try_lock() {
+        Some(processes) => processes,
+        None => return false,
+    };
+
+    let info = match sched_info(&processes, tid) {
+        Some(info) if info.status == ProcessStatus::Blocked => info,
+        _ => return true,
+    };
+    if !enqueue(&info) {
+        return false;
+    }
+    set_blocked(&mut processes, tid, false);
+    true
+}
+
+/// Move a thread (and, for a main thread, its process) between Blocked
+/// and runnable
+fn set_blocked(processes: &mut [Process], tid: u64, blocked: bool) -> bool {
+    let process = match processes.iter_mut().find(|p| p.thread(tid).is_some()) {
+        Some(process) => process,
+        None => return false,
+    };
+    let thread = match process.thread_mut(tid) {
+        Some(thread) => thread,
+        None => return false,
+    };
+    let status = match (thread.status, blocked) {
+        (ProcessStatus::Running | ProcessStatus::Ready, true) => ProcessStatus::Blocked,
+        (ProcessStatus::Blocked, false) => ProcessStatus::Ready,
+        _ => return false,
+    };
+    thread.status = status;
+    if !blocked {
+        thread.ready_since = crate::clock::monotonic_ns();
+    }
+    if tid == process.id.0 {
+        process.status = status;
+    }
+    true
+}
+
+/// Get what the scheduler needs to know about a thread
+pub fn get_sched_info(tid: u64) -> Option<SchedInfo> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    sched_info(&processes, tid)
+}
+
+/// Look a thread up for the scheduler
+fn sched_info(processes: &[Process], tid: u64) -> Option<SchedInfo> {
+    processes.iter().find_map(|p| {
+        p.thread(tid).map(|t| SchedInfo {
+            status: t.status,
+            process: p.id.0,
+            nice: p.nice,
+            class: p.sched_class,
+            running_on: t.running_on,
+        })
+    })
+}
+
+/// Get a process's nice value
+pub fn get_nice(pid: u64) -> Option<i32> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    processes.iter().find(|p| p.id.0 == pid).map(|p| p.nice)
+}
+
+/// Set a process's nice value, clamped to `NICE_MIN`..=`NICE_MAX`
+///
+/// Threads already in the ready queue move to their new priority level.
+pub fn set_nice(pid: u64, nice: i32) -> bool {
+    let nice = nice.clamp(NICE_MIN, NICE_MAX);
+    let tids: Vec<u64> = {
+        let table = get_or_init_process_table();
+        let mut processes = table.lock();
+
+        match processes.iter_mut().find(|p| p.id.0 == pid) {
+            Some(process) => {
+                process.nice = nice;
+                process.threads.iter().map(Thread::tid).collect()
             }
-        } else {
-            // Process doesn't exist
-            return None;
+            None => return false,
         }
+    };
+
+    // Release the table before taking the scheduler lock
+    crate::scheduler::reprioritize(&tids, nice);
+    true
+}
+
+/// Get a process's scheduling class
+pub fn get_sched_class(pid: u64) -> Option<SchedClass> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    processes
+        .iter()
+        .find(|p| p.id.0 == pid)
+        .map(|p| p.sched_class)
+}
+
+/// Set a process's scheduling class
+///
+/// Queued threads move between the real-time and normal queues; a running
+/// thread is switched out on the next tick.
+pub fn set_sched_class(pid: u64, class: SchedClass) -> bool {
+    let (tids, nice): (Vec<u64>, i32) = {
+        let table = get_or_init_process_table();
+        let mut processes = table.lock();
+
+        match processes.iter_mut().find(|p| p.id.0 == pid) {
+            Some(process) => {
+                process.sched_class = class;
+                (
+                    process.threads.iter().map(Thread::tid).collect(),
+                    process.nice,
+                )
+            }
+            None => return false,
+        }
+    };
+
+    // Release the table before taking the scheduler lock
+    crate::scheduler::reclassify(&tids, nice, class);
+    true
+}
+
+/// Mark a thread as exited
+///
+/// When the last live thread of a process exits, the process itself exits
+/// with the same code.
+pub fn exit_thread(tid: u64, exit_code: i64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    let process = match processes.iter_mut().find(|p| p.thread(tid).is_some()) {
+        Some(process) => process,
+        None => return false,
+    };
+
+    if let Some(thread) = process.thread_mut(tid) {
+        thread.status = ProcessStatus::Exited(ExitReason::Code(exit_code));
     }
+
+    let all_exited = process
+        .threads
+        .iter()
+        .all(|t| matches!(t.status, ProcessStatus::Exited(_)));
+    if all_exited {
+        process.status = ProcessStatus::Exited(ExitReason::Code(exit_code));
+        process.exit_code = exit_code;
+        let pid = process.id.0;
+        reparent_children(&mut processes, pid);
+    }
+    true
+}
+
+/// Run `f` on the process owning thread `tid` from an exception handler
+///
+/// Never spins on the process table: returns `None` if it is locked or no
+/// process owns `tid`.
+pub fn try_with_thread_owner<R>(tid: u64, f: impl FnOnce(&Process) -> R) -> Option<R> {
+    let table = get_or_init_process_table();
+    let processes = table.try_lock()?;
+
+    processes.iter().find(|p| p.thread(tid).is_some()).map(f)
+}
+
+/// Hand the children of an exited process to init
+fn reparent_children(processes: &mut [Process], pid: u64) {
+    if pid == INIT_PID {
+        return;
+    }
+    for child in processes.iter_mut().filter(|p| p.parent_id == pid) {
+        child.parent_id = INIT_PID;
+    }
+}
+
+/// Why a wait ended without a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitError {
+    /// The thread or process does not exist
+    NotFound,
+    /// The deadline passed first
+    TimedOut,
+}
+
+/// Wait for a thread to exit and return its exit code
+pub fn join_thread(tid: u64) -> Option<i64> {
+    join_thread_until(tid, None).ok()
+}
+
+/// Wait until tick `deadline` (`None`: forever) for a thread to exit
+pub fn join_thread_until(tid: u64, deadline: Option<u64>) -> Result<i64, WaitError> {
+    crate::timer::wait_until(deadline, || match get_thread_status(tid) {
+        Some(ProcessStatus::Exited(reason)) => Some(Ok(reason.code())),
+        Some(_) => None,
+        None => Some(Err(WaitError::NotFound)),
+    })
+    .unwrap_or(Err(WaitError::TimedOut))
+}
+
+/// Wait for a process to exit and return its exit code
+pub fn wait_process(pid: u64) -> Option<i64> {
+    wait_process_until(pid, None).ok()
+}
+
+/// Wait until tick `deadline` (`None`: forever) for a process to exit
+pub fn wait_process_until(pid: u64, deadline: Option<u64>) -> Result<i64, WaitError> {
+    crate::timer::wait_until(deadline, || match get_process_status(pid) {
+        Some(ProcessStatus::Exited(reason)) => Some(Ok(reason.code())),
+        Some(_) => None,
+        None => Some(Err(WaitError::NotFound)),
+    })
+    .unwrap_or(Err(WaitError::TimedOut))
+}
+
+/// Result of looking for an exited child
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildStatus {
+    /// A child exited and has been removed from the table
+    Exited { pid: u64, exit_code: i64 },
+    /// Matching children exist but none has exited yet
+    Running,
+    /// The parent has no matching children
+    NoChildren,
+}
+
+/// Reap one exited child of `parent` without blocking
+///
+/// `pid` selects a specific child; `None` accepts any child. A reaped
+/// child is removed from the process table, which frees its stacks, so an
+/// exited child counts as running until no CPU is still on one of its
+/// threads (a thread killed from another CPU stays switched in until that
+/// CPU switches away).
+pub fn try_reap_child(parent: u64, pid: Option<u64>) -> ChildStatus {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    let is_match = |p: &Process| p.parent_id == parent && pid.is_none_or(|pid| p.id.0 == pid);
+
+    let exited = processes.iter().position(|p| {
+        is_match(p)
+            && matches!(p.status, ProcessStatus::Exited(_))
+            && p.threads.iter().all(|t| t.running_on.is_none())
+    });
+    if let Some(index) = exited {
+        let child = processes.remove(index);
+        drop(processes); // Free the child's stacks outside the lock
+        return ChildStatus::Exited {
+            pid: child.id.0,
+            exit_code: child.exit_code,
+        };
+    }
+
+    if processes.iter().any(is_match) {
+        ChildStatus::Running
+    } else {
+        ChildStatus::NoChildren
+    }
+}
+
+/// Wait for a child of `parent` to exit and reap it
+///
+/// Like `try_reap_child`, but keeps waiting while matching children are
+/// still running unless `nohang` is set.
+pub fn wait_child(parent: u64, pid: Option<u64>, nohang: bool) -> ChildStatus {
+    if nohang {
+        return try_reap_child(parent, pid);
+    }
+    wait_child_until(parent, pid, None)
+}
+
+/// Wait until tick `deadline` (`None`: forever) for a child of `parent`
+/// to exit and reap it
+///
+/// Returns `ChildStatus::Running` if the deadline passes first.
+pub fn wait_child_until(parent: u64, pid: Option<u64>, deadline: Option<u64>) -> ChildStatus {
+    crate::timer::wait_until(deadline, || match try_reap_child(parent, pid) {
+        ChildStatus::Running => None,
+        status => Some(status),
+    })
+    .unwrap_or(ChildStatus::Running)
 }
 
 /// List all processes (for debugging)
@@ -288,49 +1248,124 @@ pub fn list_processes() -> alloc::vec::Vec<(u64, ProcessStatus)> {
     processes.iter().map(|p| (p.id.0, p.status)).collect()
 }
 
+/// Snapshot process records for `sys_ps`
+///
+/// Returns at most `max` records for processes whose PID is greater than
+/// `cursor`, in ascending PID order. Callers page through the table by
+/// passing the last PID they received as the next cursor.
+pub fn process_info(cursor: u64, max: usize) -> Vec<ProcessInfo> {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    let mut infos: Vec<ProcessInfo> = processes
+        .iter()
+        .filter(|p| p.id.0 > cursor)
+        .map(|p| p.info())
+        .collect();
+    infos.sort_unstable_by_key(|info| info.pid);
+    infos.truncate(max);
+    infos
+}
+
+/// Charge one timer tick to the process owning a running thread
+///
+/// Called from the timer interrupt, so it never spins on the process table:
+/// if the lock is held the tick is simply not accounted.
+pub fn account_tick(tid: u64) {
+    let table = get_or_init_process_table();
+    if let Some(mut processes) = table.try_lock()
+        && let Some(process) = processes.iter_mut().find(|p| p.thread(tid).is_some())
+    {
+        process.cpu_ticks = process.cpu_ticks.saturating_add(1);
+    }
+}
+
 /// Execute a single task by PID directly (no context switching)
 pub fn execute_process(pid: u64) -> Option<i64> {
     let entry_point = {
         let table = get_or_init_process_table();
         let mut processes = table.lock();
 
-        if let Some(process) = processes.iter_mut().find(|p| p.id.0 == pid) {
-            process.status = ProcessStatus::Running;
-            process.entry_point
-        } else {
-            return None;
-        }
+        let process = processes
+            .iter_mut()
+            .find(|p| p.id.0 == pid && p.task.is_none())?;
+        process.status = ProcessStatus::Running;
+        let main_thread = process.main_thread();
+        crate::tls::write_fs_base(main_thread.saved_context.fs_base);
+        main_thread.fpu.restore();
+        process.entry_point
     };
 
     // Execute the task function directly
     let task_fn = unsafe { core::mem::transmute::<usize, fn() -> i64>(entry_point) };
     let exit_code = task_fn();
+    save_fpu_state(pid);
 
     // Mark as exited
-    set_process_status(pid, ProcessStatus::Exited(exit_code));
+    set_process_status(pid, ProcessStatus::Exited(ExitReason::Code(exit_code)));
+
+    Some(exit_code)
+}
+
+/// Execute a single secondary thread by TID directly (no context switching)
+pub fn execute_thread(tid: u64) -> Option<i64> {
+    let (entry_point, arg) = {
+        let table = get_or_init_process_table();
+        let mut processes = table.lock();
+
+        let thread = find_thread_mut(&mut processes, tid)?;
+        thread.status = ProcessStatus::Running;
+        crate::tls::write_fs_base(thread.saved_context.fs_base);
+        thread.fpu.restore();
+        (thread.entry_point, thread.arg)
+    };
+
+    // Execute the thread function directly
+    let thread_fn = unsafe { core::mem::transmute::<usize, fn(usize) -> i64>(entry_point) };
+    let exit_code = thread_fn(arg);
+    save_fpu_state(tid);
+
+    exit_thread(tid, exit_code);
 
     Some(exit_code)
 }
 
-/// Execute all ready processes
+/// Execute all ready processes and threads
+///
+/// With preemption enabled the scheduler already runs ready threads from
+/// the timer interrupt (and may have started some of them), so nothing is
+/// run directly and this returns 0.
 pub fn execute_all_ready() -> u32 {
+    if crate::scheduler::is_preemption_enabled() {
+        return 0;
+    }
+
     let mut executed = 0;
 
     loop {
-        // Find next ready process
-        let pid_to_run = {
+        // Find next ready process, then next ready secondary thread
+        let (pid_to_run, tid_to_run) = {
             let table = get_or_init_process_table();
             let processes = table.lock();
 
-            processes
+            let pid = processes
                 .iter()
-                .find(|p| p.status == ProcessStatus::Ready)
-                .map(|p| p.id.0)
+                .find(|p| p.status == ProcessStatus::Ready && p.task.is_none())
+                .map(|p| p.id.0);
+            let tid = processes
+                .iter()
+                .flat_map(|p| p.threads.iter())
+                .find(|t| !t.is_main() && t.status == ProcessStatus::Ready)
+                .map(|t| t.id.0);
+            (pid, tid)
         };
 
         if let Some(pid) = pid_to_run {
             execute_process(pid);
             executed += 1;
+        } else if let Some(tid) = tid_to_run {
+            execute_thread(tid);
+            executed += 1;
         } else {
             break;
         }
@@ -339,36 +1374,86 @@ pub fn execute_all_ready() -> u32 {
     executed
 }
 
-/// Get mutable reference to process's context for saving/restoring
-pub fn get_process_context_mut(pid: u64) -> Option<*mut TaskContext> {
+/// Get mutable reference to a thread's context for saving/restoring
+///
+/// Accepts any thread ID; a PID addresses the process's main thread.
+pub fn get_process_context_mut(tid: u64) -> Option<*mut TaskContext> {
     let table = get_or_init_process_table();
     let mut processes = table.lock();
 
-    if let Some(process) = processes.iter_mut().find(|p| p.id.0 == pid) {
-        Some(&mut process.saved_context as *mut TaskContext)
-    } else {
-        None
-    }
+    find_thread_mut(&mut processes, tid).map(|t| &mut t.saved_context as *mut TaskContext)
 }
 
-/// Get process's stack pointer (RSP)
-pub fn get_process_stack_pointer(pid: u64) -> Option<u64> {
+/// Get a thread's stack pointer (RSP)
+pub fn get_process_stack_pointer(tid: u64) -> Option<u64> {
     let table = get_or_init_process_table();
     let processes = table.lock();
 
     processes
         .iter()
-        .find(|p| p.id.0 == pid)
-        .map(|p| p.saved_context.rsp)
+        .find_map(|p| p.thread(tid))
+        .map(|t| t.saved_context.rsp)
 }
 
-/// Update process's stack pointer (RSP)
-pub fn set_process_stack_pointer(pid: u64, rsp: u64) -> bool {
+/// Update a thread's stack pointer (RSP)
+pub fn set_process_stack_pointer(tid: u64, rsp: u64) -> bool {
     let table = get_or_init_process_table();
     let mut processes = table.lock();
 
-    if let Some(process) = processes.iter_mut().find(|p| p.id.0 == pid) {
-        process.saved_context.rsp = rsp;
+    if let Some(thread) = find_thread_mut(&mut processes, tid) {
+        thread.saved_context.rsp = rsp;
+        true
+    } else {
+        false
+    }
+}
+
+/// Save the CPU's FPU/SIMD registers into a thread's save area
+pub fn save_fpu_state(tid: u64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    if let Some(thread) = find_thread_mut(&mut processes, tid) {
+        thread.fpu.save();
+        true
+    } else {
+        false
+    }
+}
+
+/// Load a thread's saved FPU/SIMD registers into the CPU
+pub fn restore_fpu_state(tid: u64) -> bool {
+    let table = get_or_init_process_table();
+    let processes = table.lock();
+
+    if let Some(thread) = processes.iter().find_map(|p| p.thread(tid)) {
+        thread.fpu.restore();
+        true
+    } else {
+        false
+    }
+}
+
+/// Update a thread's saved FS base (thread pointer)
+pub fn set_thread_fs_base(tid: u64, base: u64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    if let Some(thread) = find_thread_mut(&mut processes, tid) {
+        thread.saved_context.fs_base = base;
+        true
+    } else {
+        false
+    }
+}
+
+/// Update a thread's saved GS base
+pub fn set_thread_gs_base(tid: u64, base: u64) -> bool {
+    let table = get_or_init_process_table();
+    let mut processes = table.lock();
+
+    if let Some(thread) = find_thread_mut(&mut processes, tid) {
+        thread.saved_context.gs_base = base;
         true
     } else {
         false
@@ -396,73 +1481,397 @@ pub struct ProcessMutRef {
 }
 
 impl ProcessMutRef {
-    /// Update the saved context for this process
+    /// Update the saved context for this process's main thread
     pub fn update_context(&self, ctx: TaskContext) {
         let table = get_or_init_process_table();
         let mut processes = table.lock();
-        if let Some(process) = processes.iter_mut().find(|p| p.id.0 == self.pid) {
-            process.saved_context = ctx;
+        if let Some(main_thread) = processes
+            .iter_mut()
+            .find(|p| p.id.0 == self.pid)
+            .and_then(|p| p.threads.first_mut())
+        {
+            main_thread.saved_context = ctx;
         }
     }
 }
 
-/// Context switch: Save current task's context, load next task's context
-/// This is called during process switches (e.g., on timer interrupt, syscall)
+/// The process table, held by the timer interrupt from picking the next
+/// thread until the switch is done
+///
+/// Otherwise another CPU could pick the thread being switched out before
+/// its registers are saved.
+pub struct SwitchLock {
+    processes: MutexGuard<'static, Vec<Process>>,
+}
+
+/// Take the process table for a switch from the timer interrupt
 ///
-/// # Safety
-/// Caller must ensure valid CPU state and no reentrancy
-pub unsafe fn context_switch(current_pid: Option<u64>, next_pid: u64) {
-    // If there's a current process, save its context
-    if let Some(pid) = current_pid {
-        if let Some(_ctx_ptr) = get_process_context_mut(pid) {
-            // In a real implementation, we'd save all CPU registers here
-            // For now, this is a placeholder for assembly-based save
-            set_process_status(pid, ProcessStatus::Ready);
+/// Never spins: returns `None` if it is locked.
+pub fn try_lock_for_switch() -> Option<SwitchLock> {
+    let processes = get_or_init_process_table().try_lock()?;
+    Some(SwitchLock { processes })
+}
+
+impl SwitchLock {
+    /// Look a thread up for the scheduler
+    pub fn sched_info(&self, tid: u64) -> Option<SchedInfo> {
+        sched_info(&self.processes, tid)
+    }
+
+    /// Save the registers of the thread being switched away from
+    ///
+    /// Stores `ctx` and the CPU's FPU state in the thread; a thread that
+    /// was running goes back to Ready. Returns whether it did, that is
+    /// whether the thread was preempted rather than giving up the CPU.
+    pub fn switch_out(&mut self, tid: u64, ctx: &TaskContext) -> bool {
+        let process = match self.processes.iter_mut().find(|p| p.thread(tid).is_some()) {
+            Some(process) => process,
+            None => return false,
+        };
+        if tid == process.id.0 && process.status == ProcessStatus::Running {
+            process.status = ProcessStatus::Ready;
+        }
+        let thread = match process.thread_mut(tid) {
+            Some(thread) => thread,
+            None => return false,
+        };
+        thread.saved_context = ctx.clone();
+        thread.fpu.save();
+        thread.running_on = None;
+        let preempted = thread.status == ProcessStatus::Running;
+        if preempted {
+            thread.status = ProcessStatus::Ready;
+            thread.ready_since = crate::clock::monotonic_ns();
+            process.involuntary_switches += 1;
+        } else {
+            process.voluntary_switches += 1;
         }
+        preempted
     }
 
-    // Load the next process's context
-    if let Some(_ctx_ptr) = get_process_context_mut(next_pid) {
-        // In a real implementation, we'd restore all CPU registers
-        // and jump to the process's entry point
-        set_process_status(next_pid, ProcessStatus::Running);
+    /// Load the thread being switched to on `cpu`
+    ///
+    /// Restores its FPU state, marks it Running, records its stack as the
+    /// CPU's kernel stack, counts the switch and how long the thread waited
+    /// Ready, and returns the context to resume.
+    pub fn switch_in(&mut self, tid: u64, cpu: usize) -> Option<TaskContext> {
+        let process = self
+            .processes
+            .iter_mut()
+            .find(|p| p.thread(tid).is_some())?;
+        if tid == process.id.0 {
+            process.status = ProcessStatus::Running;
+        }
+        let thread = process.thread_mut(tid)?;
+        thread.status = ProcessStatus::Running;
+        thread.running_on = Some(cpu);
+        let waited = crate::clock::monotonic_ns().saturating_sub(thread.ready_since);
+        thread.fpu.restore();
+        crate::percpu::cpu(cpu).set_kernel_stack_top(thread.stack.top());
+        let ctx = thread.saved_context.clone();
+        process.switches += 1;
+        process.ready_ns += waited;
+        crate::scheduler::stats::count_switch_in(waited);
+        Some(ctx)
     }
 }
 
-/// Get a copy of a process's context
-pub fn get_process_context(pid: u64) -> Option<TaskContext> {
+/// Get a copy of a thread's context (a PID addresses the main thread)
+pub fn get_process_context(tid: u64) -> Option<TaskContext> {
     let table = get_or_init_process_table();
     let processes = table.lock();
     processes
         .iter()
-        .find(|p| p.id.0 == pid)
-        .map(|p| p.saved_context.clone())
+        .find_map(|p| p.thread(tid))
+        .map(|t| t.saved_context.clone())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_create_process() {
         let pid = create_process(0x1000);
         assert!(pid > 0);
     }
 
-    #[test]
+    #[test_case]
     fn test_process_id_unique() {
         let pid1 = create_process(0x1000);
         let pid2 = create_process(0x2000);
         assert_ne!(pid1, pid2);
     }
 
-    #[test]
+    #[test_case]
+    fn test_process_info_cursor() {
+        let pid1 = create_process(0x1000) as u64;
+        let pid2 = create_process(0x2000) as u64;
+
+        let page = process_info(pid1 - 1, 1);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].pid, pid1);
+
+        let next = process_info(page[0].pid, 1);
+        assert_eq!(next[0].pid, pid2);
+        assert_eq!(next[0].name(), "unnamed");
+    }
+
+    #[test_case]
+    fn test_thread_shares_process() {
+        let pid = create_process(0x1000) as u64;
+        let tid = create_thread(pid, 0x2000, 7, StackSpec::Fixed(0)) as u64;
+
+        assert_ne!(tid, pid);
+        assert_eq!(thread_owner(tid), Some(pid));
+        assert_eq!(thread_owner(pid), Some(pid));
+        assert_eq!(get_thread_status(tid), Some(ProcessStatus::Ready));
+    }
+
+    #[test_case]
+    fn test_last_thread_exit_exits_process() {
+        let pid = create_process(0x1000) as u64;
+        let tid = create_thread(pid, 0x2000, 0, StackSpec::Fixed(0)) as u64;
+
+        exit_thread(pid, 0);
+        assert_eq!(get_process_status(pid), Some(ProcessStatus::Ready));
+
+        exit_thread(tid, 3);
+        assert_eq!(join_thread(tid), Some(3));
+        assert_eq!(
+            get_process_status(pid),
+            Some(ProcessStatus::Exited(ExitReason::Code(3)))
+        );
+    }
+
+    #[test_case]
+    fn test_pid_1_is_reserved_for_init() {
+        let pid = create_process(0x1000);
+        assert_ne!(pid as u64, INIT_PID);
+    }
+
+    /// Insert a new process as a child of `parent`
+    fn insert_child(parent: u64) -> u64 {
+        let mut child = Process::new(0x2000, StackSpec::Fixed(0)).unwrap();
+        let pid = child.pid();
+        child.parent_id = parent;
+        get_or_init_process_table().lock().push(child);
+        pid
+    }
+
+    /// Mark the main thread of `pid` as switched in on `cpu`
+    fn set_running_on(pid: u64, cpu: Option<usize>) {
+        let mut processes = get_or_init_process_table().lock();
+        let process = processes.iter_mut().find(|p| p.id.0 == pid).unwrap();
+        process.threads[0].running_on = cpu;
+    }
+
+    #[test_case]
+    fn test_reap_child_and_reparent_orphans() {
+        let parent = create_process(0x1000) as u64;
+        let child_pid = insert_child(parent);
+
+        assert_eq!(try_reap_child(parent, None), ChildStatus::Running);
+        assert_eq!(
+            try_reap_child(parent, Some(parent)),
+            ChildStatus::NoChildren
+        );
+
+        set_process_status(child_pid, ProcessStatus::Exited(ExitReason::Code(4)));
+        // Its stack stays until the CPU still running it switches away
+        set_running_on(child_pid, Some(1));
+        assert_eq!(try_reap_child(parent, None), ChildStatus::Running);
+        set_running_on(child_pid, None);
+        assert_eq!(
+            wait_child(parent, None, false),
+            ChildStatus::Exited {
+                pid: child_pid,
+                exit_code: 4
+            }
+        );
+        assert_eq!(get_process(child_pid), None);
+
+        // A child left behind when its parent exits is adopted by init
+        let orphan_pid = insert_child(parent);
+
+        set_process_status(parent, ProcessStatus::Exited(ExitReason::Code(0)));
+        assert_eq!(try_reap_child(parent, None), ChildStatus::NoChildren);
+        assert_eq!(
+            try_reap_child(INIT_PID, Some(orphan_pid)),
+            ChildStatus::Running
+        );
+    }
+
+    #[test_case]
+    fn test_names_and_command_line() {
+        let mut process = Process::new_with_name("shell", StackSpec::Fixed(0)).unwrap();
+        process.args.push(String::from("--login"));
+        let pid = process.pid();
+        get_or_init_process_table().lock().push(process);
+        let tid = create_thread(pid, 0x2000, 0, StackSpec::Fixed(0)) as u64;
+
+        // A secondary thread keeps its own label
+        assert!(set_thread_name(tid, "worker"));
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].name(), "shell");
+        assert_eq!(info[0].cmdline(), "shell --login");
+
+        // Naming the main thread renames the process, not its command line
+        assert!(set_thread_name(pid, "login-shell"));
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].name(), "login-shell");
+        assert_eq!(info[0].cmdline(), "shell --login");
+
+        assert!(!set_thread_name(u64::MAX, "nobody"));
+    }
+
+    #[test_case]
+    fn test_nice_value() {
+        let pid = create_process(0x1000) as u64;
+        assert_eq!(get_nice(pid), Some(0));
+
+        assert!(set_nice(pid, 5));
+        assert_eq!(get_sched_info(pid).map(|info| info.nice), Some(5));
+        assert_eq!(process_info(pid - 1, 1)[0].nice, 5);
+
+        // Out-of-range values are clamped
+        assert!(set_nice(pid, -100));
+        assert_eq!(get_nice(pid), Some(NICE_MIN));
+        assert!(set_nice(pid, 100));
+        assert_eq!(get_nice(pid), Some(NICE_MAX));
+
+        assert!(!set_nice(u64::MAX, 0));
+    }
+
+    #[test_case]
+    fn test_sched_class() {
+        let pid = create_process(0x1000) as u64;
+        assert_eq!(get_sched_class(pid), Some(SchedClass::Normal));
+
+        assert!(set_sched_class(pid, SchedClass::Fifo(50)));
+        assert_eq!(
+            get_sched_info(pid).map(|info| info.class),
+            Some(SchedClass::Fifo(50))
+        );
+        let info = process_info(pid - 1, 1)[0];
+        assert_eq!(info.policy, orbital_common::process::SCHED_FIFO);
+        assert_eq!(info.rt_priority, 50);
+
+        assert!(set_sched_class(pid, SchedClass::Normal));
+        assert!(!set_sched_class(u64::MAX, SchedClass::Normal));
+    }
+
+    #[test_case]
+    fn test_killed_process_reports_signal() {
+        use orbital_common::process::SIGSEGV;
+
+        let parent = create_process(0x1000) as u64;
+        let pid = insert_child(parent);
+        let tid = create_thread(pid, 0x2000, 0, StackSpec::Fixed(0)) as u64;
+
+        let reason = ExitReason::Killed {
+            signal: SIGSEGV,
+            rip: 0x2010,
+            addr: Some(0),
+        };
+        let (killed, _stack_top) = kill_thread_owner(tid, reason).unwrap();
+        assert_eq!(killed, pid);
+
+        // The whole process goes down, and ps can tell it was killed
+        assert_eq!(get_thread_status(pid), Some(ProcessStatus::Exited(reason)));
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].state(), Some(ProcessState::Killed));
+        assert_eq!(info[0].exit_code, SIGSEGV as i64);
+
+        assert_eq!(
+            wait_child(parent, Some(pid), false),
+            ChildStatus::Exited {
+                pid,
+                exit_code: -(SIGSEGV as i64)
+            }
+        );
+    }
+
+    #[test_case]
+    fn test_stack_overflow_reports_sigsegv() {
+        let pid = create_process(0x1000) as u64;
+        let tid = create_thread(pid, 0x2000, 0, StackSpec::Fixed(0)) as u64;
+
+        let reason = ExitReason::StackOverflow {
+            rip: 0x2010,
+            addr: 0x1000,
+        };
+        assert_eq!(
+            kill_thread_owner(tid, reason).map(|(pid, _)| pid),
+            Some(pid)
+        );
+        assert_eq!(get_process_status(pid), Some(ProcessStatus::Exited(reason)));
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].state(), Some(ProcessState::Killed));
+        assert_eq!(info[0].exit_code, SIGSEGV as i64);
+        assert_eq!(wait_process(pid), Some(-(SIGSEGV as i64)));
+    }
+
+    #[test_case]
+    fn test_kill_process() {
+        use orbital_common::process::SIGKILL;
+
+        let pid = create_process(0x1000) as u64;
+        let tid = create_thread(pid, 0x2000, 0, StackSpec::Fixed(0)) as u64;
+        assert!(kill_process(pid, SIGKILL));
+
+        let reason = ExitReason::Signaled(SIGKILL);
+        assert_eq!(get_process_status(pid), Some(ProcessStatus::Exited(reason)));
+        assert_eq!(get_thread_status(tid), Some(ProcessStatus::Exited(reason)));
+        assert_eq!(wait_process(pid), Some(-(SIGKILL as i64)));
+
+        // Already dead, or never existed
+        assert!(!kill_process(pid, SIGKILL));
+        assert!(!kill_process(u64::MAX, SIGKILL));
+    }
+
+    #[test_case]
+    fn test_executor_task_lifecycle() {
+        let pid = register_task("terminal", Waker::noop().clone()) as u64;
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].pid, pid);
+        assert_eq!(info[0].name(), "terminal");
+        assert_eq!(info[0].state(), Some(ProcessState::Ready));
+        assert_eq!(info[0].memory_bytes, 0);
+
+        assert!(start_task_poll(pid));
+        assert_eq!(get_process_status(pid), Some(ProcessStatus::Running));
+        finish_task_poll(pid, false);
+        assert_eq!(get_process_status(pid), Some(ProcessStatus::Blocked));
+
+        // Never picked up by `run`, which only starts processes with threads
+        assert_eq!(get_sched_info(pid), None);
+
+        assert!(start_task_poll(pid));
+        finish_task_poll(pid, true);
+        assert_eq!(wait_process(pid), Some(0));
+        assert!(!start_task_poll(pid));
+    }
+
+    #[test_case]
+    fn test_killed_task_is_dropped() {
+        use orbital_common::process::SIGTERM;
+
+        let pid = register_task("shell", Waker::noop().clone()) as u64;
+        assert!(kill_process(pid, SIGTERM));
+        assert!(!start_task_poll(pid));
+        let info = process_info(pid - 1, 1);
+        assert_eq!(info[0].state(), Some(ProcessState::Killed));
+        assert_eq!(info[0].exit_code, SIGTERM as i64);
+    }
+
+    #[test_case]
     fn test_invalid_entry_point() {
         let pid = create_process(0); // NULL pointer
         assert_eq!(pid, -1);
     }
 
-    #[test]
+    #[test_case]
     fn test_task_context_initialization() {
         // Test that TaskContext is properly initialized for task entry
         let stack_top = 0x8000u64;
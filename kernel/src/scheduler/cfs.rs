@@ -0,0 +1,208 @@
+//! CFS-like policy: run the thread that has had the least weighted CPU time
+//!
+//! Every thread accumulates virtual runtime while it runs, at a rate
+//! inversely proportional to the weight of its nice value (the Linux
+//! weight table: each nice step is worth about 10% of CPU). The thread
+//! with the smallest virtual runtime runs next, for its weighted share of
+//! `TARGET_LATENCY`, but never less than `MIN_GRANULARITY`.
+//!
+//! Threads that join the queue (new, or woken) start at the smallest
+//! virtual runtime seen so far, so they neither starve the others nor
+//! wait behind the whole history of long-running threads.
+
+use super::SchedPolicy;
+use alloc::vec::Vec;
+use orbital_common::process::{NICE_MAX, NICE_MIN};
+
+/// Ticks in which every runnable thread should get a turn
+pub(super) const TARGET_LATENCY: usize = 100;
+
+/// Shortest slice a thread is given, however many are runnable
+pub(super) const MIN_GRANULARITY: usize = 10;
+
+/// Weight of nice 0
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// Weight of each nice value, `NICE_MIN` first
+const NICE_WEIGHTS: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// Scheduling weight of a nice value
+fn weight(nice: i32) -> u64 {
+    NICE_WEIGHTS[(nice.clamp(NICE_MIN, NICE_MAX) - NICE_MIN) as usize]
+}
+
+/// A thread and its virtual runtime (1024 units = one tick at nice 0)
+#[derive(Debug, Clone, Copy)]
+struct Entity {
+    tid: u64,
+    weight: u64,
+    vruntime: u64,
+}
+
+/// CFS-like policy state
+pub struct Cfs {
+    /// Threads waiting to run, in arrival order (ties go to the oldest)
+    queue: Vec<Entity>,
+    /// The thread picked last, charged for each tick
+    running: Option<Entity>,
+    /// Smallest virtual runtime handed out so far; never decreases
+    min_vruntime: u64,
+    /// Slice of the running thread, in ticks
+    slice: usize,
+    /// Ticks the running thread has used of its slice
+    ticks: usize,
+}
+
+impl Cfs {
+    pub fn new() -> Self {
+        Cfs {
+            queue: Vec::new(),
+            running: None,
+            min_vruntime: 0,
+            slice: TARGET_LATENCY,
+            ticks: 0,
+        }
+    }
+}
+
+impl SchedPolicy for Cfs {
+    fn name(&self) -> &'static str {
+        "cfs"
+    }
+
+    /// The running thread keeps its virtual runtime when it is requeued;
+    /// anyone else starts at `min_vruntime`
+    fn enqueue(&mut self, tid: u64, nice: i32) {
+        if self.queue.iter().any(|entity| entity.tid == tid) {
+            return;
+        }
+        let vruntime = match self.running {
+            Some(running) if running.tid == tid => running.vruntime.max(self.min_vruntime),
+            _ => self.min_vruntime,
+        };
+        self.queue.push(Entity {
+            tid,
+            weight: weight(nice),
+            vruntime,
+        });
+    }
+
+    fn dequeue(&mut self, tid: u64) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|entity| entity.tid != tid);
+        self.queue.len() != len
+    }
+
+    /// Keeps the thread's virtual runtime; only the rate it grows at changes
+    fn renice(&mut self, tid: u64, nice: i32) {
+        let running = self.running.as_mut().filter(|entity| entity.tid == tid);
+        for entity in self
+            .queue
+            .iter_mut()
+            .chain(running)
+            .filter(|entity| entity.tid == tid)
+        {
+            entity.weight = weight(nice);
+        }
+    }
+
+    fn pick_next(&mut self) -> Option<u64> {
+        let (index, _) = self
+            .queue
+            .iter()
+            .enumerate()
+            .min_by_key(|&(index, entity)| (entity.vruntime, index))?;
+        let total_weight: u64 = self.queue.iter().map(|entity| entity.weight).sum();
+        let next = self.queue.remove(index);
+
+        let share = TARGET_LATENCY as u64 * next.weight / total_weight;
+        self.slice = (share as usize).max(MIN_GRANULARITY);
+        self.ticks = 0;
+        self.min_vruntime = self.min_vruntime.max(next.vruntime);
+        self.running = Some(next);
+        Some(next.tid)
+    }
+
+    fn tick(&mut self) -> bool {
+        if let Some(running) = self.running.as_mut() {
+            running.vruntime += NICE_0_WEIGHT * NICE_0_WEIGHT / running.weight;
+        }
+        self.ticks += 1;
+        if self.ticks >= self.slice {
+            self.ticks = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.queue.reserve(additional);
+    }
+
+    /// Takes the eligible thread with the largest virtual runtime; on the
+    /// other CPU it starts at that queue's `min_vruntime`
+    fn steal(&mut self, eligible: &dyn Fn(u64) -> bool) -> Option<u64> {
+        let (index, _) = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| eligible(entity.tid))
+            .max_by_key(|&(index, entity)| (entity.vruntime, index))?;
+        Some(self.queue.remove(index).tid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_weights() {
+        assert_eq!(weight(0), NICE_0_WEIGHT);
+        assert_eq!(weight(NICE_MIN), NICE_WEIGHTS[0]);
+        assert_eq!(weight(NICE_MAX), NICE_WEIGHTS[39]);
+        assert!(weight(-1) > weight(0) && weight(0) > weight(1));
+    }
+
+    #[test_case]
+    fn test_least_runtime_runs_next() {
+        let mut policy = Cfs::new();
+        policy.enqueue(1, 0);
+        policy.enqueue(2, 0);
+
+        // Thread 1 runs a while and is requeued behind thread 2
+        assert_eq!(policy.pick_next(), Some(1));
+        for _ in 0..5 {
+            policy.tick();
+        }
+        policy.enqueue(1, 0);
+        assert_eq!(policy.pick_next(), Some(2));
+        policy.enqueue(2, 0);
+        assert_eq!(policy.pick_next(), Some(2));
+    }
+
+    #[test_case]
+    fn test_slice_follows_weight() {
+        let mut policy = Cfs::new();
+        policy.enqueue(1, 0);
+        policy.enqueue(2, 0);
+        policy.pick_next();
+        assert_eq!(policy.slice, TARGET_LATENCY / 2);
+
+        // A batch thread next to a default one gets the minimum slice
+        let mut policy = Cfs::new();
+        policy.enqueue(1, orbital_common::process::NICE_BATCH);
+        policy.enqueue(2, 0);
+        policy.pick_next();
+        assert_eq!(policy.slice, MIN_GRANULARITY);
+    }
+}
@@ -0,0 +1,1073 @@
+//! Task scheduler - manages which process runs when
+//!
+//! The scheduler is responsible for:
+//! - Maintaining ready task queues
+//! - Selecting next task to run
+//! - Handling context switches
+//! - Supporting task suspension and resumption
+//!
+//! The scheduler works on thread IDs. A process's main thread shares its
+//! PID, so single-threaded processes are queued under their PID as before.
+//!
+//! The kernel's own context (the async executor) takes turns with the
+//! threads: while a thread runs, the kernel waits in the ready queue as
+//! `KERNEL_TID`. When a time slice expires, the timer interrupt calls
+//! `try_schedule` and `context_switch::context_switch` to move the CPU to
+//! the next thread in line.
+//!
+//! Who runs next, and for how long, is up to a `SchedPolicy`:
+//! - `round_robin`: one FIFO queue, 100 ticks each, nice ignored
+//! - `priority`: one queue per nice level with aging (the default)
+//! - `cfs`: least weighted CPU time first, slices shared by nice weight
+//!
+//! The policy is chosen at boot with `sched=round-robin|priority|cfs` on
+//! the kernel command line (see `cmdline`), so policies can be compared
+//! on the same workload without rebuilding the kernel.
+//!
+//! Threads in a real-time class (`SCHED_FIFO`/`SCHED_RR`, see `rt`) bypass
+//! the policy: they always run before normal threads, within the
+//! real-time throttle.
+//!
+//! Each CPU has a `Scheduler` of its own (see `smp`). New threads are
+//! queued on the CPU with the least to do and woken threads where they
+//! last ran; a CPU whose queue runs dry steals a thread from the busiest
+//! one. Only the boot CPU shares its time with the kernel's context; the
+//! others idle when they have nothing to run.
+
+mod cfs;
+mod priority;
+mod round_robin;
+mod rt;
+pub mod stats;
+
+pub use priority::priority_level;
+pub use rt::{RT_PERIOD, RT_RUNTIME, SchedClass};
+
+use cfs::Cfs;
+use priority::Priority;
+use round_robin::RoundRobin;
+use rt::{RR_QUANTUM, RtQueue, RtThrottle};
+
+use crate::percpu;
+use crate::process::ProcessStatus;
+use crate::smp::{BOOT_CPU, MAX_CPUS};
+use alloc::boxed::Box;
+use conquer_once::spin::OnceCell;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/// Global elapsed time in timer ticks since kernel boot
+/// The PIT ticks at `clock::TIMER_HZ` (100 Hz, 10ms per tick, by default)
+///
+/// Atomic because the timer interrupt may fire while it is being read.
+/// Only the boot CPU's ticks are counted.
+static ELAPSED_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Control whether timer interrupts perform context switching
+/// Off during boot (and in tests); the kernel enables it once init is
+/// queued
+static PREEMPTION_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Ready queue entry standing for the kernel's own context
+///
+/// No thread has ID 0 (PIDs start at `INIT_PID`).
+pub const KERNEL_TID: u64 = 0;
+
+/// Policy used when `sched=` is not on the command line or is unknown
+pub const DEFAULT_POLICY: PolicyKind = PolicyKind::Priority;
+
+/// What the scheduler needs to know about a thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedInfo {
+    pub status: ProcessStatus,
+    /// PID of the owning process
+    pub process: u64,
+    /// Nice value of the owning process
+    pub nice: i32,
+    /// Scheduling class of the owning process
+    pub class: SchedClass,
+    /// CPU that has the thread as its current thread, if any
+    pub running_on: Option<usize>,
+}
+
+/// A scheduling policy: the ready queue and the time slice of the running
+/// thread
+///
+/// `Scheduler` decides *when* to switch (the kernel sentinel, stale
+/// entries, reschedule requests); the policy decides *who* runs next and
+/// for how long. All methods except `reserve` run from the timer
+/// interrupt and must not allocate.
+pub trait SchedPolicy: Send {
+    /// Name used to select the policy at boot (`sched=<name>` on the kernel
+    /// command line)
+    fn name(&self) -> &'static str;
+
+    /// Add a runnable thread (no-op if it is already queued)
+    fn enqueue(&mut self, tid: u64, nice: i32);
+
+    /// Remove a queued thread; returns whether it was queued
+    fn dequeue(&mut self, tid: u64) -> bool;
+
+    /// Apply a new nice value to a thread (no-op if it is not queued)
+    fn renice(&mut self, tid: u64, nice: i32) {
+        if self.dequeue(tid) {
+            self.enqueue(tid, nice);
+        }
+    }
+
+    /// Take the thread to run next off the queue and start its time slice
+    fn pick_next(&mut self) -> Option<u64>;
+
+    /// Charge a timer tick to the running thread
+    /// Returns true when its time slice has run out
+    fn tick(&mut self) -> bool;
+
+    /// Number of threads waiting
+    fn queued(&self) -> usize;
+
+    /// Make room for `additional` more queued threads
+    fn reserve(&mut self, additional: usize);
+
+    /// Take a queued thread for which `eligible` holds off the queue, for
+    /// another CPU to run: the one this policy would run last
+    fn steal(&mut self, eligible: &dyn Fn(u64) -> bool) -> Option<u64>;
+}
+
+/// The policies the kernel ships
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyKind {
+    RoundRobin,
+    Priority,
+    Cfs,
+}
+
+impl PolicyKind {
+    /// Every policy, for tests and comparisons
+    pub const ALL: [PolicyKind; 3] = [
+        PolicyKind::RoundRobin,
+        PolicyKind::Priority,
+        PolicyKind::Cfs,
+    ];
+
+    /// Look a policy up by the name its `SchedPolicy::name` returns
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "round-robin" | "rr" => Some(PolicyKind::RoundRobin),
+            "priority" => Some(PolicyKind::Priority),
+            "cfs" => Some(PolicyKind::Cfs),
+            _ => None,
+        }
+    }
+
+    /// Create an empty instance of the policy
+    pub fn build(self) -> Box<dyn SchedPolicy> {
+        match self {
+            PolicyKind::RoundRobin => Box::new(RoundRobin::new()),
+            PolicyKind::Priority => Box::new(Priority::new()),
+            PolicyKind::Cfs => Box::new(Cfs::new()),
+        }
+    }
+}
+
+/// Policy selected with `sched=<name>` on the kernel command line
+pub fn boot_policy() -> PolicyKind {
+    crate::cmdline::get("sched")
+        .and_then(PolicyKind::from_name)
+        .unwrap_or(DEFAULT_POLICY)
+}
+
+/// Disable timer-based preemption (for cooperative multitasking environments like async executor)
+pub fn disable_preemption() {
+    PREEMPTION_ENABLED.store(false, Ordering::SeqCst);
+}
+
+/// Enable timer-based preemption
+pub fn enable_preemption() {
+    PREEMPTION_ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Check if preemption is currently enabled
+pub fn is_preemption_enabled() -> bool {
+    PREEMPTION_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Scheduler state
+pub struct Scheduler {
+    /// Ready queue and time slices
+    policy: Box<dyn SchedPolicy>,
+    /// Runnable real-time threads, served before the policy
+    rt: RtQueue,
+    /// Real-time bandwidth limit of this CPU
+    throttle: RtThrottle,
+    /// Current running thread ID (None while the kernel's own context runs)
+    current_process: Option<u64>,
+    /// Scheduling class of the current thread
+    current_class: SchedClass,
+    /// Ticks a real-time current thread has run since it was picked
+    rt_ticks: usize,
+    /// Switch on the next tick whatever the policy says
+    reschedule: bool,
+    /// Whether the kernel's own context takes turns with the threads (the
+    /// boot CPU, whose context is the executor)
+    shares_with_kernel: bool,
+}
+
+/// Each CPU's scheduler, indexed by `smp::cpu_id`
+static SCHEDULERS: OnceCell<[Mutex<Scheduler>; MAX_CPUS]> = OnceCell::uninit();
+
+impl Scheduler {
+    /// Create a new scheduler with the default policy
+    pub fn new() -> Self {
+        Self::with_policy(DEFAULT_POLICY)
+    }
+
+    /// Create a new scheduler running `kind` for the boot CPU
+    pub fn with_policy(kind: PolicyKind) -> Self {
+        Self::for_cpu(kind, BOOT_CPU)
+    }
+
+    /// Create a new scheduler running `kind` for CPU `cpu`
+    pub fn for_cpu(kind: PolicyKind, cpu: usize) -> Self {
+        Scheduler {
+            policy: kind.build(),
+            rt: RtQueue::new(),
+            throttle: RtThrottle::default(),
+            current_process: None,
+            current_class: SchedClass::Normal,
+            rt_ticks: 0,
+            reschedule: false,
+            shares_with_kernel: cpu == BOOT_CPU,
+        }
+    }
+
+    /// Name of the active policy
+    pub fn policy_name(&self) -> &'static str {
+        self.policy.name()
+    }
+
+    /// Add a process to the ready queue at the default nice value
+    pub fn enqueue(&mut self, pid: u64) {
+        self.enqueue_nice(pid, 0);
+    }
+
+    /// Add a thread to the ready queue with its process's nice value
+    pub fn enqueue_nice(&mut self, tid: u64, nice: i32) {
+        self.policy.enqueue(tid, nice);
+    }
+
+    /// Add a thread to the ready queue of its process's class
+    ///
+    /// A real-time thread that outranks the current one asks for a switch
+    /// on the next tick.
+    pub fn enqueue_class(&mut self, tid: u64, nice: i32, class: SchedClass) {
+        match class.rt_priority() {
+            Some(priority) => {
+                self.rt.enqueue(tid, priority, false);
+                let outranks = self
+                    .current_class
+                    .rt_priority()
+                    .is_none_or(|current| priority > current);
+                if outranks && !self.throttle.throttled() {
+                    self.reschedule = true;
+                }
+            }
+            None => self.policy.enqueue(tid, nice),
+        }
+    }
+
+    /// Apply a new nice value to a queued thread
+    pub fn renice(&mut self, tid: u64, nice: i32) {
+        self.policy.renice(tid, nice);
+    }
+
+    /// Move a thread whose process changed class to the right queue
+    ///
+    /// If it is running, it is rescheduled on the next tick.
+    pub fn reclass(&mut self, tid: u64, nice: i32, class: SchedClass) {
+        if self.current_process == Some(tid) {
+            self.current_class = class;
+            self.reschedule = true;
+        }
+        let queued = self.rt.dequeue(tid) || self.policy.dequeue(tid);
+        if queued {
+            self.enqueue_class(tid, nice, class);
+        }
+    }
+
+    /// Remove the next thread from the ready queue
+    pub fn dequeue(&mut self) -> Option<u64> {
+        self.rt.pick().or_else(|| self.policy.pick_next())
+    }
+
+    /// Number of threads waiting in the ready queue
+    pub fn queued(&self) -> usize {
+        self.rt.len() + self.policy.queued()
+    }
+
+    /// Threads queued or running here, to compare CPUs by
+    fn load(&self) -> usize {
+        self.queued() + usize::from(self.current_process.is_some())
+    }
+
+    /// Make room for `threads` more queued threads, so the timer interrupt
+    /// can queue them without allocating
+    fn reserve(&mut self, threads: usize) {
+        self.policy.reserve(threads);
+        self.rt.reserve(threads);
+    }
+
+    /// Get the current running process
+    pub fn current(&self) -> Option<u64> {
+        self.current_process
+    }
+
+    /// Set the current running process
+    pub fn set_current(&mut self, pid: Option<u64>) {
+        self.current_process = pid;
+        self.current_class = SchedClass::Normal;
+    }
+
+    /// Charge a tick to the running thread and check if it should be
+    /// switched out: its time slice expired, the real-time throttle
+    /// started or ended, or a switch was requested
+    pub fn tick(&mut self) -> bool {
+        let requested = core::mem::take(&mut self.reschedule);
+        let rt_running = self.current_class.rt_priority().is_some();
+        let unthrottled = self.throttle.tick(rt_running);
+
+        let expired = match self.current_class {
+            SchedClass::Normal => self.policy.tick(),
+            SchedClass::Fifo(_) => false,
+            SchedClass::RoundRobin(_) => {
+                self.rt_ticks += 1;
+                self.rt_ticks >= RR_QUANTUM
+            }
+        };
+        if expired {
+            stats::count_expiration();
+        }
+        let throttled = rt_running && self.throttle.throttled();
+        let rt_waiting = unthrottled && self.rt.highest().is_some();
+        // An idle CPU looks for work on every tick
+        let idle = self.current_process.is_none() && !self.shares_with_kernel;
+
+        expired || throttled || rt_waiting || requested || idle
+    }
+
+    /// Increment global elapsed time (called on each timer tick)
+    fn increment_elapsed_time() {
+        ELAPSED_TICKS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Select next process to run
+    /// Returns (previous_pid, next_pid); `None` is the kernel's own context
+    pub fn schedule(&mut self) -> (Option<u64>, Option<u64>) {
+        self.schedule_with(&crate::process::get_sched_info)
+    }
+
+    /// `schedule`, looking threads up with `info`
+    ///
+    /// Queue entries for threads that are no longer Ready (exited, or
+    /// started directly by `process::execute_all_ready`) or that another
+    /// CPU is running are dropped. The thread switched out may be picked
+    /// again.
+    pub fn schedule_with(
+        &mut self,
+        info: &dyn Fn(u64) -> Option<SchedInfo>,
+    ) -> (Option<u64>, Option<u64>) {
+        let prev = self.current_process;
+
+        // Put current thread back in queue if not blocked/exited (Ready:
+        // woken from a sleep before it was switched out); the kernel is
+        // always runnable. A preempted real-time thread keeps its place
+        // unless its round-robin slice is used up.
+        match prev {
+            Some(tid) => {
+                let runnable = |t: &SchedInfo| {
+                    matches!(t.status, ProcessStatus::Running | ProcessStatus::Ready)
+                };
+                if let Some(thread) = info(tid).filter(runnable) {
+                    match thread.class {
+                        SchedClass::Normal => self.policy.enqueue(tid, thread.nice),
+                        SchedClass::Fifo(priority) => self.rt.enqueue(tid, priority, true),
+                        SchedClass::RoundRobin(priority) => {
+                            let at_head = self.rt_ticks < RR_QUANTUM;
+                            self.rt.enqueue(tid, priority, at_head);
+                        }
+                    }
+                }
+            }
+            None if self.shares_with_kernel => self.policy.enqueue(KERNEL_TID, 0),
+            None => {}
+        }
+        self.rt_ticks = 0;
+        self.reschedule = false;
+
+        let runnable = |tid: u64| {
+            info(tid).filter(|t| {
+                if Some(tid) == prev {
+                    matches!(t.status, ProcessStatus::Running | ProcessStatus::Ready)
+                } else {
+                    t.status == ProcessStatus::Ready && t.running_on.is_none()
+                }
+            })
+        };
+
+        // Real-time threads first, unless throttled
+        if !self.throttle.throttled() {
+            while let Some(tid) = self.rt.pick() {
+                let thread = match runnable(tid) {
+                    Some(thread) => thread,
+                    None => continue,
+                };
+                if thread.class.rt_priority().is_some() {
+                    self.current_process = Some(tid);
+                    self.current_class = thread.class;
+                    return (prev, Some(tid));
+                }
+                self.policy.enqueue(tid, thread.nice);
+            }
+        }
+
+        // Get next thread from ready queue, falling back to the kernel
+        let mut next = None;
+        while let Some(tid) = self.policy.pick_next() {
+            if tid == KERNEL_TID {
+                break;
+            }
+            if runnable(tid).is_some() {
+                next = Some(tid);
+                break;
+            }
+        }
+        self.current_process = next;
+        self.current_class = SchedClass::Normal;
+
+        (prev, next)
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build every CPU's scheduler, running the policy `kind`
+///
+/// Called once the heap is up and before preemption starts: a policy
+/// allocates its queues, which the timer interrupt must never do. Until
+/// then the timer finds no scheduler and leaves the CPU alone.
+pub fn init(kind: PolicyKind) {
+    SCHEDULERS.init_once(|| core::array::from_fn(|cpu| Mutex::new(Scheduler::for_cpu(kind, cpu))));
+}
+
+/// The schedulers, once `init` has built them
+fn schedulers() -> &'static [Mutex<Scheduler>; MAX_CPUS] {
+    SCHEDULERS.get().expect("scheduler::init has not run")
+}
+
+/// The schedulers, from an interrupt handler
+///
+/// Never blocks: `None` until `init` has finished.
+fn try_schedulers() -> Option<&'static [Mutex<Scheduler>; MAX_CPUS]> {
+    SCHEDULERS.try_get().ok()
+}
+
+/// CPU `cpu`'s scheduler, or `None` before `init`
+///
+/// Code running on a CPU finds its own through `percpu::scheduler`.
+pub fn cpu_scheduler(cpu: usize) -> Option<&'static Mutex<Scheduler>> {
+    try_schedulers().map(|scheds| &scheds[cpu])
+}
+
+/// Schedulers of the CPUs that are up (none before `init`)
+fn online() -> &'static [Mutex<Scheduler>] {
+    try_schedulers().map_or(&[], |scheds| &scheds[..crate::smp::cpu_count()])
+}
+
+/// Run `f` on CPU `cpu`'s scheduler
+///
+/// Interrupts are off meanwhile: a thread holding a scheduler must not be
+/// switched out, or that CPU's timer could not reach its scheduler until
+/// the thread ran again.
+fn with_cpu<R>(cpu: usize, f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    without_interrupts(|| f(&mut schedulers()[cpu].lock()))
+}
+
+/// Run `f` on the running CPU's scheduler
+///
+/// With interrupts off, the caller cannot move to another CPU between
+/// finding its scheduler and using it.
+fn with_this_cpu<R>(f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    without_interrupts(|| {
+        let sched = percpu::scheduler().expect("scheduler::init has not run");
+        f(&mut sched.lock())
+    })
+}
+
+/// The CPU with the fewest threads queued or running
+///
+/// Never spins: a CPU whose scheduler is busy is passed over.
+fn idlest_cpu() -> usize {
+    online()
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, sched)| sched.try_lock().map_or(usize::MAX, |sched| sched.load()))
+        .map_or(BOOT_CPU, |(cpu, _)| cpu)
+}
+
+/// Make room in every CPU's queue for every thread there is, so the timer
+/// interrupt can requeue, wake and move threads without allocating
+fn reserve_all() {
+    let queued: usize = online()
+        .iter()
+        .map(|sched| without_interrupts(|| sched.lock().queued()))
+        .sum();
+    // The running threads and the kernel may join the queues too
+    let threads = queued + crate::smp::cpu_count() + 1;
+    for sched in online() {
+        without_interrupts(|| sched.lock().reserve(threads));
+    }
+}
+
+/// Switch to the policy `kind`
+///
+/// Only while no thread is scheduled: the old policy's queue is not
+/// carried over. Applies to every CPU.
+pub fn set_policy(kind: PolicyKind) -> Result<(), &'static str> {
+    for cpu in 0..MAX_CPUS {
+        if with_cpu(cpu, |sched| sched.queued() > 0 || sched.current().is_some()) {
+            return Err("threads are already scheduled");
+        }
+    }
+    for cpu in 0..MAX_CPUS {
+        with_cpu(cpu, |sched| sched.policy = kind.build());
+    }
+    Ok(())
+}
+
+/// Name of the active scheduling policy
+pub fn policy_name() -> &'static str {
+    with_cpu(BOOT_CPU, |sched| sched.policy_name())
+}
+
+/// Add a process (or thread) to the ready queue of the least busy CPU,
+/// with the nice value and class of its process
+pub fn enqueue_process(pid: u64) {
+    let (nice, class) = crate::process::get_sched_info(pid)
+        .map_or((0, SchedClass::Normal), |info| (info.nice, info.class));
+
+    with_cpu(idlest_cpu(), |sched| sched.enqueue_class(pid, nice, class));
+    reserve_all();
+}
+
+/// Tell the policy about threads whose process changed nice value
+pub fn reprioritize(tids: &[u64], nice: i32) {
+    for cpu in 0..crate::smp::cpu_count() {
+        with_cpu(cpu, |sched| {
+            for &tid in tids {
+                sched.renice(tid, nice);
+            }
+        });
+    }
+}
+
+/// Move threads whose process changed scheduling class
+pub fn reclassify(tids: &[u64], nice: i32, class: SchedClass) {
+    for cpu in 0..crate::smp::cpu_count() {
+        with_cpu(cpu, |sched| {
+            for &tid in tids {
+                sched.reclass(tid, nice, class);
+            }
+        });
+    }
+    reserve_all();
+}
+
+/// Make room in the ready queues for a thread that is about to block, so
+/// the timer interrupt can requeue it without allocating
+pub fn prepare_wakeup() {
+    reserve_all();
+}
+
+/// Requeue a thread woken by a timer, if it is still Blocked
+///
+/// Called from the timer interrupt. The thread goes back to the CPU that
+/// is still running it, if any, and otherwise to the least busy one.
+/// Returns false, leaving the thread alone, if the interrupted code holds
+/// the process table or that CPU's scheduler.
+pub fn try_wake(tid: u64) -> bool {
+    crate::process::try_wake_thread(tid, |info| {
+        let cpu = info.running_on.unwrap_or_else(idlest_cpu);
+        match cpu_scheduler(cpu).and_then(|sched| sched.try_lock()) {
+            Some(mut sched) => {
+                sched.enqueue_class(tid, info.nice, info.class);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Requeue a thread blocked on an event, if it is still Blocked
+///
+/// For wakeups from outside interrupt handlers (stdin input, say), which
+/// can wait out the locks `try_wake` gives up on.
+pub fn wake(tid: u64) {
+    while !without_interrupts(|| try_wake(tid)) {
+        core::hint::spin_loop();
+    }
+}
+
+/// Get the thread running on this CPU
+///
+/// Reads the per-CPU block: takes no lock, so it is safe from interrupt
+/// handlers, whatever the interrupted code holds.
+pub fn current_thread() -> Option<u64> {
+    percpu::current_thread()
+}
+
+/// Record the thread this CPU is now running
+///
+/// Used when the kernel starts a task directly rather than through
+/// `schedule`, so syscalls made by the task know who is calling.
+pub fn set_current_thread(tid: Option<u64>) {
+    let owner = tid.map(|tid| (tid, crate::process::thread_owner(tid).unwrap_or(tid)));
+    with_this_cpu(|sched| {
+        sched.set_current(tid);
+        percpu::this_cpu().set_current(owner);
+    });
+}
+
+/// Publish the thread `schedule` picked in the per-CPU block, for
+/// `current_thread` and `current_process`
+fn publish_current(next: Option<u64>, info: &dyn Fn(u64) -> Option<SchedInfo>) {
+    let owner = next.map(|tid| (tid, info(tid).map_or(tid, |t| t.process)));
+    percpu::this_cpu().set_current(owner);
+}
+
+/// Switch away from this CPU's current thread on the next timer tick
+///
+/// Used after the current thread has been killed so the CPU does not idle
+/// for the rest of its time slice.
+pub fn request_reschedule() {
+    without_interrupts(|| {
+        if let Some(mut sched) = percpu::scheduler().and_then(|sched| sched.try_lock()) {
+            sched.reschedule = true;
+        }
+    });
+}
+
+/// Switch away from CPU `cpu`'s current thread on its next timer tick
+///
+/// Used when a thread running on another CPU has been killed; a
+/// `SCHED_FIFO` thread would otherwise keep that CPU.
+pub fn request_reschedule_on(cpu: usize) {
+    with_cpu(cpu, |sched| sched.reschedule = true);
+}
+
+/// Get the process owning the thread running on this CPU
+///
+/// Lock-free, like `current_thread`.
+pub fn current_process() -> Option<u64> {
+    percpu::current_process()
+}
+
+/// Timer interrupt handler - call on each timer tick
+/// Returns true if context switch is needed
+///
+/// If the interrupted code holds this CPU's scheduler, or `init` has not
+/// built it yet, only the uptime advances.
+pub fn timer_tick() -> bool {
+    let cpu = crate::smp::cpu_id();
+    if cpu == BOOT_CPU {
+        Scheduler::increment_elapsed_time();
+    }
+
+    let mut sched = match percpu::scheduler().and_then(|sched| sched.try_lock()) {
+        Some(sched) => sched,
+        None => return false,
+    };
+    if let Some(pid) = sched.current() {
+        crate::process::account_tick(pid);
+    }
+    sched.tick()
+}
+
+/// Pick the next thread for this CPU with the active policy
+/// Returns (current_pid_to_save, next_pid_to_load)
+pub fn schedule() -> (Option<u64>, Option<u64>) {
+    with_this_cpu(|sched| {
+        let switch = sched.schedule();
+        publish_current(switch.1, &crate::process::get_sched_info);
+        switch
+    })
+}
+
+/// Pick the next thread for this CPU from the timer interrupt, looking
+/// threads up with `info`
+///
+/// A CPU with nothing queued first steals a thread from the busiest other
+/// CPU. Never spins: returns `None`, leaving the queues untouched, if the
+/// interrupted code holds this CPU's scheduler or there is none yet.
+pub fn try_schedule(info: &dyn Fn(u64) -> Option<SchedInfo>) -> Option<(Option<u64>, Option<u64>)> {
+    let cpu = crate::smp::cpu_id();
+    let mut sched = percpu::scheduler()?.try_lock()?;
+    if sched.queued() == 0 {
+        steal(&mut sched, cpu, info);
+    }
+    let switch = sched.schedule_with(info);
+    publish_current(switch.1, info);
+    Some(switch)
+}
+
+/// Move a thread from the busiest other CPU's queue to `thief`, the
+/// scheduler of CPU `cpu`
+///
+/// Only normal threads that no CPU is running can move. Never spins: busy
+/// schedulers are passed over.
+fn steal(
+    thief: &mut Scheduler,
+    cpu: usize,
+    info: &dyn Fn(u64) -> Option<SchedInfo>,
+) -> Option<u64> {
+    let (victim, _) = online()
+        .iter()
+        .enumerate()
+        .filter(|&(other, _)| other != cpu)
+        .filter_map(|(other, sched)| Some((other, sched.try_lock()?.policy.queued())))
+        .filter(|&(_, queued)| queued > 0)
+        .max_by_key(|&(_, queued)| queued)?;
+
+    let eligible = |tid: u64| {
+        tid != KERNEL_TID
+            && info(tid).is_some_and(|t| {
+                t.status == ProcessStatus::Ready
+                    && t.running_on.is_none()
+                    && t.class.rt_priority().is_none()
+            })
+    };
+    let tid = online()[victim].try_lock()?.policy.steal(&eligible)?;
+    let nice = info(tid).map_or(0, |t| t.nice);
+    thief.policy.enqueue(tid, nice);
+    Some(tid)
+}
+
+/// Check if a switch has been requested for this CPU's next tick
+/// Used by syscalls to determine if preemption is needed
+/// Does NOT clear the request - that's done on actual switch
+pub fn check_quantum_expired() -> bool {
+    with_this_cpu(|sched| sched.reschedule)
+}
+
+/// Get elapsed time in timer ticks since kernel boot
+pub fn get_elapsed_ticks() -> u64 {
+    ELAPSED_TICKS.load(Ordering::Relaxed)
+}
+
+/// Get elapsed time in seconds since kernel boot
+pub fn get_elapsed_seconds() -> u64 {
+    crate::clock::monotonic_ns() / orbital_common::time::NANOS_PER_SEC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::priority::{AGING_PICKS, DEFAULT_LEVEL, LEVEL_QUANTUM};
+    use super::*;
+    use alloc::vec::Vec;
+    use orbital_common::process::NICE_BATCH;
+
+    #[test_case]
+    fn test_scheduler_enqueue() {
+        let mut sched = Scheduler::new();
+        sched.enqueue(1);
+        sched.enqueue(2);
+        assert_eq!(sched.dequeue(), Some(1));
+        assert_eq!(sched.dequeue(), Some(2));
+    }
+
+    #[test_case]
+    fn test_scheduler_round_robin() {
+        for kind in PolicyKind::ALL {
+            let mut sched = Scheduler::with_policy(kind);
+            sched.enqueue(1);
+            sched.enqueue(2);
+            sched.enqueue(3);
+
+            // After 3 dequeues, should be empty
+            sched.dequeue();
+            sched.dequeue();
+            sched.dequeue();
+            assert_eq!(sched.dequeue(), None, "{}", sched.policy_name());
+        }
+    }
+
+    #[test_case]
+    fn test_policy_names() {
+        for kind in PolicyKind::ALL {
+            assert_eq!(PolicyKind::from_name(kind.build().name()), Some(kind));
+        }
+        assert_eq!(PolicyKind::from_name("rr"), Some(PolicyKind::RoundRobin));
+        assert_eq!(PolicyKind::from_name("fifo"), None);
+    }
+
+    /// Lookup for tests: threads 1-9 at nice 0, 10-19 at `NICE_BATCH`,
+    /// 20-29 `SCHED_FIFO` at 50, 30+ `SCHED_RR` at 10
+    fn lookup(status: ProcessStatus) -> impl Fn(u64) -> Option<SchedInfo> {
+        move |tid| {
+            let (nice, class) = match tid {
+                0 => return None,
+                1..=9 => (0, SchedClass::Normal),
+                10..=19 => (NICE_BATCH, SchedClass::Normal),
+                20..=29 => (0, SchedClass::Fifo(50)),
+                _ => (0, SchedClass::RoundRobin(10)),
+            };
+            Some(SchedInfo {
+                status,
+                process: tid,
+                nice,
+                class,
+                running_on: None,
+            })
+        }
+    }
+
+    /// Run `sched` for `ticks` ticks with every thread always runnable,
+    /// returning the thread that was current before each tick
+    fn run(sched: &mut Scheduler, ticks: usize) -> Vec<Option<u64>> {
+        let ready = lookup(ProcessStatus::Ready);
+        let running = lookup(ProcessStatus::Running);
+        (0..ticks)
+            .map(|_| {
+                let current = sched.current();
+                if sched.tick() {
+                    sched.schedule_with(&|tid| {
+                        if Some(tid) == current {
+                            running(tid)
+                        } else {
+                            ready(tid)
+                        }
+                    });
+                }
+                current
+            })
+            .collect()
+    }
+
+    /// Ticks until the running thread's time slice expires
+    fn slice(sched: &mut Scheduler) -> usize {
+        (1..).find(|_| sched.tick()).unwrap()
+    }
+
+    #[test_case]
+    fn test_kernel_takes_turns_with_threads() {
+        for kind in PolicyKind::ALL {
+            let mut sched = Scheduler::with_policy(kind);
+            sched.enqueue(5);
+            sched.enqueue(6);
+            let status = |tid| match tid {
+                5 | 6 => Some(SchedInfo {
+                    status: ProcessStatus::Ready,
+                    process: tid,
+                    nice: 0,
+                    class: SchedClass::Normal,
+                    running_on: None,
+                }),
+                _ => None,
+            };
+
+            // The kernel is preempted and queued behind the threads
+            assert_eq!(sched.schedule_with(&status), (None, Some(5)));
+            let running = |tid| match tid {
+                5 => Some(SchedInfo {
+                    status: ProcessStatus::Running,
+                    process: tid,
+                    nice: 0,
+                    class: SchedClass::Normal,
+                    running_on: None,
+                }),
+                _ => status(tid),
+            };
+            assert_eq!(sched.schedule_with(&running), (Some(5), Some(6)));
+
+            // Thread 6 exited: it is not requeued and the kernel runs next
+            let exited = |tid| match tid {
+                5 => status(5),
+                6 => Some(SchedInfo {
+                    status: ProcessStatus::Exited(crate::process::ExitReason::Code(0)),
+                    process: tid,
+                    nice: 0,
+                    class: SchedClass::Normal,
+                    running_on: None,
+                }),
+                _ => None,
+            };
+            assert_eq!(sched.schedule_with(&exited), (Some(6), None));
+            assert_eq!(sched.schedule_with(&exited), (None, Some(5)));
+        }
+    }
+
+    #[test_case]
+    fn test_other_cpus_idle() {
+        let mut sched = Scheduler::for_cpu(DEFAULT_POLICY, 1);
+        let ready = lookup(ProcessStatus::Ready);
+
+        // No kernel context to fall back on: an idle CPU asks for work on
+        // every tick, and a thread alone on it carries on
+        assert!(sched.tick());
+        assert_eq!(sched.schedule_with(&ready), (None, None));
+        sched.enqueue(1);
+        assert_eq!(sched.schedule_with(&ready), (None, Some(1)));
+        let running = lookup(ProcessStatus::Running);
+        assert_eq!(sched.schedule_with(&running), (Some(1), Some(1)));
+
+        // A thread another CPU is running is skipped
+        sched.enqueue(2);
+        let elsewhere = |tid| {
+            let info = if tid == 1 { running(tid) } else { ready(tid) };
+            info.map(|t| SchedInfo {
+                running_on: (tid == 2).then_some(0),
+                ..t
+            })
+        };
+        assert_eq!(sched.schedule_with(&elsewhere), (Some(1), Some(1)));
+        assert_eq!(sched.queued(), 0);
+    }
+
+    #[test_case]
+    fn test_steal_takes_last_in_line() {
+        for kind in PolicyKind::ALL {
+            let mut policy = kind.build();
+            policy.enqueue(1, 0);
+            policy.enqueue(2, 0);
+            policy.enqueue(3, 0);
+
+            // Thread 3 cannot move, so thread 2 goes
+            assert_eq!(policy.steal(&|tid| tid != 3), Some(2), "{}", policy.name());
+            assert_eq!(policy.queued(), 2);
+            assert_eq!(policy.pick_next(), Some(1));
+            assert_eq!(policy.steal(&|_| false), None);
+        }
+    }
+
+    #[test_case]
+    fn test_higher_priority_runs_first() {
+        let mut sched = Scheduler::with_policy(PolicyKind::Priority);
+        sched.enqueue_nice(10, NICE_BATCH);
+        sched.enqueue(1);
+
+        let ready = lookup(ProcessStatus::Ready);
+        assert_eq!(sched.schedule_with(&ready), (None, Some(1)));
+        assert_eq!(slice(&mut sched), LEVEL_QUANTUM[DEFAULT_LEVEL]);
+    }
+
+    #[test_case]
+    fn test_aging_prevents_starvation() {
+        let mut sched = Scheduler::with_policy(PolicyKind::Priority);
+        sched.enqueue(1);
+        sched.enqueue(2);
+        sched.enqueue_nice(10, NICE_BATCH);
+
+        // Threads 1 and 2 never block, but the batch thread still gets a
+        // turn once it has moved up to their level
+        let ready = lookup(ProcessStatus::Ready);
+        let running = lookup(ProcessStatus::Running);
+        let mut ran_batch = false;
+        sched.schedule_with(&ready);
+        for _ in 0..4 * AGING_PICKS {
+            let current = sched.current();
+            let (_, next) = sched.schedule_with(&|tid| {
+                if Some(tid) == current {
+                    running(tid)
+                } else {
+                    ready(tid)
+                }
+            });
+            if next == Some(10) {
+                ran_batch = true;
+                assert_eq!(slice(&mut sched), LEVEL_QUANTUM[3]);
+                break;
+            }
+        }
+        assert!(ran_batch);
+    }
+
+    #[test_case]
+    fn test_cfs_shares_by_nice() {
+        let mut sched = Scheduler::with_policy(PolicyKind::Cfs);
+        sched.enqueue(1);
+        sched.enqueue_nice(10, NICE_BATCH);
+
+        // Both threads stay runnable; count the ticks each one gets
+        sched.schedule_with(&lookup(ProcessStatus::Ready));
+        let ran = run(&mut sched, 2000);
+        let ticks = [1, 10].map(|tid| ran.iter().filter(|&&t| t == Some(tid)).count());
+        assert!(ticks[1] > 0);
+        assert!(ticks[0] > 4 * ticks[1]);
+    }
+
+    #[test_case]
+    fn test_rt_preempts_normal() {
+        let mut sched = Scheduler::new();
+        sched.enqueue(1);
+        let ready = lookup(ProcessStatus::Ready);
+        assert_eq!(sched.schedule_with(&ready), (None, Some(1)));
+
+        // A real-time thread waking up takes over on the next tick, and
+        // keeps the CPU: FIFO threads have no time slice
+        sched.enqueue_class(20, 0, SchedClass::Fifo(50));
+        assert!(run(&mut sched, 1) == [Some(1)]);
+        assert_eq!(sched.current(), Some(20));
+        let ran = run(&mut sched, RT_RUNTIME - 1);
+        assert!(ran.iter().all(|&tid| tid == Some(20)));
+    }
+
+    #[test_case]
+    fn test_rt_round_robin() {
+        let mut sched = Scheduler::new();
+        sched.enqueue_class(30, 0, SchedClass::RoundRobin(10));
+        sched.enqueue_class(31, 0, SchedClass::RoundRobin(10));
+        sched.schedule_with(&lookup(ProcessStatus::Ready));
+
+        let ran = run(&mut sched, 2 * RR_QUANTUM);
+        assert!(ran[..RR_QUANTUM].iter().all(|&tid| tid == Some(30)));
+        assert!(ran[RR_QUANTUM..].iter().all(|&tid| tid == Some(31)));
+    }
+
+    #[test_case]
+    fn test_rt_throttle() {
+        for kind in PolicyKind::ALL {
+            let mut sched = Scheduler::with_policy(kind);
+            sched.enqueue(1);
+            sched.enqueue_class(20, 0, SchedClass::Fifo(50));
+            sched.schedule_with(&lookup(ProcessStatus::Ready));
+
+            // A runaway FIFO thread leaves the rest of each period to the
+            // normal threads
+            let ran = run(&mut sched, 2 * RT_PERIOD);
+            let normal = ran.iter().filter(|&&tid| tid != Some(20)).count();
+            assert_eq!(normal, 2 * (RT_PERIOD - RT_RUNTIME));
+        }
+    }
+
+    #[test_case]
+    fn test_reschedule_request() {
+        let mut sched = Scheduler::new();
+        sched.reschedule = true;
+        assert_eq!(sched.tick(), true);
+        assert_eq!(sched.tick(), false);
+    }
+
+    #[test_case]
+    fn test_time_quantum() {
+        for kind in PolicyKind::ALL {
+            let mut sched = Scheduler::with_policy(kind);
+            for _ in 0..99 {
+                assert_eq!(sched.tick(), false);
+            }
+            assert_eq!(sched.tick(), true); // Should expire after 100 ticks
+        }
+    }
+}
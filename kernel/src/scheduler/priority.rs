@@ -0,0 +1,179 @@
+//! Priority policy: one round-robin queue per priority level
+//!
+//! A process's nice value picks the level its threads queue at. The
+//! highest non-empty level always runs first, and lower levels get shorter
+//! quanta, so a shell at the default nice gets the CPU back quickly from
+//! background tasks at `NICE_BATCH`. To keep busy high-priority threads
+//! from starving the rest, a level passed over `AGING_PICKS` times in a row
+//! moves its oldest thread up one level; the thread drops back to its own
+//! level when it is next requeued.
+
+use super::SchedPolicy;
+use alloc::collections::VecDeque;
+use orbital_common::process::{NICE_MAX, NICE_MIN};
+
+/// Number of priority levels; level 0 runs first
+pub const PRIORITY_LEVELS: usize = 4;
+
+/// Level of the default nice value (0), which the kernel context also uses
+pub const DEFAULT_LEVEL: usize = 2;
+
+/// Time quantum of each priority level, in timer ticks
+pub(super) const LEVEL_QUANTUM: [usize; PRIORITY_LEVELS] = [150, 125, 100, 50];
+
+/// How many picks a non-empty level can be passed over before its oldest
+/// thread moves up a level
+pub(super) const AGING_PICKS: u32 = 8;
+
+/// Priority level for a nice value: ten nice values per level
+pub fn priority_level(nice: i32) -> usize {
+    let span = (NICE_MAX - NICE_MIN + 1) as usize;
+    (nice.clamp(NICE_MIN, NICE_MAX) - NICE_MIN) as usize * PRIORITY_LEVELS / span
+}
+
+/// Queued thread and the level of its nice value (which differs from the
+/// queue it sits in once it has been aged)
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    tid: u64,
+    level: usize,
+}
+
+/// Priority policy state
+pub struct Priority {
+    /// Threads waiting to run, one queue per priority level
+    ready_queues: [VecDeque<Entry>; PRIORITY_LEVELS],
+    /// Picks each level has been passed over while it had threads waiting
+    passed_over: [u32; PRIORITY_LEVELS],
+    /// Quantum of the running thread's level
+    quantum: usize,
+    /// Ticks the running thread has used of its quantum
+    ticks: usize,
+}
+
+impl Priority {
+    pub fn new() -> Self {
+        Priority {
+            ready_queues: Default::default(),
+            passed_over: [0; PRIORITY_LEVELS],
+            quantum: LEVEL_QUANTUM[DEFAULT_LEVEL],
+            ticks: 0,
+        }
+    }
+
+    fn contains(&self, tid: u64) -> bool {
+        self.ready_queues
+            .iter()
+            .any(|queue| queue.iter().any(|entry| entry.tid == tid))
+    }
+}
+
+impl SchedPolicy for Priority {
+    fn name(&self) -> &'static str {
+        "priority"
+    }
+
+    fn enqueue(&mut self, tid: u64, nice: i32) {
+        if !self.contains(tid) {
+            let level = priority_level(nice);
+            self.ready_queues[level].push_back(Entry { tid, level });
+        }
+    }
+
+    fn dequeue(&mut self, tid: u64) -> bool {
+        let queued = self.contains(tid);
+        if queued {
+            for queue in self.ready_queues.iter_mut() {
+                queue.retain(|entry| entry.tid != tid);
+            }
+        }
+        queued
+    }
+
+    /// Takes the front of the highest non-empty level, then ages the
+    /// levels below it. The thread runs for the quantum of its own level.
+    fn pick_next(&mut self) -> Option<u64> {
+        let level = self
+            .ready_queues
+            .iter()
+            .position(|queue| !queue.is_empty())?;
+        let entry = self.ready_queues[level].pop_front()?;
+        self.passed_over[level] = 0;
+
+        for lower in level + 1..PRIORITY_LEVELS {
+            if self.ready_queues[lower].is_empty() {
+                self.passed_over[lower] = 0;
+                continue;
+            }
+            self.passed_over[lower] += 1;
+            if self.passed_over[lower] >= AGING_PICKS {
+                self.passed_over[lower] = 0;
+                if let Some(aged) = self.ready_queues[lower].pop_front() {
+                    self.ready_queues[lower - 1].push_back(aged);
+                }
+            }
+        }
+
+        self.quantum = LEVEL_QUANTUM[entry.level];
+        self.ticks = 0;
+        Some(entry.tid)
+    }
+
+    fn tick(&mut self) -> bool {
+        self.ticks += 1;
+        if self.ticks >= self.quantum {
+            self.ticks = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.ready_queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Every level gets the room: aging moves threads between levels
+    fn reserve(&mut self, additional: usize) {
+        let needed = self.queued() + additional;
+        for queue in self.ready_queues.iter_mut() {
+            queue.reserve(needed - queue.len());
+        }
+    }
+
+    /// Takes the eligible thread nearest the back of the lowest level
+    fn steal(&mut self, eligible: &dyn Fn(u64) -> bool) -> Option<u64> {
+        self.ready_queues.iter_mut().rev().find_map(|queue| {
+            let index = queue.iter().rposition(|entry| eligible(entry.tid))?;
+            queue.remove(index).map(|entry| entry.tid)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_priority_levels() {
+        assert_eq!(priority_level(NICE_MIN), 0);
+        assert_eq!(priority_level(-1), 1);
+        assert_eq!(priority_level(0), DEFAULT_LEVEL);
+        assert_eq!(priority_level(orbital_common::process::NICE_BATCH), 3);
+        assert_eq!(priority_level(NICE_MAX), PRIORITY_LEVELS - 1);
+        assert_eq!(priority_level(100), PRIORITY_LEVELS - 1);
+    }
+
+    #[test_case]
+    fn test_requeue_at_new_priority() {
+        let mut policy = Priority::new();
+        policy.enqueue(1, 0);
+        policy.enqueue(2, 0);
+        policy.renice(2, NICE_MIN);
+        policy.renice(3, NICE_MIN); // Not queued: ignored
+
+        assert_eq!(policy.pick_next(), Some(2));
+        assert_eq!(policy.pick_next(), Some(1));
+        assert_eq!(policy.pick_next(), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Round-robin policy: one FIFO queue, the same quantum for everyone
+//!
+//! Nice values are ignored. This is the scheduler the kernel started out
+//! with and the baseline the other policies are compared against.
+
+use super::SchedPolicy;
+use alloc::collections::VecDeque;
+
+/// Time quantum of every thread, in timer ticks
+pub(super) const QUANTUM: usize = 100;
+
+/// Round-robin policy state
+pub struct RoundRobin {
+    /// Threads waiting to run, oldest first
+    queue: VecDeque<u64>,
+    /// Ticks the running thread has used of its quantum
+    ticks: usize,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        RoundRobin {
+            queue: VecDeque::new(),
+            ticks: 0,
+        }
+    }
+}
+
+impl SchedPolicy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round-robin"
+    }
+
+    fn enqueue(&mut self, tid: u64, _nice: i32) {
+        if !self.queue.contains(&tid) {
+            self.queue.push_back(tid);
+        }
+    }
+
+    fn dequeue(&mut self, tid: u64) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|&queued| queued != tid);
+        self.queue.len() != len
+    }
+
+    fn pick_next(&mut self) -> Option<u64> {
+        let tid = self.queue.pop_front()?;
+        self.ticks = 0;
+        Some(tid)
+    }
+
+    fn tick(&mut self) -> bool {
+        self.ticks += 1;
+        if self.ticks >= QUANTUM {
+            self.ticks = 0;
+            true // Time quantum expired, need to context switch
+        } else {
+            false
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.queue.reserve(additional);
+    }
+
+    /// Takes the eligible thread nearest the back
+    fn steal(&mut self, eligible: &dyn Fn(u64) -> bool) -> Option<u64> {
+        let index = self.queue.iter().rposition(|&tid| eligible(tid))?;
+        self.queue.remove(index)
+    }
+}
@@ -0,0 +1,227 @@
+//! Real-time scheduling class
+//!
+//! Threads of a process set to `SCHED_FIFO` or `SCHED_RR` sit in their own
+//! queue, ordered by real-time priority, which is always served before the
+//! `SchedPolicy` of normal threads. A FIFO thread runs until it blocks or a
+//! higher priority arrives; round-robin threads of the same priority take
+//! turns every `RR_QUANTUM` ticks.
+//!
+//! So that a runaway real-time thread cannot lock up the machine, the
+//! class may use at most `RT_RUNTIME` ticks of every `RT_PERIOD`. Once the
+//! budget is spent the class is throttled: normal threads (and the kernel)
+//! run for the rest of the period.
+
+use alloc::collections::VecDeque;
+use orbital_common::process::{
+    RT_PRIORITY_MAX, RT_PRIORITY_MIN, SCHED_FIFO, SCHED_OTHER, SCHED_RR,
+};
+
+/// Length of a throttling period, in timer ticks
+pub const RT_PERIOD: usize = 100;
+
+/// Ticks of each period real-time threads may use (95%)
+pub const RT_RUNTIME: usize = 95;
+
+/// Time slice of `SCHED_RR` threads, in timer ticks
+pub const RR_QUANTUM: usize = 10;
+
+/// Scheduling class of a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedClass {
+    /// Time-shared by the active `SchedPolicy`
+    #[default]
+    Normal,
+    /// Real-time, first in first out, at the given priority
+    Fifo(u8),
+    /// Real-time, round-robin, at the given priority
+    RoundRobin(u8),
+}
+
+impl SchedClass {
+    /// Decode a `sys_sched_setscheduler` policy and priority
+    ///
+    /// `SCHED_OTHER` takes priority 0; the real-time policies
+    /// `RT_PRIORITY_MIN`..=`RT_PRIORITY_MAX`.
+    pub fn from_raw(policy: u32, priority: u32) -> Option<Self> {
+        let rt = (RT_PRIORITY_MIN..=RT_PRIORITY_MAX).contains(&priority);
+        match policy {
+            SCHED_OTHER if priority == 0 => Some(SchedClass::Normal),
+            SCHED_FIFO if rt => Some(SchedClass::Fifo(priority as u8)),
+            SCHED_RR if rt => Some(SchedClass::RoundRobin(priority as u8)),
+            _ => None,
+        }
+    }
+
+    /// The `SCHED_*` policy value
+    pub fn policy(self) -> u32 {
+        match self {
+            SchedClass::Normal => SCHED_OTHER,
+            SchedClass::Fifo(_) => SCHED_FIFO,
+            SchedClass::RoundRobin(_) => SCHED_RR,
+        }
+    }
+
+    /// Real-time priority, or `None` for normal threads
+    pub fn rt_priority(self) -> Option<u8> {
+        match self {
+            SchedClass::Normal => None,
+            SchedClass::Fifo(priority) | SchedClass::RoundRobin(priority) => Some(priority),
+        }
+    }
+}
+
+/// Runnable real-time threads, highest priority first, FIFO within a
+/// priority
+pub(super) struct RtQueue {
+    queue: VecDeque<(u64, u8)>,
+}
+
+impl RtQueue {
+    pub fn new() -> Self {
+        RtQueue {
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Queue a thread behind (or, with `at_head`, in front of) the others
+    /// of its priority; no-op if it is already queued
+    pub fn enqueue(&mut self, tid: u64, priority: u8, at_head: bool) {
+        if self.queue.iter().any(|&(queued, _)| queued == tid) {
+            return;
+        }
+        let index = self
+            .queue
+            .iter()
+            .position(|&(_, queued)| {
+                if at_head {
+                    queued <= priority
+                } else {
+                    queued < priority
+                }
+            })
+            .unwrap_or(self.queue.len());
+        self.queue.insert(index, (tid, priority));
+    }
+
+    /// Remove a queued thread; returns whether it was queued
+    pub fn dequeue(&mut self, tid: u64) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|&(queued, _)| queued != tid);
+        self.queue.len() != len
+    }
+
+    /// Take the highest-priority thread
+    pub fn pick(&mut self) -> Option<u64> {
+        self.queue.pop_front().map(|(tid, _)| tid)
+    }
+
+    /// Priority of the thread that would be picked next
+    pub fn highest(&self) -> Option<u8> {
+        self.queue.front().map(|&(_, priority)| priority)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.queue.reserve(additional);
+    }
+}
+
+/// Real-time bandwidth limit of one CPU
+#[derive(Debug, Default)]
+pub(super) struct RtThrottle {
+    /// Ticks into the current period
+    elapsed: usize,
+    /// Ticks real-time threads have run this period
+    used: usize,
+    /// Budget spent: real-time threads wait for the next period
+    throttled: bool,
+}
+
+impl RtThrottle {
+    /// Account a timer tick
+    /// Returns true when a new period lifts the throttle
+    pub fn tick(&mut self, rt_running: bool) -> bool {
+        if rt_running {
+            self.used += 1;
+        }
+        self.elapsed += 1;
+        if self.elapsed >= RT_PERIOD {
+            self.elapsed = 0;
+            self.used = 0;
+            return core::mem::take(&mut self.throttled);
+        }
+        if self.used >= RT_RUNTIME {
+            self.throttled = true;
+        }
+        false
+    }
+
+    /// Whether real-time threads are held back for the rest of the period
+    pub fn throttled(&self) -> bool {
+        self.throttled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_sched_class_from_raw() {
+        assert_eq!(
+            SchedClass::from_raw(SCHED_OTHER, 0),
+            Some(SchedClass::Normal)
+        );
+        assert_eq!(
+            SchedClass::from_raw(SCHED_FIFO, 50),
+            Some(SchedClass::Fifo(50))
+        );
+        assert_eq!(
+            SchedClass::from_raw(SCHED_RR, 99),
+            Some(SchedClass::RoundRobin(99))
+        );
+        assert_eq!(SchedClass::from_raw(SCHED_OTHER, 1), None);
+        assert_eq!(SchedClass::from_raw(SCHED_FIFO, 0), None);
+        assert_eq!(SchedClass::from_raw(SCHED_RR, 100), None);
+        assert_eq!(SchedClass::from_raw(7, 1), None);
+        assert_eq!(SchedClass::RoundRobin(3).policy(), SCHED_RR);
+    }
+
+    #[test_case]
+    fn test_rt_queue_order() {
+        let mut queue = RtQueue::new();
+        queue.enqueue(1, 10, false);
+        queue.enqueue(2, 50, false);
+        queue.enqueue(3, 10, false);
+        queue.enqueue(4, 10, true);
+        queue.enqueue(2, 99, false); // Already queued: ignored
+
+        assert_eq!(queue.highest(), Some(50));
+        assert_eq!(queue.pick(), Some(2));
+        assert_eq!(queue.pick(), Some(4));
+        assert_eq!(queue.pick(), Some(1));
+        assert_eq!(queue.pick(), Some(3));
+        assert_eq!(queue.pick(), None);
+    }
+
+    #[test_case]
+    fn test_throttle() {
+        let mut throttle = RtThrottle::default();
+        for _ in 0..RT_RUNTIME - 1 {
+            assert!(!throttle.tick(true));
+        }
+        assert!(!throttle.throttled());
+        throttle.tick(true);
+        assert!(throttle.throttled());
+
+        // The rest of the period goes to normal threads
+        for _ in RT_RUNTIME..RT_PERIOD - 1 {
+            assert!(!throttle.tick(false));
+        }
+        assert!(throttle.tick(false));
+        assert!(!throttle.throttled());
+    }
+}
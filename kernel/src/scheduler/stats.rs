@@ -0,0 +1,100 @@
+//! Scheduler statistics
+//!
+//! Counted as threads are switched: how often, why they gave up the CPU,
+//! and how long they waited Ready before getting it (a histogram, see
+//! `orbital_common::sched::latency_bucket`). Together with each CPU's
+//! preemption and idle counters (`percpu`) and each process's own counts
+//! (`ProcessInfo`), they are what `sys_sched_stats` and `top` report, so
+//! time slices can be tuned against real workloads.
+//!
+//! Everything is a relaxed atomic: the counters are bumped from the timer
+//! interrupt on every CPU and only ever read as a whole by tools.
+
+use crate::smp::MAX_CPUS;
+use core::sync::atomic::{AtomicU64, Ordering};
+use orbital_common::sched::{LATENCY_BUCKETS, SchedStats, latency_bucket};
+
+/// Threads switched onto a CPU
+static SWITCHES: AtomicU64 = AtomicU64::new(0);
+
+/// Threads switched away after blocking, sleeping or exiting
+static VOLUNTARY: AtomicU64 = AtomicU64::new(0);
+
+/// Time slices that ran out
+static EXPIRATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Ready latency histogram
+static LATENCY: [AtomicU64; LATENCY_BUCKETS] = [const { AtomicU64::new(0) }; LATENCY_BUCKETS];
+
+/// Count a thread switched in after waiting `waited_ns` Ready
+pub fn count_switch_in(waited_ns: u64) {
+    SWITCHES.fetch_add(1, Ordering::Relaxed);
+    LATENCY[latency_bucket(waited_ns)].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a thread switched out that was no longer runnable
+pub fn count_voluntary() {
+    VOLUNTARY.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a time slice that ran out
+pub(super) fn count_expiration() {
+    EXPIRATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Read every counter
+///
+/// Involuntary switches and idle time are summed over the CPUs' own
+/// counters.
+pub fn snapshot() -> SchedStats {
+    let mut stats = SchedStats::empty();
+    let cpus = crate::smp::cpu_count().min(MAX_CPUS);
+    stats.cpus = cpus as u32;
+    stats.timer_hz = crate::clock::TIMER_HZ as u32;
+    stats.uptime_ns = crate::clock::monotonic_ns();
+    stats.switches = SWITCHES.load(Ordering::Relaxed);
+    stats.voluntary_switches = VOLUNTARY.load(Ordering::Relaxed);
+    stats.quantum_expirations = EXPIRATIONS.load(Ordering::Relaxed);
+    for cpu in (0..cpus).map(crate::percpu::cpu) {
+        stats.involuntary_switches += cpu.preemptions();
+        stats.idle_ns += cpu.idle_ns();
+    }
+    for (count, bucket) in stats.latency.iter_mut().zip(&LATENCY) {
+        *count = bucket.load(Ordering::Relaxed);
+    }
+    stats.set_policy(super::policy_name());
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orbital_common::sched::latency_bucket_limit_us;
+
+    #[test_case]
+    fn test_latency_buckets() {
+        assert_eq!(latency_bucket(0), 0);
+        assert_eq!(latency_bucket(999), 0);
+        assert_eq!(latency_bucket(1_000), 1);
+        assert_eq!(latency_bucket(10_000_000), 14);
+        assert_eq!(latency_bucket(u64::MAX), LATENCY_BUCKETS - 1);
+        // Every wait lands below its bucket's limit
+        for ns in [500, 1_500, 3_000_000, 40_000_000] {
+            let limit = latency_bucket_limit_us(latency_bucket(ns)).unwrap();
+            assert!(ns / 1000 < limit);
+        }
+        assert_eq!(latency_bucket_limit_us(LATENCY_BUCKETS - 1), None);
+    }
+
+    #[test_case]
+    fn test_snapshot_counts_switches() {
+        let before = snapshot();
+        count_switch_in(2_000);
+        count_voluntary();
+        let after = snapshot();
+        assert!(after.switches > before.switches);
+        assert!(after.voluntary_switches > before.voluntary_switches);
+        assert!(after.latency[latency_bucket(2_000)] > before.latency[latency_bucket(2_000)]);
+        assert!(after.cpus >= 1);
+    }
+}
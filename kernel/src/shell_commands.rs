@@ -10,6 +10,7 @@
 /// This maintains the illusion that commands work while we prepare for the
 /// real userspace shell binary in Phase 3.
 use crate::{print, println};
+use orbital_common::process::{INIT_PID, NICE_BATCH, SIGTERM, policy_name};
 
 /// Execute a shell command (kernel version - temporary for Phase 2.5)
 pub fn execute_command(command: &str) {
@@ -27,6 +28,7 @@ pub fn execute_command(command: &str) {
         "ping" => cmd_ping(),
         "spawn" => cmd_spawn(&parts[1..]),
         "wait" => cmd_wait(&parts[1..]),
+        "kill" => cmd_kill(&parts[1..]),
         "run" => cmd_run(),
         "clear" => cmd_clear(),
         "exit" => cmd_exit(),
@@ -48,6 +50,7 @@ fn cmd_help() {
     println!("  ping            - Connectivity test");
     println!("  spawn <n>       - Spawn n tasks");
     println!("  wait <pid>      - Wait for process");
+    println!("  kill <pid>      - Kill process");
     println!("  run             - Execute ready tasks");
     println!("  clear           - Clear screen");
     println!("  exit            - Exit shell");
@@ -68,7 +71,21 @@ fn cmd_echo(args: &[&str]) {
 }
 
 fn cmd_ps() {
-    println!("Running processes (stub - use ps syscall for details)");
+    println!("  PID  PPID STATE       NI CLS RTP NAME             COMMAND");
+    for info in crate::process::process_info(0, usize::MAX) {
+        let state = info.state().map_or("?", |state| state.as_str());
+        println!(
+            "{:5} {:5} {:<10} {:3} {:<3} {:>3} {:<16} {}",
+            info.pid,
+            info.ppid,
+            state,
+            info.nice,
+            policy_name(info.policy),
+            info.rt_priority,
+            info.name(),
+            info.cmdline()
+        );
+    }
 }
 
 fn cmd_pid() {
@@ -77,8 +94,12 @@ fn cmd_pid() {
 }
 
 fn cmd_uptime() {
-    let uptime_s = crate::scheduler::get_elapsed_seconds();
-    println!("Uptime: {} seconds", uptime_s);
+    let uptime_ms = crate::clock::monotonic_ns() / 1_000_000;
+    println!(
+        "Uptime: {}.{:03} seconds",
+        uptime_ms / 1000,
+        uptime_ms % 1000
+    );
 }
 
 fn cmd_ping() {
@@ -96,6 +117,8 @@ fn cmd_spawn(args: &[&str]) {
             if let Some(entry) = crate::tasks::get_test_task(((i % 4) + 1) as usize) {
                 let pid = crate::process::create_process(entry as usize);
                 if pid > 0 {
+                    // Background tasks soak up the CPU the shell leaves idle
+                    crate::process::set_nice(pid as u64, NICE_BATCH);
                     crate::scheduler::enqueue_process(pid as u64);
                     println!("Spawned task {}: PID {}", i + 1, pid);
                 }
@@ -119,6 +142,24 @@ fn cmd_wait(args: &[&str]) {
     }
 }
 
+fn cmd_kill(args: &[&str]) {
+    let pid = match args.first().and_then(|arg| arg.parse::<u64>().ok()) {
+        Some(pid) if pid > 0 => pid,
+        _ => {
+            println!("Usage: kill <pid>");
+            return;
+        }
+    };
+
+    if pid == INIT_PID {
+        println!("kill: cannot kill init");
+    } else if crate::process::kill_process(pid, SIGTERM) {
+        println!("Killed PID {}", pid);
+    } else {
+        println!("kill: no such process: {}", pid);
+    }
+}
+
 fn cmd_run() {
     println!("Executing all ready processes...");
     let count = crate::process::execute_all_ready();
@@ -0,0 +1,370 @@
+//! Symmetric multiprocessing: starting the other CPUs
+//!
+//! The CPU the bootloader started is the bootstrap processor (the BSP,
+//! `BOOT_CPU`). The others, the application processors (APs), wait until
+//! the BSP sends each an INIT IPI and then STARTUP IPIs, which start it in
+//! real mode at a page below 1 MiB. `init` copies a trampoline there that
+//! takes the AP from real mode straight to long mode, on the kernel's page
+//! tables, and calls `ap_main` on a stack of its own.
+//!
+//! Every CPU has its own GDT and TSS (`gdt::CpuTables`), per-CPU block
+//! (`percpu`), APIC timer and run queue (see `scheduler`). An AP's own
+//! context is an idle loop, the counterpart of the executor on the BSP: it
+//! halts until its scheduler has a thread for it, stealing one from a
+//! busier CPU if need be. Device interrupts and the timer queue stay with
+//! the BSP.
+//!
+//! CPUs are numbered in the order the MADT lists them, the BSP first.
+//! Without the APICs, or with one CPU in the MADT, the kernel runs on the
+//! BSP alone.
+
+use crate::gdt::CpuTables;
+use crate::memory::BootInfoFrameAllocator;
+use crate::scheduler::KERNEL_TID;
+use crate::stack::{DEFAULT_STACK_SIZE, Stack};
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use x86_64::registers::control::{Cr0, Cr3, Cr4, Cr4Flags};
+use x86_64::registers::model_specific::{Efer, EferFlags};
+use x86_64::structures::paging::PhysFrame;
+use x86_64::{PhysAddr, VirtAddr};
+
+/// Most CPUs the kernel runs on; any more in the MADT are left off
+pub const MAX_CPUS: usize = 16;
+
+/// Number of the CPU the bootloader started
+pub const BOOT_CPU: usize = 0;
+
+/// STARTUP IPIs can only start a CPU in the first megabyte
+const TRAMPOLINE_LIMIT: u64 = 0x10_0000;
+
+/// Waits of the INIT-SIPI-SIPI sequence, in nanoseconds
+const INIT_DELAY_NS: u64 = 10_000_000;
+const STARTUP_DELAY_NS: u64 = 200_000;
+
+/// How long an AP may take to come up after its STARTUP IPIs
+const ONLINE_TIMEOUT_NS: u64 = 100_000_000;
+
+/// `APIC_IDS` entry of a CPU number not in use
+const NO_APIC_ID: u32 = u32::MAX;
+
+/// CPUs online; they are numbered from 0 to `CPU_COUNT - 1`
+static CPU_COUNT: AtomicUsize = AtomicUsize::new(1);
+
+/// Local APIC ID of each CPU number
+static APIC_IDS: [AtomicU32; MAX_CPUS] = [const { AtomicU32::new(NO_APIC_ID) }; MAX_CPUS];
+
+/// Physical address of the page set aside for the trampoline (0 = none)
+static TRAMPOLINE_PAGE: AtomicU64 = AtomicU64::new(0);
+
+/// Number of CPUs online
+pub fn cpu_count() -> usize {
+    CPU_COUNT.load(Ordering::Acquire)
+}
+
+/// Number of the CPU running the caller, from its per-CPU block
+///
+/// The caller must not move to another CPU while it uses the answer: call
+/// it from an interrupt handler or with interrupts disabled.
+pub fn cpu_id() -> usize {
+    crate::percpu::cpu_id()
+}
+
+// The trampoline. It runs at the start of the page it is copied to, in
+// real mode with CS pointing at that page; `.Lap_*_offset` are offsets from
+// its start. It patches the two absolute addresses it needs, loads a GDT
+// with a 64-bit code segment, turns on PAE, long mode and paging at once
+// (with the BSP's CR4, CR3, EFER and CR0), and jumps to 64-bit code. The
+// page is identity-mapped meanwhile, so the jump lands on it. The 64-bit
+// part loads the stack and the arguments from the parameter block
+// (`TrampolineParams`) and calls the entry point.
+core::arch::global_asm!(
+    ".global ap_trampoline_start",
+    ".global ap_trampoline_params",
+    ".global ap_trampoline_end",
+    ".code16",
+    "ap_trampoline_start:",
+    "    cli",
+    "    cld",
+    "    mov ax, cs",
+    "    mov ds, ax",
+    "    xor ebx, ebx",
+    "    mov bx, ax",
+    "    shl ebx, 4",
+    "    lea eax, [ebx + .Lap_gdt_offset]",
+    "    mov dword ptr [.Lap_gdtr_offset + 2], eax",
+    "    lea eax, [ebx + .Lap_long_mode_offset]",
+    "    mov dword ptr [.Lap_far_jump_offset], eax",
+    "    lgdt [.Lap_gdtr_offset]",
+    "    mov eax, dword ptr [.Lap_params_offset + 4]",
+    "    mov cr4, eax",
+    "    mov eax, dword ptr [.Lap_params_offset + 8]",
+    "    mov cr3, eax",
+    "    mov ecx, 0xC0000080",
+    "    mov eax, dword ptr [.Lap_params_offset + 12]",
+    "    xor edx, edx",
+    "    wrmsr",
+    "    mov eax, dword ptr [.Lap_params_offset]",
+    "    mov cr0, eax",
+    // jmp far dword [.Lap_far_jump_offset], which the assembler will not
+    // encode in 16-bit code
+    "    .byte 0x66, 0xFF, 0x2E",
+    "    .word .Lap_far_jump_offset",
+    ".code64",
+    ".Lap_long_mode:",
+    "    mov ebx, ebx",
+    "    xor eax, eax",
+    "    mov ds, eax",
+    "    mov es, eax",
+    "    mov ss, eax",
+    "    mov rsp, [rbx + .Lap_params_offset + 16]",
+    "    mov rdi, [rbx + .Lap_params_offset + 24]",
+    "    mov rsi, [rbx + .Lap_params_offset + 32]",
+    "    mov rax, [rbx + .Lap_params_offset + 40]",
+    "    call rax",
+    "    ud2",
+    ".balign 8",
+    ".Lap_gdt:",
+    "    .quad 0",
+    "    .quad 0x00AF9A000000FFFF",
+    ".Lap_gdtr:",
+    "    .word .Lap_gdtr - .Lap_gdt - 1",
+    "    .long 0",
+    ".Lap_far_jump:",
+    "    .long 0",
+    "    .word 0x08",
+    ".balign 8",
+    "ap_trampoline_params:",
+    "    .space 48",
+    "ap_trampoline_end:",
+    ".set .Lap_gdt_offset, .Lap_gdt - ap_trampoline_start",
+    ".set .Lap_gdtr_offset, .Lap_gdtr - ap_trampoline_start",
+    ".set .Lap_long_mode_offset, .Lap_long_mode - ap_trampoline_start",
+    ".set .Lap_far_jump_offset, .Lap_far_jump - ap_trampoline_start",
+    ".set .Lap_params_offset, ap_trampoline_params - ap_trampoline_start",
+);
+
+unsafe extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_params: u8;
+    static ap_trampoline_end: u8;
+}
+
+/// The trampoline's parameter block, at `ap_trampoline_params`
+///
+/// Control registers are loaded from 32-bit registers in real mode, so
+/// they are 32 bits wide here (CR3 must be below 4 GiB).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TrampolineParams {
+    cr0: u32,
+    cr4: u32,
+    cr3: u32,
+    efer: u32,
+    stack_top: u64,
+    /// First argument of `entry`
+    cpu: u64,
+    /// Second argument of `entry`
+    tables: u64,
+    entry: u64,
+}
+
+/// The trampoline's code and parameter block
+fn trampoline() -> &'static [u8] {
+    let start = &raw const ap_trampoline_start;
+    let end = &raw const ap_trampoline_end;
+    unsafe { core::slice::from_raw_parts(start, end as usize - start as usize) }
+}
+
+/// Offset of the parameter block in the trampoline
+fn params_offset() -> usize {
+    &raw const ap_trampoline_params as usize - &raw const ap_trampoline_start as usize
+}
+
+/// Set aside the page the application processors start in
+///
+/// Call before the heap is set up, while low memory is still free.
+pub fn reserve_trampoline(frame_allocator: &mut BootInfoFrameAllocator) {
+    // Page 0 holds the real-mode interrupt vectors and reads as "none"
+    while let Some(frame) = frame_allocator.allocate_frame_below(TRAMPOLINE_LIMIT) {
+        if frame.start_address().as_u64() != 0 {
+            TRAMPOLINE_PAGE.store(frame.start_address().as_u64(), Ordering::Relaxed);
+            return;
+        }
+    }
+}
+
+/// Spin until `clock::monotonic_ns` passes `deadline` or `done` holds;
+/// returns whether `done` held
+fn spin_until(deadline: u64, done: impl Fn() -> bool) -> bool {
+    loop {
+        if done() {
+            return true;
+        }
+        if crate::clock::monotonic_ns() >= deadline {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Start the application processors listed in the MADT
+///
+/// Call once, after `apic::init`, with interrupts enabled (the waits read
+/// `clock::monotonic_ns`). Returns the number of CPUs online: 1 if the
+/// APICs are not in use or no trampoline page was reserved.
+pub fn init() -> usize {
+    let madt = match crate::acpi::madt() {
+        Some(madt) if crate::apic::is_enabled() && cpu_count() == 1 => madt,
+        _ => return cpu_count(),
+    };
+    let bsp_apic_id = match crate::apic::local_apic_id() {
+        Some(apic_id) => apic_id,
+        None => return 1,
+    };
+    let frame = match TRAMPOLINE_PAGE.load(Ordering::Relaxed) {
+        0 => return 1,
+        address => PhysFrame::containing_address(PhysAddr::new(address)),
+    };
+    let cr3 = Cr3::read().0.start_address().as_u64();
+    if cr3 > u32::MAX as u64 || !crate::memory::map_identity(frame) {
+        return 1;
+    }
+    let page = match crate::memory::phys_to_virt(frame.start_address()) {
+        Some(page) => page,
+        None => return 1,
+    };
+
+    let code = trampoline();
+    unsafe {
+        core::ptr::copy_nonoverlapping(code.as_ptr(), page.as_mut_ptr::<u8>(), code.len());
+    }
+    let params = TrampolineParams {
+        cr0: Cr0::read_raw() as u32,
+        // PCIDs cannot be turned on outside long mode; APs do without
+        cr4: (Cr4::read_raw() & !Cr4Flags::PCID.bits()) as u32,
+        cr3: cr3 as u32,
+        efer: (Efer::read_raw() & !EferFlags::LONG_MODE_ACTIVE.bits()) as u32,
+        stack_top: 0,
+        cpu: 0,
+        tables: 0,
+        entry: ap_main as *const () as u64,
+    };
+    let params_page = page + params_offset() as u64;
+    let vector = (frame.start_address().as_u64() >> 12) as u8;
+
+    APIC_IDS[BOOT_CPU].store(u32::from(bsp_apic_id), Ordering::Relaxed);
+    for cpu in madt.cpus.iter().filter(|cpu| cpu.apic_id != bsp_apic_id) {
+        let number = cpu_count();
+        if number == MAX_CPUS {
+            break;
+        }
+        if !start_ap(number, cpu.apic_id, params, params_page, vector) {
+            crate::println!("CPU with APIC ID {} did not start", cpu.apic_id);
+        }
+    }
+
+    crate::memory::unmap_identity(frame);
+    cpu_count()
+}
+
+/// Start the AP with APIC ID `apic_id` as CPU number `cpu`, through the
+/// trampoline at page `vector`, and wait until it is online
+fn start_ap(
+    cpu: usize,
+    apic_id: u8,
+    mut params: TrampolineParams,
+    params_page: VirtAddr,
+    vector: u8,
+) -> bool {
+    let tables = match CpuTables::new() {
+        Ok(tables) => tables,
+        Err(_) => return false,
+    };
+    let stack = match Stack::new(DEFAULT_STACK_SIZE, KERNEL_TID) {
+        Ok(stack) => stack,
+        Err(_) => return false,
+    };
+    params.stack_top = stack.top();
+    params.cpu = cpu as u64;
+    params.tables = tables as *const CpuTables as u64;
+    unsafe {
+        core::ptr::write_volatile(params_page.as_mut_ptr::<TrampolineParams>(), params);
+    }
+    // The AP keeps the stack as its idle stack
+    core::mem::forget(stack);
+
+    APIC_IDS[cpu].store(u32::from(apic_id), Ordering::Release);
+    let online = || cpu_count() > cpu;
+    let now = crate::clock::monotonic_ns;
+
+    crate::apic::send_init(apic_id);
+    spin_until(now() + INIT_DELAY_NS, || false);
+    for _ in 0..2 {
+        crate::apic::send_startup(apic_id, vector);
+        if spin_until(now() + STARTUP_DELAY_NS, online) {
+            return true;
+        }
+    }
+    if spin_until(now() + ONLINE_TIMEOUT_NS, online) {
+        return true;
+    }
+
+    // Should it still come up, it finds it has no number and halts
+    APIC_IDS[cpu].store(NO_APIC_ID, Ordering::Release);
+    false
+}
+
+/// Where an AP enters the kernel, from the trampoline, on its idle stack
+///
+/// Sets the CPU up as `crate::init` does the BSP, goes online and then
+/// idles: this is the CPU's own context, which its scheduler switches away
+/// from whenever it has a thread to run.
+extern "C" fn ap_main(cpu: usize, tables: &'static CpuTables) -> ! {
+    let apic_id = crate::apic::local_apic_id().map(u32::from);
+    if apic_id != Some(APIC_IDS[cpu].load(Ordering::Acquire)) {
+        crate::hlt_loop();
+    }
+
+    tables.load();
+    crate::percpu::init(cpu);
+    crate::interrupts::load_idt();
+    crate::fpu::init();
+    crate::tls::init();
+    crate::apic::init_ap();
+    CPU_COUNT.fetch_add(1, Ordering::Release);
+
+    let block = crate::percpu::this_cpu();
+    loop {
+        x86_64::instructions::interrupts::disable();
+        block.idle_hlt();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_trampoline_params_layout() {
+        // Offsets the trampoline reads the block at
+        assert_eq!(core::mem::offset_of!(TrampolineParams, cr0), 0);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, cr4), 4);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, cr3), 8);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, efer), 12);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, stack_top), 16);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, cpu), 24);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, tables), 32);
+        assert_eq!(core::mem::offset_of!(TrampolineParams, entry), 40);
+        assert_eq!(
+            trampoline().len(),
+            params_offset() + core::mem::size_of::<TrampolineParams>()
+        );
+    }
+
+    #[test_case]
+    fn test_single_cpu_by_default() {
+        // Tests run on the BSP alone: nothing calls `init`
+        assert_eq!(cpu_count(), 1);
+        assert_eq!(cpu_id(), BOOT_CPU);
+    }
+}
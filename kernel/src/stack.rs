@@ -0,0 +1,471 @@
+//! Task stacks with guard pages
+//!
+//! Task stacks are mapped into a dedicated virtual region instead of being
+//! allocated on the kernel heap. The region is split into fixed-size slots,
+//! one per stack. A stack occupies the top of its slot and everything below
+//! it, including the page right under its lowest address, stays unmapped:
+//!
+//! ```text
+//! slot base                                              slot base + STACK_SLOT_SIZE
+//! | unmapped ... | guard page | stack pages ............ |
+//!                             ^ bottom                   ^ top (initial RSP)
+//! ```
+//!
+//! Running off the end of a stack therefore faults on the guard page instead
+//! of silently corrupting a neighbouring allocation, and the page fault
+//! handler can tell which task overflowed via `overflow_owner`.
+//!
+//! User stacks are growable: they reserve a large range of the slot but
+//! only the top page is mapped at spawn. A not-present fault just below the
+//! mapped part (within `MAX_GROWTH_GAP`) and inside the reserved range maps
+//! fresh pages and the task resumes; see `handle_fault`.
+
+use crate::memory;
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{
+    FrameAllocator, FrameDeallocator, Mapper, Page, PageTableFlags, Size4KiB,
+};
+
+/// Page size used for stack mappings
+pub const PAGE_SIZE: usize = 4096;
+
+/// Start of the virtual region reserved for task stacks
+pub const STACK_REGION_START: u64 = 0x_5555_0000_0000;
+
+/// Virtual address space reserved per stack (stack + guard area)
+pub const STACK_SLOT_SIZE: usize = 8 * 1024 * 1024; // 8 MiB
+
+/// Number of stack slots in the region
+pub const MAX_STACKS: usize = 512;
+
+/// Stack size used when a spawn does not ask for one
+pub const DEFAULT_STACK_SIZE: usize = 16 * 1024; // 16 KiB
+
+/// Largest stack that still leaves a guard page in its slot
+pub const MAX_STACK_SIZE: usize = STACK_SLOT_SIZE - PAGE_SIZE;
+
+/// Address space a user stack may grow into when a spawn does not ask for
+/// a specific reserve
+pub const DEFAULT_USER_STACK_RESERVE: usize = MAX_STACK_SIZE;
+
+/// How far below the mapped part of a growable stack a fault may land and
+/// still count as stack growth (large frames touch memory well below RSP)
+pub const MAX_GROWTH_GAP: usize = 64 * 1024;
+
+/// Stack allocation errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    /// Requested size is zero or larger than `MAX_STACK_SIZE`
+    InvalidSize,
+    /// Every stack slot is in use
+    NoSlots,
+    /// No physical frames left to back the stack
+    OutOfMemory,
+    /// `memory::install` has not been called yet
+    NotInitialized,
+}
+
+impl StackError {
+    /// Human-readable description
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StackError::InvalidSize => "invalid stack size",
+            StackError::NoSlots => "no free stack slots",
+            StackError::OutOfMemory => "out of memory for stack",
+            StackError::NotInitialized => "stack region not initialized",
+        }
+    }
+}
+
+/// How a new thread's stack is sized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackSpec {
+    /// Mapped in full up front; 0 selects `DEFAULT_STACK_SIZE`
+    Fixed(usize),
+    /// Reserve this much address space but map only the top page; the rest
+    /// is mapped on demand. 0 selects `DEFAULT_USER_STACK_RESERVE`
+    Growable(usize),
+}
+
+impl StackSpec {
+    /// Allocate a stack of this kind for the thread `owner`
+    pub fn allocate(self, owner: u64) -> Result<Stack, StackError> {
+        match self {
+            StackSpec::Fixed(0) => Stack::new(DEFAULT_STACK_SIZE, owner),
+            StackSpec::Fixed(size) => Stack::new(size, owner),
+            StackSpec::Growable(0) => Stack::new_growable(DEFAULT_USER_STACK_RESERVE, owner),
+            StackSpec::Growable(reserve) => Stack::new_growable(reserve, owner),
+        }
+    }
+}
+
+/// Bookkeeping for one slot of the stack region
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    in_use: bool,
+    /// Thread ID the stack belongs to
+    owner: u64,
+    /// Bytes mapped at the top of the slot
+    mapped: usize,
+    /// Bytes the stack may grow to (equal to `mapped` for fixed stacks)
+    reserved: usize,
+}
+
+impl Slot {
+    const FREE: Slot = Slot {
+        in_use: false,
+        owner: 0,
+        mapped: 0,
+        reserved: 0,
+    };
+
+    /// Lowest mapped address
+    fn bottom(&self, slot: usize) -> u64 {
+        slot_top(slot) - self.mapped as u64
+    }
+
+    /// Lowest address the stack may grow to
+    fn limit(&self, slot: usize) -> u64 {
+        slot_top(slot) - self.reserved as u64
+    }
+}
+
+static SLOTS: Mutex<[Slot; MAX_STACKS]> = Mutex::new([Slot::FREE; MAX_STACKS]);
+
+/// Run `f` on the slot table with interrupts disabled
+///
+/// The page fault handler reads the table, so it must never be interrupted
+/// while locked.
+fn with_slots<R>(f: impl FnOnce(&mut [Slot; MAX_STACKS]) -> R) -> R {
+    x86_64::instructions::interrupts::without_interrupts(|| f(&mut SLOTS.lock()))
+}
+
+/// Lowest address of a slot
+fn slot_base(slot: usize) -> u64 {
+    STACK_REGION_START + (slot * STACK_SLOT_SIZE) as u64
+}
+
+/// Highest address (exclusive) of a slot
+fn slot_top(slot: usize) -> u64 {
+    slot_base(slot) + STACK_SLOT_SIZE as u64
+}
+
+/// Slot containing `addr`, if it lies in the stack region
+fn slot_of(addr: u64) -> Option<usize> {
+    let offset = addr.checked_sub(STACK_REGION_START)? as usize;
+    let slot = offset / STACK_SLOT_SIZE;
+    (slot < MAX_STACKS).then_some(slot)
+}
+
+/// Pages covering `[start, start + size)`
+fn pages(start: u64, size: usize) -> impl Iterator<Item = Page<Size4KiB>> {
+    let first = Page::containing_address(VirtAddr::new(start));
+    let last = Page::containing_address(VirtAddr::new(start + size as u64 - 1));
+    Page::range_inclusive(first, last)
+}
+
+/// Unmap `[start, start + size)` and return the frames to the pool
+fn unmap_range(memory: &mut memory::KernelMemory, start: u64, size: usize) {
+    for page in pages(start, size) {
+        if let Ok((frame, flush)) = memory.mapper.unmap(page) {
+            flush.flush();
+            unsafe { memory.frames.deallocate_frame(frame) };
+        }
+    }
+}
+
+/// Map fresh zeroed frames at `[start, start + size)`
+///
+/// On failure everything mapped so far is undone.
+fn map_range(memory: &mut memory::KernelMemory, start: u64, size: usize) -> Result<(), StackError> {
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+    let mut mapped = 0;
+
+    for page in pages(start, size) {
+        let frame = match memory.frames.allocate_frame() {
+            Some(frame) => frame,
+            None => {
+                if mapped > 0 {
+                    unmap_range(memory, start, mapped);
+                }
+                return Err(StackError::OutOfMemory);
+            }
+        };
+        match unsafe { memory.mapper.map_to(page, frame, flags, &mut memory.frames) } {
+            Ok(flush) => flush.flush(),
+            Err(_) => {
+                unsafe { memory.frames.deallocate_frame(frame) };
+                if mapped > 0 {
+                    unmap_range(memory, start, mapped);
+                }
+                return Err(StackError::OutOfMemory);
+            }
+        }
+        unsafe { core::ptr::write_bytes(page.start_address().as_mut_ptr::<u8>(), 0, PAGE_SIZE) };
+        mapped += PAGE_SIZE;
+    }
+
+    Ok(())
+}
+
+/// A task stack mapped in the stack region
+///
+/// Dropping the stack unmaps its pages and frees the slot.
+#[derive(Debug)]
+pub struct Stack {
+    slot: usize,
+}
+
+impl Stack {
+    /// Map a stack of at least `size` bytes (rounded up to whole pages)
+    /// for the thread `owner`
+    pub fn new(size: usize, owner: u64) -> Result<Self, StackError> {
+        Self::allocate(size, size, owner)
+    }
+
+    /// Reserve `reserve` bytes for a stack that grows on demand, mapping
+    /// only its top page now
+    pub fn new_growable(reserve: usize, owner: u64) -> Result<Self, StackError> {
+        Self::allocate(PAGE_SIZE, reserve, owner)
+    }
+
+    /// Claim a slot, reserve `reserve` bytes and map the top `size` bytes
+    fn allocate(size: usize, reserve: usize, owner: u64) -> Result<Self, StackError> {
+        if size == 0 || reserve < size || reserve > MAX_STACK_SIZE {
+            return Err(StackError::InvalidSize);
+        }
+        let size = size.next_multiple_of(PAGE_SIZE);
+        let reserved = reserve.next_multiple_of(PAGE_SIZE);
+
+        let slot = with_slots(|slots| {
+            let slot = slots.iter().position(|s| !s.in_use)?;
+            slots[slot] = Slot {
+                in_use: true,
+                owner,
+                mapped: size,
+                reserved,
+            };
+            Some(slot)
+        })
+        .ok_or(StackError::NoSlots)?;
+
+        let bottom = slot_top(slot) - size as u64;
+        let mapped = memory::with_kernel_memory(|memory| map_range(memory, bottom, size))
+            .unwrap_or(Err(StackError::NotInitialized));
+
+        if let Err(e) = mapped {
+            // Nothing is mapped, so only release the slot
+            with_slots(|slots| slots[slot] = Slot::FREE);
+            return Err(e);
+        }
+
+        Ok(Stack { slot })
+    }
+
+    /// Highest address of the stack (exclusive); the initial RSP
+    pub fn top(&self) -> u64 {
+        slot_top(self.slot)
+    }
+
+    /// Lowest mapped address of the stack
+    pub fn bottom(&self) -> u64 {
+        self.top() - self.size() as u64
+    }
+
+    /// Address of the unmapped page directly below the reserved range
+    pub fn guard_page(&self) -> u64 {
+        self.top() - self.reserved() as u64 - PAGE_SIZE as u64
+    }
+
+    /// Bytes currently mapped
+    pub fn size(&self) -> usize {
+        with_slots(|slots| slots[self.slot].mapped)
+    }
+
+    /// Bytes the stack may grow to
+    pub fn reserved(&self) -> usize {
+        with_slots(|slots| slots[self.slot].reserved)
+    }
+
+    /// The mapped stack memory as a byte slice
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        let size = self.size();
+        unsafe { core::slice::from_raw_parts_mut(self.bottom() as *mut u8, size) }
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        let (bottom, size) = (self.bottom(), self.size());
+        memory::with_kernel_memory(|memory| unmap_range(memory, bottom, size));
+        with_slots(|slots| slots[self.slot] = Slot::FREE);
+    }
+}
+
+/// The stack a fault was attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultingStack {
+    /// Thread that owns the stack
+    pub owner: u64,
+    /// Top of the stack (still mapped)
+    pub stack_top: u64,
+}
+
+/// Check whether a faulting address is a stack overflow
+///
+/// Returns the overflowing stack if `addr` is below the range a live
+/// stack may use (the guard page or anything below it).
+pub fn overflow_owner(addr: u64) -> Option<FaultingStack> {
+    let slot = slot_of(addr)?;
+    let entry = SLOTS.try_lock().map(|slots| slots[slot])?;
+    if !entry.in_use || addr >= entry.limit(slot) {
+        return None;
+    }
+
+    Some(FaultingStack {
+        owner: entry.owner,
+        stack_top: slot_top(slot),
+    })
+}
+
+/// How a not-present fault in the stack region should be handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FaultKind {
+    /// Just below the mapping and within the reserve: map more pages
+    Grow,
+    /// Below the reserved range (guard area)
+    Overflow,
+    /// Inside the reserve but too far below the mapping
+    Segfault,
+}
+
+/// Classify a fault at `addr` against a stack's current layout
+fn classify(addr: u64, bottom: u64, limit: u64) -> Option<FaultKind> {
+    if addr >= bottom {
+        None // Mapped part: not a stack growth fault
+    } else if addr < limit {
+        Some(FaultKind::Overflow)
+    } else if bottom - addr <= MAX_GROWTH_GAP as u64 {
+        Some(FaultKind::Grow)
+    } else {
+        Some(FaultKind::Segfault)
+    }
+}
+
+/// Outcome of `handle_fault`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFault {
+    /// The stack was grown; resume the faulting instruction
+    Grown,
+    /// The task ran past its reserved range
+    Overflow(FaultingStack),
+    /// The task touched its reserve far below the stack: a wild access
+    Segfault(FaultingStack),
+}
+
+/// Handle a not-present page fault at `addr`
+///
+/// Grows the stack if the fault is just below a growable stack's mapping.
+/// Never waits for a lock: if the kernel's paging state is busy, a growth
+/// fault is reported as an overflow. Returns `None` if `addr` does not
+/// belong to a live stack, in which case the fault is not the stack
+/// module's business.
+pub fn handle_fault(addr: u64) -> Option<StackFault> {
+    let slot = slot_of(addr)?;
+    // Held until the new pages are recorded; never waited for, as the
+    // faulting code may be the holder
+    let mut slots = SLOTS.try_lock()?;
+    let entry = slots[slot];
+    if !entry.in_use {
+        return None;
+    }
+
+    let faulting = FaultingStack {
+        owner: entry.owner,
+        stack_top: slot_top(slot),
+    };
+    let bottom = entry.bottom(slot);
+
+    match classify(addr, bottom, entry.limit(slot))? {
+        FaultKind::Overflow => Some(StackFault::Overflow(faulting)),
+        FaultKind::Segfault => Some(StackFault::Segfault(faulting)),
+        FaultKind::Grow => {
+            let new_bottom = addr & !(PAGE_SIZE as u64 - 1);
+            let grow_by = (bottom - new_bottom) as usize;
+            let grown =
+                memory::try_with_kernel_memory(|memory| map_range(memory, new_bottom, grow_by));
+
+            match grown {
+                Some(Ok(())) => {
+                    slots[slot].mapped += grow_by;
+                    Some(StackFault::Grown)
+                }
+                // The paging lock is held, possibly by the code that
+                // faulted: waiting could deadlock, so treat it as an overflow
+                None => Some(StackFault::Overflow(faulting)),
+                // Out of memory: the task can't continue
+                Some(Err(_)) => Some(StackFault::Segfault(faulting)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_stack_size_validation() {
+        assert_eq!(Stack::new(0, 1).unwrap_err(), StackError::InvalidSize);
+        assert_eq!(
+            Stack::new(MAX_STACK_SIZE + 1, 1).unwrap_err(),
+            StackError::InvalidSize
+        );
+    }
+
+    #[test_case]
+    fn test_fault_classification() {
+        let top = slot_top(0);
+        let bottom = top - PAGE_SIZE as u64;
+        let limit = top - DEFAULT_USER_STACK_RESERVE as u64;
+
+        assert_eq!(classify(top - 8, bottom, limit), None);
+        assert_eq!(classify(bottom - 8, bottom, limit), Some(FaultKind::Grow));
+        assert_eq!(
+            classify(bottom - MAX_GROWTH_GAP as u64 - 8, bottom, limit),
+            Some(FaultKind::Segfault)
+        );
+        assert_eq!(
+            classify(limit - 8, bottom, limit),
+            Some(FaultKind::Overflow)
+        );
+    }
+
+    #[test_case]
+    fn test_grow_never_waits_for_paging_lock() {
+        let stack = Stack::new_growable(DEFAULT_USER_STACK_RESERVE, 1).unwrap();
+        let addr = stack.bottom() - 8;
+
+        let fault = memory::with_kernel_memory(|_| handle_fault(addr)).unwrap();
+        assert!(matches!(fault, Some(StackFault::Overflow(_))));
+        assert_eq!(stack.size(), PAGE_SIZE);
+
+        assert_eq!(handle_fault(addr), Some(StackFault::Grown));
+        assert_eq!(stack.size(), 2 * PAGE_SIZE);
+    }
+
+    #[test_case]
+    fn test_slot_lookup() {
+        assert_eq!(slot_of(STACK_REGION_START - 1), None);
+        assert_eq!(slot_of(STACK_REGION_START), Some(0));
+        assert_eq!(
+            slot_of(STACK_REGION_START + STACK_SLOT_SIZE as u64),
+            Some(1)
+        );
+        assert_eq!(
+            slot_of(STACK_REGION_START + (MAX_STACKS * STACK_SLOT_SIZE) as u64),
+            None
+        );
+    }
+}
@@ -9,9 +9,17 @@
 //! Syscall numbers are passed in RAX.
 //! Return values are in RAX (or error code in RAX with sign bit set).
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt;
+use orbital_common::io::O_NONBLOCK;
+use orbital_common::process::{
+    INIT_PID, NICE_MAX, NICE_MIN, PROCESS_NAME_LEN, ProcessInfo, SIGKILL, SIGTERM, WAIT_ANY,
+    WAIT_NOHANG,
+};
+use orbital_common::sched::SchedStats;
+use orbital_common::time::{CLOCK_MONOTONIC, Timespec};
 extern crate alloc;
-use alloc::format;
 
 /// Syscall error codes
 /// Follows Unix convention: negative values indicate errors
@@ -30,8 +38,12 @@ pub enum SysError {
     NotFound = -5,
     /// Generic kernel error
     Error = -6,
+    /// A blocking call's timeout expired
+    TimedOut = -7,
     /// Bad file descriptor
     BadFd = -9,
+    /// Nothing to read, and the read was not to wait (`O_NONBLOCK`)
+    WouldBlock = -11,
 }
 
 impl SysError {
@@ -50,7 +62,9 @@ impl SysError {
             -4 => Some(SysError::PermissionDenied),
             -5 => Some(SysError::NotFound),
             -6 => Some(SysError::Error),
+            -7 => Some(SysError::TimedOut),
             -9 => Some(SysError::BadFd),
+            -11 => Some(SysError::WouldBlock),
             _ => None,
         }
     }
@@ -65,11 +79,29 @@ impl fmt::Display for SysError {
             SysError::PermissionDenied => write!(f, "Permission denied"),
             SysError::NotFound => write!(f, "Not found"),
             SysError::Error => write!(f, "Kernel error"),
+            SysError::TimedOut => write!(f, "Timed out"),
             SysError::BadFd => write!(f, "Bad file descriptor"),
+            SysError::WouldBlock => write!(f, "Operation would block"),
         }
     }
 }
 
+impl From<crate::process::WaitError> for SysError {
+    fn from(e: crate::process::WaitError) -> Self {
+        match e {
+            crate::process::WaitError::NotFound => SysError::NotFound,
+            crate::process::WaitError::TimedOut => SysError::TimedOut,
+        }
+    }
+}
+
+/// Maximum number of records `sys_ps` returns per call
+/// Matches the process table limit in `process::create_process`
+const PS_MAX_RECORDS: usize = 256;
+
+/// Longest argv buffer sys_spawn accepts
+const SPAWN_MAX_ARGV: usize = 256;
+
 /// Syscall result type
 pub type SysResult = Result<usize, SysError>;
 
@@ -80,18 +112,35 @@ type SyscallHandler = fn(usize, usize, usize, usize, usize, usize) -> SysResult;
 /// Syscall dispatch table
 /// Maps syscall numbers to handler functions
 const SYSCALL_TABLE: &[Option<SyscallHandler>] = &[
-    Some(sys_hello),        // 0
-    Some(sys_log),          // 1
-    Some(sys_write),        // 2
-    Some(sys_exit),         // 3
-    Some(sys_read),         // 4
-    Some(sys_task_create),  // 5
-    Some(sys_task_wait),    // 6
-    Some(sys_get_pid),      // 7
-    Some(sys_ps),           // 8
-    Some(sys_uptime),       // 9
-    Some(sys_clear_screen), // 10
-    Some(sys_run_ready),    // 11
+    Some(sys_hello),              // 0
+    Some(sys_log),                // 1
+    Some(sys_write),              // 2
+    Some(sys_exit),               // 3
+    Some(sys_read),               // 4
+    Some(sys_task_create),        // 5
+    Some(sys_task_wait),          // 6
+    Some(sys_get_pid),            // 7
+    Some(sys_ps),                 // 8
+    Some(sys_uptime),             // 9
+    Some(sys_clear_screen),       // 10
+    Some(sys_run_ready),          // 11
+    None,                         // 12 (reserved)
+    Some(sys_thread_create),      // 13
+    Some(sys_thread_join),        // 14
+    Some(sys_thread_exit),        // 15
+    Some(sys_get_tid),            // 16
+    Some(sys_arch_prctl),         // 17
+    Some(sys_spawn),              // 18
+    Some(sys_wait),               // 19
+    Some(sys_set_name),           // 20
+    Some(sys_setpriority),        // 21
+    Some(sys_getpriority),        // 22
+    Some(sys_sched_setscheduler), // 23
+    Some(sys_sched_getscheduler), // 24
+    Some(sys_sleep),              // 25
+    Some(sys_clock_gettime),      // 26
+    Some(sys_sched_stats),        // 27
+    Some(sys_kill),               // 28
 ];
 
 /// Syscall number constants
@@ -108,6 +157,30 @@ pub mod nr {
     pub const SYS_UPTIME: usize = 9;
     pub const SYS_CLEAR_SCREEN: usize = 10;
     pub const SYS_RUN_READY: usize = 11;
+    pub const SYS_THREAD_CREATE: usize = 13;
+    pub const SYS_THREAD_JOIN: usize = 14;
+    pub const SYS_THREAD_EXIT: usize = 15;
+    pub const SYS_GET_TID: usize = 16;
+    pub const SYS_ARCH_PRCTL: usize = 17;
+    pub const SYS_SPAWN: usize = 18;
+    pub const SYS_WAIT: usize = 19;
+    pub const SYS_SET_NAME: usize = 20;
+    pub const SYS_SETPRIORITY: usize = 21;
+    pub const SYS_GETPRIORITY: usize = 22;
+    pub const SYS_SCHED_SETSCHEDULER: usize = 23;
+    pub const SYS_SCHED_GETSCHEDULER: usize = 24;
+    pub const SYS_SLEEP: usize = 25;
+    pub const SYS_CLOCK_GETTIME: usize = 26;
+    pub const SYS_SCHED_STATS: usize = 27;
+    pub const SYS_KILL: usize = 28;
+}
+
+/// `sys_arch_prctl` operation codes (same values as Linux)
+pub mod arch_prctl {
+    pub const ARCH_SET_GS: usize = 0x1001;
+    pub const ARCH_SET_FS: usize = 0x1002;
+    pub const ARCH_GET_FS: usize = 0x1003;
+    pub const ARCH_GET_GS: usize = 0x1004;
 }
 
 /// Main syscall dispatcher
@@ -307,18 +380,20 @@ fn sys_exit(
 
     // Get current process ID from scheduler
     if let Some(current_pid) = crate::scheduler::current_process() {
+        if current_pid == INIT_PID {
+            crate::println!("init exited with code {}", exit_code);
+        }
+
         // Mark process as exited with the given exit code
         crate::process::set_process_status(
             current_pid,
-            crate::process::ProcessStatus::Exited(exit_code),
+            crate::process::ProcessStatus::Exited(crate::process::ExitReason::Code(exit_code)),
         );
 
-        // Note: We don't perform context_switch here because sys_exit is called
-        // from task_wrapper_entry which is in task context, not interrupt handler context.
-        // Context switches must only happen from interrupt handlers with proper stack state.
-        // The next timer interrupt will see this task is Exited and schedule a different one.
-
-        // Just halt - the next timer interrupt will handle scheduling
+        // Context switches only happen from the timer interrupt, which
+        // sees this task is Exited and schedules a different one. Ask for
+        // that on the next tick rather than idling out the quantum.
+        crate::scheduler::request_reschedule();
         crate::hlt_loop();
     }
 
@@ -332,15 +407,20 @@ fn sys_exit(
 /// - fd=0 (stdin): reads from kernel input buffer
 /// - Other fds: returns BadFd
 ///
+/// A read of stdin blocks the calling thread until at least one byte has
+/// been typed, unless `O_NONBLOCK` is given.
+///
 /// Arguments:
 ///   arg1: file descriptor (0=stdin, others invalid)
 ///   arg2: pointer to buffer (from userspace)
 ///   arg3: number of bytes to read
+///   arg4: flags (`O_NONBLOCK`)
 ///   other arguments: unused
 ///
 /// Returns:
 ///   Success: number of bytes read
-///   Failure: negative error code (BadFd, Invalid, Fault)
+///   Failure: negative error code (BadFd, Invalid, Fault, WouldBlock when
+///   `O_NONBLOCK` is given and nothing has been typed)
 ///
 /// Safety:
 /// - Validates fd (must be 0 for stdin)
@@ -351,7 +431,7 @@ fn sys_read(
     arg1: usize,
     arg2: usize,
     arg3: usize,
-    _arg4: usize,
+    flags: usize,
     _arg5: usize,
     _arg6: usize,
 ) -> SysResult {
@@ -363,6 +443,9 @@ fn sys_read(
     if fd != 0 {
         return Err(SysError::BadFd);
     }
+    if flags as u64 & !O_NONBLOCK != 0 {
+        return Err(SysError::Invalid);
+    }
 
     // Validate length
     if len == 0 {
@@ -377,17 +460,24 @@ fn sys_read(
         return Err(SysError::Fault);
     }
 
-    // Read from kernel input buffer
-    let bytes_read = crate::input::read_input(unsafe {
+    let buf = unsafe {
         // SAFETY: We've validated:
         // 1. ptr is not NULL
         // 2. len is in valid range
         // 3. We're creating a mutable slice for writing from kernel
         // 4. Userspace is responsible for the memory being valid
         core::slice::from_raw_parts_mut(ptr, len)
-    });
+    };
 
-    Ok(bytes_read)
+    // Read from kernel input buffer
+    if flags as u64 & O_NONBLOCK != 0 {
+        match crate::input::read_input(buf) {
+            0 => Err(SysError::WouldBlock),
+            bytes_read => Ok(bytes_read),
+        }
+    } else {
+        Ok(crate::input::read_blocking(buf))
+    }
 }
 
 /// Syscall #5: Create a new process/task
@@ -397,20 +487,22 @@ fn sys_read(
 ///
 /// # Arguments
 /// - arg1: Entry point address (function pointer as usize)
+/// - arg2: Stack reserve in bytes (0 for the default); the stack starts
+///   with one page and grows on demand up to this size
 /// - Others: Reserved for future use
 ///
 /// # Returns
 /// - Ok(pid): Process ID (positive)
-/// - Err(SysError::Invalid): Invalid entry point (NULL)
-/// - Err(SysError::Error): Too many processes or other error
+/// - Err(SysError::Invalid): Invalid entry point (NULL) or stack size
+/// - Err(SysError::Error): Too many processes, no memory for the stack
 ///
 /// # Process
-/// 1. Create process with entry point (allocates stack)
+/// 1. Create process with entry point (maps a guarded stack)
 /// 2. Add to scheduler ready queue
 /// 3. Return process ID
 fn sys_task_create(
     arg1: usize,
-    _arg2: usize,
+    arg2: usize,
     _arg3: usize,
     _arg4: usize,
     _arg5: usize,
@@ -423,24 +515,21 @@ fn sys_task_create(
         return Err(SysError::Invalid);
     }
 
-    // Create the process (allocates 4KB stack, sets up context)
-    let pid = crate::process::create_process(entry_point);
+    // Create the process (maps its stack, sets up context)
+    let stack = crate::stack::StackSpec::Growable(arg2);
+    let pid = crate::process::create_process_with_stack(entry_point, stack);
 
     if pid < 0 {
         // Negative return value indicates error
         match pid {
-            -1 => Err(SysError::Invalid), // Invalid address
+            -1 => Err(SysError::Invalid), // Invalid address or stack size
             -2 => Err(SysError::Error),   // Too many processes
+            -3 => Err(SysError::Error),   // No memory for the stack
             _ => Err(SysError::Error),    // Other error
         }
     } else {
-        // Add the new process to the scheduler's ready queue
-        crate::scheduler::enqueue_process(pid as u64);
-
-        // Update status to Ready
-        crate::process::set_process_status(pid as u64, crate::process::ProcessStatus::Ready);
-
-        // Return the process ID as success
+        // Already queued as Ready on one CPU by create_process_with_stack;
+        // queueing it again could run it on two CPUs at once
         Ok(pid as usize)
     }
 }
@@ -451,15 +540,17 @@ fn sys_task_create(
 ///
 /// # Arguments
 /// - arg1: Process ID to wait for
+/// - arg2: Timeout in milliseconds (0 = wait forever)
 /// - Others: Reserved
 ///
 /// # Returns
 /// - Ok(exit_code): Task's exit code when it completes
 /// - Err(SysError::NotFound): Task doesn't exist
 /// - Err(SysError::Invalid): Invalid task ID
+/// - Err(SysError::TimedOut): The task was still running at the timeout
 fn sys_task_wait(
     arg1: usize,
-    _arg2: usize,
+    timeout_ms: usize,
     _arg3: usize,
     _arg4: usize,
     _arg5: usize,
@@ -473,9 +564,9 @@ fn sys_task_wait(
     }
 
     // Wait for process to exit
-    match crate::process::wait_process(pid) {
-        Some(exit_code) => Ok(exit_code as usize),
-        None => Err(SysError::NotFound),
+    match crate::process::wait_process_until(pid, timeout_deadline(timeout_ms)) {
+        Ok(exit_code) => Ok(exit_code as usize),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -505,70 +596,59 @@ fn sys_get_pid(
 
 /// sys_ps - List all processes
 ///
-/// Returns information about all running processes.
-/// Writes process list to an output buffer (simplified version).
+/// Fills a userspace array with fixed-size `ProcessInfo` records
+/// (see `orbital_common::process`). The kernel does no formatting; `ps` and
+/// the management daemon render the records themselves.
+///
+/// Records are returned in ascending PID order starting after `cursor`.
+/// To page through a table larger than the buffer, call again with the PID
+/// of the last record received until 0 records are returned.
 ///
 /// # Arguments
-/// - arg1: Pointer to output buffer (userspace memory)
-/// - arg2: Buffer size in bytes
+/// - arg1: Pointer to an array of `ProcessInfo` (userspace memory)
+/// - arg2: Capacity of the array in records
+/// - arg3: Cursor - only processes with PID > cursor are returned (0 = start)
 /// - Others: Reserved
 ///
 /// # Returns
-/// - Ok(bytes_written): Number of bytes written to buffer
-/// - Err(SysError::Fault): Invalid pointer
-/// - Err(SysError::Invalid): Buffer too small
+/// - Ok(count): Number of records written
+/// - Err(SysError::Fault): NULL buffer pointer
+/// - Err(SysError::Invalid): Zero or oversized capacity
 fn sys_ps(
     buf_ptr: usize,
-    buf_len: usize,
-    _arg3: usize,
+    capacity: usize,
+    cursor: usize,
     _arg4: usize,
     _arg5: usize,
     _arg6: usize,
 ) -> SysResult {
-    // Validate buffer is not NULL
-    if buf_ptr == 0 || buf_len == 0 {
-        return Err(SysError::Invalid);
+    // Validate buffer
+    if buf_ptr == 0 {
+        return Err(SysError::Fault);
     }
-
-    // Get list of processes
-    let processes = crate::process::list_processes();
-
-    // Build a simple string representation (simplified - in real kernel, would be binary format)
-    let mut output = alloc::string::String::new();
-    output.push_str("PID Status\n");
-    for (pid, status) in processes {
-        let status_str = match status {
-            crate::process::ProcessStatus::Ready => "Ready",
-            crate::process::ProcessStatus::Running => "Running",
-            crate::process::ProcessStatus::Blocked => "Blocked",
-            crate::process::ProcessStatus::Exited(_) => "Exited",
-        };
-        output.push_str(&format!("{:3} {}\n", pid, status_str));
+    if capacity == 0 || capacity > PS_MAX_RECORDS {
+        return Err(SysError::Invalid);
     }
 
-    // Copy to userspace buffer
-    let output_bytes = output.as_bytes();
-    if output_bytes.len() > buf_len {
-        return Err(SysError::Invalid); // Buffer too small
-    }
+    let records = crate::process::process_info(cursor as u64, capacity);
 
     // In a real implementation, would validate buf_ptr is accessible from userspace
     // For now, assume it's valid
     unsafe {
         core::ptr::copy_nonoverlapping(
-            output_bytes.as_ptr(),
-            buf_ptr as *mut u8,
-            output_bytes.len(),
+            records.as_ptr(),
+            buf_ptr as *mut ProcessInfo,
+            records.len(),
         );
     }
 
-    Ok(output_bytes.len())
+    Ok(records.len())
 }
 
 /// sys_uptime - Get kernel uptime in seconds
 ///
-/// Returns the number of seconds since kernel boot, tracked from timer interrupts.
-/// Timer frequency is ~100 Hz, so each tick represents ~10ms.
+/// Returns the number of whole seconds since kernel boot, from the monotonic
+/// clock (see `clock`). Use `sys_clock_gettime` for finer resolution.
 ///
 /// # Arguments
 /// - None (all arguments ignored)
@@ -631,11 +711,699 @@ fn sys_run_ready(
     Ok(count as usize)
 }
 
+/// sys_thread_create - Create a thread in the calling process
+///
+/// The new thread shares the caller's memory and is scheduled
+/// independently. Its entry function receives `arg` and its return value
+/// becomes the thread's exit code.
+///
+/// # Arguments
+/// - arg1: Entry point address (`fn(usize) -> i64`)
+/// - arg2: Argument passed to the entry point
+/// - arg3: Stack reserve in bytes (0 for the default), grown on demand
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(tid): Thread ID of the new thread
+/// - Err(SysError::Invalid): NULL entry point or invalid stack size
+/// - Err(SysError::NotFound): No current process
+/// - Err(SysError::Error): Thread limit reached or no memory for the stack
+fn sys_thread_create(
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let entry_point = arg1;
+
+    if entry_point == 0 {
+        return Err(SysError::Invalid);
+    }
+
+    let pid = crate::scheduler::current_process().ok_or(SysError::NotFound)?;
+
+    match crate::process::create_thread(
+        pid,
+        entry_point,
+        arg2,
+        crate::stack::StackSpec::Growable(arg3),
+    ) {
+        -1 => Err(SysError::Invalid),
+        -5 => Err(SysError::NotFound),
+        tid if tid < 0 => Err(SysError::Error),
+        tid => Ok(tid as usize),
+    }
+}
+
+/// sys_thread_join - Wait for a thread to exit
+///
+/// Blocks until the given thread exits and returns its exit code.
+/// Only threads of the calling process can be joined.
+///
+/// # Arguments
+/// - arg1: Thread ID to join
+/// - arg2: Timeout in milliseconds (0 = wait forever)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(exit_code): The thread's exit code
+/// - Err(SysError::Invalid): TID 0 or the calling thread itself
+/// - Err(SysError::PermissionDenied): Thread belongs to another process
+/// - Err(SysError::NotFound): Thread doesn't exist
+/// - Err(SysError::TimedOut): The thread was still running at the timeout
+fn sys_thread_join(
+    arg1: usize,
+    timeout_ms: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let tid = arg1 as u64;
+
+    if tid == 0 || crate::scheduler::current_thread() == Some(tid) {
+        return Err(SysError::Invalid);
+    }
+
+    let owner = crate::process::thread_owner(tid).ok_or(SysError::NotFound)?;
+    let caller = crate::scheduler::current_process();
+    if caller.is_some_and(|pid| pid != owner) {
+        return Err(SysError::PermissionDenied);
+    }
+
+    match crate::process::join_thread_until(tid, timeout_deadline(timeout_ms)) {
+        Ok(exit_code) => Ok(exit_code as usize),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// sys_thread_exit - Terminate the calling thread
+///
+/// Other threads of the process keep running. The process exits when its
+/// last thread exits; use sys_exit to terminate the whole process.
+///
+/// # Arguments
+/// - arg1: Exit code
+///
+/// # Returns
+/// - Never (or NotFound if there is no current thread)
+fn sys_thread_exit(
+    arg1: usize,
+    _arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let exit_code = arg1 as i64;
+
+    if let Some(tid) = crate::scheduler::current_thread() {
+        crate::process::exit_thread(tid, exit_code);
+
+        // Same as sys_exit: the next timer interrupt schedules another thread
+        crate::scheduler::request_reschedule();
+        crate::hlt_loop();
+    }
+
+    Err(SysError::NotFound)
+}
+
+/// sys_get_tid - Get the current thread ID
+///
+/// For the main thread this equals the process ID.
+///
+/// # Returns
+/// - Ok(tid): Current thread ID
+/// - Err(SysError::NotFound): No thread is currently scheduled
+fn sys_get_tid(
+    _arg1: usize,
+    _arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    crate::scheduler::current_thread()
+        .map(|tid| tid as usize)
+        .ok_or(SysError::NotFound)
+}
+
+/// sys_arch_prctl - Get or set the calling thread's FS/GS base
+///
+/// The base is stored in the thread's saved context so it survives
+/// context switches, and loaded into the CPU immediately. This is how
+/// userspace installs its thread pointer for thread-local storage.
+///
+/// # Arguments
+/// - arg1: operation (`ARCH_SET_FS`, `ARCH_GET_FS`, `ARCH_SET_GS`, `ARCH_GET_GS`)
+/// - arg2: new base for SET, or pointer to a u64 receiving the base for GET
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::Invalid): Unknown operation or non-canonical base
+/// - Err(SysError::Fault): NULL output pointer
+/// - Err(SysError::NotFound): No thread is currently scheduled
+fn sys_arch_prctl(
+    code: usize,
+    addr: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    use arch_prctl::*;
+
+    match code {
+        ARCH_SET_FS | ARCH_SET_GS => {
+            if !crate::tls::is_valid_base(addr as u64) {
+                return Err(SysError::Invalid);
+            }
+            let tid = crate::scheduler::current_thread().ok_or(SysError::NotFound)?;
+            if code == ARCH_SET_FS {
+                crate::process::set_thread_fs_base(tid, addr as u64);
+                crate::tls::write_fs_base(addr as u64);
+            } else {
+                crate::process::set_thread_gs_base(tid, addr as u64);
+                crate::tls::write_gs_base(addr as u64);
+            }
+            Ok(0)
+        }
+        ARCH_GET_FS | ARCH_GET_GS => {
+            if addr == 0 {
+                return Err(SysError::Fault);
+            }
+            let base = if code == ARCH_GET_FS {
+                crate::tls::read_fs_base()
+            } else {
+                crate::tls::read_gs_base()
+            };
+            unsafe {
+                *(addr as *mut u64) = base;
+            }
+            Ok(0)
+        }
+        _ => Err(SysError::Invalid),
+    }
+}
+
+/// Split a NUL-separated argv buffer into its arguments
+///
+/// A trailing NUL after the last argument is optional.
+fn parse_argv(bytes: &[u8]) -> Result<Vec<&str>, SysError> {
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    bytes
+        .split(|&b| b == 0)
+        .map(|arg| core::str::from_utf8(arg).map_err(|_| SysError::Invalid))
+        .collect()
+}
+
+/// Copy a name argument (sys_spawn program, sys_set_name) from userspace
+fn read_name(ptr: usize, len: usize) -> Result<&'static str, SysError> {
+    if ptr == 0 {
+        return Err(SysError::Fault);
+    }
+    if len == 0 || len > PROCESS_NAME_LEN {
+        return Err(SysError::Invalid);
+    }
+
+    // In a real implementation, would validate ptr is accessible from userspace
+    let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, len) };
+    core::str::from_utf8(bytes).map_err(|_| SysError::Invalid)
+}
+
+/// sys_spawn - Start an embedded program as a child process
+///
+/// Loads the named program (see `binary_loader::find_program`) into a new
+/// process whose parent is the caller, and queues it to run. The parent
+/// collects the child's exit code with sys_wait.
+///
+/// The program is given as an argv buffer: NUL-separated UTF-8 strings
+/// whose first element is the program name. The arguments are stored with
+/// the process and shown by `ps`.
+///
+/// # Arguments
+/// - arg1: Pointer to the argv buffer (userspace memory)
+/// - arg2: Length of the buffer in bytes (1-`SPAWN_MAX_ARGV`)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(pid): Process ID of the child
+/// - Err(SysError::Fault): NULL argv pointer
+/// - Err(SysError::Invalid): Bad length, encoding or program name length
+/// - Err(SysError::NotFound): No program with that name
+/// - Err(SysError::Error): Loading failed or the process table is full
+fn sys_spawn(
+    argv_ptr: usize,
+    argv_len: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    if argv_ptr == 0 {
+        return Err(SysError::Fault);
+    }
+    if argv_len == 0 || argv_len > SPAWN_MAX_ARGV {
+        return Err(SysError::Invalid);
+    }
+
+    // In a real implementation, would validate argv_ptr is accessible from userspace
+    let argv = unsafe { core::slice::from_raw_parts(argv_ptr as *const u8, argv_len) };
+    let argv = parse_argv(argv)?;
+    let name = argv[0];
+    if name.is_empty() || name.len() > PROCESS_NAME_LEN {
+        return Err(SysError::Invalid);
+    }
+
+    let binary = crate::binary_loader::find_program(name).ok_or(SysError::NotFound)?;
+    let mut process =
+        crate::binary_loader::load_binary(binary, name).map_err(|_| SysError::Error)?;
+    process
+        .args
+        .extend(argv[1..].iter().map(|arg| String::from(*arg)));
+
+    let pid = crate::process::insert_process(process);
+    if pid < 0 {
+        return Err(SysError::Error);
+    }
+    crate::scheduler::enqueue_process(pid as u64);
+
+    Ok(pid as usize)
+}
+
+/// sys_set_name - Label the calling thread
+///
+/// On the main thread this renames the process, which is the name `ps`
+/// shows; the command line is left alone. Services use this to tell
+/// several instances of the same program apart.
+///
+/// # Arguments
+/// - arg1: Pointer to the name (UTF-8, userspace memory)
+/// - arg2: Length of the name in bytes (1-`PROCESS_NAME_LEN`)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::Fault): NULL name pointer
+/// - Err(SysError::Invalid): Bad name length or encoding
+/// - Err(SysError::NotFound): No thread is currently scheduled
+fn sys_set_name(
+    name_ptr: usize,
+    name_len: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let name = read_name(name_ptr, name_len)?;
+    let tid = crate::scheduler::current_thread().ok_or(SysError::NotFound)?;
+
+    if crate::process::set_thread_name(tid, name) {
+        Ok(0)
+    } else {
+        Err(SysError::NotFound)
+    }
+}
+
+/// sys_wait - Wait for a child process to exit and reap it
+///
+/// Reaping removes the child from the process table. Orphaned processes
+/// are adopted by init, so init eventually reaps everything.
+///
+/// # Arguments
+/// - arg1: PID of the child to wait for, or `WAIT_ANY` (-1) for any child
+/// - arg2: Pointer to an i64 receiving the exit code (may be NULL)
+/// - arg3: Flags (`WAIT_NOHANG`)
+/// - arg4: Timeout in milliseconds (0 = wait forever; ignored with `WAIT_NOHANG`)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(pid): PID of the reaped child
+/// - Ok(0): `WAIT_NOHANG` was given and no matching child has exited
+/// - Err(SysError::Invalid): PID 0 or unknown flags
+/// - Err(SysError::NotFound): No current process, or no matching child
+/// - Err(SysError::TimedOut): No matching child exited before the timeout
+fn sys_wait(
+    arg1: usize,
+    status_ptr: usize,
+    flags: usize,
+    timeout_ms: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    use crate::process::ChildStatus;
+
+    let pid = match arg1 as i64 {
+        WAIT_ANY => None,
+        pid if pid > 0 => Some(pid as u64),
+        _ => return Err(SysError::Invalid),
+    };
+    if flags as u64 & !WAIT_NOHANG != 0 {
+        return Err(SysError::Invalid);
+    }
+    let nohang = flags as u64 & WAIT_NOHANG != 0;
+
+    let parent = crate::scheduler::current_process().ok_or(SysError::NotFound)?;
+
+    let status = if nohang {
+        crate::process::try_reap_child(parent, pid)
+    } else {
+        crate::process::wait_child_until(parent, pid, timeout_deadline(timeout_ms))
+    };
+    match status {
+        ChildStatus::Exited { pid, exit_code } => {
+            if status_ptr != 0 {
+                // In a real implementation, would validate status_ptr is accessible from userspace
+                unsafe {
+                    *(status_ptr as *mut i64) = exit_code;
+                }
+            }
+            Ok(pid as usize)
+        }
+        ChildStatus::Running if nohang => Ok(0),
+        ChildStatus::Running => Err(SysError::TimedOut),
+        ChildStatus::NoChildren => Err(SysError::NotFound),
+    }
+}
+
+/// Resolve the PID argument of sys_setpriority/sys_getpriority (0 is the
+/// calling process)
+fn priority_target(pid: usize) -> Result<u64, SysError> {
+    let pid = match pid as u64 {
+        0 => crate::scheduler::current_process().ok_or(SysError::NotFound)?,
+        pid => pid,
+    };
+    crate::process::get_process(pid).ok_or(SysError::NotFound)?;
+    Ok(pid)
+}
+
+/// A process may change its own scheduling or a child's; the kernel itself
+/// (no current process) may change anything
+fn check_priority_permission(pid: u64) -> Result<(), SysError> {
+    let caller = crate::scheduler::current_process();
+    if caller.is_some_and(|caller| pid != caller && crate::process::get_parent(pid) != Some(caller))
+    {
+        return Err(SysError::PermissionDenied);
+    }
+    Ok(())
+}
+
+/// sys_setpriority - Set a process's nice value
+///
+/// Lower values are scheduled first (see `scheduler`). A process may change
+/// its own priority or a child's; the change applies to its threads from
+/// their next time slice.
+///
+/// # Arguments
+/// - arg1: PID, or 0 for the calling process
+/// - arg2: Nice value (as a signed integer), clamped to `NICE_MIN`..=`NICE_MAX`
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::PermissionDenied): Not the caller or one of its children
+/// - Err(SysError::NotFound): No such process, or no current process for PID 0
+fn sys_setpriority(
+    arg1: usize,
+    arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let pid = priority_target(arg1)?;
+    let nice = (arg2 as isize).clamp(NICE_MIN as isize, NICE_MAX as isize) as i32;
+    check_priority_permission(pid)?;
+
+    if crate::process::set_nice(pid, nice) {
+        Ok(0)
+    } else {
+        Err(SysError::NotFound)
+    }
+}
+
+/// sys_getpriority - Get a process's nice value
+///
+/// Like Linux, the value is returned as `20 - nice` (1-40) so that it
+/// cannot be mistaken for an error code.
+///
+/// # Arguments
+/// - arg1: PID, or 0 for the calling process
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(20 - nice): Success
+/// - Err(SysError::NotFound): No such process, or no current process for PID 0
+fn sys_getpriority(
+    arg1: usize,
+    _arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let pid = priority_target(arg1)?;
+    let nice = crate::process::get_nice(pid).ok_or(SysError::NotFound)?;
+    Ok((20 - nice) as usize)
+}
+
+/// sys_sched_setscheduler - Set a process's scheduling policy
+///
+/// `SCHED_FIFO` and `SCHED_RR` threads always run before `SCHED_OTHER`
+/// ones, highest real-time priority first, within the real-time throttle
+/// (`scheduler::RT_RUNTIME` of every `RT_PERIOD` ticks). The same
+/// permission rules as sys_setpriority apply.
+///
+/// # Arguments
+/// - arg1: PID, or 0 for the calling process
+/// - arg2: Policy (`SCHED_OTHER`, `SCHED_FIFO` or `SCHED_RR`)
+/// - arg3: Real-time priority (`RT_PRIORITY_MIN`..=`RT_PRIORITY_MAX`, 0 for `SCHED_OTHER`)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::Invalid): Unknown policy, or priority out of range for it
+/// - Err(SysError::PermissionDenied): Not the caller or one of its children
+/// - Err(SysError::NotFound): No such process, or no current process for PID 0
+fn sys_sched_setscheduler(
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let pid = priority_target(arg1)?;
+    let class = u32::try_from(arg2)
+        .ok()
+        .zip(u32::try_from(arg3).ok())
+        .and_then(|(policy, priority)| crate::scheduler::SchedClass::from_raw(policy, priority))
+        .ok_or(SysError::Invalid)?;
+    check_priority_permission(pid)?;
+
+    if crate::process::set_sched_class(pid, class) {
+        Ok(0)
+    } else {
+        Err(SysError::NotFound)
+    }
+}
+
+/// sys_sched_getscheduler - Get a process's scheduling policy
+///
+/// The real-time priority is reported in `ProcessInfo::rt_priority`.
+///
+/// # Arguments
+/// - arg1: PID, or 0 for the calling process
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(policy): `SCHED_OTHER`, `SCHED_FIFO` or `SCHED_RR`
+/// - Err(SysError::NotFound): No such process, or no current process for PID 0
+fn sys_sched_getscheduler(
+    arg1: usize,
+    _arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let pid = priority_target(arg1)?;
+    let class = crate::process::get_sched_class(pid).ok_or(SysError::NotFound)?;
+    Ok(class.policy() as usize)
+}
+
+/// Deadline for a blocking call's millisecond timeout argument
+/// (0 means no timeout)
+fn timeout_deadline(timeout_ms: usize) -> Option<u64> {
+    match timeout_ms {
+        0 => None,
+        ms => Some(crate::timer::deadline_after_ms(ms as u64)),
+    }
+}
+
+/// sys_sleep - Block the calling thread for a while
+///
+/// The thread does not run until the time has passed (see `timer`). With
+/// an HPET it is woken by a comparator interrupt when the time is up;
+/// otherwise by the first timer tick after (`clock::TICK_NS`).
+///
+/// # Arguments
+/// - arg1: Milliseconds to sleep (0 returns at once)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): The time has passed
+fn sys_sleep(
+    arg1: usize,
+    _arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    crate::timer::sleep_ms(arg1 as u64);
+    Ok(0)
+}
+
+/// sys_clock_gettime - Read a clock with nanosecond resolution
+///
+/// `CLOCK_MONOTONIC` counts from boot using the best clock source found:
+/// an invariant TSC, the HPET or the TSC (see `clock`); with none of them
+/// it advances in whole timer ticks.
+///
+/// # Arguments
+/// - arg1: Clock ID (`CLOCK_MONOTONIC`)
+/// - arg2: Pointer to a `Timespec` receiving the time (userspace memory)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::Invalid): Unknown clock
+/// - Err(SysError::Fault): NULL pointer
+fn sys_clock_gettime(
+    clock_id: usize,
+    ts_ptr: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    if clock_id != CLOCK_MONOTONIC as usize {
+        return Err(SysError::Invalid);
+    }
+    if ts_ptr == 0 {
+        return Err(SysError::Fault);
+    }
+
+    let now = Timespec::from_nanos(crate::clock::monotonic_ns());
+    // In a real implementation, would validate ts_ptr is accessible from userspace
+    unsafe {
+        core::ptr::write_unaligned(ts_ptr as *mut Timespec, now);
+    }
+    Ok(0)
+}
+
+/// sys_sched_stats - Read the scheduler's statistics
+///
+/// Fills a `SchedStats`: context switches (voluntary and involuntary),
+/// expired time slices, idle time summed over the CPUs and a histogram of
+/// how long threads waited Ready for a CPU. Every counter runs from boot;
+/// tools sample twice and take the difference. Per-process counts come
+/// with `sys_ps`.
+///
+/// # Arguments
+/// - arg1: Pointer to a `SchedStats` buffer (userspace memory)
+/// - arg2: Size of the buffer in bytes
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success
+/// - Err(SysError::Fault): NULL pointer
+/// - Err(SysError::Invalid): Buffer smaller than `SchedStats`
+fn sys_sched_stats(
+    buf_ptr: usize,
+    size: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    if buf_ptr == 0 {
+        return Err(SysError::Fault);
+    }
+    if size < core::mem::size_of::<SchedStats>() {
+        return Err(SysError::Invalid);
+    }
+
+    let stats = crate::scheduler::stats::snapshot();
+    // In a real implementation, would validate buf_ptr is accessible from userspace
+    unsafe {
+        core::ptr::write_unaligned(buf_ptr as *mut SchedStats, stats);
+    }
+    Ok(0)
+}
+
+/// sys_kill - Send a signal to a process
+///
+/// There are no signal handlers yet: `SIGKILL` and `SIGTERM` both end the
+/// process at once, reported as Killed by `ps` and as the negated signal
+/// number by `sys_wait`. Works on executor tasks as well as on processes
+/// with threads. Signal 0 only checks that the process is alive.
+///
+/// # Arguments
+/// - arg1: Process ID
+/// - arg2: Signal (`SIGKILL`, `SIGTERM` or 0)
+/// - Others: Reserved
+///
+/// # Returns
+/// - Ok(0): Success (does not return if the caller killed itself)
+/// - Err(SysError::Invalid): PID 0 or an unsupported signal
+/// - Err(SysError::PermissionDenied): The target is init
+/// - Err(SysError::NotFound): No such process, or it has already exited
+fn sys_kill(
+    arg1: usize,
+    arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+    _arg6: usize,
+) -> SysResult {
+    let pid = arg1 as u64;
+    let signal = u32::try_from(arg2).map_err(|_| SysError::Invalid)?;
+    if pid == 0 || !matches!(signal, 0 | SIGKILL | SIGTERM) {
+        return Err(SysError::Invalid);
+    }
+
+    if signal == 0 {
+        return match crate::process::get_process_status(pid) {
+            Some(crate::process::ProcessStatus::Exited(_)) | None => Err(SysError::NotFound),
+            Some(_) => Ok(0),
+        };
+    }
+    if pid == INIT_PID {
+        return Err(SysError::PermissionDenied);
+    }
+    if !crate::process::kill_process(pid, signal) {
+        return Err(SysError::NotFound);
+    }
+
+    // A thread that killed its own process stops here, as in sys_exit
+    if crate::scheduler::current_thread().is_some()
+        && crate::scheduler::current_process() == Some(pid)
+    {
+        crate::hlt_loop();
+    }
+    Ok(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
-    #[test]
+    #[test_case]
     fn test_syscall_hello() {
         // Valid magic number
         let result = sys_hello(0xCAFEBABE, 0, 0, 0, 0, 0);
@@ -646,7 +1414,7 @@ mod tests {
         assert_eq!(result, Err(SysError::Invalid));
     }
 
-    #[test]
+    #[test_case]
     fn test_syscall_log() {
         // Valid length
         let result = sys_log(0x1000, 10, 0, 0, 0, 0);
@@ -657,11 +1425,11 @@ mod tests {
         assert_eq!(result, Err(SysError::Invalid));
 
         // Too long
-        let result = sys_log(0x1000, 2000, 0, 0, 0, 0);
+        let result = sys_log(0x1000, 4097, 0, 0, 0, 0);
         assert_eq!(result, Err(SysError::Invalid));
     }
 
-    #[test]
+    #[test_case]
     fn test_dispatch_table() {
         // Valid syscall number
         let result = dispatch_syscall(nr::SYS_HELLO, 0xCAFEBABE, 0, 0, 0, 0, 0);
@@ -672,7 +1440,7 @@ mod tests {
         assert_eq!(result, SysError::NotImplemented.to_return_value());
     }
 
-    #[test]
+    #[test_case]
     fn test_syscall_write() {
         // Valid fd (1 = stdout)
         let result = sys_write(1, 0x1000, 10, 0, 0, 0);
@@ -699,11 +1467,294 @@ mod tests {
         assert_eq!(result, Err(SysError::Fault));
     }
 
-    #[test]
+    #[test_case]
+    fn test_syscall_ps() {
+        let pid = crate::process::create_process(0x1000) as usize;
+        let mut records = [ProcessInfo::empty(); 4];
+        let ptr = records.as_mut_ptr() as usize;
+
+        // Cursor just below the new process returns it first
+        let result = sys_ps(ptr, records.len(), pid - 1, 0, 0, 0);
+        assert!(matches!(result, Ok(n) if n >= 1));
+        assert_eq!(records[0].pid, pid as u64);
+
+        // NULL buffer
+        let result = sys_ps(0, 4, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Fault));
+
+        // Zero capacity
+        let result = sys_ps(ptr, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+    }
+
+    #[test_case]
+    fn test_read_validates_arguments() {
+        let mut buf = [0u8; 8];
+        let ptr = buf.as_mut_ptr() as usize;
+        assert_eq!(sys_read(1, ptr, 8, 0, 0, 0), Err(SysError::BadFd));
+        assert_eq!(sys_read(0, ptr, 8, 0x1, 0, 0), Err(SysError::Invalid));
+        assert_eq!(sys_read(0, ptr, 0, 0, 0, 0), Ok(0));
+
+        // A non-blocking read of empty stdin fails rather than waiting
+        while crate::input::read_input(&mut buf) > 0 {}
+        assert_eq!(
+            sys_read(0, ptr, 8, O_NONBLOCK as usize, 0, 0),
+            Err(SysError::WouldBlock)
+        );
+    }
+
+    #[test_case]
+    fn test_thread_syscalls_validate_arguments() {
+        // NULL entry point
+        let result = sys_thread_create(0, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // TID 0
+        let result = sys_thread_join(0, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // Reserved slot between sys_run_ready and the thread syscalls
+        let result = dispatch_syscall(12, 0, 0, 0, 0, 0, 0);
+        assert_eq!(result, SysError::NotImplemented.to_return_value());
+    }
+
+    #[test_case]
+    fn test_arch_prctl_validates_arguments() {
+        // Unknown operation
+        let result = sys_arch_prctl(0, 0x1000, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // Non-canonical base
+        let result = sys_arch_prctl(arch_prctl::ARCH_SET_FS, 0x8000_0000_0000_0000, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // NULL output pointer
+        let result = sys_arch_prctl(arch_prctl::ARCH_GET_FS, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Fault));
+
+        // Reading the base back
+        let mut base = 0u64;
+        let result = sys_arch_prctl(
+            arch_prctl::ARCH_GET_FS,
+            &mut base as *mut u64 as usize,
+            0,
+            0,
+            0,
+            0,
+        );
+        assert_eq!(result, Ok(0));
+        assert_eq!(base, crate::tls::read_fs_base());
+    }
+
+    #[test_case]
+    fn test_spawn_and_wait_validate_arguments() {
+        let argv = b"no-such-program\0--verbose\0";
+
+        // NULL argv
+        let result = sys_spawn(0, 4, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Fault));
+
+        // Empty and oversized buffers
+        let result = sys_spawn(argv.as_ptr() as usize, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+        let big = [b'a'; SPAWN_MAX_ARGV + 1];
+        let result = sys_spawn(big.as_ptr() as usize, big.len(), 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // Program name empty or longer than PROCESS_NAME_LEN
+        let result = sys_spawn(b"\0shell".as_ptr() as usize, 6, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+        let long = [b'a'; PROCESS_NAME_LEN + 1];
+        let result = sys_spawn(long.as_ptr() as usize, long.len(), 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+
+        // Unknown program
+        let result = sys_spawn(argv.as_ptr() as usize, argv.len(), 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::NotFound));
+
+        // PID 0 and unknown flags
+        let result = sys_wait(0, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+        let result = sys_wait(WAIT_ANY as usize, 0, 0x80, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+    }
+
+    #[test_case]
+    fn test_parse_argv() {
+        assert_eq!(parse_argv(b"shell"), Ok(vec!["shell"]));
+        assert_eq!(
+            parse_argv(b"shell\0-c\0ps\0"),
+            Ok(vec!["shell", "-c", "ps"])
+        );
+        assert_eq!(parse_argv(b"shell\0\0x"), Ok(vec!["shell", "", "x"]));
+        assert_eq!(parse_argv(b"shell\0\xff"), Err(SysError::Invalid));
+    }
+
+    #[test_case]
+    fn test_set_name_validates_arguments() {
+        let name = "worker";
+
+        let result = sys_set_name(0, name.len(), 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Fault));
+        let result = sys_set_name(name.as_ptr() as usize, 0, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+        let long = [b'a'; PROCESS_NAME_LEN + 1];
+        let result = sys_set_name(long.as_ptr() as usize, long.len(), 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+        let result = sys_set_name(b"\xff".as_ptr() as usize, 1, 0, 0, 0, 0);
+        assert_eq!(result, Err(SysError::Invalid));
+    }
+
+    #[test_case]
+    fn test_priority_round_trip() {
+        let pid = crate::process::create_process(0x1000) as usize;
+
+        assert_eq!(sys_setpriority(pid, 10, 0, 0, 0, 0), Ok(0));
+        assert_eq!(sys_getpriority(pid, 0, 0, 0, 0, 0), Ok(10));
+        assert_eq!(sys_setpriority(pid, (-5isize) as usize, 0, 0, 0, 0), Ok(0));
+        assert_eq!(sys_getpriority(pid, 0, 0, 0, 0, 0), Ok(25));
+
+        // Clamped to the valid range
+        assert_eq!(
+            sys_setpriority(pid, (-100isize) as usize, 0, 0, 0, 0),
+            Ok(0)
+        );
+        assert_eq!(sys_getpriority(pid, 0, 0, 0, 0, 0), Ok(40));
+
+        assert_eq!(
+            sys_getpriority(usize::MAX, 0, 0, 0, 0, 0),
+            Err(SysError::NotFound)
+        );
+    }
+
+    #[test_case]
+    fn test_sched_policy_round_trip() {
+        use orbital_common::process::{SCHED_FIFO, SCHED_OTHER, SCHED_RR};
+        let pid = crate::process::create_process(0x1000) as usize;
+
+        assert_eq!(
+            sys_sched_getscheduler(pid, 0, 0, 0, 0, 0),
+            Ok(SCHED_OTHER as usize)
+        );
+        assert_eq!(
+            sys_sched_setscheduler(pid, SCHED_RR as usize, 20, 0, 0, 0),
+            Ok(0)
+        );
+        assert_eq!(
+            sys_sched_getscheduler(pid, 0, 0, 0, 0, 0),
+            Ok(SCHED_RR as usize)
+        );
+
+        // Real-time policies need a priority in range; SCHED_OTHER takes none
+        for (policy, priority) in [(SCHED_FIFO, 0), (SCHED_FIFO, 100), (SCHED_OTHER, 5), (9, 1)] {
+            assert_eq!(
+                sys_sched_setscheduler(pid, policy as usize, priority, 0, 0, 0),
+                Err(SysError::Invalid)
+            );
+        }
+        assert_eq!(
+            sys_sched_setscheduler(pid, SCHED_OTHER as usize, 0, 0, 0, 0),
+            Ok(0)
+        );
+        assert_eq!(
+            sys_sched_getscheduler(pid, 0, 0, 0, 0, 0),
+            Ok(SCHED_OTHER as usize)
+        );
+    }
+
+    #[test_case]
     fn test_error_codes() {
         assert_eq!(SysError::Invalid.to_return_value(), -1);
         assert_eq!(SysError::NotImplemented.to_return_value(), -2);
         assert_eq!(SysError::Fault.to_return_value(), -3);
+        assert_eq!(SysError::TimedOut.to_return_value(), -7);
+        assert_eq!(SysError::from_code(-7), Some(SysError::TimedOut));
         assert_eq!(SysError::BadFd.to_return_value(), -9);
+        assert_eq!(SysError::WouldBlock.to_return_value(), -11);
+        assert_eq!(SysError::from_code(-11), Some(SysError::WouldBlock));
+    }
+
+    #[test_case]
+    fn test_sleep_and_timeouts() {
+        assert_eq!(sys_sleep(0, 0, 0, 0, 0, 0), Ok(0));
+        assert_eq!(timeout_deadline(0), None);
+        let before = crate::timer::now();
+        assert!(timeout_deadline(25).is_some_and(|deadline| deadline >= before + 25_000_000));
+
+        // Missing tasks are reported at once, timeout or not
+        assert_eq!(
+            sys_task_wait(usize::MAX, 50, 0, 0, 0, 0),
+            Err(SysError::NotFound)
+        );
+    }
+
+    #[test_case]
+    fn test_clock_gettime() {
+        let mut first = Timespec::default();
+        let mut second = Timespec::default();
+        let clock = CLOCK_MONOTONIC as usize;
+        assert_eq!(
+            sys_clock_gettime(clock, &mut first as *mut Timespec as usize, 0, 0, 0, 0),
+            Ok(0)
+        );
+        assert_eq!(
+            sys_clock_gettime(clock, &mut second as *mut Timespec as usize, 0, 0, 0, 0),
+            Ok(0)
+        );
+        assert!(second >= first);
+        assert!(first.tv_nsec < orbital_common::time::NANOS_PER_SEC as i64);
+
+        assert_eq!(
+            sys_clock_gettime(clock, 0, 0, 0, 0, 0),
+            Err(SysError::Fault)
+        );
+        let mut ts = Timespec::default();
+        assert_eq!(
+            sys_clock_gettime(0, &mut ts as *mut Timespec as usize, 0, 0, 0, 0),
+            Err(SysError::Invalid)
+        );
+    }
+
+    #[test_case]
+    fn test_kill() {
+        let pid = crate::process::create_process(0x1000) as usize;
+        assert_eq!(sys_kill(pid, 0, 0, 0, 0, 0), Ok(0));
+        assert_eq!(sys_kill(pid, SIGTERM as usize, 0, 0, 0, 0), Ok(0));
+        assert_eq!(
+            crate::process::wait_process(pid as u64),
+            Some(-(SIGTERM as i64))
+        );
+        assert_eq!(sys_kill(pid, 0, 0, 0, 0, 0), Err(SysError::NotFound));
+        assert_eq!(
+            sys_kill(pid, SIGKILL as usize, 0, 0, 0, 0),
+            Err(SysError::NotFound)
+        );
+
+        assert_eq!(
+            sys_kill(INIT_PID as usize, SIGKILL as usize, 0, 0, 0, 0),
+            Err(SysError::PermissionDenied)
+        );
+        assert_eq!(
+            sys_kill(0, SIGKILL as usize, 0, 0, 0, 0),
+            Err(SysError::Invalid)
+        );
+        assert_eq!(sys_kill(pid, 64, 0, 0, 0, 0), Err(SysError::Invalid));
+    }
+
+    #[test_case]
+    fn test_sched_stats() {
+        let mut stats = SchedStats::empty();
+        let size = core::mem::size_of::<SchedStats>();
+        let ptr = &mut stats as *mut SchedStats as usize;
+        assert_eq!(sys_sched_stats(ptr, size, 0, 0, 0, 0), Ok(0));
+        assert_eq!(stats.version, orbital_common::sched::SCHED_STATS_VERSION);
+        assert!(stats.cpus >= 1);
+        assert_eq!(stats.policy(), crate::scheduler::policy_name());
+
+        assert_eq!(sys_sched_stats(0, size, 0, 0, 0, 0), Err(SysError::Fault));
+        assert_eq!(
+            sys_sched_stats(ptr, size - 1, 0, 0, 0, 0),
+            Err(SysError::Invalid)
+        );
     }
 }
@@ -35,34 +35,29 @@ pub async fn shell() {
     }
 }
 
-/// Read a line from the input buffer (blocks until newline)
+/// Read a line from the input buffer (waits until newline)
 async fn read_line() -> String {
     let mut line = String::new();
 
     loop {
-        // Read from input buffer
+        // Wait for input; other tasks run meanwhile
         let mut buf = [0u8; 256];
-        let n = crate::input::read_input(&mut buf);
+        let n = crate::input::read(&mut buf).await;
 
-        if n > 0 {
-            for i in 0..n {
-                let ch = buf[i] as char;
-                match ch {
-                    '\n' => {
-                        return line;
-                    }
-                    '\u{0008}' => {
-                        // Backspace
-                        line.pop();
-                    }
-                    _ => {
-                        line.push(ch);
-                    }
+        for &byte in &buf[..n] {
+            let ch = byte as char;
+            match ch {
+                '\n' => {
+                    return line;
+                }
+                '\u{0008}' => {
+                    // Backspace
+                    line.pop();
+                }
+                _ => {
+                    line.push(ch);
                 }
             }
         }
-
-        // Yield to other tasks
-        crate::task::keyboard::ScancodeStream::new();
     }
 }
@@ -1,29 +1,104 @@
+//! Async executor for the kernel's own tasks
+//!
+//! Tasks are queued by their wakers, which run in interrupt handlers as
+//! often as in tasks. A waker must therefore neither allocate nor spin on
+//! a lock the interrupted code holds: each task is queued at most once at a
+//! time (`TaskWaker::queued`), the queue keeps room for every task on top
+//! of what it holds, reserved when a task is spawned, and the executor only
+//! takes the queue's lock with interrupts off. The queue thus grows with
+//! the number of tasks and can never be full.
+
+use super::join::{JoinHandle, joinable};
 use super::{Task, TaskId};
-use alloc::{collections::BTreeMap, sync::Arc, task::Wake};
-use core::task::{Context, Poll, Waker};
-use crossbeam_queue::ArrayQueue;
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    sync::Arc,
+    task::Wake,
+    vec::Vec,
+};
+use core::cell::RefCell;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Waker};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+/// Tasks waiting to be polled, in the order they were woken
+struct TaskQueue {
+    ids: Mutex<VecDeque<TaskId>>,
+    /// Live tasks, each of which may be queued once
+    tasks: AtomicUsize,
+}
+
+impl TaskQueue {
+    fn new() -> Self {
+        TaskQueue {
+            ids: Mutex::new(VecDeque::new()),
+            tasks: AtomicUsize::new(0),
+        }
+    }
+
+    /// Make room for one more task to be queued
+    fn add_task(&self) {
+        let tasks = self.tasks.fetch_add(1, Ordering::Relaxed) + 1;
+        interrupts::without_interrupts(|| self.ids.lock().reserve(tasks));
+    }
+
+    fn remove_task(&self) {
+        self.tasks.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn push(&self, task_id: TaskId) {
+        interrupts::without_interrupts(|| self.ids.lock().push_back(task_id));
+    }
+
+    fn pop(&self) -> Option<TaskId> {
+        interrupts::without_interrupts(|| self.ids.lock().pop_front())
+    }
+
+    fn is_empty(&self) -> bool {
+        interrupts::without_interrupts(|| self.ids.lock().is_empty())
+    }
+}
+
+/// Tasks spawned but not yet picked up by the executor
+///
+/// Only touched by the executor and the tasks it polls, never by wakers.
+type Spawned = RefCell<Vec<(Task, Arc<TaskWaker>)>>;
 
 pub struct Executor {
     tasks: BTreeMap<TaskId, Task>,
-    task_queue: Arc<ArrayQueue<TaskId>>,
-    waker_cache: BTreeMap<TaskId, Waker>,
+    waker_cache: BTreeMap<TaskId, Arc<TaskWaker>>,
+    spawner: Spawner,
 }
 
 impl Executor {
     pub fn new() -> Self {
         Executor {
             tasks: BTreeMap::new(),
-            task_queue: Arc::new(ArrayQueue::new(100)),
             waker_cache: BTreeMap::new(),
+            spawner: Spawner {
+                queue: Arc::new(TaskQueue::new()),
+                spawned: Rc::new(RefCell::new(Vec::new())),
+            },
         }
     }
 
-    pub fn spawn(&mut self, task: Task) {
-        let task_id = task.id;
-        if self.tasks.insert(task.id, task).is_some() {
-            panic!("task with same ID already in tasks");
-        }
-        self.task_queue.push(task_id).expect("queue full");
+    /// Spawn a task, registered as a process named `name`
+    ///
+    /// See `Spawner::spawn`.
+    pub fn spawn<T: 'static>(
+        &mut self,
+        name: &str,
+        future: impl Future<Output = T> + 'static,
+    ) -> JoinHandle<T> {
+        self.spawner.spawn(name, future)
+    }
+
+    /// Handle for spawning tasks onto this executor from its tasks
+    pub fn spawner(&self) -> Spawner {
+        self.spawner.clone()
     }
 
     pub fn run(&mut self) -> ! {
@@ -33,61 +108,135 @@ impl Executor {
         }
     }
 
-    fn run_ready_tasks(&mut self) {
-        // destructure `self` to avoid borrow checker errors
-        let Self {
-            tasks,
-            task_queue,
-            waker_cache,
-        } = self;
+    /// Take over the tasks spawned since the last call
+    fn adopt_spawned(&mut self) {
+        let spawned = self.spawner.spawned.take();
+        for (task, waker) in spawned {
+            self.waker_cache.insert(task.id, waker);
+            if self.tasks.insert(task.id, task).is_some() {
+                panic!("task with same ID already in tasks");
+            }
+        }
+    }
 
-        while let Some(task_id) = task_queue.pop() {
-            let task = match tasks.get_mut(&task_id) {
+    /// Drop a task that finished or was killed
+    fn remove_task(&mut self, task_id: TaskId) {
+        self.tasks.remove(&task_id);
+        if let Some(waker) = self.waker_cache.remove(&task_id) {
+            waker.retire();
+        }
+        self.spawner.queue.remove_task();
+    }
+
+    fn run_ready_tasks(&mut self) {
+        let cpu = crate::percpu::this_cpu();
+        while let Some(task_id) = self.spawner.queue.pop() {
+            if !self.tasks.contains_key(&task_id) {
+                // queued before the executor picked it up, or gone
+                self.adopt_spawned();
+            }
+            let task = match self.tasks.get_mut(&task_id) {
                 Some(task) => task,
                 None => continue, // task no longer exists
             };
-            let waker = waker_cache
-                .entry(task_id)
-                .or_insert_with(|| TaskWaker::new(task_id, task_queue.clone()));
-            let mut context = Context::from_waker(waker);
-            match task.poll(&mut context) {
-                Poll::Ready(()) => {
-                    // task done -> remove it and its cached waker
-                    tasks.remove(&task_id);
-                    waker_cache.remove(&task_id);
-                }
-                Poll::Pending => {}
+            if task
+                .pid
+                .is_some_and(|pid| !crate::process::start_task_poll(pid))
+            {
+                // task killed -> drop it without polling again
+                self.remove_task(task_id);
+                continue;
+            }
+            let task_waker = &self.waker_cache[&task_id];
+            task_waker.queued.store(false, Ordering::Release);
+            let waker = Waker::from(task_waker.clone());
+            let mut context = Context::from_waker(&waker);
+            cpu.set_task(task.pid);
+            let poll = task.poll(&mut context);
+            cpu.set_task(None);
+            if let Some(pid) = task.pid {
+                crate::process::finish_task_poll(pid, poll.is_ready());
+            }
+            if poll.is_ready() {
+                // task done -> remove it and its cached waker
+                self.remove_task(task_id);
             }
         }
     }
 
     fn sleep_if_idle(&self) {
-        use x86_64::instructions::interrupts::{self, enable_and_hlt};
-
         interrupts::disable();
-        if self.task_queue.is_empty() {
-            enable_and_hlt();
+        if self.spawner.queue.is_empty() {
+            crate::percpu::this_cpu().idle_hlt();
         } else {
             interrupts::enable();
         }
     }
 }
 
+/// Spawns tasks onto an `Executor`
+///
+/// Kept by tasks that start others; the executor itself lives on the
+/// stack of `kernel_main`.
+#[derive(Clone)]
+pub struct Spawner {
+    queue: Arc<TaskQueue>,
+    spawned: Rc<Spawned>,
+}
+
+impl Spawner {
+    /// Queue a task, registered as a process named `name`
+    ///
+    /// The returned handle gives the task's PID, which is `None` if the
+    /// process table is full (the task still runs, but `ps` does not show
+    /// it), and is awaited for its output.
+    pub fn spawn<T: 'static>(
+        &self,
+        name: &str,
+        future: impl Future<Output = T> + 'static,
+    ) -> JoinHandle<T> {
+        let (future, mut handle) = joinable(future);
+        let mut task = Task::new(name, future);
+        let waker = TaskWaker::new(task.id, self.queue.clone());
+        let pid = crate::process::register_task(&task.name, Waker::from(waker.clone()));
+        task.pid = u64::try_from(pid).ok();
+        handle.set_pid(task.pid);
+
+        self.queue.add_task();
+        self.spawned.borrow_mut().push((task, waker.clone()));
+        waker.wake_task();
+        handle
+    }
+}
+
 struct TaskWaker {
     task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    task_queue: Arc<TaskQueue>,
+    /// The task is in the queue, or gone and never to be queued again
+    queued: AtomicBool,
 }
 
 impl TaskWaker {
-    fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
-        Waker::from(Arc::new(TaskWaker {
+    fn new(task_id: TaskId, task_queue: Arc<TaskQueue>) -> Arc<TaskWaker> {
+        Arc::new(TaskWaker {
             task_id,
             task_queue,
-        }))
+            queued: AtomicBool::new(false),
+        })
     }
 
     fn wake_task(&self) {
-        self.task_queue.push(self.task_id).expect("task_queue full");
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            self.task_queue.push(self.task_id);
+        }
+    }
+
+    /// Stop queueing the task, which the executor dropped
+    ///
+    /// Its waker lives on in timers and in its process record, but room in
+    /// the queue is only kept for live tasks.
+    fn retire(&self) {
+        self.queued.store(true, Ordering::Release);
     }
 }
 
@@ -100,3 +249,45 @@ impl Wake for TaskWaker {
         self.wake_task();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_task_queued_once() {
+        let queue = Arc::new(TaskQueue::new());
+        queue.add_task();
+        let waker = Waker::from(TaskWaker::new(TaskId::new(), queue.clone()));
+
+        waker.wake_by_ref();
+        waker.wake_by_ref();
+        assert!(queue.pop().is_some());
+        assert!(queue.is_empty());
+    }
+
+    #[test_case]
+    fn test_queue_keeps_room_for_every_task() {
+        let queue = Arc::new(TaskQueue::new());
+        let wakers: Vec<Arc<TaskWaker>> = (0..300)
+            .map(|_| {
+                queue.add_task();
+                TaskWaker::new(TaskId::new(), queue.clone())
+            })
+            .collect();
+        let capacity = queue.ids.lock().capacity();
+        for waker in &wakers {
+            waker.wake_task();
+        }
+        // Waking every task never grew the queue
+        assert_eq!(queue.ids.lock().capacity(), capacity);
+
+        // A retired waker queues nothing
+        let retired = &wakers[0];
+        assert!(queue.pop().is_some());
+        retired.queued.store(false, Ordering::Release);
+        retired.retire();
+        retired.wake_task();
+        assert_eq!(queue.ids.lock().len(), 299);
+    }
+}
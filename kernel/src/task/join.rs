@@ -0,0 +1,167 @@
+//! Awaiting and cancelling spawned tasks
+//!
+//! `Executor::spawn` wraps a task's future in `Joinable`, which keeps the
+//! output for the task's `JoinHandle`. Awaiting the handle gives the output
+//! once the task finishes, or `None` if it was cancelled or killed: the
+//! handle's waker is woken whenever the task is dropped, however it ends.
+//! Dropping a handle detaches the task, which runs on.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+use futures_util::task::AtomicWaker;
+use orbital_common::process::SIGKILL;
+use spin::Mutex;
+
+/// What a task and its handle share
+struct JoinState<T> {
+    /// The task's output, until the handle takes it
+    output: Mutex<Option<T>>,
+    /// The task was dropped, finished or not
+    dropped: AtomicBool,
+    /// `JoinHandle::cancel` was called
+    cancelled: AtomicBool,
+    /// Wakes whoever awaits the handle
+    joiner: AtomicWaker,
+    /// Wakes the task, so the executor sees the cancellation
+    task: AtomicWaker,
+}
+
+/// Handle to a spawned task, awaited for its output
+pub struct JoinHandle<T> {
+    state: Arc<JoinState<T>>,
+    pid: Option<u64>,
+}
+
+impl<T> JoinHandle<T> {
+    /// PID the task is registered under, if the process table had room
+    pub fn pid(&self) -> Option<u64> {
+        self.pid
+    }
+
+    pub(super) fn set_pid(&mut self, pid: Option<u64>) {
+        self.pid = pid;
+    }
+
+    /// Whether the task is gone, finished or not
+    pub fn is_finished(&self) -> bool {
+        self.state.dropped.load(Ordering::Acquire)
+    }
+
+    /// Stop the task: it is dropped without being polled again
+    ///
+    /// The task's process exits as if killed with `SIGKILL`. Does nothing
+    /// if the task already finished.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Release);
+        if let Some(pid) = self.pid {
+            crate::process::kill_process(pid, SIGKILL);
+        }
+        self.state.task.wake();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    /// The task's output, or `None` if it was cancelled or killed
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        self.state.joiner.register(cx.waker());
+        // The output is stored before the task is dropped
+        if let Some(output) = self.state.output.lock().take() {
+            return Poll::Ready(Some(output));
+        }
+        if self.state.dropped.load(Ordering::Acquire) {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+}
+
+/// A task's future, keeping its output for the `JoinHandle`
+pub(super) struct Joinable<F: Future> {
+    future: Pin<Box<F>>,
+    state: Arc<JoinState<F::Output>>,
+}
+
+/// Wrap `future` for spawning, with the handle to await it by
+pub(super) fn joinable<F: Future>(future: F) -> (Joinable<F>, JoinHandle<F::Output>) {
+    let state = Arc::new(JoinState {
+        output: Mutex::new(None),
+        dropped: AtomicBool::new(false),
+        cancelled: AtomicBool::new(false),
+        joiner: AtomicWaker::new(),
+        task: AtomicWaker::new(),
+    });
+    let handle = JoinHandle {
+        state: state.clone(),
+        pid: None,
+    };
+    let future = Joinable {
+        future: Box::pin(future),
+        state,
+    };
+    (future, handle)
+}
+
+impl<F: Future> Future for Joinable<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.state.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        self.state.task.register(cx.waker());
+        match self.future.as_mut().poll(cx) {
+            Poll::Ready(output) => {
+                *self.state.output.lock() = Some(output);
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F: Future> Drop for Joinable<F> {
+    fn drop(&mut self) {
+        self.state.dropped.store(true, Ordering::Release);
+        self.state.joiner.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::task::Waker;
+
+    #[test_case]
+    fn test_join_output() {
+        let mut context = Context::from_waker(Waker::noop());
+        let (mut task, mut handle) = joinable(async { 42 });
+
+        assert_eq!(Pin::new(&mut handle).poll(&mut context), Poll::Pending);
+        assert_eq!(Pin::new(&mut task).poll(&mut context), Poll::Ready(()));
+        drop(task);
+        assert!(handle.is_finished());
+        assert_eq!(
+            Pin::new(&mut handle).poll(&mut context),
+            Poll::Ready(Some(42))
+        );
+    }
+
+    #[test_case]
+    fn test_join_cancelled() {
+        let mut context = Context::from_waker(Waker::noop());
+        let (mut task, mut handle) = joinable(core::future::pending::<u8>());
+
+        assert_eq!(Pin::new(&mut task).poll(&mut context), Poll::Pending);
+        handle.cancel();
+        // The cancelled future is not polled again
+        assert_eq!(Pin::new(&mut task).poll(&mut context), Poll::Ready(()));
+        drop(task);
+        assert_eq!(Pin::new(&mut handle).poll(&mut context), Poll::Ready(None));
+    }
+}
@@ -1,4 +1,5 @@
 use alloc::boxed::Box;
+use alloc::string::String;
 use core::{
     future::Future,
     pin::Pin,
@@ -8,19 +9,27 @@ use core::{
 
 pub mod cli;
 pub mod executor;
+pub mod join;
 pub mod keyboard;
 pub mod simple_executor;
+pub mod sleep;
 pub mod terminal;
 
 pub struct Task {
     id: TaskId,
+    /// Process name the task is registered under by `Executor::spawn`
+    name: String,
+    /// PID of the task's process, once registered
+    pid: Option<u64>,
     future: Pin<Box<dyn Future<Output = ()>>>,
 }
 
 impl Task {
-    pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+    pub fn new(name: &str, future: impl Future<Output = ()> + 'static) -> Task {
         Task {
             id: TaskId::new(),
+            name: String::from(name),
+            pid: None,
             future: Box::pin(future),
         }
     }
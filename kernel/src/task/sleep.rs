@@ -0,0 +1,84 @@
+//! Async sleep
+//!
+//! `sleep` is the executor's counterpart of `timer::sleep_ms`: instead of
+//! blocking a thread it leaves a waker with the timer (`timer::wake_at`),
+//! and the timer interrupt queues the task again once the deadline passes.
+//! Other tasks run in the meantime.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+/// Future that completes at a deadline, in `clock::monotonic_ns`
+/// nanoseconds
+pub struct Sleep {
+    deadline: u64,
+    /// Timer registered with `timer::wake_at`, once polled
+    timer: Option<u64>,
+}
+
+/// Complete after `duration`
+pub fn sleep(duration: Duration) -> Sleep {
+    let ns = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+    sleep_until(crate::timer::now().saturating_add(ns))
+}
+
+/// Complete at `deadline`
+pub fn sleep_until(deadline: u64) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+impl Sleep {
+    /// When the sleep completes
+    pub fn deadline(&self) -> u64 {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if crate::timer::now() >= self.deadline {
+            if let Some(timer) = self.timer.take() {
+                crate::timer::cancel_wake(timer);
+            }
+            return Poll::Ready(());
+        }
+        let timer = crate::timer::wake_at(self.deadline, cx.waker(), self.timer);
+        self.timer = Some(timer);
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(timer) = self.timer {
+            crate::timer::cancel_wake(timer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::task::Waker;
+
+    #[test_case]
+    fn test_sleep() {
+        let mut context = Context::from_waker(Waker::noop());
+
+        let mut done = sleep(Duration::ZERO);
+        assert_eq!(Pin::new(&mut done).poll(&mut context), Poll::Ready(()));
+
+        let mut pending = sleep(Duration::from_secs(60));
+        assert_eq!(Pin::new(&mut pending).poll(&mut context), Poll::Pending);
+        assert!(pending.timer.is_some());
+        assert_eq!(Pin::new(&mut pending).poll(&mut context), Poll::Pending);
+        assert!(pending.deadline() > crate::timer::now());
+    }
+}
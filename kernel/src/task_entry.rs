@@ -35,6 +35,11 @@ pub fn get_task_entry_point() -> u64 {
     task_wrapper_entry as *const () as u64
 }
 
+/// Get the thread entry point
+pub fn get_thread_entry_point() -> u64 {
+    thread_wrapper_entry as *const () as u64
+}
+
 /// Task wrapper entry point - this is the initial RIP for all tasks
 ///
 /// This function is called when a task is scheduled and its context is restored.
@@ -70,3 +75,52 @@ pub fn task_wrapper_entry() {
         core::arch::asm!("hlt", options(noreturn));
     }
 }
+
+/// Thread wrapper entry point - the initial RIP for secondary threads
+///
+/// Like `task_wrapper_entry`, but the thread function takes an argument:
+/// - RDI contains the thread function pointer (`fn(usize) -> i64`)
+/// - RSI contains the argument to pass to it
+///
+/// When the function returns, only this thread exits (sys_thread_exit);
+/// the rest of the process keeps running.
+#[inline(never)]
+pub fn thread_wrapper_entry() {
+    let exit_code: i64;
+
+    unsafe {
+        // Move the function pointer out of RDI and pass the argument in RDI
+        core::arch::asm!(
+            "mov rax, rdi",
+            "mov rdi, rsi",
+            "call rax",
+            out("rax") exit_code,
+            clobber_abi("C"),
+        );
+    }
+
+    let _ = syscall::dispatch_syscall(
+        syscall::nr::SYS_THREAD_EXIT,
+        exit_code as usize,
+        0,
+        0,
+        0,
+        0,
+        0,
+    );
+
+    unsafe {
+        core::arch::asm!("hlt", options(noreturn));
+    }
+}
+
+/// Where a task killed by an exception resumes
+///
+/// The page fault handler redirects a task that overflowed its stack here.
+/// The task is already marked exited, so it just idles with interrupts
+/// enabled until the scheduler switches to something else.
+pub extern "C" fn killed_task_loop() -> ! {
+    loop {
+        x86_64::instructions::interrupts::enable_and_hlt();
+    }
+}
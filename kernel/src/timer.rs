@@ -0,0 +1,337 @@
+//! Kernel timers - sleeping and timeouts
+//!
+//! A thread that sleeps is marked Blocked and put in a min-heap keyed on
+//! the time it should wake at, in `clock::monotonic_ns` nanoseconds.
+//! `expire` wakes the threads whose deadline has passed: they become Ready
+//! and are queued with the scheduler again. It runs on every timer
+//! interrupt, and with an HPET also on the comparator interrupt, which is
+//! kept armed for the earliest deadline (`hpet::arm`) so sleeps end on
+//! time rather than at the next tick.
+//!
+//! Blocking calls that wait for a condition (joining a thread, reaping a
+//! child) use `wait_until`, which checks the condition once a tick and
+//! sleeps in between, giving up at an optional deadline. Waiting threads
+//! therefore leave the CPU to others instead of spinning.
+//!
+//! The kernel's own context (the executor) has no thread to block; it
+//! halts until the deadline instead, and other threads run meanwhile
+//! because the kernel context is preempted like any thread. Async tasks
+//! should not block it at all: `task::sleep` registers the task's waker
+//! with `wake_at`, and `expire` wakes it along with the threads.
+
+use crate::clock::TICK_NS;
+use crate::process::ProcessStatus;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::Waker;
+use spin::Mutex;
+
+/// Nanoseconds in a millisecond
+const NANOS_PER_MS: u64 = 1_000_000;
+
+/// A sleeping thread and the time it wakes at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Timer {
+    deadline: u64,
+    tid: u64,
+}
+
+/// Pending wakeups, earliest first
+pub struct TimerQueue {
+    heap: BinaryHeap<Reverse<Timer>>,
+}
+
+impl TimerQueue {
+    pub const fn new() -> Self {
+        TimerQueue {
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Wake `tid` at `deadline`
+    pub fn add(&mut self, tid: u64, deadline: u64) {
+        self.heap.push(Reverse(Timer { deadline, tid }));
+    }
+
+    /// The thread whose deadline passed first, if any has by `now`
+    pub fn next_expired(&self, now: u64) -> Option<u64> {
+        self.heap
+            .peek()
+            .filter(|timer| timer.0.deadline <= now)
+            .map(|timer| timer.0.tid)
+    }
+
+    /// The earliest deadline, if any timer is pending
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.heap.peek().map(|timer| timer.0.deadline)
+    }
+
+    /// Drop the earliest timer
+    pub fn pop(&mut self) {
+        self.heap.pop();
+    }
+
+    /// Number of pending timers
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl Default for TimerQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Global timer queue
+static TIMERS: Mutex<TimerQueue> = Mutex::new(TimerQueue::new());
+
+/// An async task waiting for a deadline
+struct TaskTimer {
+    id: u64,
+    deadline: u64,
+    waker: Waker,
+}
+
+/// Pending task wakeups, unordered: there are few, and firing one is a
+/// `swap_remove`, which never frees memory in the interrupt
+static TASK_TIMERS: Mutex<Vec<TaskTimer>> = Mutex::new(Vec::new());
+
+/// ID of the next task timer
+static NEXT_TASK_TIMER: AtomicU64 = AtomicU64::new(1);
+
+/// Current time in nanoseconds since boot
+pub fn now() -> u64 {
+    crate::clock::monotonic_ns()
+}
+
+/// The deadline `ms` milliseconds from now
+pub fn deadline_after_ms(ms: u64) -> u64 {
+    now().saturating_add(ms.saturating_mul(NANOS_PER_MS))
+}
+
+/// Point the HPET's comparator at the earliest deadline, thread or task
+///
+/// Without a one-shot HPET this does nothing, and deadlines are noticed on
+/// the tick after them.
+fn arm_next(timers: &TimerQueue, tasks: &[TaskTimer]) {
+    let task_deadline = tasks.iter().map(|timer| timer.deadline).min();
+    let deadline = match (timers.next_deadline(), task_deadline) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    if let Some(deadline) = deadline {
+        crate::hpet::arm(deadline);
+    }
+}
+
+/// Block the current thread until `deadline`
+pub fn sleep_until(deadline: u64) {
+    let tid = match crate::scheduler::current_thread() {
+        Some(tid) => tid,
+        None => {
+            while now() < deadline {
+                x86_64::instructions::hlt();
+            }
+            return;
+        }
+    };
+    if now() >= deadline {
+        return;
+    }
+
+    // Block and arm the timer with interrupts off, so the wakeup cannot
+    // come before the thread is Blocked (it would be lost), and the thread
+    // cannot be switched out before the timer is armed (it would never
+    // wake). Make room for the requeue first: the interrupt must not
+    // allocate.
+    crate::scheduler::prepare_wakeup();
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        if crate::process::block_thread(tid) {
+            let mut timers = TIMERS.lock();
+            timers.add(tid, deadline);
+            arm_next(&timers, &TASK_TIMERS.lock());
+        }
+    });
+
+    // The next tick switches away; we get here again once woken
+    crate::scheduler::request_reschedule();
+    while crate::process::get_thread_status(tid) == Some(ProcessStatus::Blocked) {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Block the current thread for `ms` milliseconds
+pub fn sleep_ms(ms: u64) {
+    sleep_until(deadline_after_ms(ms));
+}
+
+/// Wait until `ready` returns a value, checking once a tick
+///
+/// Returns `None` if `deadline` (`None` for no limit) passes first.
+/// `ready` is always checked at least once.
+pub fn wait_until<T>(deadline: Option<u64>, mut ready: impl FnMut() -> Option<T>) -> Option<T> {
+    loop {
+        if let Some(value) = ready() {
+            return Some(value);
+        }
+        let now = now();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            return None;
+        }
+        sleep_until(now + TICK_NS);
+    }
+}
+
+/// Wake `waker` at `deadline`
+///
+/// `id` is the timer a previous call returned, to move it rather than add
+/// another; one that already fired is added again. Returns the timer's ID
+/// for `cancel_wake`. Called from task context only: it may allocate.
+pub fn wake_at(deadline: u64, waker: &Waker, id: Option<u64>) -> u64 {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let timers = TIMERS.lock();
+        let mut tasks = TASK_TIMERS.lock();
+        let id = match id.and_then(|id| tasks.iter_mut().find(|timer| timer.id == id)) {
+            Some(timer) => {
+                timer.deadline = deadline;
+                if !timer.waker.will_wake(waker) {
+                    timer.waker = waker.clone();
+                }
+                timer.id
+            }
+            None => {
+                let id = NEXT_TASK_TIMER.fetch_add(1, Ordering::Relaxed);
+                tasks.push(TaskTimer {
+                    id,
+                    deadline,
+                    waker: waker.clone(),
+                });
+                id
+            }
+        };
+        arm_next(&timers, &tasks);
+        id
+    })
+}
+
+/// Drop a timer added by `wake_at`, if it has not fired
+pub fn cancel_wake(id: u64) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        TASK_TIMERS.lock().retain(|timer| timer.id != id);
+    });
+}
+
+/// Wake the threads and tasks whose deadline has passed
+///
+/// Called from the timer and HPET interrupts. Never spins: if the timer
+/// queues, the scheduler or the process table is held by the interrupted
+/// code, the remaining wakeups wait for the next tick.
+pub fn expire() {
+    let now = now();
+    let mut timers = match TIMERS.try_lock() {
+        Some(timers) => timers,
+        None => return,
+    };
+    while let Some(tid) = timers.next_expired(now) {
+        if !crate::scheduler::try_wake(tid) {
+            break;
+        }
+        timers.pop();
+    }
+    let mut tasks = match TASK_TIMERS.try_lock() {
+        Some(tasks) => tasks,
+        None => {
+            arm_next(&timers, &[]);
+            return;
+        }
+    };
+    let mut i = 0;
+    while i < tasks.len() {
+        if tasks[i].deadline <= now {
+            // The task's process record holds another reference to the
+            // waker, so dropping this one frees nothing
+            tasks.swap_remove(i).waker.wake();
+        } else {
+            i += 1;
+        }
+    }
+    arm_next(&timers, &tasks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_deadline_after_ms() {
+        let before = now();
+        let deadline = deadline_after_ms(20);
+        assert!(deadline >= before + 20 * NANOS_PER_MS);
+        assert!(deadline <= now() + 20 * NANOS_PER_MS);
+        assert_eq!(deadline_after_ms(u64::MAX), u64::MAX);
+    }
+
+    #[test_case]
+    fn test_timer_queue_order() {
+        let mut timers = TimerQueue::new();
+        timers.add(1, 30);
+        timers.add(2, 10);
+        timers.add(3, 20);
+
+        assert_eq!(timers.next_expired(5), None);
+        assert_eq!(timers.next_deadline(), Some(10));
+        assert_eq!(timers.next_expired(25), Some(2));
+        timers.pop();
+        assert_eq!(timers.next_expired(25), Some(3));
+        timers.pop();
+        assert_eq!(timers.next_expired(25), None);
+        assert_eq!(timers.len(), 1);
+    }
+
+    #[test_case]
+    fn test_task_timer_fires_once() {
+        use alloc::sync::Arc;
+        use alloc::task::Wake;
+        use core::sync::atomic::AtomicUsize;
+
+        struct CountWaker(AtomicUsize);
+        impl Wake for CountWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let count = Arc::new(CountWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+
+        // Keep the timer interrupt from expiring the timers first
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            let id = wake_at(now(), &waker, None);
+            // Moving a pending timer keeps its ID
+            assert_eq!(wake_at(now(), &waker, Some(id)), id);
+            expire();
+            assert_eq!(count.0.load(Ordering::Relaxed), 1);
+            expire();
+            assert_eq!(count.0.load(Ordering::Relaxed), 1);
+
+            // A cancelled timer never fires
+            let id = wake_at(now(), &waker, None);
+            cancel_wake(id);
+            expire();
+            assert_eq!(count.0.load(Ordering::Relaxed), 1);
+        });
+    }
+
+    #[test_case]
+    fn test_wait_until_ready() {
+        // A condition that already holds never sleeps
+        assert_eq!(wait_until(None, || Some(7)), Some(7));
+        assert_eq!(wait_until(Some(0), || None::<u8>), None);
+    }
+}
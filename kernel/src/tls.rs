@@ -0,0 +1,180 @@
+//! Thread-local storage support
+//!
+//! x86_64 uses TLS "variant II": the thread pointer (the FS base) points at
+//! a thread control block (TCB) whose first word is a pointer to itself, and
+//! the thread's copy of the `PT_TLS` image sits immediately below it:
+//!
+//! ```text
+//! [ .tdata | .tbss (zeroed) | pad ][ TCB self pointer ]
+//!                                  ^
+//!                                  FS base
+//! ```
+//!
+//! Userspace addresses a thread-local variable as `fs:[-offset]`, and reads
+//! the thread pointer itself with `mov rax, fs:0`.
+//!
+//! The FS/GS bases are saved per thread in `TaskContext` and loaded when the
+//! thread is entered. While the kernel runs, GS points at the per-CPU block
+//! and the thread's GS base waits in `IA32_KERNEL_GS_BASE` until `swapgs`
+//! on the way back to user mode. If the CPU supports FSGSBASE it is enabled at boot so
+//! userspace can also change the base with `wrfsbase` without a syscall.
+
+use crate::elf_loader::TlsSegment;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use x86_64::VirtAddr;
+use x86_64::registers::model_specific::{FsBase, KernelGsBase};
+
+/// Whether `rdfsbase`/`wrfsbase` are usable (set by `init`)
+static FSGSBASE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Size of the thread control block placed above the TLS image
+const TCB_SIZE: usize = 8;
+
+/// Enable the FSGSBASE instructions if the CPU supports them
+pub fn init() {
+    use x86_64::registers::control::{Cr4, Cr4Flags};
+
+    // CPUID.(EAX=07H, ECX=0):EBX bit 0 reports FSGSBASE support
+    let leaf7 = core::arch::x86_64::__cpuid_count(7, 0);
+    if leaf7.ebx & 1 == 0 {
+        return;
+    }
+
+    unsafe {
+        Cr4::update(|flags| flags.insert(Cr4Flags::FSGSBASE));
+    }
+    FSGSBASE_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether userspace may use `wrfsbase` directly
+pub fn fsgsbase_enabled() -> bool {
+    FSGSBASE_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Read the current FS base
+pub fn read_fs_base() -> u64 {
+    if fsgsbase_enabled() {
+        let base: u64;
+        unsafe {
+            core::arch::asm!("rdfsbase {}", out(reg) base, options(nomem, nostack, preserves_flags));
+        }
+        base
+    } else {
+        FsBase::read().as_u64()
+    }
+}
+
+/// Load a new FS base
+///
+/// `base` must be canonical; callers taking it from userspace validate it first.
+pub fn write_fs_base(base: u64) {
+    if fsgsbase_enabled() {
+        unsafe {
+            core::arch::asm!("wrfsbase {}", in(reg) base, options(nomem, nostack, preserves_flags));
+        }
+    } else {
+        FsBase::write(VirtAddr::new(base));
+    }
+}
+
+/// Read the current thread's GS base
+pub fn read_gs_base() -> u64 {
+    KernelGsBase::read().as_u64()
+}
+
+/// Set the GS base the current thread sees in user mode
+pub fn write_gs_base(base: u64) {
+    KernelGsBase::write(VirtAddr::new(base));
+}
+
+/// Whether `base` can be loaded into FS/GS without faulting
+pub fn is_valid_base(base: u64) -> bool {
+    VirtAddr::try_new(base).is_ok()
+}
+
+/// Per-thread TLS block built from a `PT_TLS` template
+#[derive(Debug)]
+pub struct TlsBlock {
+    /// Backing storage; over-allocated so the TCB can be aligned
+    memory: Vec<u8>,
+    /// Offset of the TCB inside `memory`
+    tcb_offset: usize,
+}
+
+impl TlsBlock {
+    /// Allocate a block for `segment` and initialise it from `binary`
+    ///
+    /// `.tdata` is copied from the segment's file image and `.tbss` is
+    /// zero-filled. Returns `None` if the segment lies outside `binary`.
+    pub fn new(segment: &TlsSegment, binary: &[u8]) -> Option<Self> {
+        let file_start = segment.offset as usize;
+        let file_end = file_start.checked_add(segment.file_size as usize)?;
+        let image = binary.get(file_start..file_end)?;
+
+        let align = (segment.align as usize).max(TCB_SIZE);
+        let mem_size = segment.mem_size as usize;
+        if image.len() > mem_size {
+            return None;
+        }
+        // The image ends at the TCB, so round its size up to the alignment
+        let tls_size = mem_size.checked_add(align - 1)? & !(align - 1);
+
+        let mut memory = alloc::vec![0u8; tls_size + TCB_SIZE + align];
+        let base = memory.as_ptr() as usize;
+        let tcb_addr = (base + tls_size + align - 1) & !(align - 1);
+        let tcb_offset = tcb_addr - base;
+
+        let image_offset = tcb_offset - tls_size;
+        memory[image_offset..image_offset + image.len()].copy_from_slice(image);
+        memory[tcb_offset..tcb_offset + TCB_SIZE].copy_from_slice(&(tcb_addr as u64).to_le_bytes());
+
+        Some(TlsBlock { memory, tcb_offset })
+    }
+
+    /// Thread pointer to load into FS (address of the TCB)
+    pub fn thread_pointer(&self) -> u64 {
+        self.memory.as_ptr() as u64 + self.tcb_offset as u64
+    }
+
+    /// Lowest address of the block
+    pub fn start(&self) -> u64 {
+        self.memory.as_ptr() as u64
+    }
+
+    /// Bytes allocated for this block
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_tls_block_layout() {
+        let binary = [0xAAu8, 0xBB, 0xCC, 0xDD];
+        let segment = TlsSegment {
+            vaddr: 0,
+            offset: 1,
+            file_size: 2,
+            mem_size: 6,
+            align: 16,
+        };
+
+        let block = TlsBlock::new(&segment, &binary).unwrap();
+        let tp = block.thread_pointer();
+        assert_eq!(tp % 16, 0);
+
+        unsafe {
+            // TCB points at itself
+            assert_eq!(*(tp as *const u64), tp);
+            // .tdata then .tbss below the thread pointer
+            let image = (tp - 16) as *const u8;
+            assert_eq!(*image, 0xBB);
+            assert_eq!(*image.add(1), 0xCC);
+            assert_eq!(*image.add(2), 0);
+        }
+    }
+}
@@ -26,7 +26,7 @@ use x86_64::instructions::interrupts;
 
 /// Maximum bytes per TTY write operation
 /// Matches sys_write validation limit
-const TTY_MAX_WRITE: usize = 4096;
+pub const TTY_MAX_WRITE: usize = 4096;
 
 /// Write to TTY device
 ///
@@ -95,41 +95,34 @@ pub fn tty_write_with_newline(buf: &[u8]) -> usize {
 mod tests {
     use super::*;
 
-    #[test]
+    #[test_case]
     fn test_tty_write_empty() {
         let result = tty_write(&[]);
         assert_eq!(result, 0);
     }
 
-    #[test]
+    #[test_case]
     fn test_tty_write_single_byte() {
         let data = [b'A'];
         let result = tty_write(&data);
         assert_eq!(result, 1);
     }
 
-    #[test]
+    #[test_case]
     fn test_tty_write_multiple_bytes() {
         let data = b"Hello, World!";
         let result = tty_write(data);
         assert_eq!(result, data.len());
     }
 
-    #[test]
+    #[test_case]
     fn test_tty_write_max_size() {
         let data = alloc::vec![b'x'; TTY_MAX_WRITE];
         let result = tty_write(&data);
         assert_eq!(result, TTY_MAX_WRITE);
     }
 
-    #[test]
-    #[should_panic(expected = "exceeds maximum size")]
-    fn test_tty_write_exceeds_max() {
-        let data = alloc::vec![b'x'; TTY_MAX_WRITE + 1];
-        let _ = tty_write(&data);
-    }
-
-    #[test]
+    #[test_case]
     fn test_tty_write_with_newline() {
         let data = b"Log message";
         let result = tty_write_with_newline(data);
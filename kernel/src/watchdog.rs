@@ -0,0 +1,278 @@
+//! Soft-lockup watchdog
+//!
+//! Executor tasks are cooperative: a task whose poll never returns (a loop
+//! that never awaits, or a program's entry point called in place) keeps
+//! every other task from running. So does a thread while preemption is
+//! off, for the whole kernel (`scheduler::disable_preemption`) or on its
+//! CPU (`percpu::preempt_disable`). The timer interrupt calls `check` on
+//! every tick, and once the same task or thread has run for the threshold
+//! without giving up the CPU, the watchdog writes one report to serial,
+//! with the interrupted RIP and the task's IDs, then acts as configured:
+//!
+//! - `off`: nothing, not even the report
+//! - `report`: only the report (the default)
+//! - `preempt`: switch away from it on every tick while it stays stuck,
+//!   as if preemption were on
+//! - `kill`: kill its process with `SIGKILL`, then as `preempt`. A task's
+//!   poll cannot be abandoned halfway: the executor drops the killed task
+//!   only if the poll returns, and switches between it and the threads
+//!   until then.
+//!
+//! The action is picked with `ORBITAL_WATCHDOG=off|report|preempt|kill`
+//! when the kernel is built and can be changed with `set_action`.
+//!
+//! Tasks are watched from the start of their poll (`PerCpu::set_task`),
+//! threads from the first tick that could not preempt them.
+
+use crate::process::ExitReason;
+use crate::smp::MAX_CPUS;
+use core::fmt;
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use orbital_common::process::SIGKILL;
+use orbital_common::time::NANOS_PER_SEC;
+
+/// Seconds a task or thread may keep the CPU before it is reported
+pub const DEFAULT_THRESHOLD_SECS: u64 = 10;
+
+/// What the watchdog does about a soft lockup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Off,
+    Report,
+    Preempt,
+    Kill,
+}
+
+impl Action {
+    /// Look an action up by its name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Action::Off),
+            "report" => Some(Action::Report),
+            "preempt" => Some(Action::Preempt),
+            "kill" => Some(Action::Kill),
+            _ => None,
+        }
+    }
+
+    /// Name used to select the action at build time
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Off => "off",
+            Action::Report => "report",
+            Action::Preempt => "preempt",
+            Action::Kill => "kill",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Action::Off,
+            1 => Action::Report,
+            2 => Action::Preempt,
+            _ => Action::Kill,
+        }
+    }
+}
+
+/// Action used when `ORBITAL_WATCHDOG` is unset or unknown
+const DEFAULT_ACTION: Action = Action::Report;
+
+/// Action selected with `ORBITAL_WATCHDOG=<name>` when the kernel was built
+pub fn boot_action() -> Action {
+    option_env!("ORBITAL_WATCHDOG")
+        .and_then(Action::from_name)
+        .unwrap_or(DEFAULT_ACTION)
+}
+
+/// The configured action, `u8::MAX` for the one chosen at build time
+static ACTION: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// The configured threshold
+static THRESHOLD_SECS: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD_SECS);
+
+/// The action taken on soft lockups
+pub fn action() -> Action {
+    match ACTION.load(Ordering::Relaxed) {
+        u8::MAX => boot_action(),
+        value => Action::from_u8(value),
+    }
+}
+
+/// Change the action taken on soft lockups
+pub fn set_action(action: Action) {
+    ACTION.store(action as u8, Ordering::Relaxed);
+}
+
+/// Report tasks and threads that keep the CPU for `secs` seconds (at least 1)
+pub fn set_threshold(secs: u64) {
+    THRESHOLD_SECS.store(secs.max(1), Ordering::Relaxed);
+}
+
+/// What is holding a CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Holder {
+    /// An executor task, by PID, polled by the CPU's own context
+    Task(u64),
+    /// A thread, and the process owning it
+    Thread { tid: u64, pid: Option<u64> },
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Holder::Task(pid) => write!(f, "task PID {}", pid),
+            Holder::Thread {
+                tid,
+                pid: Some(pid),
+            } => write!(f, "thread TID {} (PID {})", tid, pid),
+            Holder::Thread { tid, pid: None } => write!(f, "thread TID {}", tid),
+        }
+    }
+}
+
+/// Watchdog state of one CPU
+struct CpuWatch {
+    /// Thread last found running with preemption off
+    tid: AtomicU64,
+    /// Since when it has run so (0 while the running thread can be
+    /// preempted)
+    since: AtomicU64,
+    /// Start of the last run reported, so each run is reported once
+    reported: AtomicU64,
+}
+
+impl CpuWatch {
+    const fn new() -> Self {
+        CpuWatch {
+            tid: AtomicU64::new(0),
+            since: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+        }
+    }
+
+    /// Note that `tid` is running at `now`; returns since when it has
+    /// kept the CPU, if it could not be preempted
+    fn observe_thread(&self, tid: u64, preemptible: bool, now: u64) -> Option<u64> {
+        if preemptible {
+            self.since.store(0, Ordering::Relaxed);
+            return None;
+        }
+        let since = self.since.load(Ordering::Relaxed);
+        let same = self.tid.swap(tid, Ordering::Relaxed) == tid;
+        if since == 0 || !same {
+            self.since.store(now, Ordering::Relaxed);
+            return Some(now);
+        }
+        Some(since)
+    }
+
+    /// Whether the run that started at `since` has yet to be reported
+    fn first_report(&self, since: u64) -> bool {
+        self.reported.swap(since, Ordering::Relaxed) != since
+    }
+}
+
+static CPUS: [CpuWatch; MAX_CPUS] = [const { CpuWatch::new() }; MAX_CPUS];
+
+/// Look for a soft lockup on the running CPU, from its timer interrupt
+///
+/// `rip` is where the interrupt came in; `preemptible` says whether the
+/// timer may switch the current thread. Returns true if the timer must
+/// switch away even though preemption is off. Never spins: a kill the
+/// process table is too busy for is tried again on the next tick.
+pub fn check(rip: u64, preemptible: bool) -> bool {
+    let action = action();
+    if action == Action::Off {
+        return false;
+    }
+
+    let cpu = crate::percpu::this_cpu();
+    let watch = &CPUS[cpu.id()];
+    let now = crate::clock::monotonic_ns();
+    let (holder, since) = match cpu.current_thread() {
+        Some(tid) => match watch.observe_thread(tid, preemptible, now) {
+            Some(since) => {
+                let pid = crate::percpu::current_process();
+                (Holder::Thread { tid, pid }, since)
+            }
+            None => return false,
+        },
+        None => match cpu.polling() {
+            Some((pid, since)) => (Holder::Task(pid), since),
+            None => return false,
+        },
+    };
+
+    let threshold_ns = THRESHOLD_SECS.load(Ordering::Relaxed) * NANOS_PER_SEC;
+    if now.saturating_sub(since) < threshold_ns {
+        return false;
+    }
+    if watch.first_report(since) {
+        crate::serial_println!(
+            "watchdog: soft lockup on CPU {}: {} has run {} s without yielding, RIP {:#x}",
+            cpu.id(),
+            holder,
+            (now - since) / NANOS_PER_SEC,
+            rip
+        );
+    }
+    if action == Action::Kill {
+        kill(holder);
+    }
+    action >= Action::Preempt
+}
+
+/// Kill the process holding the CPU, unless it is already dead
+fn kill(holder: Holder) {
+    let killed = match holder {
+        Holder::Task(pid) => crate::process::try_kill_process(pid, SIGKILL).then_some(pid),
+        Holder::Thread { tid, .. } => {
+            crate::process::kill_thread_owner(tid, ExitReason::Signaled(SIGKILL)).map(|(pid, _)| {
+                // The thread will never drop the guards it holds
+                crate::percpu::this_cpu().clear_preempt_count();
+                pid
+            })
+        }
+    };
+    if let Some(pid) = killed {
+        crate::serial_println!("watchdog: killed PID {}", pid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_action_names() {
+        for action in [Action::Off, Action::Report, Action::Preempt, Action::Kill] {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+            assert_eq!(Action::from_u8(action as u8), action);
+        }
+        assert_eq!(Action::from_name("reboot"), None);
+        assert!(Action::Kill > Action::Preempt);
+    }
+
+    #[test_case]
+    fn test_thread_watched_while_not_preemptible() {
+        let watch = CpuWatch::new();
+        assert_eq!(watch.observe_thread(7, true, 100), None);
+        assert_eq!(watch.observe_thread(7, false, 200), Some(200));
+        assert_eq!(watch.observe_thread(7, false, 300), Some(200));
+        // Another thread starts a new run
+        assert_eq!(watch.observe_thread(8, false, 400), Some(400));
+        // A tick that could preempt ends the run
+        assert_eq!(watch.observe_thread(8, true, 500), None);
+        assert_eq!(watch.observe_thread(8, false, 600), Some(600));
+    }
+
+    #[test_case]
+    fn test_lockup_reported_once() {
+        let watch = CpuWatch::new();
+        assert!(watch.first_report(100));
+        assert!(!watch.first_report(100));
+        // A new run is reported again
+        assert!(watch.first_report(400));
+    }
+}
@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(orbital_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use orbital_kernel::fpu::FpuState;
+
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    orbital_kernel::fpu::init();
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    orbital_kernel::test_panic_handler(info)
+}
+
+/// One step of task A: xmm0 += 1.5
+fn step_a() {
+    let increment = 1.5f64;
+    unsafe {
+        core::arch::asm!(
+            "movsd xmm1, [{}]",
+            "addsd xmm0, xmm1",
+            in(reg) &increment,
+            options(nostack, readonly),
+        );
+    }
+}
+
+/// One step of task B: xmm0 = xmm0 * 2.0 - 0.75, which stays at 0.75
+/// only if xmm0 starts there, so any leaked value from A shows up
+fn step_b() {
+    let constants = [2.0f64, 0.75f64];
+    unsafe {
+        core::arch::asm!(
+            "movsd xmm1, [{0}]",
+            "movsd xmm2, [{0} + 8]",
+            "mulsd xmm0, xmm1",
+            "subsd xmm0, xmm2",
+            in(reg) constants.as_ptr(),
+            options(nostack, readonly),
+        );
+    }
+}
+
+/// Read the low double of xmm0
+fn read_xmm0() -> f64 {
+    let mut value = 0f64;
+    unsafe {
+        core::arch::asm!("movsd [{}], xmm0", in(reg) &mut value, options(nostack));
+    }
+    value
+}
+
+/// Load `value` into the low double of xmm0
+fn write_xmm0(value: f64) {
+    unsafe {
+        core::arch::asm!("movsd xmm0, [{}]", in(reg) &value, options(nostack, readonly));
+    }
+}
+
+#[test_case]
+fn interleaved_tasks_keep_their_registers() {
+    let mut task_a = FpuState::new();
+    let mut task_b = FpuState::new();
+
+    // Give B its starting value
+    task_b.restore();
+    write_xmm0(0.75);
+    task_b.save();
+
+    // Switch between the two tasks after every step, as the scheduler would
+    for _ in 0..1000 {
+        task_a.restore();
+        step_a();
+        task_a.save();
+
+        task_b.restore();
+        step_b();
+        task_b.save();
+    }
+
+    task_a.restore();
+    assert_eq!(read_xmm0(), 1500.0);
+    task_b.restore();
+    assert_eq!(read_xmm0(), 0.75);
+}
+
+#[test_case]
+fn mxcsr_is_per_task() {
+    let mut task_a = FpuState::new();
+    let mut task_b = FpuState::new();
+
+    // Task B switches SSE rounding to round-toward-zero
+    task_b.restore();
+    let rounding: u32 = 0x1F80 | (0b11 << 13);
+    unsafe {
+        core::arch::asm!("ldmxcsr [{}]", in(reg) &rounding, options(nostack, readonly));
+    }
+    task_b.save();
+
+    task_a.restore();
+    task_a.save();
+
+    assert_eq!(task_a.mxcsr(), 0x1F80);
+    assert_eq!(task_b.mxcsr(), rounding);
+}
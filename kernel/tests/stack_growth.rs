@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(orbital_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use orbital_kernel::stack::{MAX_GROWTH_GAP, PAGE_SIZE, Stack};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use orbital_kernel::allocator;
+    use orbital_kernel::memory::{self, BootInfoFrameAllocator};
+    use x86_64::VirtAddr;
+
+    orbital_kernel::gdt::init();
+    orbital_kernel::interrupts::init_idt();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::install(mapper, frame_allocator);
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    orbital_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn growable_stack_starts_with_one_page() {
+    let stack = Stack::new_growable(64 * PAGE_SIZE, 1).expect("stack allocation failed");
+    assert_eq!(stack.size(), PAGE_SIZE);
+    assert_eq!(stack.reserved(), 64 * PAGE_SIZE);
+}
+
+#[test_case]
+fn touching_below_the_stack_maps_pages() {
+    let stack = Stack::new_growable(64 * PAGE_SIZE, 2).expect("stack allocation failed");
+
+    // Walk down a page at a time, as a deepening call chain would
+    let mut addr = stack.bottom() - 8;
+    for _ in 0..16 {
+        unsafe { core::ptr::write_volatile(addr as *mut u64, addr) };
+        addr -= PAGE_SIZE as u64;
+    }
+
+    assert_eq!(stack.size(), 17 * PAGE_SIZE);
+    assert_eq!(stack.reserved(), 64 * PAGE_SIZE);
+}
+
+#[test_case]
+fn large_frame_within_gap_grows_stack() {
+    let stack = Stack::new_growable(64 * PAGE_SIZE, 3).expect("stack allocation failed");
+
+    // A single large frame skips pages; everything up to the old bottom
+    // must be mapped so the skipped pages are usable too
+    let addr = stack.bottom() - (MAX_GROWTH_GAP - PAGE_SIZE) as u64;
+    unsafe { core::ptr::write_volatile(addr as *mut u64, 0xdead_beef) };
+
+    assert_eq!(stack.bottom(), addr & !(PAGE_SIZE as u64 - 1));
+    let skipped = (stack.bottom() + PAGE_SIZE as u64) as *const u64;
+    assert_eq!(unsafe { core::ptr::read_volatile(skipped) }, 0);
+}
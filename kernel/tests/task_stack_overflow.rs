@@ -0,0 +1,122 @@
+#![no_std]
+#![no_main]
+#![feature(abi_x86_interrupt)]
+#![feature(custom_test_frameworks)]
+#![test_runner(orbital_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use lazy_static::lazy_static;
+use orbital_kernel::stack::{DEFAULT_STACK_SIZE, Stack};
+use orbital_kernel::{QemuExitCode, exit_qemu, serial_print, serial_println};
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+
+/// Thread ID the test stack is registered to
+const OWNER: u64 = 42;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use orbital_kernel::allocator;
+    use orbital_kernel::memory::{self, BootInfoFrameAllocator};
+    use x86_64::VirtAddr;
+
+    orbital_kernel::gdt::init();
+    init_test_idt();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::install(mapper, frame_allocator);
+
+    test_main();
+    loop {}
+}
+
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+    stack_overflow(); // for each recursion, the return address is pushed
+    volatile::Volatile::new(0).read(); // prevent tail recursion optimizations
+}
+
+extern "C" fn overflow_on_task_stack() -> ! {
+    stack_overflow();
+    panic!("Execution continued after stack overflow");
+}
+
+#[test_case]
+fn test_task_stack_guard_page() {
+    serial_print!("task_stack_overflow::test_task_stack_guard_page...\t");
+
+    let stack = Stack::new(DEFAULT_STACK_SIZE, OWNER).expect("stack allocation failed");
+    let top = stack.top();
+    // The stack must outlive the switch below
+    core::mem::forget(stack);
+
+    unsafe {
+        core::arch::asm!(
+            "mov rsp, {top}",
+            "call {entry}",
+            top = in(reg) top,
+            entry = sym overflow_on_task_stack,
+            options(noreturn),
+        );
+    }
+}
+
+lazy_static! {
+    static ref TEST_IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        unsafe {
+            idt.page_fault
+                .set_handler_fn(test_page_fault_handler)
+                .set_stack_index(orbital_kernel::gdt::PAGE_FAULT_IST_INDEX);
+            idt.double_fault
+                .set_handler_fn(test_double_fault_handler)
+                .set_stack_index(orbital_kernel::gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+
+        idt
+    };
+}
+
+pub fn init_test_idt() {
+    TEST_IDT.load();
+}
+
+extern "x86-interrupt" fn test_page_fault_handler(
+    _stack_frame: InterruptStackFrame,
+    _error_code: PageFaultErrorCode,
+) {
+    use x86_64::registers::control::Cr2;
+
+    match orbital_kernel::stack::overflow_owner(Cr2::read().as_u64()) {
+        Some(overflow) if overflow.owner == OWNER => {
+            serial_println!("[ok]");
+            exit_qemu(QemuExitCode::Success);
+        }
+        _ => {
+            serial_println!("[failed]\n");
+            serial_println!("Error: page fault was not reported as a stack overflow\n");
+            exit_qemu(QemuExitCode::Failed);
+        }
+    }
+    loop {}
+}
+
+extern "x86-interrupt" fn test_double_fault_handler(
+    _stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) -> ! {
+    serial_println!("[failed]\n");
+    serial_println!("Error: overflow escalated to a double fault\n");
+    exit_qemu(QemuExitCode::Failed);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    orbital_kernel::test_panic_handler(info)
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(orbital_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use orbital_kernel::tty::{TTY_MAX_WRITE, tty_write};
+use orbital_kernel::{QemuExitCode, exit_qemu, serial_print, serial_println};
+
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    tty_write_exceeds_max();
+    serial_println!("[test did not panic]");
+    exit_qemu(QemuExitCode::Failed);
+    loop {}
+}
+
+/// One byte more than a single write may take
+static DATA: [u8; TTY_MAX_WRITE + 1] = [b'x'; TTY_MAX_WRITE + 1];
+
+fn tty_write_exceeds_max() {
+    serial_print!("tty_write_overflow::tty_write_exceeds_max...\t");
+    let _ = tty_write(&DATA);
+}
+
+/// Keeps the start of the panic message
+struct Message {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl Write for Message {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let mut message = Message {
+        buf: [0; 128],
+        len: 0,
+    };
+    let _ = write!(message, "{}", info.message());
+    let expected = b"exceeds maximum size";
+    if message.buf[..message.len]
+        .windows(expected.len())
+        .any(|window| window.iter().zip(expected).all(|(a, b)| a == b))
+    {
+        serial_println!("[ok]");
+        exit_qemu(QemuExitCode::Success);
+    } else {
+        serial_println!("[failed]\n");
+        serial_println!("Error: {}\n", info);
+        exit_qemu(QemuExitCode::Failed);
+    }
+    loop {}
+}
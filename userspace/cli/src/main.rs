@@ -11,19 +11,30 @@
 //! - echo <text>: Echo text to stdout
 //! - ps: List running processes
 //! - uptime: Show kernel uptime
-//! - spawn <count>: Spawn N tasks
+//! - spawn <count>: Spawn N tasks (at batch priority)
+//! - nice <pid> <n>: Change a task's priority
+//! - chrt <pid> <policy> [prio]: Change a task's scheduling policy
+//! - kill <pid> [signal]: Kill a task
+//! - top [n] [ms]: Refreshing view of scheduler statistics
 //! - exit: Quit the CLI
 //!
 //! This shows the "policy-free kernel" principle:
 //! Kernel provides I/O syscalls, userspace provides command logic.
 
+use orbital_common::process::{
+    policy_name, signal_name, NICE_BATCH, PROCESS_INFO_VERSION, ProcessInfo, ProcessState,
+    SCHED_FIFO, SCHED_OTHER, SCHED_RR, SIGKILL, SIGTERM,
+};
+use orbital_common::sched::{latency_bucket_limit_us, SchedStats, LATENCY_BUCKETS};
+use orbital_common::time::{CLOCK_MONOTONIC, Timespec};
+
 // ============================================================================
 // Syscall Wrappers (inlined to avoid std/no_std conflicts)
 // ============================================================================
 
 /// Invoke sys_read syscall (fd=0 is stdin)
 /// 
-/// Reads up to `len` bytes from stdin into `buf`
+/// Reads up to `len` bytes from stdin into `buf`, waiting for input
 /// Returns number of bytes read
 #[inline]
 fn syscall_read(fd: i32, buf: *mut u8, len: usize) -> Result<usize, i64> {
@@ -37,6 +48,7 @@ fn syscall_read(fd: i32, buf: *mut u8, len: usize) -> Result<usize, i64> {
                 in("rdi") fd as usize,
                 in("rsi") buf,
                 in("rdx") len,
+                in("rcx") 0u64,  // flags: wait for input
                 clobber_abi("C"),
             );
         }
@@ -100,6 +112,7 @@ fn syscall_task_create(entry_addr: usize) -> Result<u64, i64> {
                 "syscall",
                 inout("rax") 5i64 => result,  // syscall #5 = SYS_TASK_CREATE
                 in("rdi") entry_addr,
+                in("rsi") 0usize,             // default stack size
                 clobber_abi("C"),
             );
         }
@@ -118,10 +131,104 @@ fn syscall_task_create(entry_addr: usize) -> Result<u64, i64> {
     }
 }
 
+/// Invoke sys_setpriority syscall
+/// Sets the nice value of a process (0 = this process)
+#[inline]
+fn syscall_setpriority(pid: u64, nice: i32) -> Result<(), i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 21i64 => result,  // syscall #21 = SYS_SETPRIORITY
+                in("rdi") pid,
+                in("rsi") nice as i64,
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, nice);
+        Err(-2)
+    }
+}
+
+/// Invoke sys_sched_setscheduler syscall
+/// Sets the scheduling policy and real-time priority of a process (0 = this process)
+#[inline]
+fn syscall_sched_setscheduler(pid: u64, policy: u32, priority: u32) -> Result<(), i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 23i64 => result,  // syscall #23 = SYS_SCHED_SETSCHEDULER
+                in("rdi") pid,
+                in("rsi") policy as u64,
+                in("rdx") priority as u64,
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, policy, priority);
+        Err(-2)
+    }
+}
+
+/// Invoke sys_kill syscall
+/// Sends a signal (`SIGTERM`, `SIGKILL`) to a process
+#[inline]
+fn syscall_kill(pid: u64, signal: u32) -> Result<(), i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 28i64 => result,  // syscall #28 = SYS_KILL
+                in("rdi") pid,
+                in("rsi") signal as u64,
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, signal);
+        Err(-2)
+    }
+}
+
 /// Invoke sys_task_wait syscall
-/// Waits for a task to complete and returns its exit code
+/// Waits for a task to complete and returns its exit code (timeout 0 = wait forever)
 #[inline]
-fn syscall_task_wait(pid: u64) -> Result<i32, i64> {
+fn syscall_task_wait(pid: u64, timeout_ms: u64) -> Result<i32, i64> {
     #[cfg(target_arch = "x86_64")]
     {
         let result: i64;
@@ -130,6 +237,7 @@ fn syscall_task_wait(pid: u64) -> Result<i32, i64> {
                 "syscall",
                 inout("rax") 6i64 => result,  // syscall #6 = SYS_TASK_WAIT
                 in("rdi") pid,
+                in("rsi") timeout_ms,
                 clobber_abi("C"),
             );
         }
@@ -143,7 +251,37 @@ fn syscall_task_wait(pid: u64) -> Result<i32, i64> {
     
     #[cfg(not(target_arch = "x86_64"))]
     {
-        let _ = pid;
+        let _ = (pid, timeout_ms);
+        Err(-2)
+    }
+}
+
+/// Invoke sys_sleep syscall
+/// Blocks this process for `ms` milliseconds
+#[inline]
+fn syscall_sleep(ms: u64) -> Result<(), i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 25i64 => result,  // syscall #25 = SYS_SLEEP
+                in("rdi") ms,
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = ms;
         Err(-2)
     }
 }
@@ -177,9 +315,9 @@ fn syscall_get_pid() -> Result<u64, i64> {
 }
 
 /// Invoke sys_ps syscall
-/// Lists running processes
+/// Fills `records` with processes whose PID is greater than `cursor`
 #[inline]
-fn syscall_ps(buf: &mut [u8]) -> Result<usize, i64> {
+fn syscall_ps(records: &mut [ProcessInfo], cursor: u64) -> Result<usize, i64> {
     #[cfg(target_arch = "x86_64")]
     {
         let result: i64;
@@ -187,8 +325,9 @@ fn syscall_ps(buf: &mut [u8]) -> Result<usize, i64> {
             std::arch::asm!(
                 "syscall",
                 inout("rax") 8i64 => result,  // syscall #8 = SYS_PS
-                in("rdi") buf.as_mut_ptr(),
-                in("rsi") buf.len(),
+                in("rdi") records.as_mut_ptr(),
+                in("rsi") records.len(),
+                in("rdx") cursor,
                 clobber_abi("C"),
             );
         }
@@ -202,13 +341,13 @@ fn syscall_ps(buf: &mut [u8]) -> Result<usize, i64> {
     
     #[cfg(not(target_arch = "x86_64"))]
     {
-        let _ = buf;
+        let _ = (records, cursor);
         Err(-2)
     }
 }
 
 /// Invoke sys_uptime syscall
-/// Returns kernel uptime in milliseconds
+/// Returns kernel uptime in whole seconds
 #[inline]
 fn syscall_uptime() -> Result<u64, i64> {
     #[cfg(target_arch = "x86_64")]
@@ -235,6 +374,69 @@ fn syscall_uptime() -> Result<u64, i64> {
     }
 }
 
+/// Invoke sys_clock_gettime syscall
+/// Reads a clock (`CLOCK_MONOTONIC`) with nanosecond resolution
+#[inline]
+fn syscall_clock_gettime(clock: u32) -> Result<Timespec, i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        let mut ts = Timespec::default();
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 26i64 => result,  // syscall #26 = SYS_CLOCK_GETTIME
+                in("rdi") clock as u64,
+                in("rsi") &mut ts as *mut Timespec,
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(ts)
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = clock;
+        Err(-2)
+    }
+}
+
+/// Invoke sys_sched_stats syscall
+/// Reads the scheduler's counters since boot
+#[inline]
+fn syscall_sched_stats() -> Result<SchedStats, i64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let result: i64;
+        let mut stats = SchedStats::empty();
+        unsafe {
+            std::arch::asm!(
+                "syscall",
+                inout("rax") 27i64 => result,  // syscall #27 = SYS_SCHED_STATS
+                in("rdi") &mut stats as *mut SchedStats,
+                in("rsi") std::mem::size_of::<SchedStats>(),
+                clobber_abi("C"),
+            );
+        }
+        
+        if result < 0 {
+            Err(result)
+        } else {
+            Ok(stats)
+        }
+    }
+    
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(-2)
+    }
+}
+
 /// Invoke sys_clear_screen syscall
 /// Clears the VGA display
 #[inline]
@@ -378,7 +580,12 @@ impl Cli {
             "uptime" => Self::cmd_uptime(),
             "pid" => Self::cmd_pid(),
             "spawn" => Self::cmd_spawn(args),
+            "nice" => Self::cmd_nice(args),
+            "chrt" => Self::cmd_chrt(args),
+            "top" => Self::cmd_top(args),
             "wait" => Self::cmd_wait(args),
+            "kill" => Self::cmd_kill(args),
+            "sleep" => Self::cmd_sleep(args),
             "ping" => Self::cmd_ping(),
             "run" => Self::cmd_run(),
             "clear" => Self::cmd_clear(),
@@ -400,7 +607,12 @@ impl Cli {
         println("  ping              - Test connectivity (responds with pong)");
         println("  spawn <N>         - Spawn task by index (1-4)");
         println("  spawn -c <N>      - Spawn N identical tasks");
-        println("  wait <PID>        - Wait for a task to complete (get exit code)");
+        println("  nice <PID> <N>    - Set a task's nice value (-20 to 19)");
+        println("  chrt <PID> <P> [R] - Set policy P (other, fifo, rr), real-time priority R (1-99)");
+        println("  wait <PID> [MS]   - Wait for a task to complete (get exit code), at most MS ms");
+        println("  kill <PID> [SIG]  - Kill a task (SIG: term, the default, or kill)");
+        println("  sleep <MS>        - Pause for MS milliseconds");
+        println("  top [N] [MS]      - Show scheduler statistics N times, every MS ms");
         println("  run               - Execute all ready processes");
         println("  clear             - Clear the screen");
         println("  exit or quit      - Exit the CLI");
@@ -410,8 +622,10 @@ impl Cli {
         println("  > ps");
         println("  > spawn 1        (spawn task 1)");
         println("  > wait 1         (wait for PID 1 to complete)");
+        println("  > wait 1 500     (give up after half a second)");
         println("  > spawn -c 3     (spawn 3 identical tasks)");
         println("  > run            (execute ready tasks)");
+        println("  > top 5 500      (5 refreshes, twice a second)");
     }
 
     /// echo command - echo arguments to stdout
@@ -439,52 +653,80 @@ impl Cli {
     }
 
     /// ps command - list running processes with details
+    ///
+    /// The kernel returns binary `ProcessInfo` records; all formatting
+    /// happens here. Pages through the table with the PID cursor.
     fn cmd_ps() {
-        println("Running processes:");
-        
-        let mut buffer = [0u8; 512];
-        match syscall_ps(&mut buffer) {
-            Ok(bytes_written) => {
-                if let Ok(ps_output) = std::str::from_utf8(&buffer[..bytes_written]) {
-                    // Parse and format the output
-                    let lines: Vec<&str> = ps_output.lines().collect();
-                    
-                    if lines.len() > 1 {
-                        // Print header with better formatting
-                        println("┌─────┬──────────────┐");
-                        println("│ PID │ Status       │");
-                        println("├─────┼──────────────┤");
-                        
-                        // Print each process
-                        for line in &lines[1..] {
-                            if !line.is_empty() {
-                                let parts: Vec<&str> = line.split_whitespace().collect();
-                                if parts.len() >= 2 {
-                                    let pid = parts[0];
-                                    let status = parts[1];
-                                    let status_padded = format!("{:<12}", status);
-                                    let msg = format!("│ {:3} │ {} │", pid, status_padded);
-                                    println(&msg);
-                                }
-                            }
-                        }
-                        println("└─────┴──────────────┘");
-                    } else {
-                        println("No processes running");
-                    }
-                } else {
-                    println("Error: Invalid process list data");
+        let mut records = [ProcessInfo::empty(); 16];
+        let mut cursor = 0u64;
+        let mut listed = 0usize;
+
+        loop {
+            let count = match syscall_ps(&mut records, cursor) {
+                Ok(n) => n,
+                Err(e) => {
+                    let msg = format!("Error reading process list: {:?}", e);
+                    println(&msg);
+                    return;
                 }
+            };
+            if count == 0 {
+                break;
             }
-            Err(e) => {
-                let msg = format!("Error reading process list: {:?}", e);
-                println(&msg);
+
+            for info in &records[..count] {
+                if listed == 0 {
+                    println("  PID  PPID STATE       NI CLS RTP  TICKS     MEM NAME             COMMAND");
+                }
+                if info.version != PROCESS_INFO_VERSION {
+                    let msg = format!("{:5} (unsupported record version {})", info.pid, info.version);
+                    println(&msg);
+                } else {
+                    let state = match info.state() {
+                        Some(ProcessState::Exited) => format!("Exited({})", info.exit_code),
+                        Some(ProcessState::Killed) => {
+                            format!("Killed({})", signal_name(info.exit_code as u32))
+                        }
+                        Some(state) => state.as_str().to_string(),
+                        None => "?".to_string(),
+                    };
+                    let msg = format!(
+                        "{:5} {:5} {:<10} {:3} {:<3} {:>3} {:6} {:6}K {:<16} {}",
+                        info.pid,
+                        info.ppid,
+                        state,
+                        info.nice,
+                        policy_name(info.policy),
+                        info.rt_priority,
+                        info.ticks,
+                        info.memory_bytes / 1024,
+                        info.name(),
+                        info.cmdline()
+                    );
+                    println(&msg);
+                }
+                listed += 1;
             }
+            cursor = records[count - 1].pid;
+        }
+
+        if listed == 0 {
+            println("No processes running");
         }
     }
 
     /// uptime command - show kernel uptime
     fn cmd_uptime() {
+        if let Ok(ts) = syscall_clock_gettime(CLOCK_MONOTONIC) {
+            let msg = format!(
+                "Kernel uptime: {}.{:03} seconds",
+                ts.tv_sec,
+                ts.tv_nsec / 1_000_000
+            );
+            println(&msg);
+            return;
+        }
+
         match syscall_uptime() {
             Ok(seconds) => {
                 let msg = format!("Kernel uptime: {} seconds", seconds);
@@ -552,6 +794,7 @@ impl Cli {
             for i in 1..=count {
                 match syscall_task_create(0x1000) {
                     Ok(pid) => {
+                        let _ = syscall_setpriority(pid, NICE_BATCH);
                         let msg = format!("  Task {}: spawned as PID {}", i, pid);
                         println(&msg);
                         spawned += 1;
@@ -591,6 +834,8 @@ impl Cli {
         // For now, spawn a generic task (kernel test tasks require kernel-side implementation)
         match syscall_task_create(0x1000) {
             Ok(pid) => {
+                // Background work must not slow the shell down
+                let _ = syscall_setpriority(pid, NICE_BATCH);
                 let msg = format!("Spawned task {} with PID: {}", task_index, pid);
                 println(&msg);
             }
@@ -601,10 +846,92 @@ impl Cli {
         }
     }
 
+    /// nice command - set the nice value of a task
+    ///
+    /// Syntax:
+    ///   nice PID N    - Lower N runs first (-20 to 19)
+    fn cmd_nice(args: &[&str]) {
+        if args.len() != 2 {
+            println("Usage: nice <PID> <N>");
+            return;
+        }
+
+        let (pid, nice) = match (args[0].parse::<u64>(), args[1].parse::<i32>()) {
+            (Ok(pid), Ok(nice)) => (pid, nice),
+            _ => {
+                println("PID and nice value must be numbers");
+                return;
+            }
+        };
+
+        match syscall_setpriority(pid, nice) {
+            Ok(()) => {
+                let msg = format!("PID {} nice value set to {}", pid, nice);
+                println(&msg);
+            }
+            Err(e) => {
+                let msg = format!("Failed to set priority of PID {}: {:?}", pid, e);
+                println(&msg);
+            }
+        }
+    }
+
+    /// chrt command - set the scheduling policy of a task
+    ///
+    /// Syntax:
+    ///   chrt PID other       - Normal time sharing (by nice value)
+    ///   chrt PID fifo R      - Real-time, run until blocked (R = 1-99)
+    ///   chrt PID rr R        - Real-time, take turns within priority R
+    fn cmd_chrt(args: &[&str]) {
+        if args.len() < 2 || args.len() > 3 {
+            println("Usage: chrt <PID> <other|fifo|rr> [PRIORITY]");
+            return;
+        }
+
+        let policy = match args[1] {
+            "other" => SCHED_OTHER,
+            "fifo" => SCHED_FIFO,
+            "rr" => SCHED_RR,
+            _ => {
+                println("Policy must be other, fifo or rr");
+                return;
+            }
+        };
+        let priority = args.get(2).map_or(Ok(0), |arg| arg.parse::<u32>());
+        let (pid, priority) = match (args[0].parse::<u64>(), priority) {
+            (Ok(pid), Ok(priority)) => (pid, priority),
+            _ => {
+                println("PID and priority must be numbers");
+                return;
+            }
+        };
+
+        match syscall_sched_setscheduler(pid, policy, priority) {
+            Ok(()) => {
+                let msg = format!(
+                    "PID {} policy set to {} {}",
+                    pid,
+                    policy_name(policy),
+                    priority
+                );
+                println(&msg);
+            }
+            Err(-1) => println("Real-time policies need a priority of 1-99; other takes none"),
+            Err(e) => {
+                let msg = format!("Failed to set policy of PID {}: {:?}", pid, e);
+                println(&msg);
+            }
+        }
+    }
+
     /// wait command - Wait for a task to complete and get exit code
+    ///
+    /// Syntax:
+    ///   wait PID       - Wait until the task exits
+    ///   wait PID MS    - Give up after MS milliseconds
     fn cmd_wait(args: &[&str]) {
-        if args.is_empty() {
-            println("Usage: wait <PID>");
+        if args.is_empty() || args.len() > 2 {
+            println("Usage: wait <PID> [MS]");
             return;
         }
 
@@ -617,15 +944,26 @@ impl Cli {
                 return;
             }
         };
+        let timeout_ms: u64 = match args.get(1).map_or(Ok(0), |arg| arg.parse()) {
+            Ok(ms) => ms,
+            Err(_) => {
+                println("Timeout must be a number of milliseconds");
+                return;
+            }
+        };
 
         let msg = format!("Waiting for task {} to complete...", pid);
         println(&msg);
 
-        match syscall_task_wait(pid) {
+        match syscall_task_wait(pid, timeout_ms) {
             Ok(exit_code) => {
                 let msg = format!("Task {} exited with code: {}", pid, exit_code);
                 println(&msg);
             }
+            Err(-7) => {
+                let msg = format!("Task {} still running after {} ms", pid, timeout_ms);
+                println(&msg);
+            }
             Err(e) => {
                 let msg = format!("Error waiting for task {}: {:?}", pid, e);
                 println(&msg);
@@ -633,6 +971,229 @@ impl Cli {
         }
     }
 
+    /// kill command - End a task
+    ///
+    /// Syntax:
+    ///   kill PID         - Send SIGTERM
+    ///   kill PID kill    - Send SIGKILL (also: term, or a signal number)
+    fn cmd_kill(args: &[&str]) {
+        let signal = match args.get(1).copied() {
+            None | Some("term") | Some("TERM") | Some("SIGTERM") => Some(SIGTERM),
+            Some("kill") | Some("KILL") | Some("SIGKILL") => Some(SIGKILL),
+            Some(number) => number.parse::<u32>().ok(),
+        };
+        let (pid, signal) = match (args.first().map(|arg| arg.parse::<u64>()), signal) {
+            (Some(Ok(pid)), Some(signal)) if args.len() <= 2 => (pid, signal),
+            _ => {
+                println("Usage: kill <PID> [term|kill]");
+                return;
+            }
+        };
+
+        match syscall_kill(pid, signal) {
+            Ok(()) => {
+                let msg = format!("Sent {} to PID {}", signal_name(signal), pid);
+                println(&msg);
+            }
+            Err(-4) => println("Init cannot be killed"),
+            Err(-5) => {
+                let msg = format!("No such process: {}", pid);
+                println(&msg);
+            }
+            Err(e) => {
+                let msg = format!("Failed to kill PID {}: {:?}", pid, e);
+                println(&msg);
+            }
+        }
+    }
+
+    /// sleep command - Pause the shell without using the CPU
+    fn cmd_sleep(args: &[&str]) {
+        let ms = match args {
+            [ms] => ms.parse::<u64>().ok(),
+            _ => None,
+        };
+        let ms = match ms {
+            Some(ms) => ms,
+            None => {
+                println("Usage: sleep <MS>");
+                return;
+            }
+        };
+
+        if let Err(e) = syscall_sleep(ms) {
+            let msg = format!("Sleep failed: {:?}", e);
+            println(&msg);
+        }
+    }
+
+    /// top command - Refreshing view of scheduler statistics
+    ///
+    /// Samples `sys_sched_stats` and the process table every MS ms and shows
+    /// what changed in between: switch rates, idle time, how long threads
+    /// waited for a CPU, and per-process CPU use and switches.
+    fn cmd_top(args: &[&str]) {
+        let parsed = match args {
+            [] => Some((10, 1000)),
+            [n] => n.parse::<u32>().ok().map(|n| (n, 1000)),
+            [n, ms] => n.parse::<u32>().ok().zip(ms.parse::<u64>().ok()),
+            _ => None,
+        };
+        let (refreshes, interval_ms) = match parsed {
+            Some((n, ms)) if n > 0 && ms > 0 => (n, ms),
+            _ => {
+                println("Usage: top [N] [MS]");
+                return;
+            }
+        };
+
+        let mut before = match Self::sample_top() {
+            Ok(sample) => sample,
+            Err(e) => {
+                let msg = format!("Error reading scheduler statistics: {:?}", e);
+                println(&msg);
+                return;
+            }
+        };
+        for _ in 0..refreshes {
+            if let Err(e) = syscall_sleep(interval_ms) {
+                let msg = format!("Sleep failed: {:?}", e);
+                println(&msg);
+                return;
+            }
+            let after = match Self::sample_top() {
+                Ok(sample) => sample,
+                Err(e) => {
+                    let msg = format!("Error reading scheduler statistics: {:?}", e);
+                    println(&msg);
+                    return;
+                }
+            };
+            let _ = syscall_clear_screen();
+            Self::draw_top(&before, &after);
+            before = after;
+        }
+    }
+
+    /// Read the scheduler's counters and every process record
+    fn sample_top() -> Result<(SchedStats, Vec<ProcessInfo>), i64> {
+        let stats = syscall_sched_stats()?;
+        let mut processes = Vec::new();
+        let mut records = [ProcessInfo::empty(); 16];
+        let mut cursor = 0u64;
+        loop {
+            let count = syscall_ps(&mut records, cursor)?;
+            if count == 0 {
+                break;
+            }
+            processes.extend(
+                records[..count]
+                    .iter()
+                    .filter(|info| info.version == PROCESS_INFO_VERSION)
+                    .copied(),
+            );
+            cursor = records[count - 1].pid;
+        }
+        Ok((stats, processes))
+    }
+
+    /// Print one `top` screen from two samples
+    fn draw_top(before: &(SchedStats, Vec<ProcessInfo>), after: &(SchedStats, Vec<ProcessInfo>)) {
+        let (old, new) = (&before.0, &after.0);
+        let elapsed_ns = new.uptime_ns.saturating_sub(old.uptime_ns).max(1);
+        let per_sec = |count: u64| count * 1_000_000_000 / elapsed_ns;
+        let cpu_ns = elapsed_ns * u64::from(new.cpus.max(1));
+        let idle = new.idle_ns.saturating_sub(old.idle_ns).min(cpu_ns);
+
+        let msg = format!(
+            "top - up {}.{:03}s, {} CPU(s), policy {}, {} Hz tick",
+            new.uptime_ns / 1_000_000_000,
+            new.uptime_ns / 1_000_000 % 1000,
+            new.cpus,
+            new.policy(),
+            new.timer_hz
+        );
+        println(&msg);
+        let msg = format!(
+            "Switches: {}/s ({}/s voluntary, {}/s preempted), {}/s time slices expired",
+            per_sec(new.switches - old.switches),
+            per_sec(new.voluntary_switches - old.voluntary_switches),
+            per_sec(new.involuntary_switches - old.involuntary_switches),
+            per_sec(new.quantum_expirations - old.quantum_expirations)
+        );
+        println(&msg);
+        let idle_permille = idle * 1000 / cpu_ns;
+        let msg = format!(
+            "CPU: {}.{}% busy, {}.{}% idle",
+            (1000 - idle_permille) / 10,
+            (1000 - idle_permille) % 10,
+            idle_permille / 10,
+            idle_permille % 10
+        );
+        println(&msg);
+
+        println("Ready latency:");
+        let waits: Vec<u64> = (0..LATENCY_BUCKETS)
+            .map(|bucket| new.latency[bucket] - old.latency[bucket])
+            .collect();
+        let most = waits.iter().copied().max().unwrap_or(0).max(1);
+        for (bucket, &count) in waits.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let label = match latency_bucket_limit_us(bucket) {
+                Some(limit) => format!("< {}", Self::format_us(limit)),
+                None => format!(">= {}", Self::format_us(1 << (bucket - 1))),
+            };
+            let bar = "#".repeat((count * 40 / most).max(1) as usize);
+            let msg = format!("  {:>9} {:7} {}", label, count, bar);
+            println(&msg);
+        }
+        if waits.iter().all(|&count| count == 0) {
+            println("  (no switches)");
+        }
+
+        println("");
+        println("  PID STATE       %CPU  SWITCH    VOL  INVOL  READY ms NAME");
+        let tick_hz = u64::from(new.timer_hz.max(1));
+        let mut rows: Vec<(u64, &ProcessInfo, Option<&ProcessInfo>)> = after
+            .1
+            .iter()
+            .filter(|info| !matches!(info.state(), Some(ProcessState::Exited | ProcessState::Killed)))
+            .map(|info| {
+                let prev = before.1.iter().find(|p| p.pid == info.pid);
+                let ticks = info.ticks - prev.map_or(0, |p| p.ticks);
+                (ticks, info, prev)
+            })
+            .collect();
+        rows.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.pid.cmp(&b.1.pid)));
+        for (ticks, info, prev) in rows {
+            let delta = |field: fn(&ProcessInfo) -> u64| field(info) - prev.map_or(0, field);
+            // Per mille of one CPU
+            let cpu = ticks * 1_000_000_000_000 / tick_hz / elapsed_ns;
+            let state = info.state().map_or("?", |state| state.as_str());
+            let msg = format!(
+                "{:5} {:<10} {:3}.{} {:7} {:6} {:6} {:9} {}",
+                info.pid,
+                state,
+                cpu / 10,
+                cpu % 10,
+                delta(|p| p.switches),
+                delta(|p| p.voluntary_switches),
+                delta(|p| p.involuntary_switches),
+                delta(|p| p.ready_ns) / 1_000_000,
+                info.name()
+            );
+            println(&msg);
+        }
+    }
+
+    /// A duration in microseconds, in µs or ms
+    fn format_us(us: u64) -> String {
+        if us >= 1000 {
+            format!("{}ms", us / 1000)
+        } else {
+            format!("{}us", us)
+        }
+    }
+
     /// ping command - Simple connectivity test
     fn cmd_ping() {
         println("pong");
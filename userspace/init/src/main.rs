@@ -0,0 +1,237 @@
+//! Init process for Orbital OS
+//!
+//! The kernel starts this binary as PID 1. It starts every service in the
+//! service list, reaps children (including orphans the kernel hands over
+//! when their parent exits) and restarts services that exit according to
+//! their restart policy, backing off when they keep crashing.
+//!
+//! Compiled for: x86_64-orbital (static, no_std)
+//! Entry point: _start()
+
+#![no_std]
+#![no_main]
+
+mod services;
+mod supervisor;
+
+use core::arch::asm;
+use core::panic::PanicInfo;
+use orbital_common::process::WAIT_ANY;
+use services::Service;
+use supervisor::{Exit, Supervisor, MAX_SERVICES};
+
+/// Syscall numbers used by init
+const SYS_WRITE: i64 = 2;
+const SYS_EXIT: i64 = 3;
+const SYS_UPTIME: i64 = 9;
+const SYS_SPAWN: i64 = 18;
+const SYS_WAIT: i64 = 19;
+const SYS_SLEEP: i64 = 25;
+
+/// Error returned for a service whose argv doesn't fit
+const SYS_ERR_INVALID: i64 = -1;
+
+/// Error returned by sys_wait when the timeout passes first
+const SYS_ERR_TIMED_OUT: i64 = -7;
+
+/// How long to sleep with no children and no restart pending; orphans
+/// handed over meanwhile are reaped when it ends
+const IDLE_SLEEP_MS: u64 = 1000;
+
+/// Longest argv buffer the kernel accepts for sys_spawn
+const ARGV_MAX: usize = 256;
+
+/// Syscall handler - invoke kernel via syscall instruction
+/// syscall ABI: rax=syscall_number, rdi=arg1, rsi=arg2, rdx=arg3
+#[inline]
+fn syscall(number: i64, arg1: i64, arg2: i64, arg3: i64) -> i64 {
+    syscall4(number, arg1, arg2, arg3, 0)
+}
+
+/// Like `syscall`, with a fourth argument in rcx
+#[inline]
+fn syscall4(number: i64, arg1: i64, arg2: i64, arg3: i64, arg4: i64) -> i64 {
+    let result: i64;
+    unsafe {
+        asm!(
+            "syscall",
+            inout("rax") number => result,
+            in("rdi") arg1,
+            in("rsi") arg2,
+            in("rdx") arg3,
+            in("rcx") arg4,
+            clobber_abi("C"),
+        );
+    }
+    result
+}
+
+/// Write text to stdout
+fn write(text: &str) {
+    syscall(SYS_WRITE, 1, text.as_ptr() as i64, text.len() as i64);
+}
+
+/// Write a signed integer (no alloc)
+fn write_int(n: i64) {
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut value = n.unsigned_abs();
+    loop {
+        digits[digits.len() - 1 - len] = b'0' + (value % 10) as u8;
+        len += 1;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+
+    if n < 0 {
+        write("-");
+    }
+    if let Ok(s) = core::str::from_utf8(&digits[digits.len() - len..]) {
+        write(s);
+    }
+}
+
+/// Seconds since boot
+fn uptime() -> u64 {
+    syscall(SYS_UPTIME, 0, 0, 0) as u64
+}
+
+/// Start a service, returning its PID or a negative error
+///
+/// sys_spawn takes argv as one NUL-separated buffer: the program name
+/// followed by the service's arguments.
+fn spawn(service: &Service) -> i64 {
+    let mut argv = [0u8; ARGV_MAX];
+    let mut len = 0;
+    for arg in core::iter::once(&service.program).chain(service.args) {
+        let end = len + arg.len();
+        if end >= argv.len() {
+            return SYS_ERR_INVALID;
+        }
+        argv[len..end].copy_from_slice(arg.as_bytes());
+        len = end + 1; // keep the NUL separator
+    }
+
+    syscall(SYS_SPAWN, argv.as_ptr() as i64, len as i64, 0)
+}
+
+/// Block for `ms` milliseconds
+fn sleep(ms: u64) {
+    syscall(SYS_SLEEP, ms as i64, 0, 0);
+}
+
+/// Reap a child, returning (pid, exit code) or a negative error
+///
+/// Blocks until a child exits, or for at most `timeout_ms` milliseconds
+/// (0 = no limit).
+fn wait(timeout_ms: u64) -> (i64, i64) {
+    let mut exit_code = 0i64;
+    let pid = syscall4(
+        SYS_WAIT,
+        WAIT_ANY,
+        &mut exit_code as *mut i64 as i64,
+        0,
+        timeout_ms as i64,
+    );
+    (pid, exit_code)
+}
+
+/// Start every service that is due
+fn start_due(supervisor: &mut Supervisor) {
+    let now = uptime();
+    // Collect first: starting a service updates the supervisor
+    let mut due = [None; MAX_SERVICES];
+    for (slot, service) in due.iter_mut().zip(supervisor.due(now)) {
+        *slot = Some(service);
+    }
+
+    for (index, service) in due.into_iter().flatten() {
+        let pid = spawn(service);
+        if pid > 0 {
+            supervisor.started(index, pid as u64, now);
+            write("init: started ");
+            write(service.name);
+            write(" (PID ");
+            write_int(pid);
+            write(")\n");
+        } else {
+            let at = supervisor.start_failed(index, now);
+            write("init: failed to start ");
+            write(service.name);
+            write(" (error ");
+            write_int(pid);
+            write("), retrying in ");
+            write_int((at - now) as i64);
+            write("s\n");
+        }
+    }
+}
+
+/// Log what happened to a reaped child
+fn report_exit(exit: Exit, pid: i64, exit_code: i64) {
+    match exit {
+        Exit::Restart(service, at) => {
+            write("init: ");
+            write(service.name);
+            write(" exited with code ");
+            write_int(exit_code);
+            write(", restarting in ");
+            write_int(at.saturating_sub(uptime()) as i64);
+            write("s\n");
+        }
+        Exit::Stopped(service) => {
+            write("init: ");
+            write(service.name);
+            write(" exited with code ");
+            write_int(exit_code);
+            write("\n");
+        }
+        Exit::Orphan => {
+            write("init: reaped orphan PID ");
+            write_int(pid);
+            write("\n");
+        }
+    }
+}
+
+/// Supervision loop: never returns
+fn supervise(supervisor: &mut Supervisor) -> ! {
+    loop {
+        start_due(supervisor);
+
+        // Block until a child exits or the next restart is due. Uptime
+        // counts whole seconds, so this never wakes before the restart.
+        let timeout_ms = supervisor
+            .next_restart()
+            .map(|at| at.saturating_sub(uptime()).max(1) * 1000);
+
+        match wait(timeout_ms.unwrap_or(0)) {
+            (pid, exit_code) if pid > 0 => {
+                let exit = supervisor.exited(pid as u64, exit_code, uptime());
+                report_exit(exit, pid, exit_code);
+            }
+            (SYS_ERR_TIMED_OUT, _) => {}
+            // No children left; orphans may still be handed over later
+            _ => sleep(timeout_ms.unwrap_or(IDLE_SLEEP_MS)),
+        }
+    }
+}
+
+/// Panic handler for no_std environment
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    write("init: panicked\n");
+    syscall(SYS_EXIT, -1, 0, 0);
+    loop {}
+}
+
+/// Entry point called by loader
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    write("init: starting services\n");
+
+    let mut supervisor = Supervisor::new();
+    supervise(&mut supervisor)
+}
@@ -0,0 +1,57 @@
+//! Service list
+//!
+//! There is no filesystem yet, so the services init starts are compiled in.
+//! `program` is the name of an embedded binary as understood by sys_spawn;
+//! it becomes argv[0], followed by `args`.
+
+/// When a service is started again after it exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Restart whenever it exits
+    Always,
+    /// Restart only if it exits with a non-zero code
+    OnFailure,
+    /// Run once
+    #[allow(dead_code)] // No one-shot services yet
+    Never,
+}
+
+impl RestartPolicy {
+    /// Whether a service that exited with `exit_code` should be restarted
+    pub fn should_restart(self, exit_code: i64) -> bool {
+        match self {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => exit_code != 0,
+            RestartPolicy::Never => false,
+        }
+    }
+}
+
+/// A service init starts and supervises
+#[derive(Debug, Clone, Copy)]
+pub struct Service {
+    /// Name used in init's log messages
+    pub name: &'static str,
+    /// Embedded program to spawn
+    pub program: &'static str,
+    /// Arguments after the program name
+    pub args: &'static [&'static str],
+    /// What to do when it exits
+    pub restart: RestartPolicy,
+}
+
+/// Services in start order
+pub const SERVICES: &[Service] = &[
+    Service {
+        name: "managementd",
+        program: "managementd",
+        args: &[],
+        restart: RestartPolicy::OnFailure,
+    },
+    Service {
+        name: "shell",
+        program: "shell",
+        args: &[],
+        restart: RestartPolicy::Always,
+    },
+];
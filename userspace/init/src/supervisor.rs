@@ -0,0 +1,126 @@
+//! Service supervision state
+//!
+//! Tracks which PID runs each service and when a service that exited should
+//! be started again. Restarts back off exponentially so a service that
+//! crashes on startup doesn't spin; the delay resets once a service has
+//! stayed up for `STABLE_SECS`.
+
+use crate::services::{Service, SERVICES};
+
+/// Delay before the first restart, in seconds
+pub const BACKOFF_INITIAL_SECS: u64 = 1;
+
+/// Longest delay between restarts, in seconds
+pub const BACKOFF_MAX_SECS: u64 = 32;
+
+/// Uptime after which a service is considered healthy again
+pub const STABLE_SECS: u64 = 10;
+
+/// Maximum number of services
+pub const MAX_SERVICES: usize = 8;
+
+/// Per-service bookkeeping
+#[derive(Debug, Clone, Copy)]
+struct ServiceState {
+    /// PID of the running instance
+    pid: Option<u64>,
+    /// Uptime (seconds) when the running instance was started
+    started_at: u64,
+    /// Uptime at which to start the service again
+    restart_at: Option<u64>,
+    /// Delay to use for the next restart
+    backoff: u64,
+}
+
+impl ServiceState {
+    const STOPPED: ServiceState = ServiceState {
+        pid: None,
+        started_at: 0,
+        restart_at: None,
+        backoff: BACKOFF_INITIAL_SECS,
+    };
+
+    /// Schedule a restart after the current backoff and double it
+    fn schedule_restart(&mut self, now: u64) -> u64 {
+        let at = now + self.backoff;
+        self.restart_at = Some(at);
+        self.backoff = (self.backoff * 2).min(BACKOFF_MAX_SECS);
+        at
+    }
+}
+
+/// What happened to a reaped process
+#[derive(Debug, Clone, Copy)]
+pub enum Exit {
+    /// A service exited and will be restarted at the given uptime
+    Restart(&'static Service, u64),
+    /// A service exited and its policy says to leave it stopped
+    Stopped(&'static Service),
+    /// Not one of ours: an orphan init adopted
+    Orphan,
+}
+
+/// Supervision state for every service in `SERVICES`
+pub struct Supervisor {
+    states: [ServiceState; MAX_SERVICES],
+}
+
+impl Supervisor {
+    /// All services stopped and due to start immediately
+    pub const fn new() -> Self {
+        let mut states = [ServiceState::STOPPED; MAX_SERVICES];
+        let mut i = 0;
+        while i < SERVICES.len() {
+            states[i].restart_at = Some(0);
+            i += 1;
+        }
+        Supervisor { states }
+    }
+
+    /// Services due to (re)start at `now`, with their index
+    pub fn due(&self, now: u64) -> impl Iterator<Item = (usize, &'static Service)> + '_ {
+        SERVICES
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| self.states[*i].restart_at.is_some_and(|at| at <= now))
+    }
+
+    /// Record that service `index` was started as `pid`
+    pub fn started(&mut self, index: usize, pid: u64, now: u64) {
+        let state = &mut self.states[index];
+        state.pid = Some(pid);
+        state.started_at = now;
+        state.restart_at = None;
+    }
+
+    /// Record that starting service `index` failed; try again after a backoff
+    pub fn start_failed(&mut self, index: usize, now: u64) -> u64 {
+        self.states[index].schedule_restart(now)
+    }
+
+    /// Record that `pid` exited with `exit_code` and decide what to do
+    pub fn exited(&mut self, pid: u64, exit_code: i64, now: u64) -> Exit {
+        let index = match self.states.iter().position(|s| s.pid == Some(pid)) {
+            Some(index) => index,
+            None => return Exit::Orphan,
+        };
+        let service = &SERVICES[index];
+        let state = &mut self.states[index];
+        state.pid = None;
+
+        if !service.restart.should_restart(exit_code) {
+            return Exit::Stopped(service);
+        }
+
+        // A service that stayed up for a while starts over with a short delay
+        if now.saturating_sub(state.started_at) >= STABLE_SECS {
+            state.backoff = BACKOFF_INITIAL_SECS;
+        }
+        Exit::Restart(service, state.schedule_restart(now))
+    }
+
+    /// Uptime of the earliest pending restart, if any
+    pub fn next_restart(&self) -> Option<u64> {
+        self.states.iter().filter_map(|s| s.restart_at).min()
+    }
+}
@@ -15,6 +15,9 @@
 //! This separation ensures the kernel remains minimal and policies remain in userspace.
 
 use orbital_common::ipc::{MgmtCommand, MgmtResponse, RawIpcMessage};
+use orbital_common::process::ProcessInfo;
+use orbital_common::sched::SchedStats;
+use orbital_common::time::Timespec;
 
 // ============================================================================
 // Syscall Wrappers
@@ -29,7 +32,9 @@ pub enum SyscallError {
     PermissionDenied,
     NotFound,
     Error,
+    TimedOut,
     BadFd,
+    WouldBlock,
 }
 
 impl SyscallError {
@@ -42,7 +47,9 @@ impl SyscallError {
             -4 => Some(SyscallError::PermissionDenied),
             -5 => Some(SyscallError::NotFound),
             -6 => Some(SyscallError::Error),
+            -7 => Some(SyscallError::TimedOut),
             -9 => Some(SyscallError::BadFd),
+            -11 => Some(SyscallError::WouldBlock),
             _ => None,
         }
     }
@@ -163,13 +170,23 @@ pub fn syscall_write(fd: i32, ptr: *const u8, len: usize) -> SyscallResult<usize
 ///   fd: file descriptor (0=stdin)
 ///   ptr: pointer to buffer to fill
 ///   len: number of bytes to read
-/// Returns: number of bytes read on success, error code on failure
+/// Returns: number of bytes read on success, error code on failure. Waits
+/// until at least one byte is available.
 pub fn syscall_read(fd: i32, ptr: *mut u8, len: usize) -> SyscallResult<usize> {
+    syscall_read_flags(fd, ptr, len, 0)
+}
+
+/// Syscall: read with flags
+///
+/// Like `syscall_read`; with `O_NONBLOCK` in `flags` it fails with
+/// `SyscallError::WouldBlock` instead of waiting for input.
+pub fn syscall_read_flags(fd: i32, ptr: *mut u8, len: usize, flags: u64) -> SyscallResult<usize> {
     // Invoke syscall 4 (SYS_READ) with:
     //   RAX = 4 (syscall number)
     //   RDI = fd (file descriptor)
     //   RSI = ptr (pointer to buffer)
     //   RDX = len (length in bytes)
+    //   RCX = flags
 
     #[cfg(target_arch = "x86_64")]
     unsafe {
@@ -180,6 +197,7 @@ pub fn syscall_read(fd: i32, ptr: *mut u8, len: usize) -> SyscallResult<usize> {
             in("rdi") fd as usize,          // first argument: fd
             in("rsi") ptr,                  // second argument: pointer
             in("rdx") len,                  // third argument: length
+            in("rcx") flags,                // fourth argument: flags
             clobber_abi("C"),               // Tell compiler C calling convention is clobbered
         );
 
@@ -193,6 +211,7 @@ pub fn syscall_read(fd: i32, ptr: *mut u8, len: usize) -> SyscallResult<usize> {
     #[cfg(not(target_arch = "x86_64"))]
     {
         // Non-x86_64 platforms: return not implemented
+        let _ = (fd, ptr, len, flags);
         Err(SyscallError::NotImplemented)
     }
 }
@@ -237,6 +256,7 @@ pub fn syscall_task_create(entry_point: usize) -> SyscallResult<u64> {
             "syscall",
             inout("rax") 5_i64 => result,  // syscall number 5 (SYS_TASK_CREATE)
             in("rdi") entry_point,          // first argument: entry point
+            in("rsi") 0usize,               // second argument: stack size (0 = default)
             clobber_abi("C"),               // Tell compiler C calling convention is clobbered
         );
 
@@ -260,9 +280,20 @@ pub fn syscall_task_create(entry_point: usize) -> SyscallResult<u64> {
 /// Arguments: task_id (process ID to wait for)
 /// Returns: exit code on success, error otherwise
 pub fn syscall_task_wait(task_id: u64) -> SyscallResult<i64> {
+    syscall_task_wait_timeout(task_id, 0)
+}
+
+/// Syscall: task_wait with a timeout
+///
+/// Like `syscall_task_wait`, but gives up after `timeout_ms` milliseconds
+/// (0 waits forever).
+/// Returns: exit code on success, `SyscallError::TimedOut` if the task
+/// was still running, error otherwise
+pub fn syscall_task_wait_timeout(task_id: u64, timeout_ms: u64) -> SyscallResult<i64> {
     // Invoke syscall 6 (SYS_TASK_WAIT) with:
     //   RAX = 6 (syscall number)
     //   RDI = task_id (process ID to wait for)
+    //   RSI = timeout_ms (0 = no timeout)
 
     #[cfg(target_arch = "x86_64")]
     unsafe {
@@ -271,6 +302,7 @@ pub fn syscall_task_wait(task_id: u64) -> SyscallResult<i64> {
             "syscall",
             inout("rax") 6_i64 => result,  // syscall number 6 (SYS_TASK_WAIT)
             in("rdi") task_id,              // first argument: task ID
+            in("rsi") timeout_ms,           // second argument: timeout
             clobber_abi("C"),               // Tell compiler C calling convention is clobbered
         );
 
@@ -284,6 +316,7 @@ pub fn syscall_task_wait(task_id: u64) -> SyscallResult<i64> {
     #[cfg(not(target_arch = "x86_64"))]
     {
         // Non-x86_64 platforms: return not implemented
+        let _ = (task_id, timeout_ms);
         Err(SyscallError::NotImplemented)
     }
 }
@@ -318,18 +351,20 @@ pub fn syscall_get_pid() -> SyscallResult<u64> {
 
 /// Syscall: ps - List all processes
 ///
-/// Writes process list to buffer in kernel.
-/// Buffer format: "PID Status\n" for each process
-/// Returns: number of bytes written
-pub fn syscall_ps(buffer: &mut [u8]) -> SyscallResult<usize> {
+/// Fills `records` with `ProcessInfo` entries for processes whose PID is
+/// greater than `cursor`, in ascending PID order. Pass 0 to start from the
+/// beginning and the last returned PID to fetch the next page.
+/// Returns: number of records written (0 when the listing is complete)
+pub fn syscall_ps(records: &mut [ProcessInfo], cursor: u64) -> SyscallResult<usize> {
     #[cfg(target_arch = "x86_64")]
     unsafe {
         let result: i64;
         core::arch::asm!(
             "syscall",
             inout("rax") 8_i64 => result,  // syscall number 8 (SYS_PS)
-            in("rdi") buffer.as_mut_ptr(),
-            in("rsi") buffer.len(),
+            in("rdi") records.as_mut_ptr(),
+            in("rsi") records.len(),
+            in("rdx") cursor,
             clobber_abi("C"),
         );
 
@@ -435,6 +470,526 @@ pub fn syscall_run_ready() -> SyscallResult<usize> {
     }
 }
 
+/// Syscall: thread_create - Start a new thread in the calling process
+///
+/// The thread shares the caller's address space and runs
+/// `entry(arg)`. Returning from `entry` exits the thread.
+/// Returns: thread ID (positive) on success, error otherwise
+pub fn syscall_thread_create(entry: extern "C" fn(usize), arg: usize) -> SyscallResult<u64> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 13_i64 => result,  // syscall number 13 (SYS_THREAD_CREATE)
+            in("rdi") entry as usize,
+            in("rsi") arg,
+            in("rdx") 0usize,  // stack size (0 = default)
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(result as u64)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: thread_join - Wait for a thread of this process to exit
+///
+/// Arguments: tid (thread ID returned by `syscall_thread_create`)
+/// Returns: the thread's exit code on success, error otherwise
+pub fn syscall_thread_join(tid: u64) -> SyscallResult<i64> {
+    syscall_thread_join_timeout(tid, 0)
+}
+
+/// Syscall: thread_join with a timeout
+///
+/// Arguments: tid, timeout_ms (0 waits forever)
+/// Returns: the thread's exit code on success, `SyscallError::TimedOut` if
+/// it was still running, error otherwise
+pub fn syscall_thread_join_timeout(tid: u64, timeout_ms: u64) -> SyscallResult<i64> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 14_i64 => result,  // syscall number 14 (SYS_THREAD_JOIN)
+            in("rdi") tid,
+            in("rsi") timeout_ms,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(result)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (tid, timeout_ms);
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: thread_exit - Terminate the calling thread
+///
+/// Other threads of the process keep running; the process exits
+/// when its last thread does.
+/// Arguments: exit_code
+pub fn syscall_thread_exit(exit_code: i32) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 15_i64 => result,  // syscall number 15 (SYS_THREAD_EXIT)
+            in("rdi") exit_code as i64,
+            clobber_abi("C"),
+        );
+
+        // Only reached if the kernel rejected the request
+        Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: get_tid - Get current thread ID
+///
+/// For the main thread this equals the process ID.
+/// Returns: thread ID (positive)
+pub fn syscall_gettid() -> SyscallResult<u64> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 16_i64 => result,  // syscall number 16 (SYS_GET_TID)
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(result as u64)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// `syscall_arch_prctl` operation: set the FS base
+pub const ARCH_SET_FS: usize = 0x1002;
+/// `syscall_arch_prctl` operation: read the FS base
+pub const ARCH_GET_FS: usize = 0x1003;
+
+/// Syscall: arch_prctl - Get or set the thread's FS/GS base
+///
+/// Used by thread-local storage runtimes to install the thread pointer.
+/// For GET operations `addr` is a pointer to a u64 receiving the base.
+/// Returns: Ok(()) on success, error otherwise
+pub fn syscall_arch_prctl(code: usize, addr: usize) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 17_i64 => result,  // syscall number 17 (SYS_ARCH_PRCTL)
+            in("rdi") code,
+            in("rsi") addr,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: spawn - Start an embedded program as a child process
+///
+/// Arguments: program name (e.g. "shell", "managementd")
+/// Returns: child process ID on success, error otherwise
+pub fn syscall_spawn(program: &str) -> SyscallResult<u64> {
+    syscall_spawn_argv(program.as_bytes())
+}
+
+/// Syscall: spawn - Start an embedded program with arguments
+///
+/// Arguments: NUL-separated argv buffer whose first element is the program
+/// name (e.g. b"shell\0--quiet"); the arguments show up in `ps`
+/// Returns: child process ID on success, error otherwise
+pub fn syscall_spawn_argv(argv: &[u8]) -> SyscallResult<u64> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 18_i64 => result,  // syscall number 18 (SYS_SPAWN)
+            in("rdi") argv.as_ptr(),
+            in("rsi") argv.len(),
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(result as u64)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: wait - Reap an exited child process
+///
+/// `pid` selects a child, or `WAIT_ANY` for any child. With `WAIT_NOHANG`
+/// in `flags` this returns `Ok(None)` instead of blocking.
+/// Returns: (pid, exit code) of the reaped child, error if there are no
+/// matching children
+pub fn syscall_wait(pid: i64, flags: u64) -> SyscallResult<Option<(u64, i64)>> {
+    syscall_wait_timeout(pid, flags, 0)
+}
+
+/// Syscall: wait with a timeout
+///
+/// Like `syscall_wait`, but a blocking wait gives up after `timeout_ms`
+/// milliseconds (0 waits forever) with `SyscallError::TimedOut`.
+pub fn syscall_wait_timeout(
+    pid: i64,
+    flags: u64,
+    timeout_ms: u64,
+) -> SyscallResult<Option<(u64, i64)>> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        let mut exit_code = 0i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 19_i64 => result,  // syscall number 19 (SYS_WAIT)
+            in("rdi") pid,
+            in("rsi") &mut exit_code as *mut i64,
+            in("rdx") flags,
+            in("rcx") timeout_ms,
+            clobber_abi("C"),
+        );
+
+        match result {
+            0 => Ok(None),
+            pid if pid > 0 => Ok(Some((pid as u64, exit_code))),
+            error => Err(SyscallError::from_return_value(error).unwrap_or(SyscallError::Error)),
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, flags, timeout_ms);
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: set_name - Label the calling thread
+///
+/// On the main thread this renames the process as shown by `ps`.
+/// Arguments: name (1-`PROCESS_NAME_LEN` bytes)
+/// Returns: Ok(()) on success, error otherwise
+pub fn syscall_set_name(name: &str) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 20_i64 => result,  // syscall number 20 (SYS_SET_NAME)
+            in("rdi") name.as_ptr(),
+            in("rsi") name.len(),
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: setpriority - Set the nice value of a process
+///
+/// Lower values are scheduled first. A process may renice itself or its
+/// children.
+/// Arguments: pid (0 for the caller), nice (clamped to `NICE_MIN`..=`NICE_MAX`)
+/// Returns: Ok(()) on success, error otherwise
+pub fn syscall_setpriority(pid: u64, nice: i32) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 21_i64 => result,  // syscall number 21 (SYS_SETPRIORITY)
+            in("rdi") pid,
+            in("rsi") nice as i64,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, nice);
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: getpriority - Get the nice value of a process
+///
+/// Arguments: pid (0 for the caller)
+/// Returns: The nice value (the kernel returns `20 - nice`; this undoes it)
+pub fn syscall_getpriority(pid: u64) -> SyscallResult<i32> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 22_i64 => result,  // syscall number 22 (SYS_GETPRIORITY)
+            in("rdi") pid,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(20 - result as i32)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = pid;
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: sched_setscheduler - Set the scheduling policy of a process
+///
+/// `SCHED_FIFO` and `SCHED_RR` threads always run before `SCHED_OTHER`
+/// ones (within the kernel's real-time throttle). A process may change
+/// itself or its children.
+/// Arguments: pid (0 for the caller), policy (`SCHED_*`), real-time
+/// priority (`RT_PRIORITY_MIN`..=`RT_PRIORITY_MAX`, 0 for `SCHED_OTHER`)
+/// Returns: Ok(()) on success, error otherwise
+pub fn syscall_sched_setscheduler(pid: u64, policy: u32, priority: u32) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 23_i64 => result,  // syscall number 23 (SYS_SCHED_SETSCHEDULER)
+            in("rdi") pid,
+            in("rsi") policy as u64,
+            in("rdx") priority as u64,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, policy, priority);
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: sched_getscheduler - Get the scheduling policy of a process
+///
+/// Arguments: pid (0 for the caller)
+/// Returns: The policy (`SCHED_OTHER`, `SCHED_FIFO` or `SCHED_RR`)
+pub fn syscall_sched_getscheduler(pid: u64) -> SyscallResult<u32> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 24_i64 => result,  // syscall number 24 (SYS_SCHED_GETSCHEDULER)
+            in("rdi") pid,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(result as u32)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = pid;
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: sleep - Block the calling thread for a while
+///
+/// The thread gives up the CPU until the time has passed. Sleeps are
+/// rounded up to whole timer ticks (10 ms by default).
+/// Arguments: ms (milliseconds to sleep)
+/// Returns: Ok(()) once the time has passed
+pub fn syscall_sleep(ms: u64) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 25_i64 => result,  // syscall number 25 (SYS_SLEEP)
+            in("rdi") ms,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = ms;
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: clock_gettime - Read a clock with nanosecond resolution
+///
+/// Arguments: clock (`CLOCK_MONOTONIC`)
+/// Returns: The current time of the clock, error for unknown clocks
+pub fn syscall_clock_gettime(clock: u32) -> SyscallResult<Timespec> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        let mut ts = Timespec::default();
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 26_i64 => result,  // syscall number 26 (SYS_CLOCK_GETTIME)
+            in("rdi") clock as u64,
+            in("rsi") &mut ts as *mut Timespec,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(ts)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = clock;
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: kill - Send a signal to a process
+///
+/// Arguments: pid, signal (`SIGTERM`, `SIGKILL`, or 0 to check the process exists)
+/// Returns: Ok(()) on success, PermissionDenied for init, NotFound if it already exited
+pub fn syscall_kill(pid: u64, signal: u32) -> SyscallResult<()> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 28_i64 => result,  // syscall number 28 (SYS_KILL)
+            in("rdi") pid,
+            in("rsi") signal as u64,
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (pid, signal);
+        Err(SyscallError::NotImplemented)
+    }
+}
+
+/// Syscall: sched_stats - Read the scheduler's statistics
+///
+/// Arguments: none
+/// Returns: Context switch, idle time and ready latency counters since boot
+pub fn syscall_sched_stats() -> SyscallResult<SchedStats> {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let result: i64;
+        let mut stats = SchedStats::empty();
+        core::arch::asm!(
+            "syscall",
+            inout("rax") 27_i64 => result,  // syscall number 27 (SYS_SCHED_STATS)
+            in("rdi") &mut stats as *mut SchedStats,
+            in("rsi") core::mem::size_of::<SchedStats>(),
+            clobber_abi("C"),
+        );
+
+        if result >= 0 {
+            Ok(stats)
+        } else {
+            Err(SyscallError::from_return_value(result).unwrap_or(SyscallError::Error))
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(SyscallError::NotImplemented)
+    }
+}
+
 /// Protocol version for IPC messages
 pub const IPC_PROTOCOL_VERSION: u32 = 1;
 
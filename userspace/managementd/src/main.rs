@@ -1,8 +1,64 @@
-fn main() {
-    println!("Orbital Management Daemon (stub)");
-    println!("Waiting for IPC connections...");
-    
+//! Orbital Management Daemon
+//!
+//! Started and supervised by init. Will own system state and answer
+//! management commands (`orbital_common::ipc::MgmtCommand`) once the kernel
+//! IPC channel is wired up; until then it announces itself and idles.
+//!
+//! Compiled for: x86_64-orbital (static, no_std)
+//! Entry point: _start()
+
+#![no_std]
+#![no_main]
+
+use core::arch::asm;
+use core::panic::PanicInfo;
+
+/// Syscall numbers used by the daemon
+const SYS_WRITE: i64 = 2;
+const SYS_EXIT: i64 = 3;
+
+/// Syscall handler - invoke kernel via syscall instruction
+/// syscall ABI: rax=syscall_number, rdi=arg1, rsi=arg2, rdx=arg3
+#[inline]
+fn syscall(number: i64, arg1: i64, arg2: i64, arg3: i64) -> i64 {
+    let result: i64;
+    unsafe {
+        asm!(
+            "syscall",
+            inout("rax") number => result,
+            in("rdi") arg1,
+            in("rsi") arg2,
+            in("rdx") arg3,
+            clobber_abi("C"),
+        );
+    }
+    result
+}
+
+/// Write text to stdout
+fn write(text: &str) {
+    syscall(SYS_WRITE, 1, text.as_ptr() as i64, text.len() as i64);
+}
+
+/// Panic handler for no_std environment
+///
+/// Exits with a failure code so init restarts the daemon.
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    write("managementd: panicked\n");
+    syscall(SYS_EXIT, -1, 0, 0);
+    loop {}
+}
+
+/// Entry point called by loader
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    write("managementd: started\n");
+
     // TODO: Implement IPC server
     // TODO: Implement state management
     // TODO: Implement system control commands
+    loop {
+        core::hint::spin_loop();
+    }
 }
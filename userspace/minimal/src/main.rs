@@ -14,6 +14,7 @@
 
 use core::arch::asm;
 use core::panic::PanicInfo;
+use orbital_common::process::ProcessInfo;
 
 /// Syscall handler - invoke kernel via syscall instruction
 /// syscall ABI: rax=syscall_number, rdi=arg1, rsi=arg2, rdx=arg3, rcx=arg4, r8=arg5, r9=arg6
@@ -34,11 +35,23 @@ fn syscall(number: i64, arg1: i64, arg2: i64, arg3: i64) -> i64 {
 }
 
 /// sys_read - Read from stdin (syscall #4, fd=0)
-/// Returns number of bytes read
+/// Waits for input; returns number of bytes read
 fn read_line(buffer: &mut [u8]) -> usize {
     let ptr = buffer.as_ptr() as i64;
     let len = buffer.len() as i64;
-    syscall(4, 0, ptr, len) as usize  // fd=0 (stdin), ptr, len
+    let result: i64;
+    unsafe {
+        asm!(
+            "syscall",
+            inout("rax") 4_i64 => result,
+            in("rdi") 0_i64, // fd=0 (stdin)
+            in("rsi") ptr,
+            in("rdx") len,
+            in("rcx") 0_i64, // flags: wait for input
+            clobber_abi("C"),
+        );
+    }
+    result.max(0) as usize
 }
 
 /// Write text via sys_write (syscall #2)
@@ -60,10 +73,11 @@ fn get_uptime() -> i64 {
 }
 
 /// List processes via sys_ps (syscall #8)
-fn list_processes(buf: &mut [u8]) -> usize {
-    let ptr = buf.as_ptr() as i64;
-    let len = buf.len() as i64;
-    syscall(8, ptr, len, 0) as usize
+/// Fills `records` with processes whose PID is greater than `cursor`
+fn list_processes(records: &mut [ProcessInfo], cursor: u64) -> i64 {
+    let ptr = records.as_mut_ptr() as i64;
+    let len = records.len() as i64;
+    syscall(8, ptr, len, cursor as i64)
 }
 
 /// Get current PID via sys_getpid (syscall #12)
@@ -108,13 +122,7 @@ fn execute_command(input: &str) {
         write_int(secs);
         writeln("s");
     } else if trimmed == "ps" {
-        let mut ps_buffer = [0u8; 512];
-        let n = list_processes(&mut ps_buffer);
-        if n > 0 {
-            if let Ok(ps_str) = core::str::from_utf8(&ps_buffer[..n]) {
-                write(ps_str);
-            }
-        }
+        print_processes();
     } else if trimmed == "clear" {
         // Clear screen using VGA control sequence
         write("\x1b[2J\x1b[H");
@@ -128,6 +136,36 @@ fn execute_command(input: &str) {
     }
 }
 
+/// Print the process table from binary sys_ps records (no alloc)
+fn print_processes() {
+    let mut records = [ProcessInfo::empty(); 8];
+    let mut cursor = 0u64;
+
+    writeln("PID PPID Status  Name  Command");
+    loop {
+        let n = list_processes(&mut records, cursor);
+        if n <= 0 {
+            break;
+        }
+
+        for info in &records[..n as usize] {
+            write_int(info.pid as i64);
+            write(" ");
+            write_int(info.ppid as i64);
+            write(" ");
+            match info.state() {
+                Some(state) => write(state.as_str()),
+                None => write("?"),
+            }
+            write(" ");
+            write(info.name());
+            write("  ");
+            writeln(info.cmdline());
+        }
+        cursor = records[n as usize - 1].pid;
+    }
+}
+
 /// Simple integer to string conversion (no alloc)
 /// Returns a static string (very limited implementation)
 /// For general use, we need a better approach